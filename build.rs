@@ -0,0 +1,15 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=proto/medi.proto");
+
+    // The gRPC service is generated from proto/medi.proto. protox compiles it in pure
+    // Rust, so building with `--features grpc` doesn't need protoc installed.
+    #[cfg(feature = "grpc")]
+    {
+        let descriptors =
+            protox::compile(["medi.proto"], ["proto"]).expect("Could not compile medi.proto");
+        tonic_build::configure()
+            .compile_fds(descriptors)
+            .expect("Could not generate the gRPC service");
+    }
+}
@@ -0,0 +1,73 @@
+use crate::note::{self, Note};
+use crate::reminder::Reminder;
+use crate::task::{Task, TaskStatus};
+use crate::timezone;
+use chrono::{Duration, NaiveDate};
+
+/// Everything planned for a single day.
+pub struct AgendaDay {
+    pub date: NaiveDate,
+    /// The journal note for the day, if one exists.
+    pub journal: Option<String>,
+    /// Reminders due on the day, soonest first.
+    pub reminders: Vec<Reminder>,
+    /// Open tasks due on the day or attached to the day's journal note.
+    pub tasks: Vec<Task>,
+}
+
+impl AgendaDay {
+    pub fn is_empty(&self) -> bool {
+        self.journal.is_none() && self.reminders.is_empty() && self.tasks.is_empty()
+    }
+}
+
+/// Builds the agenda for `days` days starting at `start` (local dates). Reminders due
+/// before `start` are returned separately as overdue. Days with nothing planned are left out.
+pub fn build(
+    notes: &[Note],
+    reminders: &[Reminder],
+    tasks: &[Task],
+    start: NaiveDate,
+    days: u32,
+) -> (Vec<Reminder>, Vec<AgendaDay>) {
+    let overdue: Vec<Reminder> = reminders
+        .iter()
+        .filter(|reminder| timezone::date(reminder.due_at) < start)
+        .cloned()
+        .collect();
+
+    let agenda = (0..days)
+        .map(|offset| start + Duration::days(offset as i64))
+        .map(|date| {
+            let journal_key = note::journal_key(date);
+            let journal = notes
+                .iter()
+                .any(|note| note.key == journal_key)
+                .then(|| journal_key.clone());
+            let mut day_reminders: Vec<Reminder> = reminders
+                .iter()
+                .filter(|reminder| timezone::date(reminder.due_at) == date)
+                .cloned()
+                .collect();
+            day_reminders.sort_by_key(|reminder| reminder.due_at);
+            let tasks = tasks
+                .iter()
+                .filter(|task| match task.due_at {
+                    Some(due_at) => timezone::date(due_at) == date,
+                    None => task.note_key == journal_key,
+                })
+                .filter(|task| !matches!(task.status, TaskStatus::Done))
+                .cloned()
+                .collect();
+            AgendaDay {
+                date,
+                journal,
+                reminders: day_reminders,
+                tasks,
+            }
+        })
+        .filter(|day| !day.is_empty())
+        .collect();
+
+    (overdue, agenda)
+}
@@ -0,0 +1,118 @@
+use crate::config::LinkStyle;
+use crate::db;
+use crate::error::AppError;
+use crate::export;
+use crate::note::Note;
+use crate::search;
+use chrono::{Duration, Utc};
+use std::collections::HashSet;
+use std::time::{Duration as Elapsed, Instant};
+
+/// Words the synthetic notes are made of.
+const WORDS: &[&str] = &[
+    "rust", "sled", "index", "query", "garden", "meeting", "project", "release", "budget",
+    "travel", "recipe", "reading", "idea", "draft", "review", "deploy", "server", "client",
+    "async", "tokio", "markdown", "journal", "backup", "network", "design", "schema", "pattern",
+    "lifetime", "borrow", "trait",
+];
+const TAGS: &[&str] = &["work", "home", "rust", "reading", "ideas", "journal"];
+const QUERIES: &[&str] = &[
+    "rust",
+    "garden meeting",
+    "tokio AND async",
+    "\"release budget\"",
+];
+
+/// One timed operation, and how many notes it covered when it works through all of them.
+pub struct Timing {
+    pub operation: &'static str,
+    pub elapsed: Elapsed,
+    pub notes: Option<usize>,
+}
+
+/// Builds a vault of `count` synthetic notes in a temporary directory and times the
+/// operations that grow with its size. The directory is removed afterwards.
+pub fn run(count: usize) -> Result<Vec<Timing>, AppError> {
+    let dir = tempfile::tempdir()?;
+    let db = sled::open(dir.path().join("medi_db"))?;
+    let index = search::open_index(&dir.path().join("medi_db").join("search_index"))?;
+    let notes = synthetic_notes(count);
+    let mut timings = Vec::new();
+    let mut time = |operation, notes, elapsed| {
+        timings.push(Timing {
+            operation,
+            elapsed,
+            notes,
+        })
+    };
+
+    let started = Instant::now();
+    db::save_notes_with_index(&db, &notes, &index)?;
+    time("create", Some(count), started.elapsed());
+
+    let started = Instant::now();
+    let mut listed = db::get_all_notes(&db)?;
+    listed.sort_by(|a, b| a.key.cmp(&b.key));
+    time("list", Some(listed.len()), started.elapsed());
+
+    let started = Instant::now();
+    for query in QUERIES {
+        search::search_notes(&index, query)?;
+    }
+    // The average of a handful of queries, which don't scale per note.
+    time("search", None, started.elapsed() / QUERIES.len() as u32);
+
+    let started = Instant::now();
+    search::rebuild_index(&index, &listed)?;
+    time("reindex", Some(count), started.elapsed());
+
+    let started = Instant::now();
+    export::write_markdown_export(
+        &listed,
+        &dir.path().join("export"),
+        LinkStyle::Wikilinks,
+        export::DEFAULT_FILENAME,
+        None,
+        &HashSet::new(),
+    )?;
+    time("export", Some(count), started.elapsed());
+
+    Ok(timings)
+}
+
+/// Notes of 50 to 400 words with a couple of tags and wikilinks, built from a fixed
+/// sequence so every run measures the same vault.
+fn synthetic_notes(count: usize) -> Vec<Note> {
+    let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut next = move |bound: usize| {
+        // xorshift64, good enough to vary the notes.
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        (seed % bound as u64) as usize
+    };
+    let now = Utc::now();
+    (0..count)
+        .map(|i| {
+            let words = 50 + next(350);
+            let mut content: Vec<String> = (0..words)
+                .map(|_| WORDS[next(WORDS.len())].to_string())
+                .collect();
+            content.push(format!("[[bench-{:05}]]", next(count)));
+            let mut tags = vec![TAGS[next(TAGS.len())].to_string()];
+            let second = TAGS[next(TAGS.len())].to_string();
+            if !tags.contains(&second) {
+                tags.push(second);
+            }
+            Note {
+                key: format!("bench-{:05}", i),
+                title: format!("Benchmark note {}", i),
+                tags,
+                content: content.join(" "),
+                created_at: now - Duration::minutes(i as i64),
+                modified_at: now,
+                ..Default::default()
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,80 @@
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+#[cfg(feature = "web")]
+use std::time::Duration;
+
+lazy_static! {
+    static ref ENTITY: Regex = Regex::new(r"&(amp|lt|gt|quot|nbsp|#39|#x27);").unwrap();
+}
+
+/// Namespace that bookmark keys are created under when no key is given.
+pub const KEY_PREFIX: &str = "bookmarks/";
+
+/// Fetches the page at `url` and returns its `<title>`, if it has one.
+/// Network or parse failures return `None` so the caller can fall back to the URL.
+#[cfg(feature = "web")]
+pub fn fetch_title(url: &str) -> Option<String> {
+    let client = reqwest::blocking::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .user_agent(concat!("medi/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .ok()?;
+    let body = client
+        .get(url)
+        .send()
+        .ok()?
+        .error_for_status()
+        .ok()?
+        .text()
+        .ok()?;
+
+    let re = Regex::new(r"(?is)<title[^>]*>(.*?)</title>").ok()?;
+    let title = re.captures(&body)?.get(1)?.as_str();
+    let title = decode_entities(&title.split_whitespace().collect::<Vec<_>>().join(" "));
+    (!title.is_empty()).then_some(title)
+}
+
+/// Without the `web` feature there's nothing to fetch the page with.
+#[cfg(not(feature = "web"))]
+pub fn fetch_title(_url: &str) -> Option<String> {
+    None
+}
+
+/// Builds the Markdown body stored for a bookmark.
+pub fn note_content(title: &str, url: &str, description: Option<&str>) -> String {
+    let mut content = format!("# {}\n\n<{}>\n", title, url);
+    if let Some(description) = description {
+        content.push_str(&format!("\n{}\n", description));
+    }
+    content
+}
+
+/// Decodes the handful of HTML entities that commonly appear in page titles, in a single
+/// pass so that an escaped entity such as `&amp;lt;` becomes `&lt;` rather than `<`.
+pub fn decode_entities(text: &str) -> String {
+    ENTITY
+        .replace_all(text, |caps: &Captures| match &caps[1] {
+            "amp" => "&",
+            "lt" => "<",
+            "gt" => ">",
+            "quot" => "\"",
+            "nbsp" => " ",
+            _ => "'",
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_entities_decodes_once() {
+        assert_eq!(
+            decode_entities("Tom &amp; Jerry &lt;3 &quot;cats&quot; &#39;n&#x27; mice"),
+            "Tom & Jerry <3 \"cats\" 'n' mice"
+        );
+        assert_eq!(decode_entities("&amp;lt;b&amp;gt;"), "&lt;b&gt;");
+        assert_eq!(decode_entities("AT&T &copy;"), "AT&T &copy;");
+    }
+}
@@ -0,0 +1,105 @@
+use crate::colours::{self, paint, Role};
+use crate::note::{self, Note};
+use crate::timezone;
+use chrono::{Datelike, NaiveDate};
+use colored::Colorize;
+use std::collections::BTreeMap;
+
+/// The notes associated with a single day.
+#[derive(Default)]
+pub struct DayNotes {
+    /// Journal notes for the day (`journal/YYYY-MM-DD`).
+    pub journal: Vec<String>,
+    /// Notes created on the day.
+    pub created: Vec<String>,
+    /// Notes modified, but not created, on the day.
+    pub modified: Vec<String>,
+}
+
+impl DayNotes {
+    /// All keys for the day, journal entries first, without duplicates.
+    pub fn keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = Vec::new();
+        for key in self
+            .journal
+            .iter()
+            .chain(&self.created)
+            .chain(&self.modified)
+        {
+            if !keys.contains(key) {
+                keys.push(key.clone());
+            }
+        }
+        keys
+    }
+}
+
+/// Groups notes by the local day they were created or modified on, and journal
+/// notes by the date in their key, keeping only days inside the month of `first`.
+pub fn notes_by_day(notes: &[Note], first: NaiveDate) -> BTreeMap<NaiveDate, DayNotes> {
+    let in_month = |date: &NaiveDate| date.year() == first.year() && date.month() == first.month();
+    let mut days: BTreeMap<NaiveDate, DayNotes> = BTreeMap::new();
+
+    for note in notes {
+        if let Some(date) = note::journal_date(&note.key).filter(in_month) {
+            days.entry(date).or_default().journal.push(note.key.clone());
+        }
+        let created = timezone::date(note.created_at);
+        let modified = timezone::date(note.modified_at);
+        if in_month(&created) {
+            days.entry(created)
+                .or_default()
+                .created
+                .push(note.key.clone());
+        }
+        if modified != created && in_month(&modified) {
+            days.entry(modified)
+                .or_default()
+                .modified
+                .push(note.key.clone());
+        }
+    }
+    days
+}
+
+/// Renders a Monday-first month grid. Days with a journal note are marked `*` (green),
+/// other days with activity `+` (cyan); today is underlined.
+pub fn render_month(first: NaiveDate, days: &BTreeMap<NaiveDate, DayNotes>) -> String {
+    let today = timezone::today();
+    let mut out = format!(
+        "{}\n",
+        format!("{:^28}", first.format("%B %Y").to_string()).bold()
+    );
+    out.push_str(&format!("{}\n", " Mo  Tu  We  Th  Fr  Sa  Su".dimmed()));
+
+    // Pad the first week up to the weekday the month starts on.
+    let offset = first.weekday().num_days_from_monday() as usize;
+    out.push_str(&"    ".repeat(offset));
+
+    let mut date = first;
+    while date.month() == first.month() {
+        let cell = match days.get(&date) {
+            Some(day) if !day.journal.is_empty() => {
+                colours::key(&format!("{:>3}*", date.day())).to_string()
+            }
+            Some(_) => paint(&format!("{:>3}+", date.day()), Role::Info).to_string(),
+            None => format!("{:>3} ", date.day()),
+        };
+        out.push_str(&if date == today {
+            cell.underline().to_string()
+        } else {
+            cell
+        });
+        if date.weekday().num_days_from_monday() == 6 {
+            out.push('\n');
+        }
+        match date.succ_opt() {
+            Some(next) => date = next,
+            None => break,
+        }
+    }
+    if !out.ends_with('\n') {
+        out.push('\n');
+    }
+    out
+}
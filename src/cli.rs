@@ -1,5 +1,12 @@
-use clap::{ArgGroup, Args, Parser, Subcommand, ValueEnum};
+use crate::config::LinkStyle;
+use crate::error::AppError;
+use crate::filter::NoteFilter;
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use clap::parser::ValueSource;
+use clap::{ArgGroup, Args, CommandFactory, Parser, Subcommand, ValueEnum};
 use clap_complete::Shell;
+use std::ffi::OsString;
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(
@@ -10,17 +17,146 @@ use clap_complete::Shell;
     long_about = None)]
 #[command(propagate_version = true)]
 pub struct Cli {
+    /// Path to the database to use, overriding MEDI_DB_PATH and the config file.
+    #[arg(long, global = true, value_name = "PATH")]
+    pub db: Option<PathBuf>,
+
+    /// When to colour output. `auto` colours it only on a terminal and honours NO_COLOR.
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        value_name = "WHEN",
+        default_value_t = ColorChoice::Auto
+    )]
+    pub color: ColorChoice,
+
+    /// Log what medi does, with timings, to stderr. Repeat (-vv) for more detail.
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Append the diagnostic log to this file instead of stderr (implies -v).
+    #[arg(long, global = true, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+
+    /// Never prompt or open the editor: fail with exit code 3 instead, for scripts and cron.
+    #[arg(long, global = true, action = clap::ArgAction::SetTrue)]
+    pub non_interactive: bool,
+
+    /// Show and read dates in UTC instead of the local (or configured) time zone.
+    #[arg(long, global = true, action = clap::ArgAction::SetTrue)]
+    pub utc: bool,
+
     #[command(subcommand)]
     pub command: Commands,
 }
 
+/// How several `--tag` filters combine.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TagMatch {
+    /// Notes must have every tag.
+    #[default]
+    All,
+    /// Notes need at least one of the tags.
+    Any,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default)]
+pub enum ColorChoice {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
 // Define the possible sorting options
 #[derive(ValueEnum, Clone, Debug, Default)]
 pub enum SortBy {
     #[default] // Default to sorting by key (alphabetical)
     Key,
+    /// Alphabetically by title.
+    Title,
     Created,
     Modified,
+    /// Word count of the content.
+    Words,
+    /// Size of the content in bytes.
+    Size,
+}
+
+/// What `medi count` groups notes by.
+#[derive(ValueEnum, Clone, Debug)]
+pub enum CountGroup {
+    /// Each tag (a note with several tags counts once per tag).
+    Tag,
+    /// The month the note was created.
+    Month,
+    /// The kind of note (note, bookmark).
+    Type,
+}
+
+/// The periods `medi stats growth` reports on.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum GrowthPeriod {
+    /// ISO weeks, e.g. 2025-W07.
+    Week,
+    Month,
+    Year,
+}
+
+/// Which releases `medi update` considers.
+#[derive(ValueEnum, Clone, Debug, Default)]
+pub enum UpdateChannel {
+    /// Regular releases only.
+    #[default]
+    Stable,
+    /// Also pre-releases such as `0.14.0-rc.1`.
+    Prerelease,
+}
+
+/// What `medi task list --group-by` nests tasks under.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum TaskGroup {
+    /// The note each task belongs to.
+    Note,
+    /// Each tag of the task's note (a task can appear under several tags).
+    Tag,
+}
+
+/// How `medi task list` prints tasks.
+#[derive(ValueEnum, Clone, Copy, Debug, Default)]
+pub enum TaskOutput {
+    /// Human-readable lines with status, due date and resolution.
+    #[default]
+    Plain,
+    /// The tasks as a JSON array, or an object of arrays with `--group-by`.
+    Json,
+    /// A table with ID, status, due date, note and description columns.
+    Table,
+    /// A `- [ ]` checklist to paste into a note or pull request.
+    Markdown,
+}
+
+/// How `medi list` prints notes.
+#[derive(ValueEnum, Clone, Debug, Default)]
+pub enum ListOutput {
+    /// Keys and tags, one note per line.
+    #[default]
+    List,
+    /// A table with word count, reading time and age columns.
+    Table,
+    /// One JSON note object per line (newline-delimited JSON).
+    Ndjson,
+}
+
+/// How `medi get` and `medi search` print notes.
+#[derive(ValueEnum, Clone, Debug, Default)]
+pub enum NoteOutput {
+    /// Human-readable output.
+    #[default]
+    Plain,
+    /// One JSON note object per line (newline-delimited JSON).
+    Ndjson,
 }
 
 #[derive(Args, Debug)]
@@ -29,27 +165,93 @@ pub enum SortBy {
         .required(true)
 ))]
 pub struct ImportArgs {
+    /// Read newline-delimited JSON note objects from a file, or from stdin with `-`.
+    #[arg(long, group = "input_source", value_name = "FILE")]
+    pub ndjson: Option<PathBuf>,
+
     /// The path to the directory containing .md files.
     #[arg(long, group = "input_source")]
     pub dir: Option<String>,
 
-    /// The path to a single .md file to import. (Requires --key)
+    /// The path to a single file to import: Markdown, a PDF whose text is extracted page by
+    /// page, or a .docx/.odt document converted with pandoc. (Requires --key)
     #[arg(long, group = "input_source", requires = "key")]
     pub file: Option<String>,
 
-    /// The key to use for the single file import.
+    /// Read a single Markdown note from stdin, with its frontmatter. (Requires --key)
+    #[arg(long, group = "input_source", requires = "key", action = clap::ArgAction::SetTrue)]
+    pub stdin: bool,
+
+    /// Import a Day One JSON export (.zip) as journal notes, with photos as attachments.
+    #[arg(long, group = "input_source", value_name = "ZIP")]
+    pub dayone: Option<PathBuf>,
+
+    /// Import a Google Takeout `Keep` folder, turning checklists into tasks.
+    #[arg(long, group = "input_source", value_name = "DIR")]
+    pub keep: Option<PathBuf>,
+
+    /// Restore notes, with their tasks and reminders, from an `export --format raw-dump`
+    /// directory.
+    #[arg(long, group = "input_source", value_name = "DIR")]
+    pub raw_dump: Option<PathBuf>,
+
+    /// Import a Joplin export (.jex), with notebooks as key prefixes.
+    #[arg(long, group = "input_source", value_name = "JEX")]
+    pub joplin: Option<PathBuf>,
+
+    /// Skip files in the directory matching this glob, e.g. `README.md` or `draft-*`.
+    /// Adds to the patterns in the directory's `.mediignore`. Can be repeated.
+    #[arg(long, requires = "dir", value_name = "GLOB")]
+    pub exclude: Vec<String>,
+
+    /// The key to use for the single file or stdin import.
     #[arg(long)]
     pub key: Option<String>,
 
     /// Overwrite an existing note with the same key.
     #[arg(long, action = clap::ArgAction::SetTrue)]
     pub overwrite: bool,
+
+    /// Use the file's modification (and, where available, creation) time for the note.
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub preserve_times: bool,
+    /// Normalise keys derived from file names to lowercase-dash slugs.
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub slug: bool,
+
+    /// Turn `- [ ]` and `- [x]` checklist items into tasks on the imported note. Keep
+    /// checklists always become tasks.
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub extract_tasks: bool,
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+pub enum QueryOutput {
+    /// One row per note with key, title, tags and modification date.
+    Table,
+    /// Only the matching keys, one per line.
+    Keys,
+    /// The matching notes as a JSON array.
+    Json,
 }
 
 #[derive(ValueEnum, Clone, Debug)]
 pub enum ExportFormat {
     Markdown,
     Json,
+    /// All notes concatenated into a single Markdown document.
+    Combined,
+    /// All notes concatenated into a single HTML document.
+    Html,
+    /// An EPUB e-book with one chapter per note.
+    Epub,
+    /// Flashcards from notes tagged `flashcard`, as a file for Anki's "Import File".
+    Anki,
+    /// One TextBundle package per note, with linked local files copied into its assets.
+    Textbundle,
+    /// One JSON file per note with every field, its tasks and reminders, plus a manifest:
+    /// a lossless backup that `import --raw-dump` restores.
+    RawDump,
 }
 
 #[derive(Args, Debug)]
@@ -61,9 +263,217 @@ pub struct ExportArgs {
     #[arg(long, value_enum, default_value_t = ExportFormat::Markdown)]
     pub format: ExportFormat,
 
-    /// Export only notes with a specific tag.
+    #[command(flatten)]
+    pub filter: NoteFilter,
+
+    /// Export only the note(s) with the given key(s).
     #[arg(long, short)]
-    pub tag: Vec<String>,
+    pub key: Vec<String>,
+
+    /// Order of notes in single-document formats. Defaults to the order of --key, or by key.
+    #[arg(long, short, value_enum)]
+    pub sort_by: Option<SortBy>,
+
+    /// Document title for the combined, html and epub formats.
+    #[arg(long)]
+    pub title: Option<String>,
+
+    /// Append a "Linked from" section listing the exported notes that link to each note.
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub backlinks: bool,
+
+    /// How the Markdown format writes [[key]] links. Defaults to `export_links` in config.toml.
+    #[arg(long, value_enum)]
+    pub links: Option<LinkStyle>,
+
+    /// With `--links markdown`, fail without writing anything if a [[key]] link points at a
+    /// note that is missing or not part of the export.
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub strict: bool,
+
+    /// Markdown only: write just the notes that changed since the last export to this
+    /// directory, and delete the files of notes that have been deleted since.
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub changed: bool,
+
+    /// Append each note's tasks as a "## Tasks" checklist. JSON exports always carry the
+    /// tasks in a `tasks` array instead.
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub tasks: bool,
+}
+
+/// Parses a date given on the command line, either as a full RFC 3339 timestamp or as a
+/// day such as `YYYY-MM-DD` or `last monday` (interpreted as midnight UTC).
+pub fn parse_date(input: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(input) {
+        return Ok(datetime.with_timezone(&Utc));
+    }
+    parse_day(input).map(|date| date.and_time(NaiveTime::MIN).and_utc())
+}
+
+/// Parses a duration such as `30m`, `12h`, `7d` or `2w`.
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let invalid = || {
+        format!(
+            "invalid duration '{}', expected e.g. 30m, 12h, 7d or 2w",
+            input
+        )
+    };
+    let split = input
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(invalid)?;
+    let (amount, unit) = input.split_at(split);
+    let amount: i64 = amount.parse().map_err(|_| invalid())?;
+    match unit {
+        "m" => Ok(Duration::minutes(amount)),
+        "h" => Ok(Duration::hours(amount)),
+        "d" => Ok(Duration::days(amount)),
+        "w" => Ok(Duration::weeks(amount)),
+        _ => Err(invalid()),
+    }
+}
+
+/// Parses a point in time such as `next tuesday 09:00`, `tomorrow` or `in 2h`,
+/// interpreted in the configured time zone.
+pub fn parse_when(input: &str) -> Result<DateTime<Utc>, String> {
+    crate::timezone::parse_when(input)
+}
+
+/// Parses a day such as `2025-07-01`, `tomorrow` or `next friday` into a date in the
+/// configured time zone.
+pub fn parse_day(input: &str) -> Result<NaiveDate, String> {
+    crate::dates::parse_day(input, crate::timezone::today())
+}
+
+/// Parses either a duration from now, such as `3d`, or a point in time such as `next monday`.
+pub fn parse_until(input: &str) -> Result<DateTime<Utc>, String> {
+    match parse_duration(input) {
+        Ok(duration) => Ok(Utc::now() + duration),
+        Err(_) => parse_when(input),
+    }
+}
+
+/// Parses a `YYYY-MM` month given on the command line into its first day.
+pub fn parse_month(input: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(&format!("{}-01", input), "%Y-%m-%d")
+        .map_err(|_| format!("invalid month '{}', expected YYYY-MM", input))
+}
+
+/// Parses `args` again with the `[defaults]` from the config added for the options the
+/// command line leaves unset, e.g. `list.sort_by = "modified"` becomes `--sort-by modified`
+/// for `medi list`. Nested commands use nested tables, like `task.list.output`.
+pub fn parse_with_defaults(args: Vec<OsString>, defaults: &toml::Table) -> Result<Cli, AppError> {
+    let invalid = |e: String| AppError::ConfigError(format!("in [defaults]: {}", e));
+    let command = Cli::command();
+    let matches = command
+        .clone()
+        .try_get_matches_from(&args)
+        .map_err(|e| invalid(e.to_string()))?;
+
+    // Find the table for the command being run.
+    let mut command = &command;
+    let mut matches = &matches;
+    let mut table = Some(defaults);
+    let mut path = Vec::new();
+    while let Some((name, sub_matches)) = matches.subcommand() {
+        command = command
+            .find_subcommand(name)
+            .expect("clap matched a known subcommand");
+        matches = sub_matches;
+        table = table.and_then(|table| table.get(name)?.as_table());
+        path.push(name);
+    }
+    let Some(table) = table else {
+        return Cli::try_parse_from(args).map_err(|e| invalid(e.to_string()));
+    };
+
+    let mut extra: Vec<OsString> = Vec::new();
+    for (name, value) in table.iter().filter(|(_, value)| !value.is_table()) {
+        let arg = find_option(command, name)
+            .map_err(|e| invalid(format!("'{}.{}' {}", path.join("."), name, e)))?;
+        if matches!(
+            matches.value_source(arg.get_id().as_str()),
+            Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+        ) {
+            continue;
+        }
+        let flag = format!("--{}", arg.get_long().unwrap_or_default());
+        let values = match value {
+            toml::Value::Array(values) => values.clone(),
+            value => vec![value.clone()],
+        };
+        for value in values {
+            match value {
+                toml::Value::Boolean(set) if !arg.get_action().takes_values() => {
+                    if set {
+                        extra.push(flag.clone().into());
+                    }
+                }
+                toml::Value::String(text) => extra.extend([flag.clone().into(), text.into()]),
+                value => extra.extend([flag.clone().into(), value.to_string().into()]),
+            }
+        }
+    }
+
+    // Options go before a `--`, after which everything is positional.
+    let mut args = args;
+    let at = args
+        .iter()
+        .position(|arg| arg == "--")
+        .unwrap_or(args.len());
+    args.splice(at..at, extra);
+    Cli::try_parse_from(args).map_err(|e| invalid(e.to_string()))
+}
+
+/// Describes the `[defaults]` entries that don't name a command or one of its options.
+pub fn defaults_problems(defaults: &toml::Table) -> Vec<String> {
+    fn check(
+        command: &clap::Command,
+        table: &toml::Table,
+        prefix: &str,
+        problems: &mut Vec<String>,
+    ) {
+        for (name, value) in table {
+            let path = format!("{}{}", prefix, name);
+            match value.as_table() {
+                Some(table) => match command.find_subcommand(name) {
+                    Some(sub) => check(sub, table, &format!("{}.", path), problems),
+                    None => problems.push(format!("defaults.{} is not a medi command", path)),
+                },
+                None => {
+                    if let Err(e) = find_option(command, name) {
+                        problems.push(format!("defaults.{} {}", path, e));
+                    }
+                }
+            }
+        }
+    }
+    let mut problems = Vec::new();
+    check(&Cli::command(), defaults, "", &mut problems);
+    problems
+}
+
+/// Finds the option of `command` a `[defaults]` entry refers to, by its name with
+/// underscores (`sort_by`) or its long flag (`sort-by`), aliases included.
+fn find_option<'a>(command: &'a clap::Command, name: &str) -> Result<&'a clap::Arg, String> {
+    let long = name.replace('_', "-");
+    let arg = command
+        .get_arguments()
+        .find(|arg| {
+            arg.get_id() == name
+                || arg.get_long() == Some(long.as_str())
+                || arg
+                    .get_all_aliases()
+                    .is_some_and(|aliases| aliases.contains(&long.as_str()))
+        })
+        .ok_or_else(|| format!("is not an option of 'medi {}'", command.get_name()))?;
+    if arg.get_long().is_none() {
+        return Err(format!(
+            "is an argument of 'medi {}'; only options can have defaults",
+            command.get_name()
+        ));
+    }
+    Ok(arg)
 }
 
 #[derive(Subcommand, Clone, Debug)]
@@ -74,13 +484,37 @@ pub enum TaskCommands {
         note_key: String,
         /// The description of the task.
         description: String,
+        /// When the task is due, e.g. "friday 17:00", "in 3 days" or 2025-07-01.
+        #[arg(long, value_parser = parse_when)]
+        due: Option<DateTime<Utc>>,
     },
     /// List all open tasks.
-    List,
+    List {
+        /// Include completed and snoozed tasks, with their resolution notes.
+        #[arg(long, short, action = clap::ArgAction::SetTrue)]
+        all: bool,
+        /// Nest tasks under their note, or under each of their note's tags.
+        #[arg(long, value_enum)]
+        group_by: Option<TaskGroup>,
+        /// How to print the tasks.
+        #[arg(long, short, value_enum, default_value_t = TaskOutput::Plain)]
+        output: TaskOutput,
+    },
     /// Mark a task as done.
     Done {
         /// The ID of the task to complete.
         task_id: u64,
+        /// A note on how the task was resolved, e.g. "shipped in v1.2".
+        #[arg(long, short)]
+        message: Option<String>,
+    },
+    /// Hide a task from `task list` for a while.
+    Snooze {
+        /// The ID of the task to snooze.
+        task_id: u64,
+        /// How long to snooze for (e.g. 3d, 12h) or until when (e.g. "next monday", now).
+        #[arg(value_parser = parse_until)]
+        until: DateTime<Utc>,
     },
     /// Prioritise a task.
     Prio {
@@ -98,6 +532,115 @@ pub enum TaskCommands {
         #[arg(long, short, action = clap::ArgAction::SetTrue)]
         force: bool,
     },
+    /// Write all tasks, done ones included, to a JSON file.
+    Export {
+        /// The file to write, or `-` for stdout.
+        #[arg(long, value_name = "FILE")]
+        json: PathBuf,
+    },
+    /// Add the tasks from a JSON file written by `task export`.
+    Import {
+        /// The file to read, or `-` for stdin.
+        file: PathBuf,
+    },
+}
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum RemindCommands {
+    /// Set a reminder to resurface a note.
+    Set {
+        /// The key of the note to be reminded about.
+        key: String,
+        /// When the reminder is due, e.g. "next tuesday 09:00", "tomorrow" or "in 2h".
+        #[arg(value_parser = parse_when)]
+        when: DateTime<Utc>,
+        /// A message to show with the reminder.
+        #[arg(long, short)]
+        message: Option<String>,
+    },
+    /// Show reminders that are due.
+    Due,
+    /// List all reminders, soonest first.
+    List,
+    /// Delete (dismiss) a reminder.
+    Delete {
+        /// The ID of the reminder to delete.
+        reminder_id: u64,
+    },
+}
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum TagCommands {
+    /// Add (or remove) a tag on every matching note.
+    Apply {
+        /// The tag to add or remove.
+        #[arg(value_name = "TAG")]
+        name: String,
+        /// Select notes with a full-text search query.
+        #[arg(long, required_unless_present_any = ["keys_from_stdin", "NoteFilter"])]
+        matching: Option<String>,
+        /// Read note keys from stdin, one per line.
+        #[arg(long, conflicts_with = "matching", action = clap::ArgAction::SetTrue)]
+        keys_from_stdin: bool,
+        /// Select the notes passing these filters, or narrow down the other selections.
+        #[command(flatten)]
+        filter: NoteFilter,
+        /// Remove the tag instead of adding it.
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        remove: bool,
+        /// Change locked notes too.
+        #[arg(long, short, action = clap::ArgAction::SetTrue)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum BookmarkCommands {
+    /// Save a URL as a bookmark note, fetching the page title.
+    Add {
+        /// The URL to bookmark.
+        url: String,
+        /// The key for the note. Defaults to `bookmarks/<slugified title>`.
+        #[arg(long, short)]
+        key: Option<String>,
+        /// The title to use instead of fetching it from the page.
+        #[arg(long)]
+        title: Option<String>,
+        /// A short description of the link.
+        #[arg(long, short)]
+        description: Option<String>,
+        /// Add one or more tags to the bookmark.
+        #[arg(long, short)]
+        tag: Vec<String>,
+    },
+    /// List all bookmarks.
+    List,
+    /// Open a bookmark in the default browser.
+    Open {
+        /// The key of the bookmark to open.
+        key: String,
+    },
+}
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum StatsCommands {
+    /// Show note count, words, open tasks and newest/oldest note for each tag.
+    Tags,
+    /// Show notes created, words written and tasks completed per week, month or year.
+    Growth {
+        /// The period to group by.
+        #[arg(long, value_enum, default_value_t = GrowthPeriod::Month)]
+        by: GrowthPeriod,
+        /// Draw a sparkline per figure instead of the table.
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        sparkline: bool,
+    },
+}
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum ConfigCommands {
+    /// Check config.toml for syntax errors, unknown settings and invalid values.
+    Validate,
 }
 
 #[derive(Subcommand)]
@@ -113,15 +656,32 @@ pub enum Commands {
     # With tags: Add tags to your note for better organization.\n  \
     medi new \"my-long-article\" --tag tag1 --tag tag2\n\n  \
     # With a title: Specify a title for your note.\n  \
-    medi new \"my-long-article\" --title \"My Long Article\"\n")]
+    medi new \"my-long-article\" --title \"My Long Article\"\n\n  \
+    # Guided: Prompts for key, title, tags and template.\n  \
+    medi new --interactive\n\n  \
+    # Slugified: Creates the key 'meeting-notes' titled 'Meeting Notes'.\n  \
+    medi new \"Meeting Notes\" --slug\n\n  \
+    # Zettelkasten: Creates a key like '202405181530-atomic-notes'.\n  \
+    medi new \"Atomic notes\" --zk -m \"One idea per note.\"\n\n  \
+    # Self-destructing: Hidden after a week and purged by 'medi maintain'.\n  \
+    medi new tmp-token -m \"abc123\" --expires 7d\n\n  \
+    # From a web page or a file: Seeds the note with its converted content.\n  \
+    medi new rust-book --from https://doc.rust-lang.org/book/ --tag reading\n\n  \
+    # Scripted: Derives the key from the title or first heading and prints it.\n  \
+    key=$(curl -s https://example.com/notes.md | medi new --auto)\n")]
     New {
         /// The key (or title) for the new note.
-        key: String,
+        #[arg(required_unless_present_any = ["interactive", "auto"])]
+        key: Option<String>,
         /// Provide the note content directly as an argument.
         #[arg(short, long)]
         message: Option<String>,
+        /// Seed the note with the readable text of a web page (URL) or a local file,
+        /// converted as `import` does.
+        #[arg(long, value_name = "URL_OR_PATH", conflicts_with_all = ["message", "template"])]
+        from: Option<String>,
         /// Add one or more tags to the note.
-        #[arg(short = 'T', long)]
+        #[arg(short = 'T', long, alias = "tags")]
         tag: Vec<String>,
         /// Specify a title for the note.
         #[arg(short, long)]
@@ -129,6 +689,23 @@ pub enum Commands {
         ///Create a note from a template
         #[arg(long)]
         template: Option<String>,
+        /// Prompt for the key, title, tags and template before opening the editor.
+        #[arg(long, short, action = clap::ArgAction::SetTrue)]
+        interactive: bool,
+        /// Normalise the key to a lowercase-dash slug (the original becomes the title).
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        slug: bool,
+        /// Derive the key from --title, the frontmatter title or the first heading of the
+        /// content, adding `-2`, `-3`, ... if it's taken, and print it on stdout.
+        #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with_all = ["key", "interactive"])]
+        auto: bool,
+        /// Generate a timestamped Zettelkasten key from the title, e.g. `202405181530-title-slug`.
+        #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with = "slug")]
+        zk: bool,
+        /// Expire the note after a duration (e.g. 12h, 7d, 2w); it is then hidden and
+        /// purged by `medi maintain`.
+        #[arg(long, value_parser = parse_duration, value_name = "DURATION")]
+        expires: Option<Duration>,
     },
     /// Edit an existing note with the specified key.
     #[command(after_help = "EXAMPLE:\n  \
@@ -137,16 +714,40 @@ pub enum Commands {
     # Add tags to a note: Adds one or more tags to the note.\n  \
     medi edit \"my-long-article\" --add-tag tag1 --add-tag tag2\n\n  \
     # Remove tags from a note: Removes one or more tags from the note.\n  \
-    medi edit \"my-long-article\" --rm-tag tag1 --rm-tag tag2\n")]
+    medi edit \"my-long-article\" --rm-tag tag1 --rm-tag tag2\n\n  \
+    # Create the note if it doesn't exist yet, starting from a template.\n  \
+    medi edit \"meeting-notes\" --create --template meeting\n\n  \
+    # Edit every note tagged project-x in one editor session:\n  \
+    medi edit --bulk --tag project-x\n")]
     Edit {
-        /// The key of the note to edit.
-        key: String,
+        /// The key of the note to edit. A title works too when no key matches.
+        #[arg(required_unless_present = "bulk", conflicts_with = "bulk")]
+        key: Option<String>,
+        /// Open all notes with the given --tag as files in one directory, in one editor
+        /// session, and save the ones that changed.
+        #[arg(long, requires = "tag", action = clap::ArgAction::SetTrue)]
+        bulk: bool,
+        /// The tag of the notes to edit with --bulk.
+        #[arg(long, requires = "bulk")]
+        tag: Vec<String>,
+        /// With several --tag: match notes with all of them (default) or any of them.
+        #[arg(long = "match", value_enum, default_value_t = TagMatch::All, value_name = "MODE")]
+        tag_match: TagMatch,
+        /// Create the note if it doesn't exist, instead of failing.
+        #[arg(long, short = 'c')]
+        create: bool,
+        /// The template to start from when the note is created.
+        #[arg(long, short = 't')]
+        template: Option<String>,
         /// Add one or more tags to the note.
         #[arg(long, short = 'a')]
         add_tag: Vec<String>,
         /// Remove one or more tags from the note.
         #[arg(long, short = 'r')]
         rm_tag: Vec<String>,
+        /// Edit the note even if it's locked.
+        #[arg(long, short, action = clap::ArgAction::SetTrue)]
+        force: bool,
     },
     /// Get the content of a note with the specified key.
     #[command(after_help = "EXAMPLE:\n  \
@@ -163,19 +764,53 @@ pub enum Commands {
     # Use --json to output the note in JSON format:\n  \
     medi get \"my-long-article\" --json\n\n  \
     # Use --tag to retrieve all notes with a specific tag:\n  \
-    medi get --tag my-tag\n")]
+    medi get --tag my-tag\n\n  \
+    # Use --meta to print only the metadata (combine with --json for scripts):\n  \
+    medi get \"my-long-article\" --meta --json\n\n  \
+    # Label each note with a '# key (title)' header and write them all to one file:\n  \
+    medi get --tag my-tag --header --out my-tag.md\n\n  \
+    # Render in the terminal with highlighted code blocks (pipe to `less -R` to page):\n  \
+    medi get \"my-long-article\" --render\n\n  \
+    # Include the note's tasks as a checklist at the end:\n  \
+    medi get \"my-long-article\" --render --tasks\n\n  \
+    # Stream notes as newline-delimited JSON:\n  \
+    medi get --tag my-tag --output ndjson | jq -c '{key, tags}'\n")]
     Get {
-        /// The key(s) of the note(s) to retrieve.
-        #[arg(required_unless_present("tag"))]
+        /// The key(s) of the note(s) to retrieve. A title works too when no key matches.
+        #[arg(required_unless_present = "NoteFilter", conflicts_with = "NoteFilter")]
         keys: Vec<String>,
 
-        /// Retrieve all notes with a specific tag.
-        #[arg(long, short, conflicts_with = "keys")]
-        tag: Vec<String>,
+        /// Retrieve all notes passing these filters instead.
+        #[command(flatten)]
+        filter: NoteFilter,
 
         /// Output the full note data as JSON.
         #[arg(long, action = clap::ArgAction::SetTrue)]
         json: bool,
+
+        /// Print only metadata (title, tags, timestamps, word count, links), not the content.
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        meta: bool,
+
+        /// Print a `# key (title)` header before each note instead of a `---` separator.
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        header: bool,
+
+        /// Write the output to a file instead of stdout.
+        #[arg(long, short, value_name = "FILE")]
+        out: Option<PathBuf>,
+
+        /// Render the Markdown for the terminal, with syntax-highlighted code blocks.
+        #[arg(long, short, action = clap::ArgAction::SetTrue, conflicts_with_all = ["json", "meta"])]
+        render: bool,
+
+        /// Append the note's tasks as a `## Tasks` checklist.
+        #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with_all = ["json", "meta", "output"])]
+        tasks: bool,
+
+        /// Output format; `ndjson` prints one JSON object per line (metadata only with --meta).
+        #[arg(long, value_enum, default_value_t = NoteOutput::Plain, conflicts_with_all = ["json", "header", "render"])]
+        output: NoteOutput,
     },
     /// List all notes.
     #[command(after_help = "EXAMPLE:\n  \
@@ -185,11 +820,29 @@ pub enum Commands {
     # You can also pipe the output to other commands for further processing.\n  \
     medi list | grep -o \"my-article\" | xargs medi get\n\n  \
     # Use --sort-by to sort the notes by key, created date, or modified date\n \
-    medi list --sort-by key")]
+    medi list --sort-by key\n\n  \
+    # Show word count, reading time and age, largest notes first:\n  \
+    medi list --output table --sort-by words\n\n  \
+    # Treat '/' in keys as folders: filter by a prefix or show an indented tree.\n  \
+    medi list --prefix work/\n  \
+    medi list --tree\n\n  \
+    # One JSON object per note and line, for streaming into other tools:\n  \
+    medi list --output ndjson")]
     List {
         /// The field to sort the notes by.
         #[arg(long, short, value_enum, default_value_t = SortBy::Key)]
         sort_by: SortBy,
+        /// How to print the notes.
+        #[arg(long, short, value_enum, default_value_t = ListOutput::List)]
+        output: ListOutput,
+        /// Only list notes whose key starts with this prefix (e.g. `work/`).
+        #[arg(long, short)]
+        prefix: Option<String>,
+        /// Render keys as an indented tree, using `/` as the separator.
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        tree: bool,
+        #[command(flatten)]
+        filter: NoteFilter,
     },
     /// Find all notes that link to the given note key.
     ///
@@ -207,32 +860,80 @@ pub enum Commands {
     medi new rust -m \"A systems programming language.\"\n\n  \
     # Find backlinks: Lists all notes that link to the specified note key.\n  \
     medi backlinks rust\n\n  \
+    # The links going out of a note instead, as JSON for editor plugins:\n  \
+    medi backlinks medi-project --outgoing --json\n\n  \
     # Use this command to discover relationships between your notes and see which notes reference a particular note.")]
     Backlinks {
         /// The key of the note to find links for.
         key: String,
+
+        /// List the links going out of the note instead of those pointing at it.
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        outgoing: bool,
+
+        /// Print the links as a JSON array, each with its source and target keys, its byte
+        /// offsets in the source note, its line and that line as context.
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        json: bool,
     },
-    /// Delete a note with the specified key.
+    /// Delete one or more notes by key.
     #[command(after_help = "EXAMPLE:\n  \
     # Delete a note: Removes the note with the specified key.\n  \
     medi delete \"my-long-article\"\n\n  \
+    # Delete several notes after a single confirmation.\n  \
+    medi delete draft-1 draft-2 draft-3\n\n  \
     # Use --force to skip confirmation.\n  \
     medi delete \"my-long-article\" --force\n\n  \
     # Note: Use this command with caution, as it will permanently delete the note.")]
     Delete {
-        /// The key of the note to delete.
-        key: String,
-        /// Skip the confirmation prompt.
+        /// The key(s) of the note(s) to delete.
+        #[arg(required_unless_present = "NoteFilter", conflicts_with = "NoteFilter")]
+        keys: Vec<String>,
+        /// Delete all notes passing these filters instead.
+        #[command(flatten)]
+        filter: NoteFilter,
+        /// Skip the confirmation prompt, and delete locked notes too.
         #[arg(long, short, action = clap::ArgAction::SetTrue)]
         force: bool,
     },
+    /// Protect a note from being edited or deleted by accident.
+    #[command(after_help = "EXAMPLE:\n  \
+    # Lock a reference note: edit, delete and bulk changes leave it alone without --force.\n  \
+    medi lock rust-cheatsheet\n\n  \
+    # Make it editable again.\n  \
+    medi unlock rust-cheatsheet")]
+    Lock {
+        /// The key of the note to lock.
+        key: String,
+    },
+    /// Make a locked note editable again.
+    Unlock {
+        /// The key of the note to unlock.
+        key: String,
+    },
     /// Search for notes by content, title, or tags.
     #[command(after_help = "EXAMPLE:\n  \
     # Search for notes containing a specific term: Finds notes with 'meeting' in the content.\n  \
-    medi search meeting")]
+    medi search meeting\n\n  \
+    # Stream the matching notes as newline-delimited JSON:\n  \
+    medi search meeting --output ndjson\n\n  \
+    # List the matching notes tagged `pinned` before the rest:\n  \
+    medi search meeting --pinned-first\n\n  \
+    # Find notes about a topic however they phrase it (requires the `llm` feature):\n  \
+    medi search \"keeping the team motivated\" --semantic")]
     Search {
         /// The search query string.
         query: String,
+        /// Output format; `ndjson` prints each matching note as a JSON object per line.
+        #[arg(long, short, value_enum, default_value_t = NoteOutput::Plain)]
+        output: NoteOutput,
+        /// Rank notes by meaning rather than keywords, using the embedding model in the
+        /// config (requires the `llm` feature).
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        semantic: bool,
+        /// List matching notes tagged `pinned` first, each group in order of relevance.
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        pinned_first: bool,
     },
     /// Reindex the search index.
     #[command(after_help = "EXAMPLE:\n  \
@@ -240,31 +941,81 @@ pub enum Commands {
     medi reindex\n\n  \
     # Use this command if you suspect the search index is out of sync with the notes.")]
     Reindex,
-    /// Interactively find and edit a note.
+    /// Interactively find and edit a note, starting from pinned and recent notes.
     #[command(after_help = "EXAMPLE:\n  \
     # Find and edit a note: Opens an interactive prompt to search and edit notes.\n  \
     medi find\n\n  \
-    # Use this command to quickly locate and modify notes without needing to remember their keys.")]
-    Find,
+    # Use this command to quickly locate and modify notes without needing to remember their keys.\n  \
+    # Notes tagged `pinned` are listed first, then the most recently modified.\n\n  \
+    # Select several notes (TAB to mark) and apply an action (add tag, delete, export) to all of them.\n  \
+    medi find --multi")]
+    Find {
+        /// Select multiple notes and apply a batch action to them.
+        #[arg(long, short, action = clap::ArgAction::SetTrue)]
+        multi: bool,
+    },
     /// Import notes from a directory or a single file.
     #[command(after_help = "EXAMPLE:\n  \
     # Import from a directory: Imports all .md files from the specified directory.\n  \
     medi import --dir /path/to/notes\n\n  \
     # Import a single file: Imports a single .md file with an mandatory key.\n  \
     medi import --file /path/to/note.md --key my-note\n\n  \
+    # Import the text of a PDF, with a horizontal rule between pages.\n  \
+    medi import --file /path/to/paper.pdf --key paper\n\n  \
+    # Convert a Word or LibreOffice document with pandoc.\n  \
+    medi import --file /path/to/minutes.docx --key minutes\n\n  \
+    # Import a Day One JSON export as journal notes.\n  \
+    medi import --dayone ~/Downloads/Export.zip\n\n  \
+    # Import Google Keep notes from an extracted Takeout archive.\n  \
+    medi import --keep ~/Downloads/Takeout/Keep\n\n  \
+    # Import a Joplin export; notebooks become key prefixes.\n  \
+    medi import --joplin ~/Downloads/notes.jex\n\n  \
     # Use --overwrite to replace an existing note with the same key.\n  \
-    medi import --file /path/to/note.md --key my-note --overwrite")]
+    medi import --file /path/to/note.md --key my-note --overwrite\n\n  \
+    # Use --preserve-times to keep the files' timestamps instead of stamping them with now.\n  \
+    medi import --dir /path/to/notes --preserve-times\n\n  \
+    # Stream notes between vaults as newline-delimited JSON:\n  \
+    medi --db ./work list --output ndjson | medi import --ndjson -")]
     Import(ImportArgs),
     /// Export notes to a file.
+    #[command(after_help = "EXAMPLE:\n  \
+    # Export all notes as Markdown files:\n  \
+    medi export ./my-notes\n\n  \
+    # Export only specific notes:\n  \
+    medi export ./published --key about --key now\n\n  \
+    # Export only notes changed since a date:\n  \
+    medi export ./my-notes --since 2025-06-01\n\n  \
+    # Export a tag as one document, or as an EPUB for an e-reader:\n  \
+    medi export rust.md --format combined --tag rust --sort-by created\n  \
+    medi export rust.epub --format epub --tag rust --title \"Rust notes\"\n\n  \
+    # Export Q:/A: flashcards from notes tagged 'flashcard' for Anki:\n  \
+    medi export study.tsv --format anki\n\n  \
+    # Write [[key]] links as [title](key.md) links for other Markdown tools:\n  \
+    medi export ./site --links markdown\n\n  \
+    # Export TextBundles, with linked images copied into each bundle:\n  \
+    medi export ./bundles --format textbundle --tag diagrams\n\n  \
+    # Publish a digital garden with visible backlinks:\n  \
+    medi export ./garden --tag garden --backlinks")]
     Export(ExportArgs),
     /// Manage tasks linked to notes.
     #[command(after_help = "EXAMPLE:\n  \
     # Add a new task linked to a note:\n  \
     medi task add my-note \"Finish writing the introduction\"\n\n  \
+    # Add a task with a due date:\n  \
+    medi task add my-note \"Send the draft\" --due \"next friday 14:00\"\n\n  \
     # List all open tasks:\n  \
     medi task list\n\n  \
-    # Mark a task as done:\n  \
-    medi task done 1\n \n  \
+    # Include completed tasks and their resolutions:\n  \
+    medi task list --all\n\n  \
+    # List tasks grouped by note, or by the note's tags:\n  \
+    medi task list --group-by note\n  \
+    medi task list --group-by tag\n\n  \
+    # Mark a task as done, optionally saying how it was resolved:\n  \
+    medi task done 1\n  \
+    medi task done 2 -m \"shipped in v1.2\"\n\n  \
+    # Hide a task until it's actionable again:\n  \
+    medi task snooze 42 3d\n  \
+    medi task snooze 42 \"next monday\"\n\n  \
     # Prioritise a task:\n  \
     medi task prio 42\n\n  \
     # Delete a task:\n  \
@@ -275,14 +1026,424 @@ pub enum Commands {
         #[command(subcommand)]
         command: TaskCommands,
     },
+    /// Set reminders that resurface notes at a given time.
+    #[command(after_help = "EXAMPLE:\n  \
+    # Resurface a note next Tuesday morning:\n  \
+    medi remind set my-note \"next tuesday 09:00\" -m \"follow up\"\n\n  \
+    # Other ways to say when:\n  \
+    medi remind set my-note tomorrow\n  \
+    medi remind set my-note \"in 2h\"\n  \
+    medi remind set my-note \"2025-09-01 14:30\"\n\n  \
+    # Show the reminders that are due:\n  \
+    medi remind due\n\n  \
+    # List all reminders and dismiss one:\n  \
+    medi remind list\n  \
+    medi remind delete 3")]
+    Remind {
+        #[command(subcommand)]
+        command: RemindCommands,
+    },
+    /// Find and replace text across all notes.
+    #[command(after_help = "EXAMPLE:\n  \
+    # Preview a rename without writing anything:\n  \
+    medi replace \"Project X\" \"Project Phoenix\" --dry-run\n\n  \
+    # Replace with a regular expression, only in notes tagged 'work':\n  \
+    medi replace 'https?://old\\.example\\.com' 'https://new.example.com' --regex --tag work")]
+    Replace {
+        /// The text (or, with --regex, the regular expression) to search for.
+        pattern: String,
+        /// The replacement text. With --regex, `$1` etc. refer to capture groups.
+        replacement: String,
+        /// Treat the pattern as a regular expression.
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        regex: bool,
+        /// Only touch the notes passing these filters.
+        #[command(flatten)]
+        filter: NoteFilter,
+        /// Show the changes without writing them.
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        dry_run: bool,
+        /// Skip the confirmation prompt, and change locked notes too.
+        #[arg(long, short, action = clap::ArgAction::SetTrue)]
+        force: bool,
+    },
+    /// Manage tags across many notes at once.
+    #[command(after_help = "EXAMPLE:\n  \
+    # Tag every note that mentions tokio:\n  \
+    medi tag apply async --matching tokio\n\n  \
+    # Remove a tag from notes listed on stdin:\n  \
+    medi list | grep draft | medi tag apply draft --remove --keys-from-stdin")]
+    Tag {
+        #[command(subcommand)]
+        command: TagCommands,
+    },
+    /// Find notes with a query combining metadata filters and full-text search.
+    #[command(after_help = "QUERY SYNTAX:\n  \
+    tag:rust               notes tagged 'rust'\n  \
+    key:work/*             keys starting with 'work/'\n  \
+    title:async            title contains 'async'\n  \
+    content:\"tokio runtime\" content contains the phrase\n  \
+    modified:>2024-06      modified after June 2024 (also created:, <, >=, <=)\n  \
+    tokio                  full-text search\n  \
+  Combine terms with AND, OR, NOT and parentheses; adjacent terms are ANDed.\n\n\
+EXAMPLE:\n  \
+    medi query \"tag:rust AND (title:async OR content:tokio) AND modified:>2024-06\"\n\n  \
+    # Feed the matching keys to another command:\n  \
+    medi query \"tag:draft NOT tag:published\" --output keys | xargs medi get --header")]
+    Query {
+        /// The query expression.
+        query: String,
+        /// How to print the matching notes.
+        #[arg(long, short, value_enum, default_value_t = QueryOutput::Table)]
+        output: QueryOutput,
+    },
+    /// Save and open web bookmarks.
+    #[command(after_help = "EXAMPLE:\n  \
+    # Bookmark a page; the title is fetched from the page:\n  \
+    medi bookmark add https://doc.rust-lang.org/book/ --tag rust\n\n  \
+    # List bookmarks and open one in the browser:\n  \
+    medi bookmark list\n  \
+    medi bookmark open bookmarks/the-rust-programming-language")]
+    Bookmark {
+        #[command(subcommand)]
+        command: BookmarkCommands,
+    },
+    /// Show the [[wikilink]] graph, as DOT or in an interactive explorer.
+    #[command(after_help = "EXAMPLE:\n  \
+    # Render the whole graph with Graphviz:\n  \
+    medi graph | dot -Tsvg > graph.svg\n\n  \
+    # Walk the graph from a note, following outgoing and incoming links:\n  \
+    medi graph --interactive rust")]
+    Graph {
+        /// The note to start from in interactive mode. Prompts for one if omitted.
+        #[arg(requires = "interactive")]
+        start: Option<String>,
+        /// Explore the graph interactively instead of printing DOT.
+        #[arg(long, short, action = clap::ArgAction::SetTrue)]
+        interactive: bool,
+    },
+    /// Show a month calendar of journal entries and note activity.
+    #[command(after_help = "EXAMPLE:\n  \
+    # Show the current month; '*' marks journal days, '+' days with other activity:\n  \
+    medi calendar\n\n  \
+    # Show a specific month and list the notes of one day:\n  \
+    medi calendar 2024-06 --day 14\n\n  \
+    # Pick a day and a note interactively and open it in your editor:\n  \
+    medi calendar -i")]
+    Calendar {
+        /// The month to show, as YYYY-MM. Defaults to the current month.
+        #[arg(value_parser = parse_month)]
+        month: Option<NaiveDate>,
+        /// List the notes of this day of the month instead of drawing the grid.
+        #[arg(long, short, value_parser = clap::value_parser!(u32).range(1..=31))]
+        day: Option<u32>,
+        /// Choose a day and one of its notes interactively, then open it in the editor.
+        #[arg(long, short, action = clap::ArgAction::SetTrue, conflicts_with = "day")]
+        interactive: bool,
+    },
+    /// Append a timestamped entry to your inbox note, creating it if needed.
+    #[command(after_help = "EXAMPLE:\n  \
+    # Capture a thought without deciding where it goes:\n  \
+    medi capture \"Look into sled's merge operators\"\n\n  \
+    # Capture from a pipe:\n  \
+    pbpaste | medi capture\n\n  \
+    # The inbox note is 'inbox' unless `inbox_key` is set in config.toml.")]
+    Capture {
+        /// The text to capture. Read from stdin when omitted.
+        text: Option<String>,
+    },
+    /// Mark a note as modified now and refresh its search index entry, without an editor.
+    #[command(after_help = "EXAMPLE:\n  \
+    # Bring a note back to the top of `medi list --sort-by modified`:\n  \
+    medi touch project-plan\n\n  \
+    # Record why, as a timestamped line at the end of the note:\n  \
+    medi touch project-plan -m \"Checked in with the team\"")]
+    Touch {
+        /// The key of the note to touch.
+        key: String,
+        /// A line to append to the note, prefixed with the current time.
+        #[arg(long, short)]
+        message: Option<String>,
+    },
+    /// Review flashcards and `review`-tagged notes that are due, with spaced repetition.
+    #[command(after_help = "EXAMPLE:\n  \
+    # Review what's due (cards in notes tagged 'flashcard', and notes tagged 'review'):\n  \
+    medi review\n\n  \
+    # Review at most 10 items from French notes:\n  \
+    medi review --tag french --limit 10\n\n  \
+    # Only list what's due:\n  \
+    medi review --due")]
+    Review {
+        /// Only review items from the notes passing these filters.
+        #[command(flatten)]
+        filter: NoteFilter,
+        /// The most items to review in one session.
+        #[arg(long, short, default_value_t = 20, value_parser = clap::value_parser!(u32).range(1..))]
+        limit: u32,
+        /// List the items that are due instead of reviewing them.
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        due: bool,
+    },
+    /// Walk through untagged and `inbox`-tagged notes one at a time and file them.
+    #[command(after_help = "EXAMPLE:\n  \
+    # File captured scraps: tag, retitle, merge into another note, archive or delete each one:\n  \
+    medi triage")]
+    Triage,
+    /// Add a `[[to-key]]` link to a note without opening the editor.
+    #[command(after_help = "EXAMPLE:\n  \
+    # Append a link at the end of the note:\n  \
+    medi link medi-project rust\n\n  \
+    # Add it as a list item under a '## Related' heading (created if missing):\n  \
+    medi link medi-project rust --section Related")]
+    Link {
+        /// The key of the note to add the link to.
+        from: String,
+        /// The key of the note to link to.
+        to: String,
+        /// Add the link as a list item under this heading.
+        #[arg(long, short)]
+        section: Option<String>,
+    },
+    /// Generate (or refresh) a map-of-content note linking every note under a tag or key prefix.
+    #[command(after_help = "EXAMPLE:\n  \
+    # Build 'moc/rust' listing every note tagged 'rust', grouped by their other tags:\n  \
+    medi moc rust\n\n  \
+    # A trailing slash selects a key prefix instead of a tag:\n  \
+    medi moc projects/\n\n  \
+    # Choose the key of the index note:\n  \
+    medi moc rust --key rust-index")]
+    Moc {
+        /// The tag, or key prefix ending in '/', whose notes to list.
+        target: String,
+        /// The key of the index note. Defaults to `moc/<target>`.
+        #[arg(long, short)]
+        key: Option<String>,
+    },
+    /// Save a readable-text snapshot of every external link in a note, marking dead links.
+    #[command(after_help = "EXAMPLE:\n  \
+    # Fetch each link in 'reading-list', save its text and list the snapshots in the note:\n  \
+    medi snapshot-links reading-list\n\n  \
+    # Run it again to refresh the snapshots and re-check the links:\n  \
+    medi snapshot-links reading-list")]
+    SnapshotLinks {
+        /// The key of the note whose links to snapshot.
+        key: String,
+    },
+    /// Keep the database and search index open in the background for faster commands.
+    #[command(after_help = "EXAMPLE:\n  \
+    # Start the daemon; search and status --prompt then use it automatically:\n  \
+    medi daemon &\n\n  \
+    # Other commands pause it while they run, so they keep working as usual:\n  \
+    medi new idea -m \"Works with the daemon running\"")]
+    Daemon,
+    /// Serve the notes and tasks over gRPC (requires the `grpc` feature).
+    #[command(after_help = "EXAMPLE:\n  \
+    # Serve NoteService and TaskService (see proto/medi.proto) on localhost:50051:\n  \
+    medi serve --grpc\n\n  \
+    # Listen on another address:\n  \
+    medi serve --grpc --addr 0.0.0.0:7000\n\n  \
+    # Build medi with gRPC support:\n  \
+    cargo install medi --features grpc")]
+    Serve {
+        /// Serve the gRPC API.
+        #[arg(long, required = true, action = clap::ArgAction::SetTrue)]
+        grpc: bool,
+        /// The address to listen on.
+        #[arg(long, default_value = "127.0.0.1:50051")]
+        addr: std::net::SocketAddr,
+    },
+    /// Open the journal note for a day, creating it if needed.
+    #[command(after_help = "EXAMPLE:\n  \
+    # Open today's journal entry in your editor:\n  \
+    medi journal\n\n  \
+    # Pre-create an entry for a future day:\n  \
+    medi journal --date 2025-07-01 -m \"Quarterly planning\"\n\n  \
+    # Relative days work too:\n  \
+    medi journal --date tomorrow -m \"Dentist at 10\"")]
+    Journal {
+        /// The day of the entry, e.g. 2025-07-01, tomorrow or \"next friday\". Defaults to today.
+        #[arg(long, short, value_parser = parse_day)]
+        date: Option<NaiveDate>,
+        /// Append a line to the entry instead of opening the editor.
+        #[arg(long, short)]
+        message: Option<String>,
+    },
+    /// Show journal entries, reminders and open tasks for the coming days.
+    #[command(after_help = "EXAMPLE:\n  \
+    # The coming week:\n  \
+    medi agenda\n\n  \
+    # The next 30 days:\n  \
+    medi agenda --days 30\n\n  \
+    # Tasks added to a journal entry show up on its day:\n  \
+    medi task add journal/2025-07-01 \"Send the agenda\"")]
+    Agenda {
+        /// How many days ahead to show, including today.
+        #[arg(long, short, default_value_t = 7, value_parser = clap::value_parser!(u32).range(1..))]
+        days: u32,
+    },
+    /// Show a unified diff between two notes, or between a note and a file.
+    #[command(after_help = "EXAMPLE:\n  \
+    # Compare two notes:\n  \
+    medi diff draft final\n\n  \
+    # Check an edited export against the stored note before importing it:\n  \
+    medi diff my-note --file ./export/my-note.md")]
+    Diff {
+        /// The key of the note to compare.
+        key: String,
+        /// The key of the note to compare against.
+        #[arg(required_unless_present = "file", conflicts_with = "file")]
+        other: Option<String>,
+        /// Compare against a file instead of another note.
+        #[arg(long, short)]
+        file: Option<PathBuf>,
+    },
     /// Show a summary of the database.
     #[command(after_help = "EXAMPLE:\n  \
     # Show a summary of the notes and tags in the database.\n]  \
     medi status\n\n  \
-    medi status --key my-note")]
+    medi status --key my-note\n\n  \
+    # A one-line summary for a shell prompt or tmux status bar:\n  \
+    medi status --prompt")]
     Status {
         /// The key of the note to show stats for. (Optional)
         key: Option<String>,
+        /// Print a terse, uncoloured one-line summary (notes, done/total tasks, priority tasks).
+        #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with = "key")]
+        prompt: bool,
+    },
+    /// Show the most characteristic terms of a note (or the vault), ranked by TF-IDF.
+    #[command(after_help = "EXAMPLE:\n  \
+    # Find candidate tags for an old note:\n  \
+    medi keywords my-note\n\n  \
+    # Surface the main themes of the whole vault:\n  \
+    medi keywords --all --top 50")]
+    Keywords {
+        /// The key of the note to analyse.
+        #[arg(required_unless_present = "all", conflicts_with = "all")]
+        key: Option<String>,
+        /// Rank terms across all notes instead of a single note.
+        #[arg(long, short, action = clap::ArgAction::SetTrue)]
+        all: bool,
+        /// How many terms to show.
+        #[arg(long, short = 'n', default_value_t = 10)]
+        top: usize,
+    },
+    /// Summarise a long note by picking its most important sentences.
+    #[command(after_help = "EXAMPLE:\n  \
+    # Skim a long meeting note:\n  \
+    medi summary standup-2025-06-02\n\n  \
+    # Keep a five-sentence summary at the top of the note:\n  \
+    medi summary standup-2025-06-02 -n 5 --insert\n\n  \
+    # Have the configured language model write it (requires the `llm` feature):\n  \
+    medi summary standup-2025-06-02 --llm")]
+    #[command(alias = "summarize")]
+    Summary {
+        /// The key of the note to summarise.
+        key: String,
+        /// How many sentences (or, with --llm, bullet points) the summary has.
+        #[arg(long, short = 'n', default_value_t = 3)]
+        sentences: usize,
+        /// Write the summary to the top of the note under a `## Summary` heading, replacing
+        /// an earlier one.
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        insert: bool,
+        /// Ask the language model in the config to write the summary.
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        llm: bool,
+    },
+    /// Answer a question from your notes with a language model (requires the `llm` feature).
+    #[command(after_help = "EXAMPLE:\n  \
+    medi ask \"what did we decide about the Postgres migration?\"\n\n  \
+    # Give the model more notes to work from:\n  \
+    medi ask \"who owns the release?\" --notes 10")]
+    Ask {
+        /// The question to answer.
+        question: String,
+        /// How many of the best matching notes to send with the question.
+        #[arg(long, short = 'n', default_value_t = 5)]
+        notes: usize,
+    },
+    /// Suggest tags for a note from the tags of the notes most similar to it.
+    #[command(after_help = "EXAMPLE:\n  \
+    # See which tags similar notes use:\n  \
+    medi suggest-tags my-note\n\n  \
+    # Add the top three suggestions to the note:\n  \
+    medi suggest-tags my-note --top 3 --apply")]
+    SuggestTags {
+        /// The key of the note to suggest tags for.
+        key: String,
+        /// How many tags to suggest.
+        #[arg(long, short = 'n', default_value_t = 5)]
+        top: usize,
+        /// Add the suggested tags to the note.
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        apply: bool,
+    },
+    /// Count notes, optionally grouped by tag, month or type.
+    #[command(after_help = "EXAMPLE:\n  \
+    # How many notes are there?\n  \
+    medi count\n\n  \
+    # Notes and words per month for everything tagged 'journal':\n  \
+    medi count --tag journal --group-by month --words\n\n  \
+    # Notes per tag, changed since the start of the year:\n  \
+    medi count --group-by tag --since 2025-01-01")]
+    Count {
+        /// Only count the notes passing these filters.
+        #[command(flatten)]
+        filter: NoteFilter,
+        /// Group the counts.
+        #[arg(long, short, value_enum)]
+        group_by: Option<CountGroup>,
+        /// Also show the total number of words.
+        #[arg(long, short, action = clap::ArgAction::SetTrue)]
+        words: bool,
+    },
+    /// Apply the vault's maintenance policies: archive old notes and purge expired ones.
+    /// Also retries any queued search index updates.
+    #[command(after_help = "EXAMPLE:\n  \
+    # See what would be archived (per the [[archive]] policies in config.toml) or purged:\n  \
+    medi maintain --dry-run\n\n  \
+    # Apply the policies:\n  \
+    medi maintain")]
+    Maintain {
+        /// Report what would be done without changing anything.
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        dry_run: bool,
+        /// Delete expired notes without asking for confirmation, and archive or delete
+        /// locked notes too.
+        #[arg(long, short, action = clap::ArgAction::SetTrue)]
+        force: bool,
+    },
+    /// Show statistics about the vault.
+    #[command(after_help = "EXAMPLE:\n  \
+    # See which tags are alive and which are abandoned:\n  \
+    medi stats tags\n\n  \
+    # How the vault grew, month by month:\n  \
+    medi stats growth --by month\n\n  \
+    # The same at a glance:\n  \
+    medi stats growth --by week --sparkline")]
+    Stats {
+        #[command(subcommand)]
+        command: StatsCommands,
+    },
+    /// List note titles, or find notes that share a title.
+    #[command(after_help = "EXAMPLE:\n  \
+    # Every key and its title, tab-separated:\n  \
+    medi titles\n\n  \
+    # Titles used by more than one note (ignoring case), which make lookups by title ambiguous:\n  \
+    medi titles --dupes\n\n  \
+    # Give those notes new titles, one prompt per note:\n  \
+    medi titles --dupes --rename")]
+    Titles {
+        /// Only list the notes whose title another note has too.
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        dupes: bool,
+        /// Ask for a new title for each of them.
+        #[arg(long, requires = "dupes", action = clap::ArgAction::SetTrue)]
+        rename: bool,
+        /// Rename locked notes too.
+        #[arg(long, short, requires = "rename", action = clap::ArgAction::SetTrue)]
+        force: bool,
     },
     /// Run a lint check on all notes.
     #[command(after_help = "EXAMPLE:\n  \
@@ -303,6 +1464,52 @@ pub enum Commands {
         /// The key of the note to render.
         key: String,
     },
+    /// Inspect the configuration file.
+    #[command(after_help = "EXAMPLE:\n  \
+    # Check config.toml after editing it; exits non-zero if anything is wrong:\n  \
+    medi config validate")]
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+    /// Move a database from the legacy ~/.medi/medi_db to the standard data directory.
+    #[command(after_help = "EXAMPLE:\n  \
+    # See what would be moved:\n  \
+    medi migrate-paths --dry-run\n\n  \
+    # Move the database and its search index, and point db_path in config.toml at it:\n  \
+    medi migrate-paths")]
+    MigratePaths {
+        /// Show what would be moved without moving anything.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Time create, list, search, reindex and export on a synthetic vault.
+    #[command(after_help = "EXAMPLE:\n  \
+    # Measure how medi performs with 10,000 notes (the default):\n  \
+    medi bench\n\n  \
+    # A larger vault, to see how the timings scale:\n  \
+    medi bench --notes 50000\n\n  \
+    # The vault is built in a temporary directory and removed afterwards;\n  \
+    # your own notes are never touched.")]
+    Bench {
+        /// How many notes to generate.
+        #[arg(long, default_value_t = 10_000)]
+        notes: usize,
+    },
+    /// Create a project-local vault in the current directory, or set medi up with --global.
+    #[command(after_help = "EXAMPLE:\n  \
+    # Create a .medi/ vault for the current project:\n  \
+    medi init\n\n  \
+    # medi then uses this vault automatically from this directory and any subdirectory,\n  \
+    # falling back to the global vault elsewhere.\n\n  \
+    # Choose where notes live and which editor to use, add sample templates and a welcome\n  \
+    # note (this also runs the first time medi is started in a terminal):\n  \
+    medi init --global")]
+    Init {
+        /// Set up medi itself instead: the config, sample templates and a welcome note.
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        global: bool,
+    },
     /// Generates shell completion scripts.
     #[command(name = "generate-completion", hide = true)] // Hidden from help
     Completion {
@@ -312,5 +1519,12 @@ pub enum Commands {
     /// Update the medi application.
     #[command(name = "update", hide = true)] // Hidden from help
     /// Checks for a new version of medi and updates it if available.
-    Update,
+    Update {
+        /// Which releases to update to.
+        #[arg(long, value_enum, default_value_t = UpdateChannel::Stable)]
+        channel: UpdateChannel,
+        /// Only report whether a newer version is available.
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        check: bool,
+    },
 }
@@ -0,0 +1,116 @@
+use crate::note::Note;
+use icu_collator::{Collator, CollatorOptions};
+use std::cmp::Ordering;
+use std::env;
+use std::sync::OnceLock;
+
+static LOCALE: OnceLock<String> = OnceLock::new();
+
+/// Checks a locale name such as `sv-SE` or `de`, returning a collator for it.
+pub fn parse(name: &str) -> Result<Collator, String> {
+    let invalid = || {
+        format!(
+            "unknown locale '{}', expected a name like 'en-GB' or 'sv'",
+            name
+        )
+    };
+    let locale = name.parse().map_err(|_| invalid())?;
+    Collator::try_new(&locale, CollatorOptions::new()).map_err(|_| invalid())
+}
+
+/// Sets the locale used for sorting for the rest of the run. Defaults to the system's.
+pub fn set_locale(name: &str) {
+    let _ = LOCALE.set(name.to_string());
+}
+
+/// The locale from `LC_ALL`, `LC_COLLATE` or `LANG`, e.g. `sv_SE.UTF-8` becomes `sv-SE`.
+/// `C` and `POSIX`, or no setting at all, use the language-neutral root collation.
+fn system_locale() -> String {
+    let value = ["LC_ALL", "LC_COLLATE", "LANG"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_default();
+    let name = value
+        .split(['.', '@'])
+        .next()
+        .unwrap_or_default()
+        .replace('_', "-");
+    match name.as_str() {
+        "" | "C" | "POSIX" => "und".to_string(),
+        _ => name,
+    }
+}
+
+/// The collator for the configured or system locale, or the root collation if that locale
+/// isn't known.
+fn collator() -> Collator {
+    let name = LOCALE.get().cloned().unwrap_or_else(system_locale);
+    parse(&name)
+        .or_else(|_| parse("und"))
+        .expect("the root collation is built in")
+}
+
+/// Compares keys one `/`-separated segment at a time, so `work/b` stays next to `work/a`
+/// whatever the locale makes of the `/`. Keys the collation can't tell apart fall back to
+/// byte order, keeping the sort stable between runs.
+fn cmp_keys(collator: &Collator, a: &str, b: &str) -> Ordering {
+    a.split('/')
+        .zip(b.split('/'))
+        .map(|(a, b)| collator.compare(a, b))
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or_else(|| a.split('/').count().cmp(&b.split('/').count()))
+        .then_with(|| a.cmp(b))
+}
+
+/// Sorts notes by key in the locale's alphabetical order.
+pub fn sort_by_key(notes: &mut [Note]) {
+    let collator = collator();
+    notes.sort_by(|a, b| cmp_keys(&collator, &a.key, &b.key));
+}
+
+/// Sorts notes by title in the locale's alphabetical order, then by key.
+pub fn sort_by_title(notes: &mut [Note]) {
+    let collator = collator();
+    notes.sort_by(|a, b| {
+        collator
+            .compare(&a.title, &b.title)
+            .then_with(|| cmp_keys(&collator, &a.key, &b.key))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(locale: &str, keys: &[&str]) -> Vec<String> {
+        let collator = parse(locale).unwrap();
+        let mut keys: Vec<String> = keys.iter().map(|key| key.to_string()).collect();
+        keys.sort_by(|a, b| cmp_keys(&collator, a, b));
+        keys
+    }
+
+    #[test]
+    fn test_keys_sort_alphabetically_for_the_locale() {
+        // Byte order would put "Zebra" first and "éclair" last.
+        assert_eq!(
+            sorted("en", &["éclair", "Zebra", "apple", "eclipse"]),
+            ["apple", "éclair", "eclipse", "Zebra"]
+        );
+        // Swedish puts å, ä and ö after z; German files ä under a.
+        assert_eq!(
+            sorted("sv", &["öl", "äpple", "zon"]),
+            ["zon", "äpple", "öl"]
+        );
+        assert_eq!(
+            sorted("de", &["öl", "äpple", "zon"]),
+            ["äpple", "öl", "zon"]
+        );
+        // Segments are compared in turn, so a folder's notes stay together.
+        assert_eq!(
+            sorted("en", &["work-log", "work/b", "work/a", "work"]),
+            ["work", "work/a", "work/b", "work-log"]
+        );
+        assert!(parse("not a locale!").is_err());
+    }
+}
@@ -1,19 +1,219 @@
+use crate::cli::ColorChoice;
+use crate::config::{ThemeConfig, ThemeName};
 use colored::*;
+use std::sync::OnceLock;
 
-/// Utility functions for printing messages in different colors
+/// What a piece of output is, which decides its colour in the theme.
+#[derive(Clone, Copy)]
+pub enum Role {
+    Success,
+    Info,
+    Warn,
+    Error,
+    /// Note keys.
+    Key,
+    /// Tags.
+    Tag,
+}
+
+/// A colour and whether it's bold. `None` leaves the text in the terminal's colour.
+#[derive(Clone, Copy)]
+struct Style {
+    colour: Option<Color>,
+    bold: bool,
+}
+
+/// The style of each role.
+struct Palette {
+    success: Style,
+    info: Style,
+    warn: Style,
+    error: Style,
+    key: Style,
+    tag: Style,
+}
+
+static PALETTE: OnceLock<Palette> = OnceLock::new();
+
+pub const EXPECTED_COLOUR: &str =
+    "expected a colour name like 'bright blue', '#rrggbb' or 'none', optionally after 'bold'";
+
+/// Sets the palette used by all output from the config's `[theme]`. Colours that can't be
+/// parsed are reported and left at the theme's default.
+pub fn set_theme(theme: &ThemeConfig) {
+    let base = builtin(theme.name);
+    let pick = |name: &str, custom: &Option<String>, default: Style| match custom {
+        Some(spec) => parse_style(spec).unwrap_or_else(|| {
+            warn(&format!(
+                "Ignoring theme colour {} = '{}': {}",
+                name, spec, EXPECTED_COLOUR
+            ));
+            default
+        }),
+        None => default,
+    };
+    let palette = Palette {
+        success: pick("success", &theme.success, base.success),
+        info: pick("info", &theme.info, base.info),
+        warn: pick("warn", &theme.warn, base.warn),
+        error: pick("error", &theme.error, base.error),
+        key: pick("key", &theme.key, base.key),
+        tag: pick("tag", &theme.tag, base.tag),
+    };
+    let _ = PALETTE.set(palette);
+}
+
+fn builtin(name: ThemeName) -> Palette {
+    let style = |colour: Option<Color>, bold: bool| Style { colour, bold };
+    let rgb = |r, g, b| Some(Color::TrueColor { r, g, b });
+    match name {
+        ThemeName::Default => Palette {
+            success: style(Some(Color::Green), true),
+            info: style(Some(Color::Cyan), false),
+            warn: style(Some(Color::Yellow), false),
+            error: style(Some(Color::Red), true),
+            key: style(Some(Color::Green), true),
+            tag: style(Some(Color::Cyan), false),
+        },
+        // Avoids yellow and cyan, which are hard to read on a white background.
+        ThemeName::Light => Palette {
+            success: style(Some(Color::Green), true),
+            info: style(Some(Color::Blue), false),
+            warn: style(Some(Color::Magenta), false),
+            error: style(Some(Color::Red), true),
+            key: style(Some(Color::Blue), true),
+            tag: style(Some(Color::Magenta), false),
+        },
+        ThemeName::Solarized => Palette {
+            success: style(rgb(0x85, 0x99, 0x00), true),
+            info: style(rgb(0x2a, 0xa1, 0x98), false),
+            warn: style(rgb(0xb5, 0x89, 0x00), false),
+            error: style(rgb(0xdc, 0x32, 0x2f), true),
+            key: style(rgb(0x26, 0x8b, 0xd2), true),
+            tag: style(rgb(0x6c, 0x71, 0xc4), false),
+        },
+        ThemeName::Monochrome => Palette {
+            success: style(None, true),
+            info: style(None, false),
+            warn: style(None, false),
+            error: style(None, true),
+            key: style(None, true),
+            tag: style(None, false),
+        },
+    }
+}
+
+/// Whether `spec` is a colour the theme accepts.
+pub fn is_valid(spec: &str) -> bool {
+    parse_style(spec).is_some()
+}
+
+/// Parses a colour such as `green`, `bold bright blue`, `#268bd2` or `none`.
+fn parse_style(spec: &str) -> Option<Style> {
+    let spec = spec.trim();
+    let (bold, colour) = match spec.strip_prefix("bold") {
+        Some(rest) => (true, rest.trim()),
+        None => (false, spec),
+    };
+    let colour = match colour {
+        "" | "none" => None,
+        hex if hex.starts_with('#') && hex.len() == 7 => {
+            let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+            Some(Color::TrueColor {
+                r: channel(1)?,
+                g: channel(3)?,
+                b: channel(5)?,
+            })
+        }
+        name => Some(name.parse::<Color>().ok()?),
+    };
+    Some(Style { colour, bold })
+}
+
+/// Styles `text` for `role` with the current theme.
+pub fn paint(text: &str, role: Role) -> ColoredString {
+    let palette = PALETTE.get_or_init(|| builtin(ThemeName::Default));
+    let style = match role {
+        Role::Success => palette.success,
+        Role::Info => palette.info,
+        Role::Warn => palette.warn,
+        Role::Error => palette.error,
+        Role::Key => palette.key,
+        Role::Tag => palette.tag,
+    };
+    let mut painted = match style.colour {
+        Some(colour) => text.color(colour),
+        None => text.normal(),
+    };
+    if style.bold {
+        painted = painted.bold();
+    }
+    painted
+}
+
+/// Styles a note key.
+pub fn key(text: &str) -> ColoredString {
+    paint(text, Role::Key)
+}
+
+/// Styles a tag.
+pub fn tag(text: &str) -> ColoredString {
+    paint(text, Role::Tag)
+}
+
+/// Applies the `--color` flag. `auto` leaves the decision to the environment: colour on a
+/// terminal, unless NO_COLOR is set or CLICOLOR_FORCE asks for it anyway.
+pub fn set_color(choice: ColorChoice) {
+    match choice {
+        ColorChoice::Auto => {}
+        ColorChoice::Always => control::set_override(true),
+        ColorChoice::Never => control::set_override(false),
+    }
+}
+
+/// Utility functions for printing status messages in different colors
 /// for better visibility and user experience in the command line interface.
+/// They all write to stderr, so that stdout carries only the data a command produces
+/// and can be piped into other tools.
 pub fn success(message: &str) {
-    println!("{}", message.green().bold());
+    eprintln!("{}", paint(message, Role::Success));
 }
 
 pub fn info(message: &str) {
-    println!("{}", message.cyan());
+    eprintln!("{}", paint(message, Role::Info));
 }
 
 pub fn warn(message: &str) {
-    eprintln!("{}", message.yellow());
+    eprintln!("{}", paint(message, Role::Warn));
 }
 
 pub fn error(message: &str) {
-    eprintln!("{}", message.red().bold());
+    eprintln!("{}", paint(message, Role::Error));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_style() {
+        let style = parse_style("bold bright blue").unwrap();
+        assert!(style.bold);
+        assert_eq!(style.colour, Some(Color::BrightBlue));
+
+        let style = parse_style("#268bd2").unwrap();
+        assert!(!style.bold);
+        assert_eq!(
+            style.colour,
+            Some(Color::TrueColor {
+                r: 0x26,
+                g: 0x8b,
+                b: 0xd2
+            })
+        );
+
+        assert_eq!(parse_style("none").unwrap().colour, None);
+        assert!(parse_style("#zzzzzz").is_none());
+        assert!(parse_style("chartreuse").is_none());
+    }
 }
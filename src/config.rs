@@ -1,11 +1,162 @@
+use crate::error::AppError;
+use crate::note::Note;
+use crate::{collate, colours, export, search, timezone};
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+/// The settings `config.toml` may contain, used to point out typos.
+const KNOWN_KEYS: &[&str] = &[
+    "db_path",
+    "default_export_dir",
+    "slugify_keys",
+    "zk_ids",
+    "edit_creates",
+    "inbox_key",
+    "pandoc_path",
+    "editor",
+    "export_links",
+    "export_filename",
+    "theme",
+    "archive",
+    "defaults",
+    "large_note_warn_kb",
+    "max_note_kb",
+    "timezone",
+    "sort_locale",
+    "index_heap_mb",
+    "llm",
+];
+/// The note size, in KB, above which saving warns unless `large_note_warn_kb` says otherwise.
+pub const DEFAULT_LARGE_NOTE_WARN_KB: u64 = 1024;
+
+const THEME_KEYS: &[&str] = &["name", "success", "info", "warn", "error", "key", "tag"];
+const ARCHIVE_KEYS: &[&str] = &["tag", "archive_after_days"];
+const LLM_KEYS: &[&str] = &["endpoint", "model", "api_key_env", "embedding_model"];
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Config {
     pub db_path: Option<PathBuf>,
     pub default_export_dir: Option<PathBuf>,
+    /// Normalise new and imported keys to lowercase-dash slugs.
+    #[serde(default)]
+    pub slugify_keys: bool,
+    /// Generate Zettelkasten keys like `202405181530-title-slug` for new notes.
+    #[serde(default)]
+    pub zk_ids: bool,
+    /// Offer to create the note when `medi edit` is given a key that doesn't exist.
+    #[serde(default)]
+    pub edit_creates: bool,
+    /// The note `medi capture` appends to. Defaults to `inbox`.
+    pub inbox_key: Option<String>,
+    /// The pandoc executable used to import `.docx` and `.odt` files. Defaults to
+    /// `pandoc` on the `PATH`.
+    pub pandoc_path: Option<PathBuf>,
+    /// The command notes are edited with, e.g. `code --wait`. Defaults to `$VISUAL` or
+    /// `$EDITOR`.
+    pub editor: Option<String>,
+    /// How the Markdown export writes `[[key]]` links.
+    #[serde(default)]
+    pub export_links: LinkStyle,
+    /// File names for the Markdown export, e.g. `{date}-{slug}.md`. Defaults to `{key}.md`.
+    pub export_filename: Option<String>,
+    /// The colours used for output.
+    #[serde(default)]
+    pub theme: ThemeConfig,
+    /// Policies applied by `medi maintain` to archive old notes.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub archive: Vec<ArchivePolicy>,
+    /// Warn when saving a note larger than this many KB. Defaults to 1024; 0 turns it off.
+    pub large_note_warn_kb: Option<u64>,
+    /// Refuse to save notes larger than this many KB.
+    pub max_note_kb: Option<u64>,
+    /// The time zone dates are shown and read in, e.g. `Europe/Stockholm`. Defaults to the system's.
+    pub timezone: Option<String>,
+    /// The locale whose alphabetical order keys and titles are sorted in, e.g. `sv-SE`.
+    /// Defaults to the system's.
+    pub sort_locale: Option<String>,
+    /// The heap, in MB, of the search index writers. Defaults to 100 for rebuilds and 50
+    /// for updates, and is scaled down when memory is short.
+    pub index_heap_mb: Option<u64>,
+    /// The language model used by `medi ask` and `medi summary --llm`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub llm: Option<LlmConfig>,
+    /// Default options per command, e.g. `list.sort_by = "modified"`. Options given on the
+    /// command line win.
+    #[serde(default, skip_serializing_if = "toml::Table::is_empty")]
+    pub defaults: toml::Table,
+}
+
+/// How links between notes are written in Markdown exports.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkStyle {
+    /// `[[key]]`, as stored.
+    #[default]
+    Wikilinks,
+    /// `[title](key.md)`, for tools that don't understand wikilinks.
+    Markdown,
+}
+
+/// The `[theme]` table: a built-in theme, with any of its colours overridden. Colours are
+/// names like `"bright blue"`, hex like `"#268bd2"` or `"none"`, optionally after `"bold"`.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct ThemeConfig {
+    #[serde(default)]
+    pub name: ThemeName,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub success: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub info: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warn: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// The colour of note keys.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    /// The colour of tags.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+}
+
+/// The built-in colour themes.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeName {
+    /// Bright colours for dark terminal backgrounds.
+    #[default]
+    Default,
+    /// Darker colours that stay readable on light backgrounds.
+    Light,
+    /// The Solarized accent colours (needs a true-colour terminal).
+    Solarized,
+    /// No colours, only bold.
+    Monochrome,
+}
+
+/// The `[llm]` table: an OpenAI-compatible chat completions endpoint, hosted or local.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct LlmConfig {
+    /// Defaults to a local Ollama server.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+    pub model: String,
+    /// The environment variable holding the API key, so the key stays out of the config.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key_env: Option<String>,
+    /// The model `medi search --semantic` embeds notes with, served from the `embeddings`
+    /// endpoint next to `endpoint`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub embedding_model: Option<String>,
+}
+
+/// Archive notes that haven't been modified for `archive_after_days`.
+/// Without a `tag` the policy applies to every note.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ArchivePolicy {
+    pub tag: Option<String>,
+    pub archive_after_days: i64,
 }
 
 impl Default for Config {
@@ -30,19 +181,47 @@ impl Default for Config {
 
         Config {
             db_path: Option::from(default_db_path),
-            default_export_dir: Option::from(default_export_dir),
+            default_export_dir,
+            slugify_keys: false,
+            zk_ids: false,
+            edit_creates: false,
+            inbox_key: None,
+            pandoc_path: None,
+            editor: None,
+            export_links: LinkStyle::default(),
+            export_filename: None,
+            theme: ThemeConfig::default(),
+            archive: Vec::new(),
+            large_note_warn_kb: None,
+            max_note_kb: None,
+            timezone: None,
+            sort_locale: None,
+            index_heap_mb: None,
+            llm: None,
+            defaults: toml::Table::new(),
         }
     }
 }
 
+/// Returns the directory holding note templates, e.g. `~/.config/medi/templates`.
+pub fn templates_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("medi").join("templates"))
+}
+
+/// The path of `config.toml`, e.g. `~/.config/medi/config.toml`.
+pub fn config_path() -> Result<PathBuf, AppError> {
+    dirs::config_dir()
+        .map(|dir| dir.join("medi").join("config.toml"))
+        .ok_or_else(|| AppError::ConfigError("Config directory not found".into()))
+}
+
 /// Loads the config from disk, creating a default one if it doesn't exist.
-pub fn load() -> Result<Config, std::io::Error> {
-    let config_dir = dirs::config_dir()
-        .expect("Could not find config directory")
-        .join("medi");
+pub fn load() -> Result<Config, AppError> {
+    let config_path = config_path()?;
+    let config_dir = config_path.parent().unwrap_or(Path::new("."));
 
     // Create config directory if it doesn't exist.
-    fs::create_dir_all(&config_dir)?;
+    fs::create_dir_all(config_dir)?;
     let templates_dir = config_dir.join("templates");
     fs::create_dir_all(&templates_dir)?;
 
@@ -95,19 +274,163 @@ pub fn load() -> Result<Config, std::io::Error> {
         fs::write(example_template_path, template_content.trim())?;
     }
 
-    let config_path = config_dir.join("config.toml");
-
     // If the config file doesn't exist, create it with default values.
     if !config_path.exists() {
         let default_config = Config::default();
-        let toml_string =
-            toml::to_string_pretty(&default_config).expect("Could not serialize default config");
+        let toml_string = toml::to_string_pretty(&default_config)
+            .map_err(|e| AppError::ConfigError(e.to_string()))?;
         fs::write(&config_path, toml_string)?;
     }
 
     // Read the config file from disk.
-    let toml_content = fs::read_to_string(config_path)?;
-    let config: Config = toml::from_str(&toml_content).expect("Could not deserialize config file");
+    let toml_content = fs::read_to_string(&config_path)?;
+    parse(&config_path, &toml_content)
+}
 
-    Ok(config)
+/// Sets a top-level `key` in the config file, keeping the rest of it (and its comments)
+/// as it is.
+pub fn set_value(config_path: &Path, key: &str, value: toml::Value) -> Result<(), AppError> {
+    let content = fs::read_to_string(config_path).unwrap_or_default();
+    let setting = format!("{} = {}", key, value);
+    let mut replaced = false;
+    let mut lines: Vec<String> = content
+        .lines()
+        .map(|line| {
+            let name = line.split('=').next().unwrap_or_default().trim();
+            if name == key && !replaced {
+                replaced = true;
+                setting.clone()
+            } else {
+                line.to_string()
+            }
+        })
+        .collect();
+    if !replaced {
+        // Top-level keys must come before any [table].
+        lines.insert(0, setting);
+    }
+    fs::write(config_path, lines.join("\n") + "\n")?;
+    Ok(())
+}
+
+/// Parses `content`, read from `path`. Errors name the file, the line and what was
+/// expected instead of the offending value.
+pub fn parse(path: &Path, content: &str) -> Result<Config, AppError> {
+    toml::from_str(content).map_err(|e| {
+        let location = match e.span() {
+            Some(span) => {
+                let line = content[..span.start].matches('\n').count() + 1;
+                let text = content.lines().nth(line - 1).unwrap_or_default().trim();
+                format!(", line {}: `{}`", line, text)
+            }
+            None => String::new(),
+        };
+        AppError::ConfigError(format!(
+            "{}{}\n{}\nValid settings are {}.",
+            path.display(),
+            location,
+            e.message().trim_end(),
+            KNOWN_KEYS.join(", ")
+        ))
+    })
+}
+
+/// Checks a config that parsed for settings medi doesn't know (usually typos) and values
+/// it would reject or ignore later. Returns a description of each problem.
+pub fn problems(config: &Config, content: &str) -> Vec<String> {
+    let mut problems = Vec::new();
+    let line_of = |key: &str| {
+        content
+            .lines()
+            .position(|line| {
+                let line = line.trim_start();
+                line.starts_with(key) && line[key.len()..].trim_start().starts_with('=')
+            })
+            .map_or(String::new(), |n| format!(" (line {})", n + 1))
+    };
+    let mut unknown = |table: &toml::Table, known: &[&str], prefix: &str| {
+        for key in table.keys().filter(|key| !known.contains(&key.as_str())) {
+            problems.push(format!(
+                "unknown setting '{}{}'{}",
+                prefix,
+                key,
+                line_of(key)
+            ));
+        }
+    };
+    if let Ok(table) = content.parse::<toml::Table>() {
+        unknown(&table, KNOWN_KEYS, "");
+        if let Some(theme) = table.get("theme").and_then(toml::Value::as_table) {
+            unknown(theme, THEME_KEYS, "theme.");
+        }
+        for policy in table
+            .get("archive")
+            .and_then(toml::Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(toml::Value::as_table)
+        {
+            unknown(policy, ARCHIVE_KEYS, "archive.");
+        }
+        if let Some(llm) = table.get("llm").and_then(toml::Value::as_table) {
+            unknown(llm, LLM_KEYS, "llm.");
+        }
+    }
+
+    let theme = &config.theme;
+    for (role, colour) in [
+        ("success", &theme.success),
+        ("info", &theme.info),
+        ("warn", &theme.warn),
+        ("error", &theme.error),
+        ("key", &theme.key),
+        ("tag", &theme.tag),
+    ] {
+        if let Some(colour) = colour.as_deref().filter(|c| !colours::is_valid(c)) {
+            problems.push(format!(
+                "theme.{} = '{}'{}: {}",
+                role,
+                colour,
+                line_of(role),
+                colours::EXPECTED_COLOUR
+            ));
+        }
+    }
+    if let Some(template) = &config.export_filename {
+        if let Err(AppError::ConfigError(e)) = export::export_filename(template, &Note::default()) {
+            problems.push(format!("{}{}", e, line_of("export_filename")));
+        }
+    }
+    if let Some(pandoc) = config.pandoc_path.as_ref().filter(|path| !path.exists()) {
+        problems.push(format!(
+            "pandoc_path '{}' does not exist{}",
+            pandoc.display(),
+            line_of("pandoc_path")
+        ));
+    }
+    if let Some(Err(e)) = config.timezone.as_deref().map(timezone::parse) {
+        problems.push(format!("{}{}", e, line_of("timezone")));
+    }
+    if let Some(Err(e)) = config.sort_locale.as_deref().map(collate::parse) {
+        problems.push(format!("{}{}", e, line_of("sort_locale")));
+    }
+    if let Some(mb) = config.index_heap_mb.filter(|mb| *mb < search::MIN_HEAP_MB) {
+        problems.push(format!(
+            "index_heap_mb = {}{}: the search index needs at least {} MB",
+            mb,
+            line_of("index_heap_mb"),
+            search::MIN_HEAP_MB
+        ));
+    }
+    for policy in config.archive.iter().filter(|p| p.archive_after_days <= 0) {
+        problems.push(format!(
+            "archive policy{} has archive_after_days = {}, expected a positive number of days",
+            policy
+                .tag
+                .as_ref()
+                .map_or(String::new(), |tag| format!(" for '{}'", tag)),
+            policy.archive_after_days
+        ));
+    }
+    problems
 }
@@ -0,0 +1,155 @@
+use crate::config::Config;
+use crate::error::AppError;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::process::Command;
+
+/// Separates the text of consecutive PDF pages in the imported note.
+const PAGE_BREAK: &str = "\n\n---\n\n";
+
+/// Reads a file for import as Markdown. Markdown and plain text files are taken as they
+/// are; other formats are converted based on their extension.
+pub fn to_markdown(path: &Path, config: &Config) -> Result<String, AppError> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_lowercase);
+    match extension.as_deref() {
+        Some("pdf") => pdf_to_markdown(&fs::read(path)?),
+        Some("docx") | Some("odt") => {
+            let pandoc = config.pandoc_path.as_deref().unwrap_or(Path::new("pandoc"));
+            pandoc_to_markdown(pandoc, path)
+        }
+        _ => Ok(fs::read_to_string(path)?),
+    }
+}
+
+/// Extracts the text of each page of a PDF, with a horizontal rule between pages.
+/// Scanned pages without a text layer come out empty, as there is no OCR.
+fn pdf_to_markdown(bytes: &[u8]) -> Result<String, AppError> {
+    let pages = pdf_extract::extract_text_from_mem_by_pages(bytes)
+        .map_err(|e| AppError::Convert(format!("couldn't read PDF: {}", e)))?;
+    let pages: Vec<String> = pages.iter().map(|page| tidy_page(page)).collect();
+    Ok(format!("{}\n", pages.join(PAGE_BREAK).trim()))
+}
+
+/// Converts a word processor document with pandoc, keeping headings, lists and tables as
+/// GitHub-flavoured Markdown.
+fn pandoc_to_markdown(pandoc: &Path, path: &Path) -> Result<String, AppError> {
+    let output = Command::new(pandoc)
+        .arg(path)
+        .args(["--to", "gfm", "--wrap", "none"])
+        .output()
+        .map_err(|e| match e.kind() {
+            ErrorKind::NotFound => AppError::Convert(format!(
+                "'{}' not found; install pandoc or set pandoc_path in config.toml to import {}",
+                pandoc.display(),
+                path.display()
+            )),
+            _ => AppError::Io(e),
+        })?;
+    if !output.status.success() {
+        return Err(AppError::Convert(format!(
+            "pandoc couldn't convert {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// Trims trailing whitespace and collapses runs of blank lines, which PDF layouts
+/// produce a lot of.
+fn tidy_page(text: &str) -> String {
+    let mut out = String::new();
+    let mut blank_lines = 0;
+    for line in text.lines().map(str::trim_end) {
+        if line.is_empty() {
+            blank_lines += 1;
+            continue;
+        }
+        if !out.is_empty() {
+            out.push_str(if blank_lines > 0 { "\n\n" } else { "\n" });
+        }
+        out.push_str(line);
+        blank_lines = 0;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a minimal PDF with one line of Helvetica text per page.
+    fn pdf(pages: &[&str]) -> Vec<u8> {
+        let font_id = 3 + 2 * pages.len();
+        let mut objects = vec![
+            "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                (0..pages.len())
+                    .map(|i| format!("{} 0 R", 3 + 2 * i))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                pages.len()
+            ),
+        ];
+        for (i, text) in pages.iter().enumerate() {
+            let stream = format!("BT /F1 12 Tf 72 720 Td ({}) Tj ET", text);
+            objects.push(format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] /Contents {} 0 R /Resources << /Font << /F1 {} 0 R >> >> >>",
+                4 + 2 * i,
+                font_id
+            ));
+            objects.push(format!(
+                "<< /Length {} >>\nstream\n{}\nendstream",
+                stream.len(),
+                stream
+            ));
+        }
+        objects.push(
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+                .to_string(),
+        );
+
+        let mut out = b"%PDF-1.4\n".to_vec();
+        let mut offsets = Vec::new();
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(out.len());
+            out.extend(format!("{} 0 obj\n{}\nendobj\n", i + 1, object).bytes());
+        }
+        let xref = out.len();
+        out.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).bytes());
+        for offset in offsets {
+            out.extend(format!("{:010} 00000 n \n", offset).bytes());
+        }
+        out.extend(
+            format!(
+                "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+                objects.len() + 1,
+                xref
+            )
+            .bytes(),
+        );
+        out
+    }
+
+    #[test]
+    fn test_pdf_pages_are_separated_by_rules() {
+        let markdown = pdf_to_markdown(&pdf(&["First page", "Second page"])).unwrap();
+        assert_eq!(markdown, "First page\n\n---\n\nSecond page\n");
+        assert!(pdf_to_markdown(b"not a pdf").is_err());
+    }
+
+    #[test]
+    fn test_missing_pandoc_is_reported() {
+        let config = Config {
+            pandoc_path: Some("/nonexistent/pandoc".into()),
+            ..Config::default()
+        };
+        let error = to_markdown(Path::new("minutes.docx"), &config).unwrap_err();
+        assert!(error.to_string().contains("set pandoc_path in config.toml"));
+    }
+}
@@ -0,0 +1,196 @@
+use crate::config::Config;
+use crate::error::AppError;
+use crate::note::Note;
+use crate::{colours, db, initialise_search_index, search};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sled::Db;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+use tantivy::{Index, IndexReader, ReloadPolicy};
+use tracing::debug;
+
+/// The socket the daemon listens on, next to the database it serves.
+pub fn socket_path(config: &Config, db_override: Option<&Path>) -> PathBuf {
+    db::db_path(config, db_override).join("daemon.sock")
+}
+
+/// How long the daemon waits on a client to send its request or take the response, so a
+/// client that connects and goes quiet can't hold up everyone else.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A request to the daemon, sent as one line of JSON.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Request {
+    /// The notes matching a search query, best match first.
+    Search { query: String },
+    /// The `status --prompt` line.
+    Prompt,
+    /// Release the database until the connection closes, so the client can open it.
+    Pause,
+}
+
+/// The daemon's answer to a [`Request`], sent as one line of JSON.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum Response {
+    Notes { notes: Vec<Note> },
+    Prompt { line: String },
+    Paused,
+    Error { message: String },
+}
+
+/// The database and search index the daemon keeps open between requests.
+struct Warm {
+    db: Db,
+    index: Index,
+    reader: IndexReader,
+}
+
+impl Warm {
+    fn open(config: &Config, db_override: Option<&Path>) -> Result<Self, AppError> {
+        let db = db::open(config.clone(), db_override)?;
+        let index = initialise_search_index(config, db_override)?;
+        db::replay_pending_index(&db, &index)?;
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::OnCommitWithDelay)
+            .try_into()?;
+        Ok(Warm { db, index, reader })
+    }
+
+    fn handle(&self, request: &Request) -> Result<Response, AppError> {
+        match request {
+            Request::Search { query } => {
+                let keys = search::search_with_reader(&self.reader, &self.index, query, 10)?;
+                let now = Utc::now();
+                let notes = keys
+                    .iter()
+                    .filter_map(|key| db::get_note(&self.db, key).ok())
+                    .filter(|note| !note.is_expired(now))
+                    .collect();
+                Ok(Response::Notes { notes })
+            }
+            Request::Prompt => Ok(Response::Prompt {
+                line: crate::prompt_line(&self.db)?,
+            }),
+            Request::Pause => Ok(Response::Paused),
+        }
+    }
+}
+
+/// Runs the daemon until it's killed: opens the database and search index once, then
+/// answers requests on the socket one connection at a time.
+pub fn serve(config: &Config, db_override: Option<&Path>) -> Result<(), AppError> {
+    let socket = socket_path(config, db_override);
+    if connect(&socket).is_some() {
+        return Err(AppError::Server(format!(
+            "a daemon is already listening on '{}'",
+            socket.display()
+        )));
+    }
+    let mut warm = Some(Warm::open(config, db_override)?);
+    // A socket file left behind by a daemon that was killed.
+    let _ = fs::remove_file(&socket);
+    let listener = UnixListener::bind(&socket)?;
+    colours::info(&format!(
+        "Daemon listening on '{}' (Ctrl+C to stop)",
+        socket.display()
+    ));
+
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                colours::warn(&format!("Could not accept a connection: {}", e));
+                continue;
+            }
+        };
+        if let Err(e) = stream
+            .set_read_timeout(Some(CLIENT_TIMEOUT))
+            .and_then(|_| stream.set_write_timeout(Some(CLIENT_TIMEOUT)))
+        {
+            debug!(error = %e, "could not set the client timeouts");
+            continue;
+        }
+        let request = match read_message::<Request>(&mut stream) {
+            Ok(request) => request,
+            Err(e) => {
+                debug!(error = %e, "ignoring a malformed request");
+                continue;
+            }
+        };
+        if let Request::Pause = request {
+            // Close everything, let the client work, and reopen once it hangs up, however
+            // long its command takes.
+            drop(warm.take());
+            let _ = write_message(&mut stream, &Response::Paused);
+            let _ = stream.set_read_timeout(None);
+            let _ = stream.read_to_end(&mut Vec::new());
+            warm = reopen(config, db_override);
+            continue;
+        }
+        if warm.is_none() {
+            warm = reopen(config, db_override);
+        }
+        let response = match warm.as_ref().map(|warm| warm.handle(&request)) {
+            Some(Ok(response)) => response,
+            Some(Err(e)) => Response::Error {
+                message: e.to_string(),
+            },
+            None => Response::Error {
+                message: "the daemon couldn't reopen the database".to_string(),
+            },
+        };
+        if let Err(e) = write_message(&mut stream, &response) {
+            debug!(error = %e, "client went away before the response");
+        }
+    }
+    Ok(())
+}
+
+/// Reopens the database after a pause, retrying briefly in case the client's process is
+/// still letting go of the lock.
+fn reopen(config: &Config, db_override: Option<&Path>) -> Option<Warm> {
+    for _ in 0..20 {
+        match Warm::open(config, db_override) {
+            Ok(warm) => return Some(warm),
+            Err(e) => debug!(error = %e, "database still locked"),
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    colours::error("Could not reopen the database after a pause.");
+    None
+}
+
+/// Connects to a running daemon, if there is one.
+pub fn connect(socket: &Path) -> Option<UnixStream> {
+    UnixStream::connect(socket).ok()
+}
+
+/// Sends a request over `stream` and waits for the response.
+pub fn request(stream: &mut UnixStream, request: &Request) -> Result<Response, AppError> {
+    write_message(stream, request)?;
+    match read_message(stream)? {
+        Response::Error { message } => Err(AppError::Server(message)),
+        response => Ok(response),
+    }
+}
+
+fn write_message<T: Serialize>(stream: &mut UnixStream, message: &T) -> Result<(), AppError> {
+    let mut line = serde_json::to_vec(message)?;
+    line.push(b'\n');
+    stream.write_all(&line)?;
+    Ok(())
+}
+
+fn read_message<T: for<'de> Deserialize<'de>>(stream: &mut UnixStream) -> Result<T, AppError> {
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    Ok(serde_json::from_str(&line)?)
+}
@@ -0,0 +1,208 @@
+use chrono::{
+    DateTime, Datelike, Duration, LocalResult, NaiveDate, NaiveTime, TimeZone, Utc, Weekday,
+};
+
+/// The time of day used when an expression only names a day.
+const DEFAULT_TIME: (u32, u32) = (9, 0);
+
+/// Parses a point in time relative to `now`, in the time zone of `now`. Accepts RFC 3339,
+/// `now`, `in <n> <unit>`, `<n> <unit> ago`, and any day understood by [`parse_day`]
+/// optionally followed by `HH:MM`. A day without a time means 09:00.
+pub fn parse_when<Tz: TimeZone>(input: &str, now: DateTime<Tz>) -> Result<DateTime<Utc>, String> {
+    let input = input.trim().to_lowercase();
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(&input) {
+        return Ok(datetime.with_timezone(&Utc));
+    }
+
+    let words: Vec<&str> = input.split_whitespace().collect();
+    if words == ["now"] {
+        return Ok(now.with_timezone(&Utc));
+    }
+    if let Some(offset) = parse_relative(&words)? {
+        return Ok((now + offset).with_timezone(&Utc));
+    }
+
+    // Split off a trailing time of day, if there is one.
+    let (day_words, time) = match words.split_last() {
+        Some((last, rest)) if last.contains(':') => (rest, parse_time(last)?),
+        _ => (
+            words.as_slice(),
+            NaiveTime::from_hms_opt(DEFAULT_TIME.0, DEFAULT_TIME.1, 0).unwrap_or(NaiveTime::MIN),
+        ),
+    };
+    let today = now.date_naive();
+    let date = match day_words {
+        [] => today,
+        words => day_from_words(words, today).ok_or_else(|| not_understood(&input))?,
+    };
+    to_utc(&now.timezone(), date, time)
+}
+
+/// Parses a calendar day relative to `today`. Accepts `YYYY-MM-DD`, `today`, `tomorrow`,
+/// `yesterday`, `[next|last] <weekday>`, `next week`, `last week`, `in <n> days` and
+/// `<n> days ago`.
+pub fn parse_day(input: &str, today: NaiveDate) -> Result<NaiveDate, String> {
+    let input = input.trim().to_lowercase();
+    let words: Vec<&str> = input.split_whitespace().collect();
+    if let Some(offset) = parse_relative(&words)? {
+        return Ok(today + Duration::days(offset.num_days()));
+    }
+    day_from_words(&words, today).ok_or_else(|| not_understood(&input))
+}
+
+fn not_understood(input: &str) -> String {
+    format!(
+        "couldn't understand '{}', try e.g. 2025-07-01, \"tomorrow\", \"next friday 14:00\" or \"in 3 days\"",
+        input
+    )
+}
+
+fn day_from_words(words: &[&str], today: NaiveDate) -> Option<NaiveDate> {
+    match words {
+        ["today"] => Some(today),
+        ["tomorrow"] => today.succ_opt(),
+        ["yesterday"] => today.pred_opt(),
+        ["next", "week"] => Some(today + Duration::weeks(1)),
+        ["last", "week"] => Some(today - Duration::weeks(1)),
+        ["last", day] => Some(previous_weekday(today, parse_weekday(day)?)),
+        ["next", day] | [day] if parse_weekday(day).is_some() => {
+            Some(next_weekday(today, parse_weekday(day)?))
+        }
+        [date] => NaiveDate::parse_from_str(date, "%Y-%m-%d").ok(),
+        _ => None,
+    }
+}
+
+/// Parses `in <n> <unit>` and `<n> <unit> ago` into a signed offset, or `None` if the
+/// words aren't a relative expression at all.
+fn parse_relative(words: &[&str]) -> Result<Option<Duration>, String> {
+    match words {
+        ["in", amount @ ..] if !amount.is_empty() => parse_offset(&amount.join(" ")).map(Some),
+        [amount @ .., "ago"] if !amount.is_empty() => {
+            parse_offset(&amount.join(" ")).map(|offset| Some(-offset))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Parses `2d`, `3 days`, `90 minutes` and similar into a duration.
+fn parse_offset(text: &str) -> Result<Duration, String> {
+    let split = text
+        .find(|c: char| !c.is_ascii_digit())
+        .filter(|&split| split > 0)
+        .ok_or_else(|| {
+            format!(
+                "expected an amount and a unit, e.g. \"3 days\", got '{}'",
+                text
+            )
+        })?;
+    let (amount, unit) = text.split_at(split);
+    let amount: i64 = amount
+        .parse()
+        .map_err(|_| format!("'{}' is too large", amount))?;
+    match unit.trim().trim_end_matches('s') {
+        "m" | "min" | "minute" => Ok(Duration::minutes(amount)),
+        "h" | "hour" => Ok(Duration::hours(amount)),
+        "d" | "day" => Ok(Duration::days(amount)),
+        "w" | "week" => Ok(Duration::weeks(amount)),
+        unit => Err(format!(
+            "unknown unit '{}', expected minutes, hours, days or weeks",
+            unit
+        )),
+    }
+}
+
+fn parse_time(text: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(text, "%H:%M")
+        .map_err(|_| format!("invalid time of day '{}', expected HH:MM", text))
+}
+
+fn parse_weekday(text: &str) -> Option<Weekday> {
+    text.parse().ok()
+}
+
+/// The first `weekday` strictly after `today`.
+fn next_weekday(today: NaiveDate, weekday: Weekday) -> NaiveDate {
+    let days_ahead =
+        (weekday.num_days_from_monday() + 7 - today.weekday().num_days_from_monday()) % 7;
+    let days_ahead = if days_ahead == 0 { 7 } else { days_ahead };
+    today + Duration::days(days_ahead as i64)
+}
+
+/// The last `weekday` strictly before `today`.
+fn previous_weekday(today: NaiveDate, weekday: Weekday) -> NaiveDate {
+    let days_back =
+        (today.weekday().num_days_from_monday() + 7 - weekday.num_days_from_monday()) % 7;
+    let days_back = if days_back == 0 { 7 } else { days_back };
+    today - Duration::days(days_back as i64)
+}
+
+/// Converts a date and time in `zone` to UTC, picking the earlier instant around DST changes.
+fn to_utc<Tz: TimeZone>(
+    zone: &Tz,
+    date: NaiveDate,
+    time: NaiveTime,
+) -> Result<DateTime<Utc>, String> {
+    match zone.from_local_datetime(&date.and_time(time)) {
+        LocalResult::Single(datetime) | LocalResult::Ambiguous(datetime, _) => {
+            Ok(datetime.with_timezone(&Utc))
+        }
+        LocalResult::None => Err(format!(
+            "{} {} doesn't exist in the time zone",
+            date,
+            time.format("%H:%M")
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Local;
+
+    /// Saturday 2024-05-18 15:30 local time.
+    fn now() -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 5, 18, 15, 30, 0).unwrap()
+    }
+
+    fn local(input: &str) -> String {
+        parse_when(input, now())
+            .unwrap()
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M")
+            .to_string()
+    }
+
+    #[test]
+    fn test_parse_when_relative_days() {
+        assert_eq!(local("next tuesday 09:00"), "2024-05-21 09:00");
+        assert_eq!(local("saturday"), "2024-05-25 09:00");
+        assert_eq!(local("tomorrow 18:45"), "2024-05-19 18:45");
+        assert_eq!(local("last friday"), "2024-05-17 09:00");
+        assert_eq!(local("2024-07-01"), "2024-07-01 09:00");
+    }
+
+    #[test]
+    fn test_parse_when_offsets() {
+        assert_eq!(local("in 2 hours"), "2024-05-18 17:30");
+        assert_eq!(local("in 3d"), "2024-05-21 15:30");
+        assert_eq!(local("1 week ago"), "2024-05-11 15:30");
+    }
+
+    #[test]
+    fn test_parse_when_errors() {
+        let error = |input| parse_when(input, now()).unwrap_err();
+        assert!(error("someday").starts_with("couldn't understand 'someday'"));
+        assert!(error("tomorrow 25:00").contains("invalid time of day '25:00'"));
+        assert!(error("in 3 fortnights").contains("unknown unit 'fortnight'"));
+    }
+
+    #[test]
+    fn test_parse_day() {
+        let day = |input| parse_day(input, now().date_naive()).unwrap().to_string();
+        assert_eq!(day("yesterday"), "2024-05-17");
+        assert_eq!(day("in 3 days"), "2024-05-21");
+        assert_eq!(day("next week"), "2024-05-25");
+        assert_eq!(day("2025-07-01"), "2025-07-01");
+    }
+}
@@ -0,0 +1,191 @@
+use crate::error::AppError;
+use crate::note::{self, Note};
+use chrono::{DateTime, Local, Utc};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use zip::ZipArchive;
+
+/// Metadata field holding the Day One entry id, used to skip entries imported before.
+pub const UUID_FIELD: &str = "dayone_uuid";
+
+lazy_static! {
+    /// Day One refers to an entry's photos as `dayone-moment://<identifier>`.
+    static ref MOMENT: Regex = Regex::new(r"dayone-moment://([0-9A-Za-z]+)").unwrap();
+}
+
+/// One journal file of a Day One JSON export.
+#[derive(Deserialize)]
+struct Journal {
+    entries: Vec<Entry>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Entry {
+    uuid: String,
+    creation_date: DateTime<Utc>,
+    modified_date: Option<DateTime<Utc>>,
+    #[serde(default)]
+    text: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    location: Option<Location>,
+    weather: Option<Weather>,
+    #[serde(default)]
+    photos: Vec<Photo>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Location {
+    place_name: Option<String>,
+    locality_name: Option<String>,
+    country: Option<String>,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Weather {
+    conditions_description: Option<String>,
+    temperature_celsius: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct Photo {
+    identifier: String,
+    md5: String,
+    #[serde(rename = "type")]
+    kind: Option<String>,
+}
+
+/// Reads a Day One JSON export (the `.zip` from "Export > JSON") into journal notes.
+/// Photos are extracted into `attachments` and linked from the note's content. Notes are
+/// keyed by the entry's local date; the caller makes keys unique.
+pub fn read_export(path: &Path, attachments: &Path) -> Result<Vec<Note>, AppError> {
+    let mut archive = ZipArchive::new(fs::File::open(path)?)?;
+    let journal_files: Vec<String> = archive
+        .file_names()
+        .filter(|name| name.ends_with(".json") && !name.contains('/'))
+        .map(str::to_string)
+        .collect();
+    if journal_files.is_empty() {
+        return Err(AppError::Convert(format!(
+            "{} has no journal .json files; is it a Day One JSON export?",
+            path.display()
+        )));
+    }
+
+    let mut notes = Vec::new();
+    for name in journal_files {
+        let mut json = String::new();
+        archive.by_name(&name)?.read_to_string(&mut json)?;
+        let journal: Journal = serde_json::from_str(&json)?;
+        for entry in journal.entries {
+            let photos = extract_photos(&mut archive, &entry.photos, attachments)?;
+            notes.push(to_note(entry, &photos));
+        }
+    }
+    Ok(notes)
+}
+
+/// Copies an entry's photos out of the archive, returning their paths by identifier.
+/// Photos are stored by their MD5 so importing the same export twice doesn't duplicate them.
+fn extract_photos(
+    archive: &mut ZipArchive<fs::File>,
+    photos: &[Photo],
+    attachments: &Path,
+) -> Result<HashMap<String, String>, AppError> {
+    let mut paths = HashMap::new();
+    for photo in photos {
+        let file_name = format!("{}.{}", photo.md5, photo.kind.as_deref().unwrap_or("jpeg"));
+        let Ok(mut file) = archive.by_name(&format!("photos/{}", file_name)) else {
+            continue;
+        };
+        let target = attachments.join(&file_name);
+        if !target.exists() {
+            fs::create_dir_all(attachments)?;
+            let mut bytes = Vec::new();
+            file.read_to_end(&mut bytes)?;
+            fs::write(&target, bytes)?;
+        }
+        paths.insert(photo.identifier.clone(), note::link_target(&target));
+    }
+    Ok(paths)
+}
+
+fn to_note(entry: Entry, photos: &HashMap<String, String>) -> Note {
+    let date = entry.creation_date.with_timezone(&Local).date_naive();
+    let content = MOMENT
+        .replace_all(&entry.text, |caps: &regex::Captures| {
+            photos
+                .get(&caps[1])
+                .cloned()
+                .unwrap_or_else(|| caps[0].to_string())
+        })
+        // Day One escapes Markdown punctuation it didn't mean as formatting.
+        .replace("\\.", ".")
+        .replace("\\-", "-")
+        .replace("\\!", "!");
+
+    let title = content
+        .lines()
+        .map(|line| line.trim_start_matches('#').trim())
+        .find(|line| !line.is_empty() && !line.starts_with("!["))
+        .map(str::to_string)
+        .unwrap_or_else(|| date.format("%A, %-d %B %Y").to_string());
+
+    let mut tags = vec!["journal".to_string()];
+    tags.extend(entry.tags.into_iter().filter(|tag| tag != "journal"));
+
+    let mut metadata = BTreeMap::new();
+    metadata.insert(UUID_FIELD.to_string(), entry.uuid);
+    if let Some(location) = entry.location {
+        let place: Vec<String> = [
+            location.place_name,
+            location.locality_name,
+            location.country,
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        if !place.is_empty() {
+            metadata.insert("location".to_string(), place.join(", "));
+        }
+        if let (Some(latitude), Some(longitude)) = (location.latitude, location.longitude) {
+            metadata.insert(
+                "coordinates".to_string(),
+                format!("{:.5}, {:.5}", latitude, longitude),
+            );
+        }
+    }
+    if let Some(weather) = entry.weather {
+        let temperature = weather
+            .temperature_celsius
+            .map(|celsius| format!("{:.0}°C", celsius));
+        let parts: Vec<String> = [weather.conditions_description, temperature]
+            .into_iter()
+            .flatten()
+            .collect();
+        if !parts.is_empty() {
+            metadata.insert("weather".to_string(), parts.join(", "));
+        }
+    }
+
+    Note {
+        key: note::journal_key(date),
+        title,
+        tags,
+        content: format!("{}\n", content.trim_end()),
+        created_at: entry.creation_date,
+        modified_at: entry.modified_date.unwrap_or(entry.creation_date),
+        metadata,
+        ..Default::default()
+    }
+}
@@ -1,39 +1,83 @@
-use crate::colours::warn;
+use crate::colours::{info, warn};
 use crate::config::Config;
 use crate::error::AppError;
+use crate::export::ExportState;
 use crate::note::Note;
+use crate::reminder::Reminder;
+use crate::review::ReviewState;
 use crate::search;
-use crate::task::Task;
+#[cfg(feature = "llm")]
+use crate::semantic::Embedding;
+use crate::task::{Task, TaskStatus};
 use chrono::Utc;
-use serde_json;
-use sled::Db;
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use sled::{Db, IVec};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::Instant;
 use std::{env, fs, str};
-use tantivy::{Index, IndexWriter, TantivyDocument};
+use tantivy::Index;
+use tracing::{debug, trace};
+
+/// Name of the directory that marks a project-local vault.
+pub const LOCAL_VAULT_DIR: &str = ".medi";
+
+/// Walks up from the current directory looking for a project-local `.medi/` vault
+/// and returns the database path inside it.
+/// The home directory is skipped, since `~/.medi` holds the legacy global database.
+pub fn find_local_vault() -> Option<PathBuf> {
+    let cwd = env::current_dir().ok()?;
+    let home = dirs::home_dir();
+    cwd.ancestors()
+        .filter(|dir| Some(*dir) != home.as_deref())
+        .map(|dir| dir.join(LOCAL_VAULT_DIR))
+        .find(|vault| vault.is_dir())
+        .map(|vault| vault.join("medi_db"))
+}
 
 // Helper function to open the database
-// It checks the environment variable `MEDI_DB_PATH` for the database path.
-// If the variable is not set, it defaults to `~/.medi/medi_db`
+// An explicit `db_override` (the global `--db` flag) always wins.
+// Otherwise it checks the environment variable `MEDI_DB_PATH` for the database path.
+// Then it looks for a project-local `.medi/` vault in the current directory or an ancestor.
+// If none is found, it falls back to the config file and finally to `~/.medi/medi_db`
 // It ensures the parent directory exists before opening the database.
 // If the database cannot be opened, it returns an AppError::Sled.
 // If the home directory cannot be found, it returns an AppError::Io.
 // If the database is opened successfully, it returns a sled::Db instance.
-pub fn open(config: Config) -> Result<Db, AppError> {
-    let db_path = match env::var("MEDI_DB_PATH") {
-        Ok(path_str) => PathBuf::from(path_str),
-        Err(_) => config.db_path.clone().unwrap_or_else(|| {
-            // Default path logic
-            let mut path = dirs::home_dir().expect("Could not find home directory.");
-            path.push(".medi/medi_db");
-            path
-        }),
-    };
+pub fn open(config: Config, db_override: Option<&Path>) -> Result<Db, AppError> {
+    let db_path = db_path(&config, db_override);
 
     // Ensure the parent directory exists.
     if let Some(parent) = db_path.parent() {
         fs::create_dir_all(parent)?;
     }
-    sled::open(db_path).map_err(AppError::from)
+    let started = Instant::now();
+    let db = sled::open(&db_path)?;
+    debug!(path = %db_path.display(), elapsed = ?started.elapsed(), "opened database");
+    Ok(db)
+}
+
+/// Resolves the database path the way `open` does.
+pub fn db_path(config: &Config, db_override: Option<&Path>) -> PathBuf {
+    match (db_override, env::var("MEDI_DB_PATH")) {
+        (Some(path), _) => path.to_path_buf(),
+        (None, Ok(path_str)) => PathBuf::from(path_str),
+        (None, Err(_)) => find_local_vault()
+            .or_else(|| config.db_path.clone())
+            .unwrap_or_else(|| {
+                // Default path logic
+                let mut path = dirs::home_dir().expect("Could not find home directory.");
+                path.push(".medi/medi_db");
+                path
+            }),
+    }
+}
+
+/// The directory holding files attached to notes, such as imported photos.
+pub fn attachments_dir(config: &Config, db_override: Option<&Path>) -> PathBuf {
+    db_path(config, db_override).join("attachments")
 }
 
 /// Checks if a key exists in the database.
@@ -41,49 +85,369 @@ pub fn key_exists(db: &Db, key: &str) -> Result<bool, AppError> {
     db.contains_key(key).map_err(AppError::from)
 }
 
-/// Saves a Note object to the database by serializing it to JSON.
-pub fn save_note(db: &Db, note: &Note) -> Result<(), AppError> {
-    let json_bytes = serde_json::to_vec(note)?;
+/// Finds an existing note key that equals `key` ignoring case, but isn't identical to it.
+/// Used to stop "Meeting-Notes" and "meeting-notes" from silently diverging.
+pub fn find_key_ignoring_case(db: &Db, key: &str) -> Result<Option<String>, AppError> {
+    let lowered = key.to_lowercase();
+    Ok(get_all_keys(db)?
+        .into_iter()
+        .find(|existing| existing != key && existing.to_lowercase() == lowered))
+}
 
-    db.insert(&note.key, json_bytes)?;
+/// Saves a Note object to the database by serializing it to JSON, without touching the
+/// search index.
+#[cfg(test)]
+pub fn save_note(db: &Db, note: &Note) -> Result<(), AppError> {
+    let mut batch = sled::Batch::default();
+    stage_note(db, &mut batch, &note.key, Some(note))?;
+    db.apply_batch(batch)?;
     db.flush()?;
     Ok(())
 }
 
+/// Prefix of the chunks holding the content of large notes, outside their main record.
+const CONTENT_PREFIX: &str = "__content__/";
+/// The first key after every chunk key (`0` follows `/`), where scans over the notes resume.
+const CONTENT_END: &str = "__content__0";
+/// Content above this size is moved out of the note's record into chunks, so scans that
+/// deserialise every note don't have to wade through it.
+const LARGE_CONTENT_BYTES: usize = 256 * 1024;
+const CHUNK_BYTES: usize = 64 * 1024;
+
+/// The size limits set in the config: warn above the first, refuse to save above the second.
+static SIZE_LIMITS: OnceLock<(Option<u64>, Option<u64>)> = OnceLock::new();
+
+/// Sets the note size (in KB) above which saving warns, and the size above which it fails.
+pub fn set_note_size_limits(warn_kb: Option<u64>, max_kb: Option<u64>) {
+    let _ = SIZE_LIMITS.set((warn_kb, max_kb));
+}
+
+/// Set while a [`LockOverride`] is alive: saves and deletes may change locked notes.
+static OVERRIDE_LOCKS: AtomicBool = AtomicBool::new(false);
+
+/// Lets locked notes be changed and deleted until dropped.
+pub struct LockOverride;
+
+/// Allows changes to locked notes, for `--force` and for unlocking, until the returned
+/// override is dropped.
+pub fn override_locks() -> LockOverride {
+    OVERRIDE_LOCKS.store(true, Ordering::Relaxed);
+    LockOverride
+}
+
+impl Drop for LockOverride {
+    fn drop(&mut self) {
+        OVERRIDE_LOCKS.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Fails if the note stored under `key` is locked, unless a [`LockOverride`] is alive.
+/// Every save and delete of a note checks this, so no command can skip the lock.
+pub fn check_unlocked(db: &Db, key: &str) -> Result<(), AppError> {
+    /// Just the lock flag of a note's record, without the rest of it.
+    #[derive(Deserialize)]
+    struct LockState {
+        #[serde(default)]
+        locked: bool,
+    }
+
+    if OVERRIDE_LOCKS.load(Ordering::Relaxed) {
+        return Ok(());
+    }
+    let Some(record) = db.get(key)? else {
+        return Ok(());
+    };
+    // A record that doesn't parse as a note can't be locked either.
+    if serde_json::from_slice::<LockState>(&record).is_ok_and(|state| state.locked) {
+        return Err(AppError::Locked(key.to_string()));
+    }
+    Ok(())
+}
+
+fn chunk_prefix(key: &str) -> String {
+    // A NUL separator keeps the chunks of `a` apart from those of `a/b`.
+    format!("{}{}\0", CONTENT_PREFIX, key)
+}
+
+/// Adds the writes for saving `note` under `key` (or removing it, for `None`) to `batch`:
+/// the note's record, plus its content chunks when it's large. Chunks from an earlier,
+/// larger version are removed. Fails if the stored note is locked.
+fn stage_note(
+    db: &Db,
+    batch: &mut sled::Batch,
+    key: &str,
+    note: Option<&Note>,
+) -> Result<(), AppError> {
+    check_unlocked(db, key)?;
+    for old_chunk in db.scan_prefix(chunk_prefix(key)).keys() {
+        batch.remove(old_chunk?);
+    }
+    let Some(note) = note else {
+        batch.remove(key.as_bytes());
+        batch.remove(STATS_KEY);
+        return Ok(());
+    };
+
+    check_key(key)?;
+    check_size(note)?;
+    if !db.contains_key(key)? {
+        batch.remove(STATS_KEY);
+    }
+    if note.content.len() <= LARGE_CONTENT_BYTES {
+        batch.insert(key.as_bytes(), serde_json::to_vec(note)?);
+        return Ok(());
+    }
+    let mut record = serde_json::to_value(note)?;
+    record["content"] = serde_json::Value::String(String::new());
+    batch.insert(key.as_bytes(), serde_json::to_vec(&record)?);
+    for (i, chunk) in content_chunks(&note.content).enumerate() {
+        let chunk_key = format!("{}{:06}", chunk_prefix(key), i);
+        batch.insert(chunk_key.as_bytes(), chunk.as_bytes());
+    }
+    debug!(key, bytes = note.content.len(), "stored content in chunks");
+    Ok(())
+}
+
+/// Splits `content` into pieces of about [`CHUNK_BYTES`], on character boundaries.
+fn content_chunks(content: &str) -> impl Iterator<Item = &str> {
+    let mut rest = content;
+    std::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let mut end = CHUNK_BYTES.min(rest.len());
+        while !rest.is_char_boundary(end) {
+            end += 1;
+        }
+        let (chunk, tail) = rest.split_at(end);
+        rest = tail;
+        Some(chunk)
+    })
+}
+
+/// Warns about a note above the configured warning size, and refuses one above the limit.
+fn check_size(note: &Note) -> Result<(), AppError> {
+    let (warn_kb, max_kb) = SIZE_LIMITS.get().copied().unwrap_or((None, None));
+    let kb = note.content.len() as u64 / 1024;
+    if let Some(max_kb) = max_kb.filter(|&max_kb| kb > max_kb) {
+        return Err(AppError::NoteTooLarge(note.key.clone(), kb, max_kb));
+    }
+    if warn_kb.is_some_and(|warn_kb| warn_kb > 0 && kb > warn_kb) {
+        warn(&format!(
+            "'{}' is {} KB; very large notes slow down search and export.",
+            note.key, kb
+        ));
+    }
+    Ok(())
+}
+
+/// Puts the content of a large note, kept in chunks, back into it.
+fn load_content(db: &Db, note: &mut Note) -> Result<(), AppError> {
+    if !note.content.is_empty() {
+        return Ok(());
+    }
+    for chunk in db.scan_prefix(chunk_prefix(&note.key)).values() {
+        note.content.push_str(str::from_utf8(&chunk?)?);
+    }
+    Ok(())
+}
+
+/// Prefix of the note embeddings kept for semantic search.
+#[cfg(feature = "llm")]
+const EMBEDDING_PREFIX: &str = "__embedding__/";
+
+/// Prefix of the state of Markdown exports, by export directory.
+const EXPORT_STATE_PREFIX: &str = "__export__/";
+
+/// Prefix of the queue of notes whose search index update hasn't been committed yet.
+/// Entries are written in the same batch as the note itself, and removed once the index
+/// has caught up.
+const PENDING_INDEX_PREFIX: &str = "__index_pending__/";
+
+fn pending_index_key(key: &str) -> String {
+    format!("{}{}", PENDING_INDEX_PREFIX, key)
+}
+
+/// Set while an [`IndexBatch`] is alive: saves and deletes leave their index updates queued.
+static DEFER_INDEX: AtomicBool = AtomicBool::new(false);
+
+/// Collects the index updates of a command that touches many notes, and commits them all
+/// at once when dropped, instead of one commit per note.
+pub struct IndexBatch<'a> {
+    db: &'a Db,
+    index: &'a Index,
+}
+
+/// Defers index commits until the returned batch is dropped.
+pub fn batch_index_commits<'a>(db: &'a Db, index: &'a Index) -> IndexBatch<'a> {
+    DEFER_INDEX.store(true, Ordering::Relaxed);
+    IndexBatch { db, index }
+}
+
+impl Drop for IndexBatch<'_> {
+    fn drop(&mut self) {
+        DEFER_INDEX.store(false, Ordering::Relaxed);
+        // The queued updates are the pending index entries, so draining them is the commit.
+        match replay_pending_index(self.db, self.index) {
+            Ok(0) => {}
+            Ok(count) => info(&format!("Updated the search index for {} note(s).", count)),
+            Err(e) => warn(&format!(
+                "Saved, but the search index couldn't be updated ({}). It will be retried on the next command.",
+                e
+            )),
+        }
+    }
+}
+
 /// Saves a Note to the database and updates the search index.
+/// If the index can't be updated, the note is still saved and the update is queued.
 pub fn save_note_with_index(db: &Db, note: &Note, index: &Index) -> Result<(), AppError> {
-    // Save to the primary database first
-    save_note(db, note)?;
+    save_notes_with_index(db, std::slice::from_ref(note), index)
+}
 
-    // Update the search index
-    let mut index_writer: tantivy::IndexWriter<tantivy::TantivyDocument> =
-        index.writer(50_000_000)?;
+/// Saves several notes to the database and updates the search index with a single commit.
+/// If the index can't be updated, the notes are still saved and the update is queued.
+pub fn save_notes_with_index(db: &Db, notes: &[Note], index: &Index) -> Result<(), AppError> {
+    if notes.is_empty() {
+        return Ok(());
+    }
 
-    // For updates, first delete the old document using the search module function.
-    search::delete_note_from_index(&note.key, &mut index_writer)?;
+    let writes = notes
+        .iter()
+        .map(|note| (note.key.clone(), Some(note)))
+        .collect();
+    let keys = write_queued(db, writes)?;
+    if DEFER_INDEX.load(Ordering::Relaxed) {
+        return Ok(());
+    }
+    let indexed = index_changes(index, notes, &[]);
+    finish_queued(db, &keys, indexed)
+}
+
+/// Deletes a note from the database and the search index.
+/// If the index can't be updated, the note is still deleted and the update is queued.
+pub fn delete_note_with_index(db: &Db, key: &str, index: &Index) -> Result<(), AppError> {
+    delete_notes_with_index(db, &[key.to_string()], index)
+}
+
+/// Deletes several notes from the database and the search index with a single commit.
+/// Nothing is deleted if any of the keys doesn't exist.
+pub fn delete_notes_with_index(db: &Db, keys: &[String], index: &Index) -> Result<(), AppError> {
+    if let Some(missing) = keys
+        .iter()
+        .find(|key| !db.contains_key(key).unwrap_or(false))
+    {
+        return Err(AppError::KeyNotFound(missing.clone()));
+    }
+    if keys.is_empty() {
+        return Ok(());
+    }
+    let writes = keys.iter().map(|key| (key.clone(), None)).collect();
+    let keys = write_queued(db, writes)?;
+    if DEFER_INDEX.load(Ordering::Relaxed) {
+        return Ok(());
+    }
+    let deletes: Vec<&str> = keys.iter().map(String::as_str).collect();
+    let indexed = index_changes(index, &[], &deletes);
+    finish_queued(db, &keys, indexed)
+}
+
+/// Applies `writes` (a `None` note removes the key) and queues an index update per key,
+/// in one atomic batch. Returns the written keys.
+fn write_queued(db: &Db, writes: Vec<(String, Option<&Note>)>) -> Result<Vec<String>, AppError> {
+    let mut keys = Vec::with_capacity(writes.len());
+    let mut batch = sled::Batch::default();
+    for (key, note) in writes {
+        stage_note(db, &mut batch, &key, note)?;
+        batch.insert(pending_index_key(&key).as_bytes(), &b""[..]);
+        keys.push(key);
+    }
+    db.apply_batch(batch)?;
+    db.flush()?;
+    Ok(keys)
+}
 
-    // Add the new/updated document using the search module function.
-    search::add_note_to_index(note, &mut index_writer)?;
+/// Dequeues the index updates for `keys` once the index has caught up. If it couldn't be
+/// updated, they stay queued for [`replay_pending_index`] and the user is warned.
+fn finish_queued(db: &Db, keys: &[String], indexed: Result<(), AppError>) -> Result<(), AppError> {
+    if let Err(e) = indexed {
+        warn(&format!(
+            "Saved, but the search index couldn't be updated ({}). It will be retried on the next command.",
+            e
+        ));
+        return Ok(());
+    }
+    let mut batch = sled::Batch::default();
+    for key in keys {
+        batch.remove(pending_index_key(key).as_bytes());
+    }
+    db.apply_batch(batch)?;
+    db.flush()?;
+    Ok(())
+}
 
-    // Commit changes to the index
+/// Re-indexes `upserts` and removes `deletes` from the search index in a single commit.
+fn index_changes(index: &Index, upserts: &[Note], deletes: &[&str]) -> Result<(), AppError> {
+    let started = Instant::now();
+    let mut index_writer = search::writer(index, search::UPDATE_HEAP_MB)?;
+    for key in deletes {
+        trace!(key, "removing from index");
+        search::delete_note_from_index(key, &mut index_writer)?;
+    }
+    for note in upserts {
+        trace!(key = %note.key, "indexing");
+        // For updates, first delete the old document.
+        search::delete_note_from_index(&note.key, &mut index_writer)?;
+        search::add_note_to_index(note, &mut index_writer)?;
+    }
     index_writer.commit()?;
+    debug!(
+        upserts = upserts.len(),
+        deletes = deletes.len(),
+        elapsed = ?started.elapsed(),
+        "committed index changes"
+    );
     Ok(())
 }
 
-/// Deletes a note from the database and the search index.
-pub fn delete_note_with_index(db: &Db, key: &str, index: &Index) -> Result<(), AppError> {
-    // Delete from the primary database first
-    match delete_note(db, key) {
-        Ok(()) => {
-            // Remove from the search index only if the note existed and was deleted
-            let mut index_writer: IndexWriter<TantivyDocument> = index.writer(50_000_000)?;
-            // Use the dedicated function from the search module
-            search::delete_note_from_index(key, &mut index_writer)?;
-            index_writer.commit()?;
-            Ok(())
+/// The number of notes waiting in the queue of pending index updates.
+pub fn pending_index_count(db: &Db) -> Result<usize, AppError> {
+    Ok(db.scan_prefix(PENDING_INDEX_PREFIX).count())
+}
+
+/// Drains the queue of pending index updates, left by saves whose index update failed or
+/// was interrupted by a crash. Each queued note is re-indexed from what the database holds
+/// now. Returns the number of notes re-indexed.
+pub fn replay_pending_index(db: &Db, index: &Index) -> Result<usize, AppError> {
+    let markers = db
+        .scan_prefix(PENDING_INDEX_PREFIX)
+        .keys()
+        .collect::<Result<Vec<_>, _>>()?;
+    if markers.is_empty() {
+        return Ok(0);
+    }
+    debug!(queued = markers.len(), "replaying pending index updates");
+
+    let mut upserts = Vec::new();
+    let mut deletes = Vec::new();
+    for marker in &markers {
+        let key = String::from_utf8_lossy(&marker[PENDING_INDEX_PREFIX.len()..]).into_owned();
+        match get_note(db, &key) {
+            Ok(note) => upserts.push(note),
+            Err(AppError::KeyNotFound(_)) => deletes.push(key),
+            Err(e) => return Err(e),
         }
-        Err(e) => Err(e),
     }
+    let deletes: Vec<&str> = deletes.iter().map(String::as_str).collect();
+    index_changes(index, &upserts, &deletes)?;
+
+    let mut batch = sled::Batch::default();
+    for marker in &markers {
+        batch.remove(marker.clone());
+    }
+    db.apply_batch(batch)?;
+    db.flush()?;
+    Ok(markers.len())
 }
 
 /// Retrieves a Note object from the database by deserializing it from JSON.
@@ -104,7 +468,8 @@ pub fn get_note(db: &Db, key: &str) -> Result<Note, AppError> {
             key
         )));
     }
-    let note: Note = serde_json::from_slice(&value_ivec).map_err(AppError::from)?;
+    let mut note: Note = serde_json::from_slice(&value_ivec).map_err(AppError::from)?;
+    load_content(db, &mut note)?;
     Ok(note)
 }
 
@@ -114,11 +479,14 @@ pub fn get_note(db: &Db, key: &str) -> Result<Note, AppError> {
 // If the key does not exist, it returns an AppError::KeyNotFound.
 // If the key exists, it removes the note from the database and flushes the changes.
 // If there is an error during the process, it returns an AppError.
+#[allow(dead_code)] // Commands delete through `delete_note_with_index`.
 pub fn delete_note(db: &Db, key: &str) -> Result<(), AppError> {
     if !db.contains_key(key)? {
         return Err(AppError::KeyNotFound(key.to_string()));
     }
-    db.remove(key)?;
+    let mut batch = sled::Batch::default();
+    stage_note(db, &mut batch, key, None)?;
+    db.apply_batch(batch)?;
     db.flush()?;
     Ok(())
 }
@@ -140,6 +508,7 @@ pub fn delete_tasks_for_note(db: &Db, note_key: &str) -> Result<usize, AppError>
         let task_key = format!("tasks/{}", task.id);
         batch.remove(task_key.as_bytes());
     }
+    batch.remove(STATS_KEY);
 
     db.apply_batch(batch)?;
     db.flush()?;
@@ -147,21 +516,75 @@ pub fn delete_tasks_for_note(db: &Db, note_key: &str) -> Result<usize, AppError>
     Ok(tasks_to_delete.len())
 }
 
+/// Key prefixes of internal values, tasks and reminders, which share the tree with notes.
+const RESERVED_PREFIXES: [&str; 3] = ["__", "tasks/", "reminders/"];
+
+/// Whether a database key holds a note, rather than an internal value, task or reminder.
+fn is_note_key(key: &[u8]) -> bool {
+    !RESERVED_PREFIXES
+        .iter()
+        .any(|prefix| key.starts_with(prefix.as_bytes()))
+}
+
+/// Refuses a note key under a reserved prefix: the note would be hidden from every
+/// listing and read back as a task or reminder.
+pub fn check_key(key: &str) -> Result<(), AppError> {
+    if is_note_key(key.as_bytes()) {
+        Ok(())
+    } else {
+        Err(AppError::ReservedKey(key.to_string()))
+    }
+}
+
+/// Iterates over the whole tree except the content chunks of large notes, which scans
+/// would otherwise read only to skip them.
+fn note_records(db: &Db) -> impl Iterator<Item = sled::Result<(IVec, IVec)>> {
+    db.range(..CONTENT_PREFIX).chain(db.range(CONTENT_END..))
+}
+
+/// Counts the notes without reading them, for callers that need to be fast.
+pub fn count_notes(db: &Db) -> Result<usize, AppError> {
+    let mut count = 0;
+    for result in note_records(db) {
+        let (key, _) = result?;
+        if is_note_key(&key) {
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// Returns the keys of all notes, without deserialising them or loading their content.
+pub fn get_all_keys(db: &Db) -> Result<Vec<String>, AppError> {
+    let mut keys = Vec::new();
+    for result in note_records(db) {
+        let (key, _) = result?;
+        if is_note_key(&key) {
+            keys.push(str::from_utf8(&key)?.to_string());
+        }
+    }
+    Ok(keys)
+}
+
 /// Returns all notes as a vector of `Note` structs.
 pub fn get_all_notes(db: &Db) -> Result<Vec<Note>, AppError> {
+    let started = Instant::now();
     let mut notes = Vec::new();
 
-    for result in db.iter() {
+    for result in note_records(db) {
         let (key_bytes, value_bytes) = result?;
 
-        // Skip internal keys and task-related entries.
-        if key_bytes.starts_with(b"__") || key_bytes.starts_with(b"tasks/") {
+        // Skip internal keys, tasks and reminders.
+        if !is_note_key(&key_bytes) {
             continue;
         }
 
         // Attempt to deserialize the value as a Note.
         match serde_json::from_slice::<Note>(&value_bytes) {
-            Ok(note) => notes.push(note),
+            Ok(mut note) => {
+                load_content(db, &mut note)?;
+                notes.push(note);
+            }
             Err(_) => {
                 // If deserialization fails, it could be an empty note or corrupted data.
                 // We can handle empty notes gracefully here.
@@ -174,6 +597,7 @@ pub fn get_all_notes(db: &Db) -> Result<Vec<Note>, AppError> {
                             tags: Vec::new(),
                             created_at: Utc::now(),
                             modified_at: Utc::now(),
+                            ..Default::default()
                         });
                     }
                 } else if let Ok(key) = str::from_utf8(&key_bytes) {
@@ -187,6 +611,7 @@ pub fn get_all_notes(db: &Db) -> Result<Vec<Note>, AppError> {
         }
     }
 
+    debug!(count = notes.len(), elapsed = ?started.elapsed(), "loaded all notes");
     Ok(notes)
 }
 
@@ -196,7 +621,10 @@ pub fn get_all_notes(db: &Db) -> Result<Vec<Note>, AppError> {
 pub fn save_task(db: &Db, task: &Task) -> Result<(), AppError> {
     let key = format!("tasks/{}", task.id);
     let json_bytes = serde_json::to_vec(task)?;
-    db.insert(key, json_bytes)?;
+    let mut batch = sled::Batch::default();
+    batch.insert(key.as_bytes(), json_bytes);
+    batch.remove(STATS_KEY);
+    db.apply_batch(batch)?;
     db.flush()?;
     Ok(())
 }
@@ -207,7 +635,10 @@ pub fn delete_task(db: &Db, task_id: u64) -> Result<(), AppError> {
     if !db.contains_key(&key)? {
         return Err(AppError::KeyNotFound(key));
     }
-    db.remove(key)?;
+    let mut batch = sled::Batch::default();
+    batch.remove(key.as_bytes());
+    batch.remove(STATS_KEY);
+    db.apply_batch(batch)?;
     db.flush()?;
     Ok(())
 }
@@ -224,23 +655,26 @@ pub fn get_all_tasks(db: &Db) -> Result<Vec<Task>, AppError> {
         .collect()
 }
 
-/// A simple way to get the next available ID for a new task.
-/// This uses sled's built-in ID generation feature.
-/// It is amazing but gives u64 IDs, which is overkill for our needs, no one wants ID 2000001 for a task.
+// A simple way to get the next available ID for a new task.
+// This uses sled's built-in ID generation feature.
+// It is amazing but gives u64 IDs, which is overkill for our needs, no one wants ID 2000001 for a task.
 /*pub fn get_next_task_id_sled(db: &Db) -> Result<u64, AppError> {
     // This is a simple counter stored at a known key.
     let id = db.generate_id()?;
     Ok(id)
 }*/
 
-/// Get the next available ID using a homegrown method.
-/// Got help by Gemini for this one.
+/// Get the next available task ID.
 pub fn get_next_task_id(db: &Db) -> Result<u64, AppError> {
-    const TASK_COUNTER_KEY: &[u8] = b"__counter__/tasks";
+    next_counter_value(db, b"__counter__/tasks")
+}
 
+/// Get the next available ID from the counter at `counter_key` using a homegrown method.
+/// Got help by Gemini for this one.
+fn next_counter_value(db: &Db, counter_key: &[u8]) -> Result<u64, AppError> {
     // `update_and_fetch` is an atomic operation, which makes it safe
     // to use even if multiple programs were running at once.
-    let new_id_bytes = db.update_and_fetch(TASK_COUNTER_KEY, |old_value| {
+    let new_id_bytes = db.update_and_fetch(counter_key, |old_value| {
         // If there's an old value, parse it. Otherwise, start at 0.
         let old_id = match old_value {
             Some(bytes) => {
@@ -265,18 +699,204 @@ pub fn get_next_task_id(db: &Db) -> Result<u64, AppError> {
             u64::from_le_bytes(buf)
         }
         None => {
-            return Err(AppError::Database(
-                "Failed to update task counter".to_string(),
-            ))
+            return Err(AppError::Database(format!(
+                "Failed to update counter '{}'",
+                String::from_utf8_lossy(counter_key)
+            )))
         }
     };
 
     Ok(new_id)
 }
 
-/// Resets the task ID counter to 0.
-/// This is mainly useful for testing purposes.
-/// In a real-world scenario, resetting the counter could lead to ID collisions.
+// -------------------- Reminders --------------------
+
+/// Saves a reminder to the database.
+pub fn save_reminder(db: &Db, reminder: &Reminder) -> Result<(), AppError> {
+    let key = format!("reminders/{}", reminder.id);
+    let json_bytes = serde_json::to_vec(reminder)?;
+    db.insert(key, json_bytes)?;
+    db.flush()?;
+    Ok(())
+}
+
+/// Deletes a reminder by its ID.
+pub fn delete_reminder(db: &Db, reminder_id: u64) -> Result<(), AppError> {
+    let key = format!("reminders/{}", reminder_id);
+    if db.remove(key)?.is_none() {
+        return Err(AppError::ReminderNotFound(reminder_id));
+    }
+    db.flush()?;
+    Ok(())
+}
+
+/// Retrieves all reminders from the database, soonest first.
+pub fn get_all_reminders(db: &Db) -> Result<Vec<Reminder>, AppError> {
+    let mut reminders = db
+        .scan_prefix("reminders/")
+        .values()
+        .map(|result| {
+            let value_bytes = result?;
+            let reminder: Reminder = serde_json::from_slice(&value_bytes)?;
+            Ok(reminder)
+        })
+        .collect::<Result<Vec<Reminder>, AppError>>()?;
+    reminders.sort_by_key(|reminder| reminder.due_at);
+    Ok(reminders)
+}
+
+/// Deletes all reminders attached to a note, returning how many were removed.
+pub fn delete_reminders_for_note(db: &Db, note_key: &str) -> Result<usize, AppError> {
+    let mut batch = sled::Batch::default();
+    let mut count = 0;
+    for reminder in get_all_reminders(db)? {
+        if reminder.note_key == note_key {
+            batch.remove(format!("reminders/{}", reminder.id).as_bytes());
+            count += 1;
+        }
+    }
+    if count > 0 {
+        db.apply_batch(batch)?;
+        db.flush()?;
+    }
+    Ok(count)
+}
+
+/// Get the next available reminder ID.
+pub fn get_next_reminder_id(db: &Db) -> Result<u64, AppError> {
+    next_counter_value(db, b"__counter__/reminders")
+}
+
+// -------------------- Stats --------------------
+
+/// The cached [`Stats`]. Writes that add or remove notes, or change tasks, drop it in the
+/// same batch, and the next read counts again.
+const STATS_KEY: &str = "__stats__";
+
+/// The counts shown by `status --prompt`.
+#[derive(Serialize, Deserialize)]
+pub struct Stats {
+    pub notes: usize,
+    pub tasks: usize,
+    pub done_tasks: usize,
+    pub prio_tasks: usize,
+}
+
+/// Returns the note and task counts, from the cache when no write has changed them since
+/// they were last counted.
+pub fn get_stats(db: &Db) -> Result<Stats, AppError> {
+    if let Some(stats) = db
+        .get(STATS_KEY)?
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+    {
+        return Ok(stats);
+    }
+    let tasks = get_all_tasks(db)?;
+    let stats = Stats {
+        notes: count_notes(db)?,
+        tasks: tasks.len(),
+        done_tasks: tasks
+            .iter()
+            .filter(|t| matches!(t.status, TaskStatus::Done))
+            .count(),
+        prio_tasks: tasks
+            .iter()
+            .filter(|t| matches!(t.status, TaskStatus::Prio))
+            .count(),
+    };
+    db.insert(STATS_KEY, serde_json::to_vec(&stats)?)?;
+    db.flush()?;
+    debug!(notes = stats.notes, tasks = stats.tasks, "counted stats");
+    Ok(stats)
+}
+
+// -------------------- Reviews --------------------
+
+/// Saves the spaced-repetition schedule of a review item.
+pub fn save_review_state(db: &Db, state: &ReviewState) -> Result<(), AppError> {
+    let key = format!("__review__/{}", state.item_id);
+    let json_bytes = serde_json::to_vec(state)?;
+    db.insert(key, json_bytes)?;
+    db.flush()?;
+    Ok(())
+}
+
+/// Retrieves the schedules of all reviewed items, by item ID.
+pub fn get_review_states(db: &Db) -> Result<HashMap<String, ReviewState>, AppError> {
+    db.scan_prefix("__review__/")
+        .values()
+        .map(|result| {
+            let value_bytes = result?;
+            let state: ReviewState = serde_json::from_slice(&value_bytes)?;
+            Ok((state.item_id.clone(), state))
+        })
+        .collect()
+}
+
+// -------------------- Embeddings --------------------
+
+/// Retrieves the stored note embeddings used by semantic search, by note key.
+#[cfg(feature = "llm")]
+pub fn get_embeddings(db: &Db) -> Result<HashMap<String, Embedding>, AppError> {
+    db.scan_prefix(EMBEDDING_PREFIX)
+        .map(|result| {
+            let (key_bytes, value_bytes) = result?;
+            let key = str::from_utf8(&key_bytes[EMBEDDING_PREFIX.len()..])?.to_string();
+            Ok((key, serde_json::from_slice(&value_bytes)?))
+        })
+        .collect()
+}
+
+/// Saves note embeddings in one batch.
+#[cfg(feature = "llm")]
+pub fn save_embeddings(db: &Db, embeddings: &[(String, Embedding)]) -> Result<(), AppError> {
+    let mut batch = sled::Batch::default();
+    for (key, embedding) in embeddings {
+        batch.insert(
+            format!("{}{}", EMBEDDING_PREFIX, key).as_bytes(),
+            serde_json::to_vec(embedding)?,
+        );
+    }
+    db.apply_batch(batch)?;
+    db.flush()?;
+    Ok(())
+}
+
+/// Deletes the embeddings of the given notes.
+#[cfg(feature = "llm")]
+pub fn delete_embeddings(db: &Db, keys: &[String]) -> Result<(), AppError> {
+    let mut batch = sled::Batch::default();
+    for key in keys {
+        batch.remove(format!("{}{}", EMBEDDING_PREFIX, key).as_bytes());
+    }
+    db.apply_batch(batch)?;
+    db.flush()?;
+    Ok(())
+}
+
+fn export_state_key(dir: &Path) -> Result<String, AppError> {
+    let dir = std::path::absolute(dir)?;
+    Ok(format!("{}{}", EXPORT_STATE_PREFIX, dir.display()))
+}
+
+/// Retrieves what the last Markdown export to `dir` wrote, empty if there was none.
+pub fn get_export_state(db: &Db, dir: &Path) -> Result<ExportState, AppError> {
+    match db.get(export_state_key(dir)?)? {
+        Some(bytes) => Ok(serde_json::from_slice(&bytes)?),
+        None => Ok(ExportState::new()),
+    }
+}
+
+/// Saves what a Markdown export to `dir` wrote, for the next `export --changed`.
+pub fn save_export_state(db: &Db, dir: &Path, state: &ExportState) -> Result<(), AppError> {
+    db.insert(export_state_key(dir)?, serde_json::to_vec(state)?)?;
+    db.flush()?;
+    Ok(())
+}
+
+// Resets the task ID counter to 0.
+// This is mainly useful for testing purposes.
+// In a real-world scenario, resetting the counter could lead to ID collisions.
 /*pub fn reset_task_counter(db: &Db) -> Result<(), AppError> {
     const TASK_COUNTER_KEY: &[u8] = b"__counter__/tasks";
     db.insert(TASK_COUNTER_KEY, &0u64.to_le_bytes())?;
@@ -300,6 +920,7 @@ pub fn delete_all_tasks(db: &Db) -> Result<usize, AppError> {
         batch.remove(key.clone());
         count += 1;
     }
+    batch.remove(STATS_KEY);
 
     // Apply the batch deletion.
     db.apply_batch(batch)?;
@@ -315,6 +936,7 @@ mod tests {
     use crate::note::Note;
     use chrono::Utc;
     use sled::Config;
+    use tantivy::{IndexWriter, TantivyDocument};
 
     #[test]
     fn test_save_and_get_note_success() {
@@ -331,6 +953,7 @@ mod tests {
             content: "Mock note content".to_string(),
             created_at: Utc::now(),
             modified_at: Utc::now(),
+            ..Default::default()
         };
 
         // Execute save_note
@@ -356,6 +979,7 @@ mod tests {
             tags: vec![],
             created_at: Utc::now(),
             modified_at: Utc::now(),
+            ..Default::default()
         };
         let note2 = Note {
             key: "note-b".to_string(),
@@ -364,6 +988,7 @@ mod tests {
             tags: vec![],
             created_at: Utc::now(),
             modified_at: Utc::now(),
+            ..Default::default()
         };
         save_note(&db, &note1).unwrap();
         save_note(&db, &note2).unwrap();
@@ -408,6 +1033,7 @@ mod tests {
             tags: vec![],
             created_at: Utc::now(),
             modified_at: Utc::now(),
+            ..Default::default()
         };
         save_note(&db, &original_note).unwrap();
 
@@ -418,6 +1044,7 @@ mod tests {
             tags: vec!["updated".to_string()],
             created_at: original_note.created_at, // creation time should not change
             modified_at: Utc::now(),
+            ..Default::default()
         };
 
         let result = save_note(&db, &updated_note);
@@ -428,4 +1055,79 @@ mod tests {
         assert_eq!(retrieved_note.title, "Updated Title");
         assert_eq!(retrieved_note.tags, vec!["updated"]);
     }
+
+    fn note(key: &str, content: &str) -> Note {
+        Note {
+            key: key.to_string(),
+            title: key.to_string(),
+            content: content.to_string(),
+            created_at: Utc::now(),
+            modified_at: Utc::now(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_save_with_index_queues_update_when_index_fails() {
+        let db = Config::new().temporary(true).open().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let index = search::open_index(dir.path()).unwrap();
+        save_note_with_index(&db, &note("gone", "obsolete"), &index).unwrap();
+
+        // Holding the writer lock makes the index updates fail.
+        let lock: IndexWriter<TantivyDocument> = index.writer(15_000_000).unwrap();
+        save_note_with_index(&db, &note("new", "quokka"), &index).unwrap();
+        delete_note_with_index(&db, "gone", &index).unwrap();
+        assert_eq!(get_note(&db, "new").unwrap().content, "quokka");
+        assert!(!key_exists(&db, "gone").unwrap());
+        assert_eq!(pending_index_count(&db).unwrap(), 2);
+
+        drop(lock);
+        assert_eq!(replay_pending_index(&db, &index).unwrap(), 2);
+        assert_eq!(search::search_notes(&index, "quokka").unwrap(), vec!["new"]);
+        assert!(search::search_notes(&index, "obsolete").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_replay_pending_index_reindexes_interrupted_saves() {
+        let db = Config::new().temporary(true).open().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let index = search::open_index(dir.path()).unwrap();
+
+        // Simulate a crash after the database write but before the index commit.
+        let crashed = note("crashed", "zanzibar");
+        write_queued(&db, vec![("crashed".to_string(), Some(&crashed))]).unwrap();
+        assert!(search::search_notes(&index, "zanzibar").unwrap().is_empty());
+
+        assert_eq!(replay_pending_index(&db, &index).unwrap(), 1);
+        assert_eq!(
+            search::search_notes(&index, "zanzibar").unwrap(),
+            vec!["crashed"]
+        );
+        assert_eq!(replay_pending_index(&db, &index).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_large_note_is_chunked_and_cleaned_up() {
+        let db = Config::new().temporary(true).open().unwrap();
+        let big = note("big", &"ünïcödé log line\n".repeat(20_000));
+        save_note(&db, &big).unwrap();
+
+        let stored: Note = serde_json::from_slice(&db.get("big").unwrap().unwrap()).unwrap();
+        assert!(stored.content.is_empty());
+        assert_eq!(get_note(&db, "big").unwrap().content, big.content);
+        assert_eq!(get_all_notes(&db).unwrap()[0].content, big.content);
+        // Scans over the notes never touch the chunks.
+        assert_eq!(note_records(&db).count(), 1);
+        assert_eq!(get_all_keys(&db).unwrap(), ["big"]);
+
+        // Shrinking the note drops its chunks.
+        save_note(&db, &note("big", "small now")).unwrap();
+        assert_eq!(db.scan_prefix(chunk_prefix("big")).count(), 0);
+        assert_eq!(get_note(&db, "big").unwrap().content, "small now");
+
+        save_note(&db, &big).unwrap();
+        delete_note(&db, "big").unwrap();
+        assert_eq!(db.scan_prefix(chunk_prefix("big")).count(), 0);
+    }
 }
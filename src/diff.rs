@@ -0,0 +1,57 @@
+use crate::colours::{paint, Role};
+use similar::{ChangeTag, TextDiff};
+
+/// Builds a coloured unified diff between two texts, labelled with the given names.
+/// Returns an empty string when the texts are identical.
+pub fn unified_diff(old: &str, new: &str, old_name: &str, new_name: &str) -> String {
+    let diff = TextDiff::from_lines(old, new);
+    let mut out = String::new();
+
+    for (i, group) in diff.grouped_ops(3).iter().enumerate() {
+        if i == 0 {
+            out.push_str(&format!(
+                "{}\n",
+                paint(&format!("--- {}", old_name), Role::Error)
+            ));
+            out.push_str(&format!(
+                "{}\n",
+                paint(&format!("+++ {}", new_name), Role::Success)
+            ));
+        }
+        let (first, last) = (&group[0], &group[group.len() - 1]);
+        let old_range = first.old_range().start..last.old_range().end;
+        let new_range = first.new_range().start..last.new_range().end;
+        out.push_str(&format!(
+            "{}\n",
+            paint(
+                format!(
+                    "@@ -{},{} +{},{} @@",
+                    old_range.start + 1,
+                    old_range.len(),
+                    new_range.start + 1,
+                    new_range.len()
+                )
+                .as_str(),
+                Role::Info
+            )
+        ));
+
+        for op in group {
+            for change in diff.iter_changes(op) {
+                let line = change.to_string_lossy();
+                let line = line.trim_end_matches('\n');
+                match change.tag() {
+                    ChangeTag::Delete => {
+                        out.push_str(&format!("{}\n", paint(&format!("-{}", line), Role::Error)))
+                    }
+                    ChangeTag::Insert => out.push_str(&format!(
+                        "{}\n",
+                        paint(&format!("+{}", line), Role::Success)
+                    )),
+                    ChangeTag::Equal => out.push_str(&format!(" {}\n", line)),
+                }
+            }
+        }
+    }
+    out
+}
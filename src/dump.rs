@@ -0,0 +1,139 @@
+use crate::error::AppError;
+use crate::note::{self, Note};
+use crate::reminder::Reminder;
+use crate::task::Task;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// The file listing every note file of a raw dump.
+pub const MANIFEST: &str = "manifest.json";
+/// Marks a manifest as belonging to a raw dump.
+const FORMAT: &str = "medi-raw-dump";
+/// The newest dump layout this version reads and the one it writes.
+const VERSION: u32 = 1;
+
+/// The index of a raw dump: what wrote it and where each note's file is.
+#[derive(Serialize, Deserialize)]
+pub struct Manifest {
+    pub format: String,
+    pub version: u32,
+    pub medi_version: String,
+    pub exported_at: DateTime<Utc>,
+    pub notes: Vec<ManifestEntry>,
+}
+
+/// A note file in the dump, with the SHA-256 digest of its contents.
+#[derive(Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub key: String,
+    pub file: String,
+    pub sha256: String,
+}
+
+/// What one note file holds: the note with every field as stored, and its tasks and
+/// reminders.
+#[derive(Serialize, Deserialize)]
+pub struct NoteFile {
+    pub note: Note,
+    #[serde(default)]
+    pub tasks: Vec<Task>,
+    #[serde(default)]
+    pub reminders: Vec<Reminder>,
+}
+
+/// The file name for a note: a readable slug of the key plus part of the key's digest,
+/// so keys differing only in case or punctuation never share a file.
+fn file_name(key: &str) -> String {
+    let slug = note::slugify(key).replace('/', "--");
+    format!("notes/{}-{}.json", slug, &note::digest(key)[..12])
+}
+
+/// Writes one JSON file per note into `dir/notes`, and the manifest last, so a dump
+/// without a manifest is recognisably incomplete. Returns the number of notes written.
+pub fn write(
+    notes: Vec<Note>,
+    tasks: &[Task],
+    reminders: &[Reminder],
+    dir: &Path,
+) -> Result<usize, AppError> {
+    fs::create_dir_all(dir.join("notes"))?;
+    let mut entries = Vec::new();
+    for note in notes {
+        let file = file_name(&note.key);
+        let contents = NoteFile {
+            tasks: tasks
+                .iter()
+                .filter(|task| task.note_key == note.key)
+                .cloned()
+                .collect(),
+            reminders: reminders
+                .iter()
+                .filter(|reminder| reminder.note_key == note.key)
+                .cloned()
+                .collect(),
+            note,
+        };
+        let json = serde_json::to_string_pretty(&contents)?;
+        fs::write(dir.join(&file), &json)?;
+        entries.push(ManifestEntry {
+            key: contents.note.key,
+            file,
+            sha256: note::digest(&json),
+        });
+    }
+
+    let count = entries.len();
+    let manifest = Manifest {
+        format: FORMAT.to_string(),
+        version: VERSION,
+        medi_version: env!("CARGO_PKG_VERSION").to_string(),
+        exported_at: Utc::now(),
+        notes: entries,
+    };
+    fs::write(dir.join(MANIFEST), serde_json::to_string_pretty(&manifest)?)?;
+    Ok(count)
+}
+
+/// Reads the manifest of the dump in `dir`, refusing anything that isn't a raw dump this
+/// version understands.
+pub fn read_manifest(dir: &Path) -> Result<Manifest, AppError> {
+    let path = dir.join(MANIFEST);
+    let manifest: Manifest = serde_json::from_str(
+        &fs::read_to_string(&path)
+            .map_err(|e| AppError::Dump(format!("can't read '{}': {}", path.display(), e)))?,
+    )?;
+    if manifest.format != FORMAT {
+        return Err(AppError::Dump(format!(
+            "'{}' is not a medi raw dump",
+            path.display()
+        )));
+    }
+    if manifest.version > VERSION {
+        return Err(AppError::Dump(format!(
+            "the dump has layout version {}, but this medi reads up to {}; upgrade medi to import it",
+            manifest.version, VERSION
+        )));
+    }
+    Ok(manifest)
+}
+
+/// Reads the note file of a manifest entry, checking it against its digest.
+pub fn read_note(dir: &Path, entry: &ManifestEntry) -> Result<NoteFile, AppError> {
+    let json = fs::read_to_string(dir.join(&entry.file))?;
+    if note::digest(&json) != entry.sha256 {
+        return Err(AppError::Dump(format!(
+            "'{}' doesn't match its digest in the manifest",
+            entry.file
+        )));
+    }
+    let contents: NoteFile = serde_json::from_str(&json)?;
+    if contents.note.key != entry.key {
+        return Err(AppError::Dump(format!(
+            "'{}' holds '{}', but the manifest lists it for '{}'",
+            entry.file, contents.note.key, entry.key
+        )));
+    }
+    Ok(contents)
+}
@@ -37,12 +37,39 @@ pub enum AppError {
     #[error("Key '{0}' already exists. Use 'edit' to modify it.")]
     KeyExists(String),
 
+    #[error("Key '{0}' is reserved: keys can't start with '__', 'tasks/' or 'reminders/'")]
+    ReservedKey(String),
+
+    #[error("Key '{0}' differs only in case from existing note '{1}'")]
+    KeyCaseConflict(String, String),
+
+    #[error("'{0}' matches the titles of several notes: {1}")]
+    AmbiguousTitle(String, String),
+
+    #[error("Note '{0}' is locked. Unlock it with 'medi unlock {0}' or pass --force.")]
+    Locked(String),
+
+    #[error("Note '{0}' is not a bookmark")]
+    NotABookmark(String),
+
     #[error("Self-update error: {0}")]
     SelfUpdate(#[from] self_update::errors::Error),
 
+    #[error("Update verification failed: {0}")]
+    UpdateVerification(String),
+
     #[error("Linting error: {0}")]
     Lint(#[from] LintError), // Ensure LintError implements From for this to work with
 
+    #[error("Invalid pattern: {0}")]
+    Pattern(String),
+
+    #[error("Raw dump error: {0}")]
+    Dump(String),
+
+    #[error("Invalid query: {0}")]
+    Query(String),
+
     #[error("Search operation failed: {0}")]
     Search(String),
 
@@ -52,9 +79,47 @@ pub enum AppError {
     #[error("Task with ID '{0}' not found")]
     TaskNotFound(u64),
 
+    #[error("Server error: {0}")]
+    Server(String),
+
+    #[error("Reminder with ID '{0}' not found")]
+    ReminderNotFound(u64),
+
     #[error("Configuration error: {0}")]
     ConfigError(String),
 
     #[error("GUI error: {0}")]
     GuiError(String),
+
+    #[error("Invalid frontmatter: {0}")]
+    Frontmatter(String),
+
+    #[error("Conversion error: {0}")]
+    Convert(String),
+
+    #[error("Archive error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+
+    #[error("Note '{0}' is {1} KB, over the {2} KB limit (max_note_kb in config.toml)")]
+    NoteTooLarge(String, u64, u64),
+
+    #[error("Would {0}, but --non-interactive is set")]
+    NonInteractive(String),
+
+    #[error("{0} link(s) in the export couldn't be resolved")]
+    UnresolvedLinks(usize),
+
+    #[error("Language model error: {0}")]
+    Llm(String),
+}
+
+impl AppError {
+    /// The process exit code for this error: 3 when a prompt was refused by
+    /// `--non-interactive`, so scripts can tell it apart, and 1 otherwise.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            AppError::NonInteractive(_) => 3,
+            _ => 1,
+        }
+    }
 }
@@ -0,0 +1,76 @@
+use crate::error::AppError;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use std::fs;
+use std::path::Path;
+
+/// The file in an imported directory listing patterns of files to skip, one per line.
+pub const IGNORE_FILE: &str = ".mediignore";
+
+/// Glob patterns of files a directory import skips, from the directory's `.mediignore`
+/// and `--exclude`. As in `.gitignore`, a pattern without a `/` matches a file name at any
+/// depth, e.g. `README.md`; others match the path from the imported directory, e.g.
+/// `drafts/**`. Blank lines and lines starting with `#` are ignored.
+pub struct Excludes {
+    globs: GlobSet,
+}
+
+impl Excludes {
+    /// Reads `dir`'s `.mediignore`, if there is one, and adds the `extra` patterns.
+    pub fn load(dir: &Path, extra: &[String]) -> Result<Self, AppError> {
+        let file = fs::read_to_string(dir.join(IGNORE_FILE)).unwrap_or_default();
+        let patterns = file
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .chain(extra.iter().map(|pattern| pattern.trim()));
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns {
+            let pattern = pattern.trim_start_matches('/');
+            let pattern = if pattern.contains('/') {
+                pattern.to_string()
+            } else {
+                format!("**/{}", pattern)
+            };
+            // `*` stops at a `/`, as in `.gitignore`; `**` crosses directories.
+            let glob = GlobBuilder::new(&pattern)
+                .literal_separator(true)
+                .build()
+                .map_err(|e| AppError::Pattern(format!("'{}': {}", pattern, e.kind())))?;
+            builder.add(glob);
+        }
+        let globs = builder
+            .build()
+            .map_err(|e| AppError::Pattern(e.to_string()))?;
+        Ok(Excludes { globs })
+    }
+
+    /// Whether the file at `path`, relative to the imported directory, is skipped.
+    pub fn matches(&self, path: &Path) -> bool {
+        self.globs.is_match(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_patterns_match_names_anywhere_and_paths_from_the_root() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join(IGNORE_FILE),
+            "# Not notes\nREADME.md\n\nnode_modules/**\n",
+        )
+        .unwrap();
+        let excludes = Excludes::load(dir.path(), &["draft-*.md".to_string()]).unwrap();
+
+        assert!(excludes.matches(Path::new("README.md")));
+        assert!(excludes.matches(Path::new("docs/README.md")));
+        assert!(excludes.matches(Path::new("node_modules/pkg/notes.md")));
+        assert!(excludes.matches(Path::new("draft-plan.md")));
+        assert!(!excludes.matches(Path::new("plan.md")));
+        assert!(!excludes.matches(Path::new("docs/node_modules/a.md")));
+
+        assert!(Excludes::load(dir.path(), &["[oops".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,428 @@
+use crate::config::LinkStyle;
+use crate::error::AppError;
+use crate::graph::LinkGraph;
+use crate::links;
+use crate::note::{self, Note};
+use crate::timezone;
+use chrono::Utc;
+use lazy_static::lazy_static;
+use pulldown_cmark::{html, Options, Parser};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use tracing::trace;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// The file name template used when `export_filename` isn't configured.
+pub const DEFAULT_FILENAME: &str = "{key}.md";
+
+/// The `[[key]]` links in `notes` to notes that aren't among them, which a Markdown-link
+/// export leaves as they are. Returns `(from, target)` pairs in the order of `notes`.
+pub fn unresolved_links(notes: &[Note]) -> Vec<(String, String)> {
+    let exported: HashSet<&str> = notes.iter().map(|note| note.key.as_str()).collect();
+    notes
+        .iter()
+        .flat_map(|note| {
+            note::extract_links(&note.content)
+                .into_iter()
+                .filter(|target| !exported.contains(target.as_str()))
+                .map(|target| (note.key.clone(), target))
+        })
+        .collect()
+}
+
+/// What a Markdown export wrote for a note: its file, relative to the export directory,
+/// and a digest of the content. Remembered per directory for `export --changed`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ExportedFile {
+    pub file: PathBuf,
+    pub digest: String,
+}
+
+/// The files of a Markdown export, by note key.
+pub type ExportState = BTreeMap<String, ExportedFile>;
+
+/// The outcome of [`write_markdown_export`].
+pub struct MarkdownExport {
+    pub state: ExportState,
+    /// How many files were written.
+    pub written: usize,
+    /// How many files of notes no longer exported were deleted.
+    pub removed: usize,
+}
+
+/// Writes each note's content to a file in `dir` named by `filename` (see
+/// `export_filename`), creating the directory if needed. With `LinkStyle::Markdown`,
+/// links between the exported notes become relative `[title](file.md)` links.
+///
+/// Given the `previous` state of the directory, only files whose content or name changed
+/// are written, and the files of notes that were exported then but have since been deleted
+/// are removed. Notes still in `existing` that this export leaves out, e.g. because of a
+/// filter, keep their files and stay recorded in the new state.
+pub fn write_markdown_export(
+    notes: &[Note],
+    dir: &Path,
+    links: LinkStyle,
+    filename: &str,
+    previous: Option<&ExportState>,
+    existing: &HashSet<String>,
+) -> Result<MarkdownExport, AppError> {
+    fs::create_dir_all(dir)?;
+    let mut files: HashMap<String, (String, PathBuf)> = HashMap::new();
+    let mut taken = HashSet::new();
+    for note in notes {
+        let name = export_filename(filename, note)?;
+        // Two notes with the same file name, e.g. the same title, get "-2", "-3", ...
+        let mut file = PathBuf::from(&name);
+        let mut n = 1;
+        while !taken.insert(file.clone()) {
+            n += 1;
+            file = match name.rsplit_once('.') {
+                Some((stem, extension)) => PathBuf::from(format!("{}-{}.{}", stem, n, extension)),
+                None => PathBuf::from(format!("{}-{}", name, n)),
+            };
+        }
+        files.insert(note.key.clone(), (note.title.clone(), file));
+    }
+
+    let mut export = MarkdownExport {
+        state: ExportState::new(),
+        written: 0,
+        removed: 0,
+    };
+    for note in notes {
+        let file = &files[&note.key].1;
+        let file_path = dir.join(file);
+        // Write the note's .content, not the whole note object
+        let content = match links {
+            LinkStyle::Wikilinks => note.content.clone(),
+            LinkStyle::Markdown => links::to_markdown(&note.content, file, &files),
+        };
+        let exported = ExportedFile {
+            file: file.clone(),
+            digest: note::digest(&content),
+        };
+        let unchanged = previous.and_then(|state| state.get(&note.key)) == Some(&exported);
+        if !unchanged || !file_path.is_file() {
+            trace!(key = %note.key, path = %file_path.display(), "writing");
+            if let Some(parent) = file_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&file_path, content)?;
+            export.written += 1;
+        }
+        export.state.insert(note.key.clone(), exported);
+    }
+
+    // Files of deleted notes, or whose file name changed, go.
+    for (key, old) in previous.into_iter().flatten() {
+        if !files.contains_key(key) && existing.contains(key) {
+            if !taken.contains(&old.file) {
+                export.state.insert(key.clone(), old.clone());
+            }
+        } else if !taken.contains(&old.file) {
+            trace!(key, path = %old.file.display(), "removing");
+            match fs::remove_file(dir.join(&old.file)) {
+                Ok(()) => export.removed += 1,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+    Ok(export)
+}
+
+lazy_static! {
+    /// Markdown links and images, capturing the link target (optionally in angle brackets).
+    static ref LOCAL_LINK: Regex = Regex::new(r"(!?\[[^\]]*\]\()(<[^>]+>|[^)\s]+)(\))").unwrap();
+}
+
+/// Writes each note as a TextBundle (`<dir>/<key>.textbundle`) holding `text.markdown`,
+/// `info.json` and an `assets` folder. Images and links pointing at files in `attachments`
+/// are copied into `assets` and rewritten to point there; relative paths are resolved
+/// against `attachments`. Returns the number of assets copied.
+pub fn write_textbundles(
+    notes: &[Note],
+    dir: &Path,
+    attachments: &Path,
+) -> Result<usize, AppError> {
+    let info = serde_json::to_string_pretty(&serde_json::json!({
+        "version": 2,
+        "type": "net.daringfireball.markdown",
+        "transient": false,
+        "creatorIdentifier": "io.github.cladam.medi",
+    }))?;
+    let mut asset_count = 0;
+    for note in notes {
+        let bundle = dir.join(format!("{}.textbundle", note.key));
+        let assets_dir = bundle.join("assets");
+        fs::create_dir_all(&assets_dir)?;
+
+        // Maps each source file to its name in `assets`, so repeated links share a copy.
+        let mut assets: HashMap<PathBuf, String> = HashMap::new();
+        let mut failed = None;
+        let content = LOCAL_LINK.replace_all(&note.content, |caps: &regex::Captures| {
+            let target = caps[2].trim_start_matches('<').trim_end_matches('>');
+            if target.contains("://") || target.starts_with('#') {
+                return caps[0].to_string();
+            }
+            let Some(source) = attachment_source(target, attachments) else {
+                return caps[0].to_string();
+            };
+            let source = source.as_path();
+            let name = match assets.get(source) {
+                Some(name) => name.clone(),
+                None => {
+                    let file_name = source
+                        .file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_default();
+                    // Different files with the same name get a numbered prefix.
+                    let name = if assets.values().any(|taken| *taken == file_name) {
+                        format!("{}-{}", assets.len() + 1, file_name)
+                    } else {
+                        file_name
+                    };
+                    if let Err(e) = fs::copy(source, assets_dir.join(&name)) {
+                        failed.get_or_insert(e);
+                    }
+                    assets.insert(source.to_path_buf(), name.clone());
+                    name
+                }
+            };
+            format!("{}assets/{}{}", &caps[1], name, &caps[3])
+        });
+        if let Some(e) = failed {
+            return Err(AppError::Io(e));
+        }
+        asset_count += assets.len();
+
+        fs::write(bundle.join("text.markdown"), content.as_bytes())?;
+        fs::write(bundle.join("info.json"), &info)?;
+    }
+    Ok(asset_count)
+}
+
+/// Resolves a link target to a file inside `attachments`. Targets with `..` components, and
+/// absolute paths or symlinks leading outside `attachments`, resolve to nothing so that
+/// exporting a note can't copy arbitrary files into a bundle.
+fn attachment_source(target: &str, attachments: &Path) -> Option<PathBuf> {
+    let path = Path::new(target);
+    if path.components().any(|c| c == Component::ParentDir) {
+        return None;
+    }
+    let source = attachments.join(path).canonicalize().ok()?;
+    let root = attachments.canonicalize().ok()?;
+    (source.starts_with(&root) && source.is_file()).then_some(source)
+}
+
+/// Fills in an `export_filename` template for a note. Fields are `{key}` (namespaced keys
+/// become subdirectories), `{slug}` (the title as a slug), `{date}` (the creation date,
+/// YYYY-MM-DD) and `{tags}` (the tags joined by dashes).
+pub fn export_filename(template: &str, note: &Note) -> Result<String, AppError> {
+    let mut name = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        name.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        let field = &rest[start + 1..start + end];
+        let value = match field {
+            "key" => note.key.clone(),
+            "slug" => note::slugify(&note.title.replace('/', " ")),
+            "date" => timezone::format(note.created_at, "%Y-%m-%d"),
+            "tags" => note.tags.iter().map(|tag| note::slugify(tag)).collect::<Vec<_>>().join("-"),
+            _ => {
+                return Err(AppError::ConfigError(format!(
+                    "unknown field '{{{}}}' in export_filename, expected {{key}}, {{slug}}, {{date}} or {{tags}}",
+                    field
+                )))
+            }
+        };
+        name.push_str(&value);
+        rest = &rest[start + end + 1..];
+    }
+    name.push_str(rest);
+    Ok(name)
+}
+
+/// Appends a "Linked from" section to every note that other notes in `notes` link to.
+/// With `anchors`, entries link to `#key` (the section ids of the HTML export);
+/// otherwise they are `[[key]]` wikilinks.
+pub fn with_backlinks(notes: Vec<Note>, anchors: bool) -> Vec<Note> {
+    let graph = LinkGraph::from_notes(&notes);
+    notes
+        .into_iter()
+        .map(|mut note| {
+            let backlinks = graph.backlinks(&note.key);
+            if !backlinks.is_empty() {
+                let mut section = String::from("\n\n## Linked from\n\n");
+                for (key, title) in backlinks {
+                    if anchors {
+                        section.push_str(&format!("- [{}](#{})\n", title, key));
+                    } else {
+                        section.push_str(&format!("- [[{}]]\n", key));
+                    }
+                }
+                note.content = format!("{}{}", note.content.trim_end(), section);
+            }
+            note
+        })
+        .collect()
+}
+
+/// Concatenates notes into a single Markdown document with a heading per note.
+pub fn combined_markdown(notes: &[Note], title: &str) -> String {
+    let mut out = format!("# {}\n\n", title);
+    for note in notes {
+        out.push_str(&format!("## {}\n\n", note.title));
+        out.push_str(note.content.trim());
+        out.push_str("\n\n");
+    }
+    out
+}
+
+/// Concatenates notes into a single standalone HTML document with a heading per note.
+pub fn combined_html(notes: &[Note], title: &str) -> String {
+    let mut body = String::new();
+    for note in notes {
+        body.push_str(&format!(
+            "<section id=\"{}\">\n<h2>{}</h2>\n{}</section>\n",
+            escape_xml(&note.key),
+            escape_xml(&note.title),
+            markdown_to_html(&note.content)
+        ));
+    }
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\" />\n<title>{title}</title>\n</head>\n<body>\n<h1>{title}</h1>\n{body}</body>\n</html>\n",
+        title = escape_xml(title),
+        body = body
+    )
+}
+
+/// Writes the notes as an EPUB 3 book with one chapter per note.
+pub fn write_epub(notes: &[Note], title: &str, path: &Path) -> Result<(), AppError> {
+    let file = fs::File::create(path)?;
+    let mut zip = ZipWriter::new(file);
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    // The mimetype must be the first entry and stored uncompressed.
+    zip.start_file("mimetype", stored)?;
+    zip.write_all(b"application/epub+zip")?;
+
+    zip.start_file("META-INF/container.xml", deflated)?;
+    zip.write_all(
+        br#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#,
+    )?;
+
+    let mut manifest = String::new();
+    let mut spine = String::new();
+    let mut toc = String::new();
+    for (i, note) in notes.iter().enumerate() {
+        let id = format!("note{}", i + 1);
+        let href = format!("{}.xhtml", id);
+        manifest.push_str(&format!(
+            "    <item id=\"{}\" href=\"{}\" media-type=\"application/xhtml+xml\"/>\n",
+            id, href
+        ));
+        spine.push_str(&format!("    <itemref idref=\"{}\"/>\n", id));
+        toc.push_str(&format!(
+            "      <li><a href=\"{}\">{}</a></li>\n",
+            href,
+            escape_xml(&note.title)
+        ));
+
+        zip.start_file(format!("OEBPS/{}", href), deflated)?;
+        zip.write_all(
+            xhtml_page(
+                &note.title,
+                &format!(
+                    "<h1>{}</h1>\n{}",
+                    escape_xml(&note.title),
+                    markdown_to_html(&note.content)
+                ),
+            )
+            .as_bytes(),
+        )?;
+    }
+
+    zip.start_file("OEBPS/nav.xhtml", deflated)?;
+    zip.write_all(
+        xhtml_page(
+            title,
+            &format!(
+                "<nav epub:type=\"toc\" id=\"toc\">\n    <h1>{}</h1>\n    <ol>\n{}    </ol>\n</nav>",
+                escape_xml(title),
+                toc
+            ),
+        )
+        .as_bytes(),
+    )?;
+
+    zip.start_file("OEBPS/content.opf", deflated)?;
+    zip.write_all(
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="book-id">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier id="book-id">urn:medi:{id}</dc:identifier>
+    <dc:title>{title}</dc:title>
+    <dc:language>en</dc:language>
+    <meta property="dcterms:modified">{modified}</meta>
+  </metadata>
+  <manifest>
+    <item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+{manifest}  </manifest>
+  <spine>
+{spine}  </spine>
+</package>
+"#,
+            id = Utc::now().timestamp(),
+            title = escape_xml(title),
+            modified = Utc::now().format("%Y-%m-%dT%H:%M:%SZ"),
+            manifest = manifest,
+            spine = spine
+        )
+        .as_bytes(),
+    )?;
+
+    zip.finish()?;
+    Ok(())
+}
+
+/// Renders Markdown to an HTML fragment.
+fn markdown_to_html(markdown: &str) -> String {
+    let parser = Parser::new_ext(markdown, Options::all());
+    let mut out = String::new();
+    html::push_html(&mut out, parser);
+    out
+}
+
+/// Wraps a body fragment in a minimal XHTML page, as required by EPUB.
+fn xhtml_page(title: &str, body: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE html>\n<html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\">\n<head><title>{}</title></head>\n<body>\n{}\n</body>\n</html>\n",
+        escape_xml(title),
+        body
+    )
+}
+
+/// Escapes the characters that are significant in HTML/XML text and attributes.
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
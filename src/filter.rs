@@ -0,0 +1,130 @@
+use crate::cli::{parse_date, TagMatch};
+use crate::note::{Note, NoteKind, ARCHIVED_TAG};
+use chrono::{DateTime, Utc};
+use clap::{Args, ValueEnum};
+
+/// What a filter does with archived notes (those tagged `archived`).
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Archived {
+    /// Leave archived notes out.
+    Exclude,
+    /// Keep only archived notes.
+    Only,
+}
+
+/// The note filters shared by the commands that work on many notes, so `--tag`, `--since`
+/// and the rest mean the same everywhere and can be combined. Expired notes never match:
+/// they stay hidden until `medi maintain` purges them.
+#[derive(Args, Clone, Debug, Default)]
+pub struct NoteFilter {
+    /// Only notes with these tags.
+    #[arg(long, short)]
+    pub tag: Vec<String>,
+    /// With several --tag: match notes with all of them (default) or any of them.
+    #[arg(long = "match", value_enum, default_value_t = TagMatch::All, value_name = "MODE")]
+    pub tag_match: TagMatch,
+    /// Only notes modified on or after this date: YYYY-MM-DD, RFC 3339 or e.g. "last monday".
+    #[arg(long, value_parser = parse_date)]
+    pub since: Option<DateTime<Utc>>,
+    /// Only notes last modified before this date: YYYY-MM-DD, RFC 3339 or e.g. "yesterday".
+    #[arg(long, value_parser = parse_date)]
+    pub before: Option<DateTime<Utc>>,
+    /// Only notes of this kind.
+    #[arg(long, value_enum)]
+    pub kind: Option<NoteKind>,
+    /// Leave out archived notes, or keep only those. They're included by default.
+    #[arg(long, value_enum, value_name = "WHICH")]
+    pub archived: Option<Archived>,
+}
+
+impl NoteFilter {
+    /// Whether any filter was given, as opposed to matching every note.
+    pub fn is_set(&self) -> bool {
+        !self.tag.is_empty()
+            || self.since.is_some()
+            || self.before.is_some()
+            || self.kind.is_some()
+            || self.archived.is_some()
+    }
+
+    /// Whether the note passes every filter.
+    pub fn matches(&self, note: &Note, now: DateTime<Utc>) -> bool {
+        let archived = note.tags.iter().any(|tag| tag == ARCHIVED_TAG);
+        !note.is_expired(now)
+            && note.has_tags(&self.tag, self.tag_match)
+            && self.since.is_none_or(|since| note.modified_at >= since)
+            && self.before.is_none_or(|before| note.modified_at < before)
+            && self.kind.is_none_or(|kind| note.kind == kind)
+            && match self.archived {
+                None => true,
+                Some(Archived::Exclude) => !archived,
+                Some(Archived::Only) => archived,
+            }
+    }
+
+    /// Keeps the notes that pass every filter.
+    pub fn apply(&self, notes: Vec<Note>) -> Vec<Note> {
+        let now = Utc::now();
+        notes
+            .into_iter()
+            .filter(|note| self.matches(note, now))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    fn note(key: &str, tags: &[&str], modified_at: DateTime<Utc>) -> Note {
+        Note {
+            key: key.to_string(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            modified_at,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_filters_combine() {
+        let june = Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap();
+        let now = june + Duration::days(100);
+        let mut expired = note("expired", &["work"], june);
+        expired.expires_at = Some(now - Duration::days(1));
+        let notes = [
+            note("old", &["work"], june - Duration::days(30)),
+            note("new", &["work"], june + Duration::days(1)),
+            note("shelved", &["work", ARCHIVED_TAG], june + Duration::days(2)),
+            note("home", &["home"], june + Duration::days(3)),
+            expired,
+        ];
+        let keys = |filter: &NoteFilter| -> Vec<&str> {
+            notes
+                .iter()
+                .filter(|note| filter.matches(note, now))
+                .map(|note| note.key.as_str())
+                .collect()
+        };
+
+        let all = NoteFilter::default();
+        assert!(!all.is_set());
+        assert_eq!(keys(&all), ["old", "new", "shelved", "home"]);
+
+        let filter = NoteFilter {
+            tag: vec!["work".to_string()],
+            since: Some(june),
+            archived: Some(Archived::Exclude),
+            ..Default::default()
+        };
+        assert!(filter.is_set());
+        assert_eq!(keys(&filter), ["new"]);
+
+        let filter = NoteFilter {
+            before: Some(june + Duration::days(3)),
+            archived: Some(Archived::Only),
+            ..Default::default()
+        };
+        assert_eq!(keys(&filter), ["shelved"]);
+    }
+}
@@ -0,0 +1,77 @@
+use crate::error::AppError;
+use crate::interactive;
+#[cfg(unix)]
+use crossbeam_channel::unbounded;
+#[cfg(not(unix))]
+use dialoguer::{FuzzySelect, MultiSelect};
+#[cfg(unix)]
+use skim::options::SkimOptionsBuilder;
+#[cfg(unix)]
+use skim::{Skim, SkimItem};
+#[cfg(unix)]
+use std::sync::Arc;
+
+/// Opens an interactive fuzzy finder over the given keys and returns the selected keys.
+/// With `multi` set, several keys can be marked (TAB in skim) before accepting.
+/// Returns an empty vector if the user aborts without picking anything.
+#[cfg(unix)]
+pub fn select_keys(keys: Vec<String>, prompt: &str, multi: bool) -> Result<Vec<String>, AppError> {
+    interactive::require("open the fuzzy finder")?;
+    // Create a crossbeam channel.
+    let (tx, rx) = unbounded();
+
+    // Send each key through the channel.
+    for key in keys {
+        let item: Arc<dyn SkimItem> = Arc::new(key);
+        let _ = tx.send(item);
+    }
+    drop(tx);
+
+    // Configure and run the fuzzy finder.
+    let options = SkimOptionsBuilder::default()
+        .height("30%".to_string())
+        .prompt(prompt.to_string())
+        .reverse(true)
+        .border(Some("─".to_string()))
+        .multi(multi)
+        .build()
+        .unwrap();
+
+    // `Skim::run_with` launches the interactive fuzzy finder.
+    // We pass the receiver `rx` which `skim` will use to get the items.
+    let selected_items = Skim::run_with(&options, Some(rx))
+        .filter(|out| !out.is_abort)
+        .map(|out| out.selected_items)
+        .unwrap_or_default();
+
+    Ok(selected_items
+        .iter()
+        .map(|item| item.output().to_string())
+        .collect())
+}
+
+/// Fallback for platforms where skim isn't available (e.g. Windows).
+/// Uses dialoguer's built-in fuzzy matcher instead, or a checkbox list for multi-select.
+#[cfg(not(unix))]
+pub fn select_keys(keys: Vec<String>, prompt: &str, multi: bool) -> Result<Vec<String>, AppError> {
+    interactive::require("open the fuzzy finder")?;
+    let prompt = prompt.trim_end_matches([':', ' ']);
+
+    if multi {
+        let selection = MultiSelect::new()
+            .with_prompt(prompt)
+            .items(&keys)
+            .max_length(15)
+            .interact_opt()?
+            .unwrap_or_default();
+        return Ok(selection.into_iter().map(|i| keys[i].clone()).collect());
+    }
+
+    let selection = FuzzySelect::new()
+        .with_prompt(prompt)
+        .items(&keys)
+        .max_length(15)
+        .interact_opt()?;
+
+    Ok(selection.map(|i| keys[i].clone()).into_iter().collect())
+}
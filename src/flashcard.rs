@@ -0,0 +1,122 @@
+use crate::note::Note;
+
+/// Tag marking notes that contain flashcards.
+pub const FLASHCARD_TAG: &str = "flashcard";
+
+/// A question/answer pair, or a cloze deletion, found in a note.
+pub struct Card {
+    pub front: String,
+    /// Empty for cloze cards, whose answers are inside `front`.
+    pub back: String,
+    pub cloze: bool,
+}
+
+/// Extracts the cards from a note. A card is either a `Q:` line followed by an `A:` line
+/// (each may continue over the following lines, up to a blank line or the next `Q:`),
+/// or a paragraph with Anki-style cloze deletions such as `{{c1::Paris}}`.
+pub fn extract(note: &Note) -> Vec<Card> {
+    let mut cards = Vec::new();
+    let mut question: Option<String> = None;
+    let mut answer: Option<String> = None;
+    let mut paragraph = String::new();
+
+    let mut finish = |question: &mut Option<String>, answer: &mut Option<String>| {
+        if let (Some(front), Some(back)) = (question.take(), answer.take()) {
+            cards.push(Card {
+                front: front.trim().to_string(),
+                back: back.trim().to_string(),
+                cloze: false,
+            });
+        }
+    };
+    let mut clozes = Vec::new();
+
+    for line in note.content.lines().chain(std::iter::once("")) {
+        let trimmed = line.trim();
+        if let Some(rest) = trimmed.strip_prefix("Q:") {
+            finish(&mut question, &mut answer);
+            question = Some(rest.trim().to_string());
+        } else if let Some(rest) = trimmed.strip_prefix("A:").filter(|_| question.is_some()) {
+            answer = Some(rest.trim().to_string());
+        } else if trimmed.is_empty() {
+            finish(&mut question, &mut answer);
+        } else if let Some(text) = answer.as_mut().or(question.as_mut()) {
+            text.push('\n');
+            text.push_str(trimmed);
+        }
+
+        // Cloze paragraphs are collected independently of Q/A cards.
+        if trimmed.is_empty() {
+            if paragraph.contains("{{c") && paragraph.contains("::") {
+                clozes.push(paragraph.trim().to_string());
+            }
+            paragraph.clear();
+        } else {
+            paragraph.push_str(trimmed);
+            paragraph.push('\n');
+        }
+    }
+    cards.extend(clozes.into_iter().map(|front| Card {
+        front,
+        back: String::new(),
+        cloze: true,
+    }));
+    cards
+}
+
+/// Formats cards as a tab-separated file for Anki's "Import File", using its file headers
+/// to pick the Basic or Cloze note type per row. The note's other tags are kept.
+pub fn anki_tsv(notes: &[Note]) -> (String, usize) {
+    let mut out = String::from("#separator:tab\n#html:true\n#notetype column:1\n#tags column:4\n");
+    let mut count = 0;
+    for note in notes {
+        let tags: Vec<String> = note
+            .tags
+            .iter()
+            .filter(|tag| *tag != FLASHCARD_TAG)
+            .map(|tag| tag.replace(' ', "_"))
+            .collect();
+        for card in extract(note) {
+            let notetype = if card.cloze { "Cloze" } else { "Basic" };
+            out.push_str(&format!(
+                "{}\t{}\t{}\t{}\n",
+                notetype,
+                anki_field(&card.front),
+                anki_field(&card.back),
+                tags.join(" ")
+            ));
+            count += 1;
+        }
+    }
+    (out, count)
+}
+
+/// Escapes a field for an HTML-enabled Anki import; line breaks become `<br>`.
+fn anki_field(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('\t', " ")
+        .replace('\n', "<br>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_question_answer_and_cloze_cards() {
+        let note = Note {
+            key: "french".to_string(),
+            content: "# French\n\nQ: Capital of France?\nA: Paris\n\nQ: Two lines?\nA: First\nsecond\nQ: Unanswered\n\nThe capital of Italy is {{c1::Rome}}.\n".to_string(),
+            ..Default::default()
+        };
+        let cards = extract(&note);
+        assert_eq!(cards.len(), 3);
+        assert_eq!(cards[0].front, "Capital of France?");
+        assert_eq!(cards[0].back, "Paris");
+        assert_eq!(cards[1].back, "First\nsecond");
+        assert!(cards[2].cloze);
+        assert_eq!(cards[2].front, "The capital of Italy is {{c1::Rome}}.");
+    }
+}
@@ -0,0 +1,159 @@
+use crate::error::AppError;
+use crate::timezone;
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::BTreeMap;
+
+lazy_static! {
+    static ref FIELD: Regex = Regex::new(r"^([A-Za-z_][\w-]*):\s*(.*)$").unwrap();
+    static ref LIST_ITEM: Regex = Regex::new(r"^\s+-\s+(.*)$").unwrap();
+}
+
+/// Metadata given in a frontmatter block at the top of a note, such as:
+///
+/// ```text
+/// ---
+/// title: Weekly sync
+/// tags: [work, meetings]
+/// created: 2024-05-18 09:30
+/// ---
+/// ```
+#[derive(Debug, Default, PartialEq)]
+pub struct Frontmatter {
+    pub title: Option<String>,
+    pub tags: Vec<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub modified_at: Option<DateTime<Utc>>,
+    /// Any other fields, kept as note metadata.
+    pub metadata: BTreeMap<String, String>,
+}
+
+/// Splits a frontmatter block off the start of `text`, returning it and the text after it.
+/// Text that doesn't start with `field: value` lines between two `---` lines is returned
+/// whole, so a note that merely opens with a horizontal rule is left alone.
+pub fn split(text: &str) -> Result<(Option<Frontmatter>, &str), AppError> {
+    let mut lines = text.split_inclusive('\n');
+    if lines.next().map(str::trim_end) != Some("---") {
+        return Ok((None, text));
+    }
+    let mut offset = text.find('\n').map_or(text.len(), |end| end + 1);
+    let mut fields: Vec<(String, Vec<String>)> = Vec::new();
+    for line in lines {
+        offset += line.len();
+        let line = line.trim_end();
+        if line == "---" || line == "..." {
+            let body = text[offset..].trim_start_matches(['\r', '\n']);
+            return Ok((Some(from_fields(fields)?), body));
+        }
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+        if let Some(caps) = FIELD.captures(line) {
+            let value = caps[2].trim();
+            let values = if value.is_empty() {
+                Vec::new()
+            } else {
+                vec![value.to_string()]
+            };
+            fields.push((caps[1].to_lowercase(), values));
+        } else if let (Some(caps), Some((_, values))) =
+            (LIST_ITEM.captures(line), fields.last_mut())
+        {
+            values.push(caps[1].trim().to_string());
+        } else {
+            return Ok((None, text));
+        }
+    }
+    // No closing line, so it wasn't frontmatter after all.
+    Ok((None, text))
+}
+
+fn from_fields(fields: Vec<(String, Vec<String>)>) -> Result<Frontmatter, AppError> {
+    let mut frontmatter = Frontmatter::default();
+    for (name, values) in fields {
+        let value = unquote(&values.join(" "));
+        match name.as_str() {
+            "title" => frontmatter.title = Some(value).filter(|title| !title.is_empty()),
+            "tags" | "tag" => frontmatter.tags.extend(
+                values
+                    .iter()
+                    .flat_map(|value| list(value))
+                    .map(|tag| tag.trim_start_matches('#').to_string())
+                    .filter(|tag| !tag.is_empty()),
+            ),
+            "created" | "created_at" | "date" => {
+                frontmatter.created_at = Some(timestamp(&name, &value)?)
+            }
+            "modified" | "modified_at" | "updated" => {
+                frontmatter.modified_at = Some(timestamp(&name, &value)?)
+            }
+            _ => {
+                let items: Vec<String> = values.iter().flat_map(|value| list(value)).collect();
+                frontmatter.metadata.insert(name, items.join(", "));
+            }
+        }
+    }
+    Ok(frontmatter)
+}
+
+/// The items of a `[a, b]` list, or the value itself without quotes.
+fn list(value: &str) -> Vec<String> {
+    match value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+        Some(items) => items
+            .split(',')
+            .map(unquote)
+            .filter(|item| !item.is_empty())
+            .collect(),
+        None => vec![unquote(value)],
+    }
+}
+
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    for quote in ['"', '\''] {
+        if let Some(inner) = value
+            .strip_prefix(quote)
+            .and_then(|v| v.strip_suffix(quote))
+        {
+            return inner.to_string();
+        }
+    }
+    value.to_string()
+}
+
+fn timestamp(name: &str, value: &str) -> Result<DateTime<Utc>, AppError> {
+    timezone::parse_when(value).map_err(|e| AppError::Frontmatter(format!("{}: {}", name, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_split() {
+        let text = "---\ntitle: \"Weekly sync\"\ntags: [work, '#meetings']\n\
+                    created: 2024-05-18T09:30:00Z\nsource: email\n---\n\nBody text\n";
+        let (frontmatter, body) = split(text).unwrap();
+        let frontmatter = frontmatter.unwrap();
+        assert_eq!(frontmatter.title.as_deref(), Some("Weekly sync"));
+        assert_eq!(frontmatter.tags, vec!["work", "meetings"]);
+        assert_eq!(
+            frontmatter.created_at,
+            Some(Utc.with_ymd_and_hms(2024, 5, 18, 9, 30, 0).unwrap())
+        );
+        assert_eq!(frontmatter.metadata["source"], "email");
+        assert_eq!(body, "Body text\n");
+
+        let (frontmatter, _) = split("---\ntags:\n  - a\n  - b\n---\nx").unwrap();
+        assert_eq!(frontmatter.unwrap().tags, vec!["a", "b"]);
+
+        // A horizontal rule followed by prose isn't frontmatter.
+        let text = "---\nJust some text.\n---\n";
+        assert_eq!(split(text).unwrap(), (None, text));
+        assert_eq!(split("no frontmatter").unwrap().0, None);
+
+        assert!(split("---\ncreated: someday\n---\n").is_err());
+    }
+}
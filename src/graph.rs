@@ -0,0 +1,245 @@
+use crate::error::AppError;
+use crate::note::{self, Note};
+use crate::{colours, interactive};
+use colored::Colorize;
+use dialoguer::Select;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The `[[wikilink]]` graph between notes.
+pub struct LinkGraph {
+    titles: BTreeMap<String, String>,
+    outgoing: BTreeMap<String, Vec<String>>,
+    incoming: BTreeMap<String, BTreeSet<String>>,
+}
+
+/// What the user chose to do next in the interactive explorer.
+pub enum Step {
+    Visit(String),
+    Edit,
+    Back,
+    Quit,
+}
+
+impl LinkGraph {
+    pub fn from_notes(notes: &[Note]) -> Self {
+        let mut graph = LinkGraph {
+            titles: BTreeMap::new(),
+            outgoing: BTreeMap::new(),
+            incoming: BTreeMap::new(),
+        };
+        for note in notes {
+            graph.titles.insert(note.key.clone(), note.title.clone());
+            let links: Vec<String> = note::extract_links(&note.content)
+                .into_iter()
+                .filter(|link| link != &note.key)
+                .collect();
+            for link in &links {
+                graph
+                    .incoming
+                    .entry(link.clone())
+                    .or_default()
+                    .insert(note.key.clone());
+            }
+            graph.outgoing.insert(note.key.clone(), links);
+        }
+        graph
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.titles.contains_key(key)
+    }
+
+    /// Returns the key and title of every note linking to `key`, ordered by key.
+    pub fn backlinks(&self, key: &str) -> Vec<(String, String)> {
+        self.incoming
+            .get(key)
+            .into_iter()
+            .flatten()
+            .map(|from| (from.clone(), self.titles[from].clone()))
+            .collect()
+    }
+
+    /// Renders the graph in Graphviz DOT format. Links to missing notes are drawn dashed.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph medi {\n    rankdir=LR;\n    node [shape=box];\n");
+        for (key, title) in &self.titles {
+            out.push_str(&format!(
+                "    \"{}\" [label=\"{}\"];\n",
+                escape(key),
+                escape(title)
+            ));
+        }
+        for (from, links) in &self.outgoing {
+            for to in links {
+                let style = if self.contains(to) {
+                    ""
+                } else {
+                    " [style=dashed]"
+                };
+                out.push_str(&format!(
+                    "    \"{}\" -> \"{}\"{};\n",
+                    escape(from),
+                    escape(to),
+                    style
+                ));
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// Shows the links around `current` and asks where to go next.
+    pub fn prompt_step(&self, current: &str, can_go_back: bool) -> Result<Step, AppError> {
+        interactive::require("ask which link to follow")?;
+        let outgoing = self.outgoing.get(current).cloned().unwrap_or_default();
+        let incoming: Vec<String> = self
+            .incoming
+            .get(current)
+            .map(|keys| keys.iter().cloned().collect())
+            .unwrap_or_default();
+
+        println!(
+            "\n{} {} {}",
+            colours::key("●"),
+            colours::key(current),
+            format!("({})", self.titles.get(current).map_or("", String::as_str)).dimmed()
+        );
+        println!("  {} outgoing, {} incoming", outgoing.len(), incoming.len());
+
+        // Link entries come first; `targets[i]` is the note behind `items[i]`.
+        let mut items = Vec::new();
+        let mut targets = Vec::new();
+        for key in &outgoing {
+            if self.contains(key) {
+                items.push(format!("→ {}", key));
+            } else {
+                items.push(format!("→ {} (missing)", key));
+            }
+            targets.push(key.clone());
+        }
+        for key in &incoming {
+            items.push(format!("← {}", key));
+            targets.push(key.clone());
+        }
+        items.push("Open in editor".to_string());
+        if can_go_back {
+            items.push("Back".to_string());
+        }
+        items.push("Quit".to_string());
+
+        let Some(choice) = Select::new()
+            .with_prompt("Follow a link")
+            .items(&items)
+            .default(0)
+            .interact_opt()?
+        else {
+            return Ok(Step::Quit);
+        };
+
+        if let Some(key) = targets.get(choice) {
+            return Ok(Step::Visit(key.clone()));
+        }
+        Ok(match items[choice].as_str() {
+            "Open in editor" => Step::Edit,
+            "Back" => Step::Back,
+            _ => Step::Quit,
+        })
+    }
+}
+
+/// A `[[target]]` link in the content of the `source` note, with where it is: the byte
+/// range of the whole link in the content, its line (from 1) and that line as context.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Link {
+    pub source: String,
+    pub target: String,
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub context: String,
+}
+
+/// Every link in `note`, in the order they appear, repeats included.
+pub fn links_in(note: &Note) -> Vec<Link> {
+    let content = &note.content;
+    let mut links = Vec::new();
+    let mut from = 0;
+    while let Some(start) = content[from..].find("[[").map(|i| from + i) {
+        let Some(close) = content[start + 2..].find("]]").map(|i| start + 2 + i) else {
+            break;
+        };
+        let target = content[start + 2..close].trim();
+        if target.is_empty() || target.contains('\n') {
+            from = start + 2;
+            continue;
+        }
+        let line_start = content[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = content[close..]
+            .find('\n')
+            .map_or(content.len(), |i| close + i);
+        links.push(Link {
+            source: note.key.clone(),
+            target: target.to_string(),
+            start,
+            end: close + 2,
+            line: content[..start].matches('\n').count() + 1,
+            context: content[line_start..line_end].trim().to_string(),
+        });
+        from = close + 2;
+    }
+    links
+}
+
+/// Every link to `key` from the other notes, ordered by the linking note's key.
+pub fn links_to(notes: &[Note], key: &str) -> Vec<Link> {
+    let mut links: Vec<Link> = notes
+        .iter()
+        .filter(|note| note.key != key)
+        .flat_map(links_in)
+        .filter(|link| link.target == key)
+        .collect();
+    links.sort_by(|a, b| a.source.cmp(&b.source).then(a.start.cmp(&b.start)));
+    links
+}
+
+/// Escapes a string for use inside a quoted DOT identifier.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_links_carry_their_position_and_line() {
+        let note = Note {
+            key: "plan".to_string(),
+            content: "# Plan\nLearn [[rust]] and [[ tokio ]].\n[[]] [[rust]]".to_string(),
+            ..Default::default()
+        };
+        let links = links_in(&note);
+        let found: Vec<(&str, usize, usize, usize)> = links
+            .iter()
+            .map(|l| (l.target.as_str(), l.start, l.end, l.line))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("rust", 13, 21, 2),
+                ("tokio", 26, 37, 2),
+                ("rust", 44, 52, 3)
+            ]
+        );
+        assert_eq!(&note.content[13..21], "[[rust]]");
+        assert_eq!(links[0].context, "Learn [[rust]] and [[ tokio ]].");
+
+        let other = Note {
+            key: "rust".to_string(),
+            content: "Self: [[rust]]".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(links_to(&[other, note], "rust").len(), 2);
+    }
+}
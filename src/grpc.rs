@@ -0,0 +1,296 @@
+use crate::error::AppError;
+use crate::graph::{self, Link};
+use crate::note::Note;
+use crate::task::{Task, TaskStatus};
+use crate::{db, search};
+use chrono::{DateTime, Utc};
+use sled::Db;
+use std::net::SocketAddr;
+use tantivy::Index;
+use tonic::{Request, Response, Status};
+
+/// Types and clients generated from `proto/medi.proto`.
+pub mod proto {
+    tonic::include_proto!("medi.v1");
+}
+
+use proto::note_service_server::{NoteService, NoteServiceServer};
+use proto::task_service_server::{TaskService, TaskServiceServer};
+
+/// A server-streaming response built from an already collected list.
+type ListStream<T> = tokio_stream::Iter<std::vec::IntoIter<Result<T, Status>>>;
+
+/// Serves the NoteService and TaskService on `addr` until the process is stopped.
+pub fn serve(db: Db, index: Index, addr: SocketAddr) -> Result<(), AppError> {
+    // A single-threaded runtime runs one request at a time, so index writers never overlap.
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    let service = MediService { db, index };
+    runtime.block_on(async {
+        tonic::transport::Server::builder()
+            .add_service(NoteServiceServer::new(service.clone()))
+            .add_service(TaskServiceServer::new(service))
+            .serve(addr)
+            .await
+            .map_err(|e| AppError::Server(e.to_string()))
+    })
+}
+
+#[derive(Clone)]
+struct MediService {
+    db: Db,
+    index: Index,
+}
+
+fn status(error: AppError) -> Status {
+    match error {
+        AppError::KeyNotFound(_) | AppError::TaskNotFound(_) => {
+            Status::not_found(error.to_string())
+        }
+        AppError::KeyExists(_) | AppError::KeyCaseConflict(..) => {
+            Status::already_exists(error.to_string())
+        }
+        AppError::Locked(_) => Status::failed_precondition(error.to_string()),
+        _ => Status::internal(error.to_string()),
+    }
+}
+
+fn parse_time(text: &str) -> Result<Option<DateTime<Utc>>, String> {
+    if text.is_empty() {
+        return Ok(None);
+    }
+    DateTime::parse_from_rfc3339(text)
+        .map(|time| Some(time.with_timezone(&Utc)))
+        .map_err(|_| format!("invalid timestamp '{}'", text))
+}
+
+impl From<&Note> for proto::Note {
+    fn from(note: &Note) -> Self {
+        proto::Note {
+            key: note.key.clone(),
+            title: note.title.clone(),
+            content: note.content.clone(),
+            tags: note.tags.clone(),
+            created_at: note.created_at.to_rfc3339(),
+            modified_at: note.modified_at.to_rfc3339(),
+        }
+    }
+}
+
+impl From<&Link> for proto::Link {
+    fn from(link: &Link) -> Self {
+        proto::Link {
+            source: link.source.clone(),
+            target: link.target.clone(),
+            start: link.start as u64,
+            end: link.end as u64,
+            line: link.line as u32,
+            context: link.context.clone(),
+        }
+    }
+}
+
+impl From<&Task> for proto::Task {
+    fn from(task: &Task) -> Self {
+        let status = match task.status {
+            TaskStatus::Open => proto::TaskStatus::Open,
+            TaskStatus::Prio => proto::TaskStatus::Prio,
+            TaskStatus::Done => proto::TaskStatus::Done,
+        };
+        proto::Task {
+            id: task.id,
+            note_key: task.note_key.clone(),
+            description: task.description.clone(),
+            status: status.into(),
+            created_at: task.created_at.to_rfc3339(),
+        }
+    }
+}
+
+fn stream<T>(items: Vec<T>) -> Response<ListStream<T>> {
+    Response::new(tokio_stream::iter(
+        items.into_iter().map(Ok).collect::<Vec<_>>(),
+    ))
+}
+
+#[tonic::async_trait]
+impl NoteService for MediService {
+    type ListNotesStream = ListStream<proto::Note>;
+    type SearchNotesStream = ListStream<proto::Note>;
+    type ListLinksStream = ListStream<proto::Link>;
+
+    async fn get_note(
+        &self,
+        request: Request<proto::GetNoteRequest>,
+    ) -> Result<Response<proto::Note>, Status> {
+        let note = db::get_note(&self.db, &request.into_inner().key).map_err(status)?;
+        Ok(Response::new((&note).into()))
+    }
+
+    async fn list_notes(
+        &self,
+        request: Request<proto::ListNotesRequest>,
+    ) -> Result<Response<Self::ListNotesStream>, Status> {
+        let request = request.into_inner();
+        let now = Utc::now();
+        let mut notes = db::get_all_notes(&self.db).map_err(status)?;
+        notes.retain(|note| {
+            !note.is_expired(now)
+                && note.key.starts_with(&request.prefix)
+                && request.tags.iter().all(|tag| note.tags.contains(tag))
+        });
+        notes.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(stream(notes.iter().map(Into::into).collect()))
+    }
+
+    async fn search_notes(
+        &self,
+        request: Request<proto::SearchNotesRequest>,
+    ) -> Result<Response<Self::SearchNotesStream>, Status> {
+        let keys = search::search_notes(&self.index, &request.into_inner().query)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        let now = Utc::now();
+        let notes: Vec<proto::Note> = keys
+            .iter()
+            .filter_map(|key| db::get_note(&self.db, key).ok())
+            .filter(|note| !note.is_expired(now))
+            .map(|note| (&note).into())
+            .collect();
+        Ok(stream(notes))
+    }
+
+    async fn save_note(
+        &self,
+        request: Request<proto::SaveNoteRequest>,
+    ) -> Result<Response<proto::Note>, Status> {
+        let incoming = request
+            .into_inner()
+            .note
+            .ok_or_else(|| Status::invalid_argument("missing note"))?;
+        if incoming.key.is_empty() {
+            return Err(Status::invalid_argument("missing note key"));
+        }
+
+        let now = Utc::now();
+        let note = match db::get_note(&self.db, &incoming.key) {
+            Ok(existing) if existing.locked => {
+                return Err(status(AppError::Locked(existing.key)));
+            }
+            Ok(mut existing) => {
+                existing.title = incoming.title;
+                existing.content = incoming.content;
+                existing.tags = incoming.tags;
+                existing.modified_at = now;
+                existing
+            }
+            Err(AppError::KeyNotFound(_)) => {
+                if let Some(existing) =
+                    db::find_key_ignoring_case(&self.db, &incoming.key).map_err(status)?
+                {
+                    return Err(status(AppError::KeyCaseConflict(incoming.key, existing)));
+                }
+                Note {
+                    title: if incoming.title.is_empty() {
+                        incoming.key.clone()
+                    } else {
+                        incoming.title
+                    },
+                    key: incoming.key,
+                    content: incoming.content,
+                    tags: incoming.tags,
+                    created_at: parse_time(&incoming.created_at)
+                        .map_err(Status::invalid_argument)?
+                        .unwrap_or(now),
+                    modified_at: now,
+                    ..Default::default()
+                }
+            }
+            Err(e) => return Err(status(e)),
+        };
+        db::save_note_with_index(&self.db, &note, &self.index).map_err(status)?;
+        Ok(Response::new((&note).into()))
+    }
+
+    async fn delete_note(
+        &self,
+        request: Request<proto::DeleteNoteRequest>,
+    ) -> Result<Response<proto::DeleteNoteResponse>, Status> {
+        let key = request.into_inner().key;
+        if db::get_note(&self.db, &key).map_err(status)?.locked {
+            return Err(status(AppError::Locked(key)));
+        }
+        let deleted_tasks = db::delete_tasks_for_note(&self.db, &key).map_err(status)?;
+        db::delete_reminders_for_note(&self.db, &key).map_err(status)?;
+        db::delete_note_with_index(&self.db, &key, &self.index).map_err(status)?;
+        Ok(Response::new(proto::DeleteNoteResponse {
+            deleted_tasks: deleted_tasks as u32,
+        }))
+    }
+
+    async fn list_links(
+        &self,
+        request: Request<proto::ListLinksRequest>,
+    ) -> Result<Response<Self::ListLinksStream>, Status> {
+        let request = request.into_inner();
+        let links = if request.outgoing {
+            graph::links_in(&db::get_note(&self.db, &request.key).map_err(status)?)
+        } else {
+            graph::links_to(&db::get_all_notes(&self.db).map_err(status)?, &request.key)
+        };
+        Ok(stream(links.iter().map(Into::into).collect()))
+    }
+}
+
+#[tonic::async_trait]
+impl TaskService for MediService {
+    type ListTasksStream = ListStream<proto::Task>;
+
+    async fn list_tasks(
+        &self,
+        request: Request<proto::ListTasksRequest>,
+    ) -> Result<Response<Self::ListTasksStream>, Status> {
+        let note_key = request.into_inner().note_key;
+        let mut tasks = db::get_all_tasks(&self.db).map_err(status)?;
+        tasks.retain(|task| note_key.is_empty() || task.note_key == note_key);
+        tasks.sort_by_key(|task| task.id);
+        Ok(stream(tasks.iter().map(Into::into).collect()))
+    }
+
+    async fn add_task(
+        &self,
+        request: Request<proto::AddTaskRequest>,
+    ) -> Result<Response<proto::Task>, Status> {
+        let request = request.into_inner();
+        db::get_note(&self.db, &request.note_key).map_err(status)?;
+        let task = Task {
+            id: db::get_next_task_id(&self.db).map_err(status)?,
+            note_key: request.note_key,
+            description: request.description,
+            status: TaskStatus::Open,
+            created_at: Utc::now(),
+            due_at: None,
+            completed_at: None,
+            resolution: None,
+            hidden_until: None,
+        };
+        db::save_task(&self.db, &task).map_err(status)?;
+        Ok(Response::new((&task).into()))
+    }
+
+    async fn complete_task(
+        &self,
+        request: Request<proto::CompleteTaskRequest>,
+    ) -> Result<Response<proto::Task>, Status> {
+        let id = request.into_inner().id;
+        let mut task = db::get_all_tasks(&self.db)
+            .map_err(status)?
+            .into_iter()
+            .find(|task| task.id == id)
+            .ok_or_else(|| status(AppError::TaskNotFound(id)))?;
+        task.status = TaskStatus::Done;
+        task.completed_at = Some(Utc::now());
+        db::save_task(&self.db, &task).map_err(status)?;
+        Ok(Response::new((&task).into()))
+    }
+}
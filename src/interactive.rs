@@ -0,0 +1,25 @@
+use crate::error::AppError;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set by the global `--non-interactive` flag.
+static DISABLED: AtomicBool = AtomicBool::new(false);
+
+/// Turns prompts (and the editor) off for the rest of the run.
+pub fn disable() {
+    DISABLED.store(true, Ordering::Relaxed);
+}
+
+/// Whether medi may prompt, i.e. `--non-interactive` wasn't given.
+pub fn enabled() -> bool {
+    !DISABLED.load(Ordering::Relaxed)
+}
+
+/// Call before prompting: fails with [`AppError::NonInteractive`], naming `what` would
+/// have been asked, when prompts are turned off.
+pub fn require(what: &str) -> Result<(), AppError> {
+    if enabled() {
+        Ok(())
+    } else {
+        Err(AppError::NonInteractive(what.to_string()))
+    }
+}
@@ -0,0 +1,193 @@
+use crate::error::AppError;
+use crate::note::{self, Note};
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+/// Metadata field holding the Joplin item id of an imported note. Used to skip notes
+/// imported before and to resolve links between notes.
+pub const ID_FIELD: &str = "joplin_id";
+
+lazy_static! {
+    /// Joplin links notes and resources as `[text](:/<id>)`.
+    static ref ITEM_LINK: Regex = Regex::new(r"(!?)\[([^\]]*)\]\(:/([0-9a-f]{32})\)").unwrap();
+    static ref FIELD: Regex = Regex::new(r"^([a-z_]+):(?: (.*))?$").unwrap();
+}
+
+/// Item types in Joplin's serialisation format (the `type_` field).
+const TYPE_NOTE: &str = "1";
+const TYPE_FOLDER: &str = "2";
+const TYPE_TAG: &str = "5";
+const TYPE_NOTE_TAG: &str = "6";
+
+/// A serialised Joplin item: the text, followed by a blank line and `name: value` fields.
+struct Item {
+    text: String,
+    fields: HashMap<String, String>,
+}
+
+impl Item {
+    fn parse(serialised: &str) -> Item {
+        let lines: Vec<&str> = serialised.trim_end().lines().collect();
+        let mut split = lines.len();
+        while split > 0 && FIELD.is_match(lines[split - 1]) {
+            split -= 1;
+        }
+        let fields = lines[split..]
+            .iter()
+            .filter_map(|line| FIELD.captures(line))
+            .map(|caps| {
+                let value = caps.get(2).map_or("", |value| value.as_str());
+                (caps[1].to_string(), value.to_string())
+            })
+            .collect();
+        Item {
+            text: lines[..split].join("\n").trim_end().to_string(),
+            fields,
+        }
+    }
+
+    fn field(&self, name: &str) -> &str {
+        self.fields.get(name).map_or("", String::as_str)
+    }
+
+    /// The first line of the text, which is the title of notes, notebooks and tags.
+    fn title(&self) -> &str {
+        self.text.lines().next().unwrap_or("").trim()
+    }
+
+    fn time(&self, name: &str) -> Option<DateTime<Utc>> {
+        self.field(name).parse().ok()
+    }
+}
+
+/// Reads a Joplin export (`.jex`, a tar of serialised items) into notes. Notebooks become
+/// key prefixes, e.g. `work/meetings/standup`, and Joplin tags become tags. Resources are
+/// copied to `attachments` and links to them point there; links to other notes keep their
+/// Joplin ids until `link_notes` turns them into wikilinks.
+pub fn read_export(path: &Path, attachments: &Path) -> Result<Vec<Note>, AppError> {
+    let mut archive = tar::Archive::new(fs::File::open(path)?);
+    let mut items = Vec::new();
+    let mut resources = HashMap::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let entry_path = entry.path()?.into_owned();
+        let Some(file_name) = entry_path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if entry_path.starts_with("resources") {
+            // Resource files are named `<id>.<extension>`.
+            let id = file_name.split('.').next().unwrap_or(file_name).to_string();
+            fs::create_dir_all(attachments)?;
+            let target = attachments.join(file_name);
+            entry.unpack(&target)?;
+            resources.insert(id, note::link_target(&target));
+        } else if file_name.ends_with(".md") {
+            let mut serialised = String::new();
+            entry.read_to_string(&mut serialised)?;
+            items.push(Item::parse(&serialised));
+        }
+    }
+    if items.is_empty() {
+        return Err(AppError::Convert(format!(
+            "{} has no notes; is it a Joplin JEX export?",
+            path.display()
+        )));
+    }
+
+    let of_type = |kind: &'static str| items.iter().filter(move |item| item.field("type_") == kind);
+    let folders: HashMap<&str, &Item> = of_type(TYPE_FOLDER)
+        .map(|folder| (folder.field("id"), folder))
+        .collect();
+    let tag_names: HashMap<&str, &str> = of_type(TYPE_TAG)
+        .map(|tag| (tag.field("id"), tag.title()))
+        .collect();
+    let mut note_tags: HashMap<&str, Vec<String>> = HashMap::new();
+    for link in of_type(TYPE_NOTE_TAG) {
+        if let Some(name) = tag_names.get(link.field("tag_id")) {
+            note_tags
+                .entry(link.field("note_id"))
+                .or_default()
+                .push(name.to_string());
+        }
+    }
+
+    let mut notes = Vec::new();
+    for item in of_type(TYPE_NOTE) {
+        // Encrypted notes can't be read, and conflict copies duplicate another note.
+        if item.field("encryption_applied") == "1" || item.field("is_conflict") == "1" {
+            continue;
+        }
+        let id = item.field("id");
+        let title = item.title().to_string();
+
+        let mut key_parts = Vec::new();
+        let mut parent = folders.get(item.field("parent_id"));
+        while let Some(folder) = parent {
+            key_parts.push(note::slugify(&folder.title().replace('/', " ")));
+            parent = folders.get(folder.field("parent_id"));
+        }
+        key_parts.reverse();
+        let slug = note::slugify(&title.replace('/', " "));
+        key_parts.push(if slug.is_empty() {
+            id.to_string()
+        } else {
+            slug
+        });
+        key_parts.retain(|part| !part.is_empty());
+
+        // The body follows the title after a blank line.
+        let body = item.text.lines().skip(2).collect::<Vec<_>>().join("\n");
+        let content = ITEM_LINK.replace_all(&body, |caps: &regex::Captures| {
+            match resources.get(&caps[3]) {
+                Some(path) => format!("{}[{}]({})", &caps[1], &caps[2], path),
+                None => caps[0].to_string(),
+            }
+        });
+
+        let modified_at = item.time("user_updated_time").or(item.time("updated_time"));
+        let created_at = item.time("user_created_time").or(item.time("created_time"));
+        let mut metadata = BTreeMap::new();
+        metadata.insert(ID_FIELD.to_string(), id.to_string());
+        if !item.field("source_url").is_empty() {
+            metadata.insert(
+                "source_url".to_string(),
+                item.field("source_url").to_string(),
+            );
+        }
+        notes.push(Note {
+            key: key_parts.join("/"),
+            title: if title.is_empty() {
+                id.to_string()
+            } else {
+                title
+            },
+            tags: note_tags.remove(id).unwrap_or_default(),
+            content: format!("{}\n", content.trim()),
+            created_at: created_at.or(modified_at).unwrap_or_else(Utc::now),
+            modified_at: modified_at.unwrap_or_else(Utc::now),
+            metadata,
+            ..Default::default()
+        });
+    }
+    Ok(notes)
+}
+
+/// Turns links to other Joplin notes into `[[key]]` wikilinks, given the keys of the
+/// imported notes by their Joplin id.
+pub fn link_notes(notes: &mut [Note], keys: &HashMap<String, String>) {
+    for note in notes {
+        note.content = ITEM_LINK
+            .replace_all(&note.content, |caps: &regex::Captures| {
+                match keys.get(&caps[3]) {
+                    Some(key) if caps[1].is_empty() => format!("[[{}]]", key),
+                    _ => caps[0].to_string(),
+                }
+            })
+            .into_owned();
+    }
+}
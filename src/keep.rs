@@ -0,0 +1,250 @@
+use crate::bookmark::decode_entities;
+use crate::error::AppError;
+use crate::note::{self, Note, ARCHIVED_TAG, PINNED_TAG};
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Metadata field holding the Takeout file name (without extension) a note came from,
+/// used to skip notes imported before.
+pub const SOURCE_FIELD: &str = "keep_file";
+
+lazy_static! {
+    static ref HTML_TITLE: Regex = Regex::new(r"(?is)<title>(.*?)</title>").unwrap();
+    static ref HTML_CONTENT: Regex =
+        Regex::new(r#"(?is)<div class="content">(.*?)</div>\s*(?:<div class="(?:labels|attachments)|</body>|$)"#).unwrap();
+    static ref HTML_LIST_ITEM: Regex =
+        Regex::new(r#"(?is)<li class="listitem( checked)?">.*?<span class="text">(.*?)</span>"#).unwrap();
+    static ref HTML_LABEL: Regex = Regex::new(r#"(?is)<span class="label-name">(.*?)</span>"#).unwrap();
+    static ref HTML_TAG: Regex = Regex::new(r"(?s)<[^>]+>").unwrap();
+}
+
+/// A note as Google Takeout writes it to `Keep/<title>.json`.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct KeepNote {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    text_content: String,
+    #[serde(default)]
+    list_content: Vec<ListItem>,
+    #[serde(default)]
+    labels: Vec<Label>,
+    #[serde(default)]
+    attachments: Vec<Attachment>,
+    #[serde(default)]
+    is_pinned: bool,
+    #[serde(default)]
+    is_archived: bool,
+    #[serde(default)]
+    is_trashed: bool,
+    created_timestamp_usec: Option<i64>,
+    user_edited_timestamp_usec: Option<i64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListItem {
+    text: String,
+    #[serde(default)]
+    is_checked: bool,
+}
+
+#[derive(Deserialize)]
+struct Label {
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Attachment {
+    file_path: String,
+}
+
+/// Reads the notes in a Takeout `Keep` folder. Notes are read from their `.json` files;
+/// older exports that only have `.html` files are read from those. Checklists become
+/// Markdown checkboxes, labels become tags, and pinned and archived notes are tagged
+/// `pinned` and `archived`. Trashed notes are left out. Attached files are copied to
+/// `attachments` and linked from the note.
+pub fn read_takeout(dir: &Path, attachments: &Path) -> Result<Vec<Note>, AppError> {
+    if !dir.is_dir() {
+        return Err(AppError::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("Directory not found: {}", dir.display()),
+        )));
+    }
+    let mut paths: Vec<_> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .collect();
+    paths.sort();
+
+    let mut notes = Vec::new();
+    for path in &paths {
+        let extension = path.extension().and_then(|ext| ext.to_str());
+        let note = match extension {
+            Some("json") => {
+                let keep: KeepNote = serde_json::from_str(&fs::read_to_string(path)?)?;
+                if keep.is_trashed {
+                    continue;
+                }
+                from_json(keep, path, attachments)?
+            }
+            // Only for notes without a JSON file, which has more detail.
+            Some("html") if !path.with_extension("json").exists() => {
+                from_html(&fs::read_to_string(path)?, path)?
+            }
+            _ => continue,
+        };
+        notes.push(note);
+    }
+    Ok(notes)
+}
+
+fn from_json(keep: KeepNote, path: &Path, attachments: &Path) -> Result<Note, AppError> {
+    let mut content = keep.text_content.trim_end().to_string();
+    let checklist = checklist(
+        keep.list_content
+            .iter()
+            .map(|item| (item.text.as_str(), item.is_checked)),
+    );
+    if !checklist.is_empty() {
+        if !content.is_empty() {
+            content.push_str("\n\n");
+        }
+        content.push_str(&checklist);
+    }
+
+    let dir = path.parent().unwrap_or(Path::new("."));
+    for attachment in &keep.attachments {
+        let source = dir.join(&attachment.file_path);
+        if !source.is_file() {
+            continue;
+        }
+        fs::create_dir_all(attachments)?;
+        let target = attachments.join(&attachment.file_path);
+        fs::copy(&source, &target)?;
+        content.push_str(&format!(
+            "\n\n![{}]({})",
+            attachment.file_path,
+            note::link_target(&target)
+        ));
+    }
+
+    let modified_at = keep
+        .user_edited_timestamp_usec
+        .and_then(DateTime::from_timestamp_micros)
+        .unwrap_or_else(Utc::now);
+    let created_at = keep
+        .created_timestamp_usec
+        .and_then(DateTime::from_timestamp_micros)
+        .unwrap_or(modified_at);
+
+    let mut tags: Vec<String> = keep.labels.into_iter().map(|label| label.name).collect();
+    if keep.is_pinned {
+        tags.push(PINNED_TAG.to_string());
+    }
+    if keep.is_archived {
+        tags.push(ARCHIVED_TAG.to_string());
+    }
+    Ok(to_note(
+        path,
+        keep.title,
+        content,
+        tags,
+        created_at,
+        modified_at,
+    ))
+}
+
+fn from_html(html: &str, path: &Path) -> Result<Note, AppError> {
+    let title = HTML_TITLE
+        .captures(html)
+        .map(|caps| decode_entities(caps[1].trim()))
+        .unwrap_or_default();
+    let body = HTML_CONTENT
+        .captures(html)
+        .map(|caps| caps[1].to_string())
+        .unwrap_or_default();
+
+    let items: Vec<(String, bool)> = HTML_LIST_ITEM
+        .captures_iter(&body)
+        .map(|caps| (html_to_text(&caps[2]), caps.get(1).is_some()))
+        .collect();
+    let content = if items.is_empty() {
+        html_to_text(&body)
+    } else {
+        checklist(
+            items
+                .iter()
+                .map(|(text, checked)| (text.as_str(), *checked)),
+        )
+    };
+    let tags = HTML_LABEL
+        .captures_iter(html)
+        .map(|caps| decode_entities(caps[1].trim()))
+        .collect();
+
+    let modified_at: DateTime<Utc> = fs::metadata(path)?.modified()?.into();
+    Ok(to_note(
+        path,
+        title,
+        content,
+        tags,
+        modified_at,
+        modified_at,
+    ))
+}
+
+/// Formats checklist items as Markdown checkboxes.
+fn checklist<'a>(items: impl Iterator<Item = (&'a str, bool)>) -> String {
+    items
+        .map(|(text, checked)| format!("- [{}] {}", if checked { "x" } else { " " }, text.trim()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn html_to_text(html: &str) -> String {
+    let text = html.replace("<br>", "\n").replace("<br/>", "\n");
+    decode_entities(HTML_TAG.replace_all(&text, "").trim())
+}
+
+fn to_note(
+    path: &Path,
+    title: String,
+    content: String,
+    tags: Vec<String>,
+    created_at: DateTime<Utc>,
+    modified_at: DateTime<Utc>,
+) -> Note {
+    let file_stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    // Untitled notes are named after their first line, or the file Takeout gave them.
+    let title = Some(title.trim().to_string())
+        .filter(|title| !title.is_empty())
+        .or_else(|| content.lines().next().map(|line| line.trim().to_string()))
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| file_stem.clone());
+    let key = Some(note::slugify(&title.replace('/', " ")))
+        .filter(|key| !key.is_empty())
+        .unwrap_or_else(|| note::slugify(&file_stem.replace('/', " ")));
+
+    let mut metadata = BTreeMap::new();
+    metadata.insert(SOURCE_FIELD.to_string(), file_stem);
+    Note {
+        key,
+        title,
+        tags,
+        content: format!("{}\n", content.trim_end()),
+        created_at,
+        modified_at,
+        metadata,
+        ..Default::default()
+    }
+}
@@ -0,0 +1,165 @@
+use crate::note::{self, Note};
+use std::collections::{HashMap, HashSet};
+
+/// Common English words that never make useful keywords.
+const STOPWORDS: &[&str] = &[
+    "about", "above", "after", "again", "against", "all", "also", "and", "any", "are", "because",
+    "been", "before", "being", "below", "between", "both", "but", "can", "could", "did", "does",
+    "doing", "down", "during", "each", "few", "for", "from", "further", "had", "has", "have",
+    "having", "her", "here", "hers", "him", "his", "how", "into", "its", "itself", "just", "let",
+    "like", "more", "most", "much", "must", "not", "now", "off", "once", "one", "only", "other",
+    "our", "ours", "out", "over", "own", "same", "she", "should", "some", "such", "than", "that",
+    "the", "their", "them", "then", "there", "these", "they", "this", "those", "through", "too",
+    "under", "until", "use", "used", "using", "very", "was", "way", "were", "what", "when",
+    "where", "which", "while", "who", "whom", "why", "will", "with", "would", "you", "your",
+    "yours",
+];
+
+/// Splits text into lowercase candidate terms, dropping short words, numbers and stopwords.
+pub fn terms(text: &str) -> Vec<String> {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_'))
+        .map(|word| word.trim_matches(|c| c == '-' || c == '_').to_lowercase())
+        .filter(|word| word.chars().count() >= 3)
+        .filter(|word| !word.chars().all(|c| c.is_numeric()))
+        .filter(|word| !STOPWORDS.contains(&word.as_str()))
+        .collect()
+}
+
+/// Ranks the terms of `targets` by TF-IDF against the whole `corpus` and returns the
+/// `top` highest scoring terms. With several targets, their scores are summed.
+pub fn rank(targets: &[&Note], corpus: &[Note], top: usize) -> Vec<(String, f64)> {
+    let idf = Idf::from_corpus(corpus);
+    let mut scores: HashMap<String, f64> = HashMap::new();
+    for note in targets {
+        for (term, score) in idf.vector(note) {
+            *scores.entry(term).or_default() += score;
+        }
+    }
+
+    let mut ranked: Vec<(String, f64)> = scores.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    ranked.truncate(top);
+    ranked
+}
+
+/// The TF-IDF weight of each term of `target`, against the whole `corpus`.
+pub fn weights(target: &Note, corpus: &[Note]) -> HashMap<String, f64> {
+    Idf::from_corpus(corpus).vector(target)
+}
+
+/// Finds the `top` notes of `corpus` most similar to `target`, by cosine similarity of
+/// their TF-IDF vectors. The target itself (by key) and unrelated notes are left out.
+pub fn similar<'a>(target: &Note, corpus: &'a [Note], top: usize) -> Vec<(&'a Note, f64)> {
+    let idf = Idf::from_corpus(corpus);
+    let target_vector = idf.vector(target);
+    let mut ranked: Vec<(&Note, f64)> = corpus
+        .iter()
+        .filter(|note| note.key != target.key)
+        .map(|note| (note, cosine(&target_vector, &idf.vector(note))))
+        .filter(|(_, score)| *score > 0.0)
+        .collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.key.cmp(&b.0.key)));
+    ranked.truncate(top);
+    ranked
+}
+
+/// Similarity above which a new note is reported as a near-duplicate.
+const DUPLICATE_THRESHOLD: f64 = 0.8;
+
+/// Notes with fewer distinct terms than this are too short to compare meaningfully.
+const MIN_DUPLICATE_TERMS: usize = 3;
+
+/// Returns the existing note most similar to `candidate`, if it looks like a near-duplicate.
+pub fn near_duplicate<'a>(candidate: &Note, corpus: &'a [Note]) -> Option<(&'a Note, f64)> {
+    let distinct: HashSet<String> = terms(&candidate.content).into_iter().collect();
+    if distinct.len() < MIN_DUPLICATE_TERMS {
+        return None;
+    }
+    similar(candidate, corpus, 1)
+        .into_iter()
+        .next()
+        .filter(|(_, score)| *score >= DUPLICATE_THRESHOLD)
+}
+
+/// How many similar notes get a say in tag suggestions.
+const NEIGHBOURS: usize = 10;
+
+/// Suggests up to `top` tags for `target` from the tags of its most similar notes, each
+/// tag scored by the summed similarity of the notes carrying it. Tags the note already
+/// has, and the archive tag, are never suggested.
+pub fn suggest_tags(target: &Note, corpus: &[Note], top: usize) -> Vec<(String, f64)> {
+    let mut scores: HashMap<String, f64> = HashMap::new();
+    for (neighbour, similarity) in similar(target, corpus, NEIGHBOURS) {
+        for tag in &neighbour.tags {
+            if !target.tags.contains(tag) && tag != note::ARCHIVED_TAG {
+                *scores.entry(tag.clone()).or_default() += similarity;
+            }
+        }
+    }
+
+    let mut ranked: Vec<(String, f64)> = scores.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    ranked.truncate(top);
+    ranked
+}
+
+/// Inverse document frequencies over a corpus.
+struct Idf {
+    /// Document frequency: in how many notes each term appears.
+    document_frequency: HashMap<String, usize>,
+    total_docs: f64,
+}
+
+impl Idf {
+    fn from_corpus(corpus: &[Note]) -> Self {
+        let mut document_frequency: HashMap<String, usize> = HashMap::new();
+        for note in corpus {
+            let unique: HashSet<String> = terms(&note_text(note)).into_iter().collect();
+            for term in unique {
+                *document_frequency.entry(term).or_default() += 1;
+            }
+        }
+        Idf {
+            document_frequency,
+            total_docs: corpus.len() as f64,
+        }
+    }
+
+    /// The TF-IDF score of every term in the note.
+    fn vector(&self, note: &Note) -> HashMap<String, f64> {
+        let note_terms = terms(&note_text(note));
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for term in &note_terms {
+            *counts.entry(term.clone()).or_default() += 1;
+        }
+        counts
+            .into_iter()
+            .map(|(term, count)| {
+                let tf = count as f64 / note_terms.len() as f64;
+                let df = self.document_frequency.get(&term).copied().unwrap_or(0) as f64;
+                // Smoothed IDF, so terms found in every note still score above zero.
+                let idf = ((1.0 + self.total_docs) / (1.0 + df)).ln() + 1.0;
+                (term, tf * idf)
+            })
+            .collect()
+    }
+}
+
+fn cosine(a: &HashMap<String, f64>, b: &HashMap<String, f64>) -> f64 {
+    let dot: f64 = a
+        .iter()
+        .filter_map(|(term, x)| b.get(term).map(|y| x * y))
+        .sum();
+    let norm = |v: &HashMap<String, f64>| v.values().map(|x| x * x).sum::<f64>().sqrt();
+    let norms = norm(a) * norm(b);
+    if norms == 0.0 {
+        0.0
+    } else {
+        dot / norms
+    }
+}
+
+/// The text a note's keywords are drawn from: its title and content.
+fn note_text(note: &Note) -> String {
+    format!("{}\n{}", note.title, note.content)
+}
@@ -1,46 +1,101 @@
+mod agenda;
+mod bench;
+mod bookmark;
+mod calendar;
 mod cli;
+mod collate;
 pub mod colours;
 pub mod config;
+mod convert;
+#[cfg(unix)]
+mod daemon;
+mod dates;
+mod dayone;
 mod db;
+mod diff;
+mod dump;
 mod error;
+mod exclude;
+mod export;
+mod filter;
+mod finder;
+mod flashcard;
+mod frontmatter;
+mod graph;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+mod interactive;
+mod joplin;
+mod keep;
+mod keywords;
+mod links;
+#[cfg(feature = "llm")]
+mod llm;
+mod logging;
+mod maintain;
+mod migrate;
+mod moc;
 mod note;
+mod onboard;
 mod preview;
+mod query;
+mod reminder;
+mod render;
+mod review;
 mod search;
+#[cfg(feature = "llm")]
+mod semantic;
+mod snapshot;
+mod stats;
+mod summary;
 mod task;
+mod timezone;
+mod triage;
+mod update;
+mod wizard;
 
-use crate::cli::{ExportFormat, SortBy};
-use crate::note::{JsonExport, Note};
+use crate::cli::{
+    BookmarkCommands, ExportFormat, ListOutput, NoteOutput, QueryOutput, SortBy, StatsCommands,
+    TaskGroup, TaskOutput,
+};
+use crate::colours::{paint, Role};
+use crate::frontmatter::Frontmatter;
+use crate::note::{JsonExport, Note, NoteKind, NoteMeta};
+use crate::reminder::Reminder;
 use crate::task::{Task, TaskStatus};
 use atty::Stream;
-use chrono::Utc;
+use chrono::{DateTime, Datelike, Local, Utc};
 use clap::CommandFactory;
-pub use cli::{Cli, Commands};
+pub use cli::{parse_with_defaults, Cli, Commands, ConfigCommands};
 use colored::Colorize;
-use config::Config;
-use crossbeam_channel::unbounded;
-use dialoguer::Confirm;
+use config::{Config, LinkStyle};
+use dialoguer::{Confirm, Input, Select};
 use error::AppError;
 use regex::Regex;
+use sled::Db;
+use tantivy::Index;
 
 use crate::preview::PreviewApp;
 use rumdl_lib::lint;
-#[cfg(unix)]
-use skim::options::SkimOptionsBuilder;
-#[cfg(unix)]
-use skim::{Skim, SkimItem};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::io::Read;
-use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::path::{Component, Path, PathBuf};
+use std::time::Instant;
 use std::{env, fs, io};
 use tempfile::Builder as TempBuilder;
+use tracing::debug;
 
 /// Initialise or open the Tantivy search index located at the specified path.
-pub fn initialise_search_index(config: &Config) -> Result<tantivy::Index, AppError> {
-    let search_index_path = match env::var("MEDI_DB_PATH") {
-        Ok(path_str) => PathBuf::from(path_str).join("search_index"),
-        Err(_) => config
-            .db_path
-            .as_ref()
+/// An explicit `db_override` (from `--db`) takes precedence over `MEDI_DB_PATH` and the config.
+pub fn initialise_search_index(
+    config: &Config,
+    db_override: Option<&Path>,
+) -> Result<tantivy::Index, AppError> {
+    let search_index_path = match (db_override, env::var("MEDI_DB_PATH")) {
+        (Some(path), _) => path.join("search_index"),
+        (None, Ok(path_str)) => PathBuf::from(path_str).join("search_index"),
+        (None, Err(_)) => db::find_local_vault()
+            .or_else(|| config.db_path.clone())
             .map(|db_path| db_path.join("search_index"))
             .unwrap_or_else(|| {
                 dirs::data_dir()
@@ -62,13 +117,468 @@ fn format_tags(tags: &[String]) -> String {
         format!(
             " [{}]",
             tags.iter()
-                .map(|t| format!("#{}", t).cyan().to_string())
+                .map(|t| colours::tag(&format!("#{}", t)).to_string())
                 .collect::<Vec<String>>()
                 .join(" ")
         )
     }
 }
 
+/// Prints notes (sorted by key) as an indented tree, treating `/` in keys as a hierarchy.
+fn print_key_tree(notes: &[Note]) {
+    let mut printed_namespaces: Vec<String> = Vec::new();
+    for note in notes {
+        let parts: Vec<&str> = note.key.split('/').collect();
+        let depth = parts.len() - 1;
+
+        // Print any namespace headings we haven't seen yet.
+        for level in 0..depth {
+            let namespace = parts[..=level].join("/");
+            if !printed_namespaces.contains(&namespace) {
+                println!("{}{}/", "  ".repeat(level), parts[level].bold());
+                printed_namespaces.push(namespace);
+            }
+        }
+
+        println!(
+            "{}- {}{}",
+            "  ".repeat(depth),
+            colours::key(parts[depth]),
+            format_tags(&note.tags)
+        );
+    }
+}
+
+/// Prints notes as a table with word count, reading time and age (time since last change).
+fn print_note_table(notes: &[Note]) {
+    let key_width = notes.iter().map(|n| n.key.len()).max().unwrap_or(0).max(3);
+    println!(
+        "{}",
+        format!(
+            "{:<key_width$}  {:>6}  {:>5}  {:>5}  TAGS",
+            "KEY", "WORDS", "READ", "AGE"
+        )
+        .bold()
+    );
+    for note in notes {
+        let words = count_words(&note.content);
+        println!(
+            "{}  {:>6}  {:>5}  {:>5}  {}",
+            colours::key(&format!("{:<key_width$}", note.key)),
+            words,
+            format!("{}m", calculate_reading_time(words)),
+            format_age(Utc::now() - note.modified_at),
+            note.tags.join(", ")
+        );
+    }
+}
+
+/// Moves the notes tagged `pinned` before the others, keeping the order within each group.
+fn pinned_to_top(notes: &mut [Note]) {
+    notes.sort_by_key(|note| !note.is_pinned());
+}
+
+/// Prints the notes found by `search`.
+fn print_search_results(notes: &[Note], output: NoteOutput) -> Result<(), AppError> {
+    if notes.is_empty() {
+        colours::warn("No matching notes found.");
+        return Ok(());
+    }
+
+    if let NoteOutput::Ndjson = output {
+        return print_ndjson(notes);
+    }
+
+    eprintln!("{}:", "Search Results".bold().underline());
+    for note in notes {
+        let tags_str = format_tags(&note.tags);
+        println!("- {}{}", colours::key(&note.key), tags_str);
+    }
+    Ok(())
+}
+
+/// The terse `status --prompt` line: notes, done/total tasks and priority tasks. Read from
+/// the cached counts, so it doesn't scan the notes or tasks on every prompt redraw.
+fn prompt_line(db: &Db) -> Result<String, AppError> {
+    let stats = db::get_stats(db)?;
+    Ok(format!(
+        "📝 {} ✅ {}/{} ⭐{}",
+        stats.notes, stats.done_tasks, stats.tasks, stats.prio_tasks
+    ))
+}
+
+/// Prints notes as newline-delimited JSON, one note object per line.
+fn print_ndjson(notes: &[Note]) -> Result<(), AppError> {
+    let mut stdout = io::stdout().lock();
+    for note in notes {
+        serde_json::to_writer(&mut stdout, note)?;
+        io::Write::write_all(&mut stdout, b"\n")?;
+    }
+    Ok(())
+}
+
+/// A `## Tasks` checklist of the tasks for `note_key`, or nothing if it has none.
+fn task_section(tasks: &[Task], note_key: &str) -> String {
+    let lines: Vec<String> = tasks
+        .iter()
+        .filter(|task| task.note_key == note_key)
+        .map(|task| task::checklist_line(task, None))
+        .collect();
+    if lines.is_empty() {
+        return String::new();
+    }
+    format!("## Tasks\n\n{}", lines.join("\n"))
+}
+
+/// Nests tasks under their note's key, or under each of their note's tags as `#tag`.
+fn group_tasks<'a>(
+    tasks: &'a [Task],
+    group: TaskGroup,
+    notes: &HashMap<String, Note>,
+) -> BTreeMap<String, Vec<&'a Task>> {
+    let mut groups: BTreeMap<String, Vec<&Task>> = BTreeMap::new();
+    for task in tasks {
+        match group {
+            TaskGroup::Note => groups.entry(task.note_key.clone()).or_default().push(task),
+            TaskGroup::Tag => match notes.get(&task.note_key).map(|note| &note.tags) {
+                Some(tags) if !tags.is_empty() => {
+                    for tag in tags {
+                        groups.entry(format!("#{}", tag)).or_default().push(task);
+                    }
+                }
+                _ => groups
+                    .entry("(untagged)".to_string())
+                    .or_default()
+                    .push(task),
+            },
+        }
+    }
+    groups
+}
+
+/// Prints tasks as a table, with a first column naming each task's group if `grouped`.
+fn print_task_table(rows: &[(&str, &Task)], grouped: bool) {
+    let width =
+        |values: Vec<usize>, header: &str| values.into_iter().max().unwrap_or(0).max(header.len());
+    let group_width = width(rows.iter().map(|(group, _)| group.len()).collect(), "GROUP");
+    let id_width = width(
+        rows.iter().map(|(_, t)| t.id.to_string().len()).collect(),
+        "ID",
+    );
+    let note_width = width(rows.iter().map(|(_, t)| t.note_key.len()).collect(), "NOTE");
+    let group_column = |group: &str| {
+        if grouped {
+            format!("{:<group_width$}  ", group)
+        } else {
+            String::new()
+        }
+    };
+    println!(
+        "{}",
+        format!(
+            "{}{:>id_width$}  {:<6}  {:<16}  {:<note_width$}  DESCRIPTION",
+            group_column("GROUP"),
+            "ID",
+            "STATUS",
+            "DUE",
+            "NOTE"
+        )
+        .bold()
+    );
+    for (group, task) in rows {
+        let status = match task.status {
+            TaskStatus::Open => "open",
+            TaskStatus::Prio => "prio",
+            TaskStatus::Done => "done",
+        };
+        let due = task
+            .due_at
+            .map(|due| timezone::format(due, "%Y-%m-%d %H:%M"))
+            .unwrap_or_default();
+        println!(
+            "{}{:>id_width$}  {:<6}  {:<16}  {}  {}",
+            group_column(group),
+            task.id,
+            status,
+            due,
+            colours::key(&format!("{:<note_width$}", task.note_key)),
+            task.description
+        );
+    }
+}
+
+/// Formats a duration compactly, e.g. `45m`, `3h`, `12d`, `5mo`, `2y`.
+fn format_age(age: chrono::Duration) -> String {
+    match age.num_days() {
+        0 if age.num_hours() == 0 => format!("{}m", age.num_minutes().max(0)),
+        0 => format!("{}h", age.num_hours()),
+        days @ 1..=59 => format!("{}d", days),
+        days @ 60..=729 => format!("{}mo", days / 30),
+        days => format!("{}y", days / 365),
+    }
+}
+
+/// Prints a reminder as `[id] <local due time> key: message`.
+fn print_reminder(reminder: &Reminder) {
+    println!(
+        "[{}] {} {}{}",
+        reminder.id,
+        paint(
+            &timezone::format(reminder.due_at, "%Y-%m-%d %H:%M"),
+            Role::Warn,
+        ),
+        colours::key(&reminder.note_key),
+        reminder
+            .message
+            .as_ref()
+            .map(|message| format!(": {}", message))
+            .unwrap_or_default()
+    );
+}
+
+/// Formats a task as a single line, with its due date highlighted once overdue and the
+/// resolution of a completed task.
+fn format_task(task: &Task, show_note: bool, now: DateTime<Utc>) -> String {
+    // Format the status with colour
+    let status_str = match task.status {
+        TaskStatus::Open => paint("[Open]", Role::Info),
+        TaskStatus::Prio => paint("[Prio] ⭐", Role::Warn).bold(),
+        TaskStatus::Done => paint("[Done]", Role::Success),
+    };
+    let note_str = if show_note {
+        format!(" (for note {})", colours::key(&task.note_key))
+    } else {
+        String::new()
+    };
+    let due_str = match task.due_at {
+        Some(_) if matches!(task.status, TaskStatus::Done) => "".normal(),
+        Some(due_at) => {
+            let due = format!(" due {}", timezone::format(due_at, "%Y-%m-%d %H:%M"));
+            if due_at < now {
+                paint(&due, Role::Error)
+            } else {
+                due.dimmed()
+            }
+        }
+        None => "".normal(),
+    };
+    let snoozed_str = match task.hidden_until {
+        Some(until) if task.is_snoozed(now) => format!(
+            " snoozed until {}",
+            timezone::format(until, "%Y-%m-%d %H:%M")
+        )
+        .dimmed(),
+        _ => "".normal(),
+    };
+    let resolution_str = task
+        .resolution
+        .as_ref()
+        .map(|resolution| format!(" — {}", resolution).dimmed())
+        .unwrap_or_default();
+    format!(
+        "[{}] {}: {}{}{}{}{}",
+        task.id, status_str, task.description, note_str, due_str, snoozed_str, resolution_str
+    )
+}
+
+/// Formats a note's metadata as aligned `field: value` lines.
+fn format_note_meta(meta: &NoteMeta) -> String {
+    let row =
+        |label: &str, value: String| format!("{} {}\n", format!("{:<9}", label).bold(), value);
+    [
+        row("key:", meta.key.clone()),
+        row("title:", meta.title.clone()),
+        row("tags:", meta.tags.join(", ")),
+        row(
+            "created:",
+            timezone::format(meta.created_at, "%Y-%m-%d %H:%M"),
+        ),
+        row(
+            "modified:",
+            timezone::format(meta.modified_at, "%Y-%m-%d %H:%M"),
+        ),
+        row("words:", meta.word_count.to_string()),
+        row("links:", meta.links.join(", ")),
+    ]
+    .into_iter()
+    .chain(meta.locked.then(|| row("locked:", "yes".to_string())))
+    .chain(
+        meta.metadata
+            .iter()
+            .map(|(field, value)| row(&format!("{}:", field), value.clone())),
+    )
+    .collect()
+}
+
+/// The error for `--llm` and `medi ask` in a build without the `llm` feature.
+#[cfg(not(feature = "llm"))]
+fn llm_unsupported() -> AppError {
+    AppError::Unsupported(
+        "medi was built without language model support; reinstall it with `--features llm`"
+            .to_string(),
+    )
+}
+
+/// Fails if the note is locked, unless `force` overrides the lock.
+fn check_unlocked(note: &Note, force: bool) -> Result<(), AppError> {
+    if note.locked && !force {
+        return Err(AppError::Locked(note.key.clone()));
+    }
+    Ok(())
+}
+
+/// Whether a bulk operation has to leave the note alone because it's locked, saying so
+/// when it does.
+fn skip_locked(note: &Note, force: bool) -> bool {
+    let skip = note.locked && !force;
+    if skip {
+        colours::warn(&format!("Skipped '{}': note is locked.", note.key));
+    }
+    skip
+}
+
+/// Resolves a `get` or `edit` argument to a key: the argument itself if a note has that
+/// key, otherwise the key of the note whose title matches it. An argument that matches
+/// nothing comes back unchanged, to be reported as not found.
+fn resolve_key(db: &Db, arg: &str) -> Result<String, AppError> {
+    if db::key_exists(db, arg)? {
+        return Ok(arg.to_string());
+    }
+    let notes = db::get_all_notes(db)?;
+    match note::match_title(&notes, arg).as_slice() {
+        [] => Ok(arg.to_string()),
+        [note] => {
+            colours::info(&format!("Using '{}' ({})", note.key, note.title));
+            Ok(note.key.clone())
+        }
+        candidates => Err(AppError::AmbiguousTitle(
+            arg.to_string(),
+            candidates
+                .iter()
+                .map(|note| format!("{} ({})", note.key, note.title))
+                .collect::<Vec<_>>()
+                .join(", "),
+        )),
+    }
+}
+
+/// Locks or unlocks a note. This changes how the note is handled, not the note itself, so
+/// it doesn't count as a modification.
+fn set_locked(db: &Db, key: &str, locked: bool, search_index: &Index) -> Result<(), AppError> {
+    let mut note = db::get_note(db, key)?;
+    let state = if locked { "locked" } else { "unlocked" };
+    if note.locked == locked {
+        colours::info(&format!("'{}' is already {}.", key, state));
+        return Ok(());
+    }
+    note.locked = locked;
+    // Unlocking is the one change a locked note always takes.
+    let _override = db::override_locks();
+    db::save_note_with_index(db, &note, search_index)?;
+    colours::success(&format!("Note '{}' is now {}.", key, state));
+    Ok(())
+}
+
+/// Writes the notes as `<key>.md` files into a temporary directory, opens the editor on
+/// the directory once, and returns the notes whose files changed. Deleted files leave
+/// their notes alone.
+fn edit_notes_in_dir(notes: Vec<Note>) -> Result<Vec<Note>, AppError> {
+    interactive::require("open the editor")?;
+    let dir = TempBuilder::new().prefix("medi-notes-").tempdir()?;
+    // Keys that could lead outside the directory, like `../x` or `/x`, get numbered files
+    // instead. Keys can't start with `__`, so these names never clash with a key's.
+    let paths: Vec<PathBuf> = notes
+        .iter()
+        .enumerate()
+        .map(|(i, note)| {
+            let contained = Path::new(&note.key)
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)));
+            if contained {
+                dir.path().join(format!("{}.md", note.key))
+            } else {
+                dir.path().join(format!("__note-{}.md", i + 1))
+            }
+        })
+        .collect();
+    for (note, path) in notes.iter().zip(&paths) {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, &note.content)?;
+    }
+    edit::edit_file(dir.path())?;
+
+    let mut changed = Vec::new();
+    for (mut note, path) in notes.into_iter().zip(paths) {
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                colours::warn(&format!(
+                    "'{}' was removed; the note is unchanged.",
+                    note.key
+                ));
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if content.trim() != note.content.trim() {
+            note.content = content;
+            note.modified_at = Utc::now();
+            changed.push(note);
+        }
+    }
+    Ok(changed)
+}
+
+/// Opens a note's content in the user's editor and saves it if it changed. Locked notes
+/// are refused before the editor opens.
+fn edit_note_content(db: &Db, mut note: Note, search_index: &Index) -> Result<(), AppError> {
+    db::check_unlocked(db, &note.key)?;
+    interactive::require("open the editor")?;
+    let tempfile = TempBuilder::new()
+        .prefix("medi-note-")
+        .suffix(".md")
+        .tempfile()?;
+
+    let temppath = tempfile.path().to_path_buf();
+    fs::write(&temppath, &note.content)?;
+    edit::edit_file(&temppath)?;
+
+    let updated_content = fs::read_to_string(&temppath)?;
+    if updated_content.trim() != note.content.trim() {
+        note.content = updated_content;
+        note.modified_at = Utc::now();
+
+        // This will overwrite the old note.
+        db::save_note_with_index(db, &note, search_index)?;
+        colours::success(&format!("Successfully updated note: '{}'", note.key));
+    } else {
+        colours::info("Note content unchanged.");
+    }
+    Ok(())
+}
+
+/// Reads a template from the templates directory. A missing template gives an empty note.
+fn template_content(name: &str) -> Result<String, AppError> {
+    let templates_dir = config::templates_dir()
+        .ok_or_else(|| AppError::ConfigError("Config directory not found".into()))?;
+    Ok(fs::read_to_string(templates_dir.join(format!("{}.md", name))).unwrap_or_default())
+}
+
+/// Opens the editor on `initial_content` and returns what was saved.
+fn compose_in_editor(initial_content: &str) -> Result<String, AppError> {
+    interactive::require("open the editor")?;
+    let tempfile = TempBuilder::new()
+        .prefix("medi-note-")
+        .suffix(".md")
+        .tempfile()?;
+    let temppath = tempfile.path().to_path_buf();
+    fs::write(&temppath, initial_content)?;
+    edit::edit_file(&temppath)?;
+    Ok(fs::read_to_string(&temppath)?)
+}
+
 /// Helper function to calculate reading time
 fn calculate_reading_time(word_count: usize) -> u64 {
     // Assuming an average reading speed of 225 words per minute
@@ -81,6 +591,156 @@ fn count_words(text: &str) -> usize {
     text.split_whitespace().count()
 }
 
+/// Deletes notes along with their tasks and reminders, committing the search index once.
+/// Returns how many tasks and reminders went with them.
+fn delete_notes(db: &Db, keys: &[String], index: &Index) -> Result<(usize, usize), AppError> {
+    // Checked up front, so a locked note keeps its tasks and reminders too.
+    for key in keys {
+        db::check_unlocked(db, key)?;
+    }
+    let (mut tasks, mut reminders) = (0, 0);
+    for key in keys {
+        tasks += db::delete_tasks_for_note(db, key)?;
+        reminders += db::delete_reminders_for_note(db, key)?;
+    }
+    db::delete_notes_with_index(db, keys, index)?;
+    Ok((tasks, reminders))
+}
+
+/// Reads the content `new --from` starts a note with: the readable text of a web page,
+/// followed by its address, or a local file converted as `import` does.
+fn content_from(source: &str, config: &Config) -> Result<String, AppError> {
+    if source.starts_with("http://") || source.starts_with("https://") {
+        let text = snapshot::fetch(source).map_err(|reason| {
+            AppError::Convert(format!("couldn't fetch {}: {}", source, reason))
+        })?;
+        Ok(format!("{}\n\nSource: <{}>\n", text, source))
+    } else {
+        convert::to_markdown(Path::new(source), config)
+    }
+}
+
+/// Turns the `- [ ]`/`- [x]` checklist items of a note into tasks linked to it, skipping
+/// items it already has a task for. Returns how many tasks were created.
+fn extract_tasks(db: &Db, note: &Note) -> Result<usize, AppError> {
+    let existing: HashSet<String> = db::get_all_tasks(db)?
+        .into_iter()
+        .filter(|task| task.note_key == note.key)
+        .map(|task| task.description)
+        .collect();
+    let mut count = 0;
+    for (description, checked) in task::checklist_items(&note.content) {
+        if existing.contains(&description) {
+            continue;
+        }
+        db::save_task(
+            db,
+            &Task {
+                id: db::get_next_task_id(db)?,
+                note_key: note.key.clone(),
+                description,
+                status: if checked {
+                    TaskStatus::Done
+                } else {
+                    TaskStatus::Open
+                },
+                created_at: note.created_at,
+                due_at: None,
+                completed_at: checked.then_some(note.modified_at),
+                resolution: None,
+                hidden_until: None,
+            },
+        )?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Runs [`extract_tasks`] over imported notes for `import --extract-tasks` and reports the
+/// total.
+fn extract_imported_tasks(db: &Db, notes: &[Note]) -> Result<(), AppError> {
+    let mut count = 0;
+    for note in notes {
+        count += extract_tasks(db, note)?;
+    }
+    if count > 0 {
+        colours::info(&format!("Extracted {} task(s) from checklists.", count));
+    }
+    Ok(())
+}
+
+/// Reads the content of a new note: the message, the converted `--from` source, piped stdin
+/// (with its frontmatter) or, failing those, what's written in the editor, starting from
+/// the template.
+fn new_note_content(
+    message: Option<String>,
+    from: Option<String>,
+    template: Option<&str>,
+    config: &Config,
+) -> Result<(String, Option<Frontmatter>), AppError> {
+    if let Some(message) = message {
+        return Ok((message, None));
+    }
+    if let Some(source) = from {
+        return Ok((content_from(&source, config)?, None));
+    }
+    if !atty::is(Stream::Stdin) {
+        let mut buffer = String::new();
+        io::stdin().read_to_string(&mut buffer)?;
+        let (frontmatter, body) = frontmatter::split(&buffer)?;
+        return Ok((body.to_string(), frontmatter));
+    }
+    // Open the editor, with the template or blank.
+    let initial_content = match template {
+        Some(template_name) => template_content(template_name)?,
+        None => String::new(),
+    };
+    Ok((compose_in_editor(&initial_content)?, None))
+}
+
+/// Drops notes imported before, matched on their `id_field` metadata, and makes the keys
+/// of the rest unique by adding `-2`, `-3`, ... Returns the new notes and how many were
+/// skipped.
+fn new_imports(db: &Db, notes: Vec<Note>, id_field: &str) -> Result<(Vec<Note>, usize), AppError> {
+    let existing = db::get_all_notes(db)?;
+    let imported_before: HashSet<&String> = existing
+        .iter()
+        .filter_map(|note| note.metadata.get(id_field))
+        .collect();
+    let mut taken: HashSet<String> = existing.iter().map(|note| note.key.clone()).collect();
+
+    let mut imported = Vec::new();
+    let mut skipped = 0;
+    for mut note in notes {
+        if note
+            .metadata
+            .get(id_field)
+            .is_some_and(|id| imported_before.contains(id))
+        {
+            skipped += 1;
+            continue;
+        }
+        let base = note.key.clone();
+        let mut n = 1;
+        while taken.contains(&note.key) {
+            n += 1;
+            note.key = format!("{}-{}", base, n);
+        }
+        taken.insert(note.key.clone());
+        imported.push(note);
+    }
+    Ok((imported, skipped))
+}
+
+/// Reads the creation and modification times of a file.
+/// Platforms that don't expose a creation time fall back to the modification time.
+fn file_times(path: &Path) -> Result<(DateTime<Utc>, DateTime<Utc>), AppError> {
+    let metadata = fs::metadata(path)?;
+    let modified: DateTime<Utc> = metadata.modified()?.into();
+    let created: DateTime<Utc> = metadata.created().map(Into::into).unwrap_or(modified);
+    Ok((created, modified))
+}
+
 // A helper function to handle the linting and reporting
 fn run_linter_on_notes(notes_to_lint: Vec<Note>) -> Result<usize, AppError> {
     let mut total_issues = 0;
@@ -94,7 +754,7 @@ fn run_linter_on_notes(notes_to_lint: Vec<Note>) -> Result<usize, AppError> {
             for issue in issues {
                 println!(
                     "  - {} (Line: {}, Rule: {})",
-                    issue.message.yellow(),
+                    paint(&issue.message, Role::Warn),
                     issue.line,
                     issue.rule_name.as_deref().unwrap_or("<unknown>")
                 );
@@ -105,91 +765,622 @@ fn run_linter_on_notes(notes_to_lint: Vec<Note>) -> Result<usize, AppError> {
     Ok(total_issues)
 }
 
+/// Runs `medi config validate`. This has to work when the config can't be loaded, so
+/// unlike `run` it doesn't take one.
+pub fn validate_config(cli: Cli) -> Result<(), AppError> {
+    colours::set_color(cli.color);
+    let path = config::config_path()?;
+    if !path.exists() {
+        colours::info(&format!(
+            "No config file at '{}'; medi will create one with the defaults.",
+            path.display()
+        ));
+        return Ok(());
+    }
+    let content = fs::read_to_string(&path)?;
+    let config = config::parse(&path, &content)?;
+    let mut problems = config::problems(&config, &content);
+    problems.extend(cli::defaults_problems(&config.defaults));
+    if problems.is_empty() {
+        colours::success(&format!("'{}' is valid.", path.display()));
+        return Ok(());
+    }
+    for problem in &problems {
+        colours::warn(&format!("- {}", problem));
+    }
+    Err(AppError::ConfigError(format!(
+        "{} problem(s) in '{}'",
+        problems.len(),
+        path.display()
+    )))
+}
+
+/// Offers the setup of `medi init --global` when medi is started in a terminal for the
+/// first time, before running the command. Returns the config, reloaded if it changed.
+pub fn offer_setup(cli: &Cli, config: Config) -> Result<Config, AppError> {
+    let terminal = atty::is(Stream::Stdin) && atty::is(Stream::Stdout);
+    if !terminal
+        || cli.non_interactive
+        || matches!(
+            cli.command,
+            Commands::Init { .. } | Commands::Completion { .. }
+        )
+    {
+        return Ok(config);
+    }
+    colours::set_color(cli.color);
+    let accepted = Confirm::new()
+        .with_prompt("Welcome to medi! Set it up now? (later: medi init --global)")
+        .default(true)
+        .interact()?;
+    if !accepted {
+        return Ok(config);
+    }
+    setup(&config, cli.db.as_deref())?;
+    println!();
+    config::load()
+}
+
+/// Runs `medi init --global`: asks where notes are stored, which editor to use and whether
+/// to add sample templates, saves the answers in the config and creates a welcome note.
+fn setup(config: &Config, db_override: Option<&Path>) -> Result<(), AppError> {
+    let config_path = config::config_path()?;
+    let answers = onboard::prompt(&db::db_path(config, db_override), config.editor.as_deref())?;
+    if answers.db_path != db::db_path(config, db_override) {
+        config::set_value(
+            &config_path,
+            "db_path",
+            toml::Value::String(answers.db_path.to_string_lossy().into_owned()),
+        )?;
+    }
+    if let Some(editor) = answers.editor.filter(|e| config.editor.as_ref() != Some(e)) {
+        config::set_value(&config_path, "editor", toml::Value::String(editor))?;
+    }
+    colours::success(&format!(
+        "Saved your settings in '{}'",
+        config_path.display()
+    ));
+    if answers.templates {
+        let templates_dir = config::templates_dir()
+            .ok_or_else(|| AppError::ConfigError("Config directory not found".into()))?;
+        let written = onboard::write_templates(&templates_dir)?;
+        if !written.is_empty() {
+            colours::success(&format!(
+                "Added the templates {} to '{}'",
+                written.join(", "),
+                templates_dir.display()
+            ));
+        }
+    }
+
+    // Open the vault the way every other command will, with the new settings.
+    let config = config::load()?;
+    let db = db::open(config.clone(), db_override)?;
+    let search_index = initialise_search_index(&config, db_override)?;
+    if db::key_exists(&db, onboard::WELCOME_KEY)? {
+        colours::info(&format!(
+            "Kept the existing '{}' note.",
+            onboard::WELCOME_KEY
+        ));
+    } else {
+        let welcome = onboard::welcome_note();
+        db::save_note_with_index(&db, &welcome, &search_index)?;
+        let tasks = extract_tasks(&db, &welcome)?;
+        colours::success(&format!(
+            "Created the note '{}' with {} tasks; open it with 'medi get {}'",
+            welcome.key, tasks, welcome.key
+        ));
+    }
+    colours::info(&format!(
+        "Your notes are stored in '{}'.",
+        db::db_path(&config, db_override).display()
+    ));
+    println!("\n{}", onboard::QUICKSTART);
+    Ok(())
+}
+
+/// Sets the time zone dates are shown and read in: UTC with `--utc`, else the configured
+/// `timezone`, else the system's.
+pub fn set_timezone(cli: &Cli, config: &Config) {
+    if cli.utc {
+        timezone::set(timezone::Zone::Utc);
+    } else if let Some(name) = &config.timezone {
+        match timezone::parse(name) {
+            Ok(zone) => timezone::set(zone),
+            Err(e) => colours::warn(&format!("Ignoring the timezone setting: {}", e)),
+        }
+    }
+}
+
 // The main logic function, which takes the parsed CLI commands
 pub fn run(cli: Cli, config: Config) -> Result<(), AppError> {
-    // Open the database
-    let db = db::open(config.clone())?; // Clone config for search index init
-                                        // Initialise the search index
-    let search_index =
-        initialise_search_index(&config).map_err(|e| AppError::Search(e.to_string()))?;
+    colours::set_theme(&config.theme);
+    colours::set_color(cli.color);
+    // The configured editor beats $VISUAL and $EDITOR, which is where the editor looks.
+    if let Some(editor) = &config.editor {
+        env::set_var("VISUAL", editor);
+    }
+    logging::init(cli.verbose, cli.log_file.as_deref())?;
+    if cli.non_interactive {
+        interactive::disable();
+    }
+    if let Some(name) = &config.sort_locale {
+        match collate::parse(name) {
+            Ok(_) => collate::set_locale(name),
+            Err(e) => colours::warn(&format!("Ignoring the sort_locale setting: {}", e)),
+        }
+    }
+    db::set_note_size_limits(
+        Some(
+            config
+                .large_note_warn_kb
+                .unwrap_or(config::DEFAULT_LARGE_NOTE_WARN_KB),
+        ),
+        config.max_note_kb,
+    );
+    if let Some(mb) = config.index_heap_mb {
+        search::set_heap_mb(mb);
+    }
+
+    if let Commands::Init { global: true } = cli.command {
+        return setup(&config, cli.db.as_deref());
+    }
+    // `init` creates a vault, so it must run before any database is opened.
+    if let Commands::Init { .. } = cli.command {
+        let vault = env::current_dir()?.join(db::LOCAL_VAULT_DIR);
+        if vault.is_dir() {
+            colours::warn(&format!(
+                "A medi vault already exists at '{}'.",
+                vault.display()
+            ));
+            return Ok(());
+        }
+        fs::create_dir_all(&vault)?;
+        db::open(config.clone(), Some(&vault.join("medi_db")))?;
+        colours::success(&format!(
+            "Initialised a project-local vault in '{}'",
+            vault.display()
+        ));
+        colours::info("medi will use it from this directory and any subdirectory.");
+        return Ok(());
+    }
+
+    // Moving the database has to happen before it's opened (and locked).
+    if let Commands::MigratePaths { dry_run } = cli.command {
+        let Some(migration) = migrate::plan(&config)? else {
+            colours::info("No database at ~/.medi/medi_db; nothing to migrate.");
+            return Ok(());
+        };
+        let verb = if dry_run { "Would move" } else { "Moved" };
+        if !dry_run {
+            migrate::apply(&migration)?;
+            migrate::update_config(&config::config_path()?, &migration.to)?;
+        }
+        colours::success(&format!(
+            "{} the database from '{}' to '{}'.",
+            verb,
+            migration.from.display(),
+            migration.to.display()
+        ));
+        if let Some(index) = &migration.stray_index {
+            colours::info(&format!(
+                "{} the search index from '{}' into it.",
+                verb,
+                index.display()
+            ));
+        }
+        if !dry_run {
+            colours::info("db_path in config.toml now points at the new location.");
+        }
+        return Ok(());
+    }
+
+    // `bench` works on a throwaway vault of its own.
+    if let Commands::Bench { notes } = cli.command {
+        colours::info(&format!("Benchmarking with {} synthetic notes...", notes));
+        let timings = bench::run(notes)?;
+        println!(
+            "{}",
+            format!("{:<10}  {:>10}  {:>12}", "OPERATION", "TOTAL", "PER NOTE").bold()
+        );
+        for timing in timings {
+            let per_note = match timing.notes {
+                Some(count) => format!(
+                    "{:.1}µs",
+                    timing.elapsed.as_secs_f64() * 1e6 / count.max(1) as f64
+                ),
+                None => "-".to_string(),
+            };
+            println!(
+                "{:<10}  {:>8.1}ms  {:>12}",
+                timing.operation,
+                timing.elapsed.as_secs_f64() * 1000.0,
+                per_note
+            );
+        }
+        return Ok(());
+    }
+
+    // Keys piped from another medi command, as in `medi list | medi tag apply`, have to be
+    // read before opening the database: the other command holds it until it exits, and
+    // stdin only ends once it has.
+    let piped_keys = match &cli.command {
+        Commands::Tag {
+            command:
+                cli::TagCommands::Apply {
+                    keys_from_stdin: true,
+                    ..
+                },
+        } => {
+            let mut buffer = String::new();
+            io::stdin().read_to_string(&mut buffer)?;
+            Some(
+                buffer
+                    .lines()
+                    // Accept `medi list` output as well as bare keys.
+                    .map(|line| line.trim().trim_start_matches("- "))
+                    .map(|line| line.split_whitespace().next().unwrap_or(""))
+                    .filter(|key| !key.is_empty())
+                    .map(str::to_string)
+                    .collect::<Vec<String>>(),
+            )
+        }
+        _ => None,
+    };
+
+    // `daemon` opens the database itself and keeps it open.
+    if let Commands::Daemon = cli.command {
+        #[cfg(unix)]
+        return daemon::serve(&config, cli.db.as_deref());
+        #[cfg(not(unix))]
+        return Err(AppError::Unsupported(
+            "medi daemon needs Unix domain sockets".to_string(),
+        ));
+    }
+
+    // With a daemon running, let it answer the commands it serves; anything else pauses
+    // it, so its lock on the database is released until this command is done.
+    #[cfg(unix)]
+    let _daemon_lease = match daemon::connect(&daemon::socket_path(&config, cli.db.as_deref())) {
+        Some(mut stream) => match &cli.command {
+            Commands::Search {
+                query,
+                output,
+                semantic: false,
+                pinned_first,
+            } => {
+                let request = daemon::Request::Search {
+                    query: query.clone(),
+                };
+                if let daemon::Response::Notes { mut notes } =
+                    daemon::request(&mut stream, &request)?
+                {
+                    if *pinned_first {
+                        pinned_to_top(&mut notes);
+                    }
+                    print_search_results(&notes, output.clone())?;
+                }
+                return Ok(());
+            }
+            Commands::Status { prompt: true, .. } => {
+                if let daemon::Response::Prompt { line } =
+                    daemon::request(&mut stream, &daemon::Request::Prompt)?
+                {
+                    println!("{}", line);
+                }
+                return Ok(());
+            }
+            _ => {
+                daemon::request(&mut stream, &daemon::Request::Pause)?;
+                Some(stream)
+            }
+        },
+        None => None,
+    };
+
+    // Open the database (clone config for search index init)
+    let db = db::open(config.clone(), cli.db.as_deref())?;
+    // `status --prompt` runs on every prompt redraw, so it skips opening the search index.
+    if let Commands::Status { prompt: true, .. } = cli.command {
+        println!("{}", prompt_line(&db)?);
+        return Ok(());
+    }
+
+    // Initialise the search index
+    let search_index = initialise_search_index(&config, cli.db.as_deref())
+        .map_err(|e| AppError::Search(e.to_string()))?;
+    // Drain index updates queued by earlier commands whose index writes failed.
+    if let Err(e) = db::replay_pending_index(&db, &search_index) {
+        colours::warn(&format!(
+            "Could not bring the search index up to date: {}",
+            e
+        ));
+    }
+
+    // Commands that can touch many notes commit their index changes once, at the end.
+    let _index_batch = matches!(
+        cli.command,
+        Commands::Import(_) | Commands::Replace { .. } | Commands::Tag { .. }
+    )
+    .then(|| db::batch_index_commits(&db, &search_index));
 
     match cli.command {
         Commands::New {
             key,
             message,
+            from,
             title,
             tag,
             template,
+            interactive,
+            slug,
+            auto,
+            zk,
+            expires,
         } => {
+            // With --auto the content comes first, as the key is derived from it.
+            let mut input = None;
+            // With --interactive, prompt for the metadata; the flags become defaults.
+            let (key, title, tag, template) = if interactive {
+                let mut known_tags: Vec<String> = db::get_all_notes(&db)?
+                    .into_iter()
+                    .flat_map(|note| note.tags)
+                    .collect();
+                known_tags.sort();
+                known_tags.dedup();
+                let input = wizard::new_note(
+                    &db,
+                    key,
+                    title,
+                    tag,
+                    known_tags,
+                    config::templates_dir().as_deref(),
+                )?;
+                (
+                    input.key,
+                    Some(input.title),
+                    input.tags,
+                    input.template.or(template),
+                )
+            } else if auto {
+                let (content, frontmatter) =
+                    new_note_content(message.clone(), from.clone(), template.as_deref(), &config)?;
+                let derived = title
+                    .clone()
+                    .or_else(|| frontmatter.as_ref().and_then(|f| f.title.clone()))
+                    .or_else(|| note::first_heading(&content))
+                    .ok_or_else(|| {
+                        AppError::Unsupported(
+                            "--auto needs a --title, or content with a heading, to derive a key from"
+                                .to_string(),
+                        )
+                    })?;
+                input = Some((content, frontmatter));
+                (derived, title, tag, template)
+            } else {
+                // clap guarantees a key unless --interactive or --auto is set.
+                (key.unwrap_or_default(), title, tag, template)
+            };
+            // A title given on the command line wins over one in piped frontmatter.
+            let given_title = title.clone();
+
+            // Normalise the key, keeping what the user typed as the title.
+            let (key, title) = if zk || config.zk_ids {
+                // Bump the timestamp a minute at a time until the key is unique.
+                let mut timestamp = Local::now().naive_local();
+                let mut zk_key = note::zk_key(timestamp, &key);
+                while db::key_exists(&db, &zk_key)? {
+                    timestamp += chrono::Duration::minutes(1);
+                    zk_key = note::zk_key(timestamp, &key);
+                }
+                colours::info(&format!("Using key '{}'", zk_key));
+                (zk_key, title.or(Some(key)))
+            } else if slug || auto || config.slugify_keys {
+                let mut slugged = note::slugify(&key);
+                if slugged.is_empty() {
+                    return Err(AppError::Unsupported(format!(
+                        "'{}' does not produce a usable key",
+                        key
+                    )));
+                }
+                if auto {
+                    let base = slugged.clone();
+                    let mut n = 1;
+                    while db::key_exists(&db, &slugged)?
+                        || db::find_key_ignoring_case(&db, &slugged)?.is_some()
+                    {
+                        n += 1;
+                        slugged = format!("{}-{}", base, n);
+                    }
+                } else if slugged != key {
+                    colours::info(&format!("Using key '{}'", slugged));
+                }
+                (slugged, title.or(Some(key)))
+            } else {
+                (key, title)
+            };
+
             // Check for key existence here
+            db::check_key(&key)?;
             if db::key_exists(&db, &key)? {
                 return Err(AppError::KeyExists(key));
             }
+            if let Some(existing) = db::find_key_ignoring_case(&db, &key)? {
+                return Err(AppError::KeyCaseConflict(key, existing));
+            }
 
             // Determine the final content based on the input method.
-            let content = if let Some(message_content) = message {
-                message_content
-            } else if !atty::is(Stream::Stdin) {
-                let mut buffer = String::new();
-                io::stdin().read_to_string(&mut buffer)?;
-                buffer
-            } else {
-                // Open the editor.
-                let initial_content = if let Some(template_name) = template {
-                    let config_dir = dirs::config_dir().ok_or_else(|| {
-                        AppError::ConfigError("Config directory not found".into())
-                    })?;
-                    let template_path = config_dir
-                        .join("medi/templates")
-                        .join(format!("{}.md", template_name));
-
-                    // Read the template file, return empty string if it fails (e.g. not found).
-                    fs::read_to_string(template_path).unwrap_or_default()
-                } else {
-                    // No template, so start with a blank editor.
-                    String::new()
-                };
-
-                // Now, open the editor with the initial content.
-                let tempfile = TempBuilder::new()
-                    .prefix("medi-note-")
-                    .suffix(".md")
-                    .tempfile()?;
-                let temppath = tempfile.path().to_path_buf();
-                // Write the initial content (template or empty) to the temp file.
-                fs::write(&temppath, &initial_content)?;
-                // Open the pre-filled temp file in the editor.
-                edit::edit_file(&temppath)?;
-                // Read the final content back.
-                fs::read_to_string(&temppath)?
+            let (content, frontmatter) = match input {
+                Some(input) => input,
+                None => new_note_content(message, from, template.as_deref(), &config)?,
             };
 
             // Save the note if content is not empty.
             if content.trim().is_empty() {
                 colours::warn("Note creation cancelled (empty content).");
             } else {
+                let frontmatter = frontmatter.unwrap_or_default();
+                let mut tags = tag;
+                for extra in frontmatter.tags {
+                    if !tags.contains(&extra) {
+                        tags.push(extra);
+                    }
+                }
                 // Create a new Note instance with all the metadata
                 let new_note = Note {
                     key: key.clone(),
-                    // Use the title flag, or default to the key
-                    title: title.unwrap_or_else(|| key.clone()),
-                    tags: tag,
+                    // Use the title flag or frontmatter, or default to the key
+                    title: given_title
+                        .or(frontmatter.title)
+                        .or(title)
+                        .unwrap_or_else(|| key.clone()),
+                    tags,
                     content,
-                    created_at: Utc::now(),
-                    modified_at: Utc::now(),
+                    created_at: frontmatter.created_at.unwrap_or_else(Utc::now),
+                    modified_at: frontmatter.modified_at.unwrap_or_else(Utc::now),
+                    expires_at: expires.map(|duration| Utc::now() + duration),
+                    metadata: frontmatter.metadata,
+                    ..Default::default()
                 };
+
+                // Warn about near-duplicates, and offer to use the existing note in a terminal.
+                let existing_notes = db::get_all_notes(&db)?;
+                if let Some((similar, score)) = keywords::near_duplicate(&new_note, &existing_notes)
+                {
+                    colours::warn(&format!(
+                        "This looks {:.0}% similar to '{}'.",
+                        score * 100.0,
+                        similar.key
+                    ));
+                    if atty::is(Stream::Stdin) && interactive::enabled() {
+                        let choices = [
+                            "Save as a new note".to_string(),
+                            format!("Open '{}' instead", similar.key),
+                            format!("Append to '{}'", similar.key),
+                            "Cancel".to_string(),
+                        ];
+                        let choice = Select::new()
+                            .with_prompt("What would you like to do?")
+                            .items(&choices)
+                            .default(0)
+                            .interact()?;
+                        let similar_key = similar.key.clone();
+                        match choice {
+                            0 => {}
+                            1 => {
+                                let existing = db::get_note(&db, &similar_key)?;
+                                return edit_note_content(&db, existing, &search_index);
+                            }
+                            2 => {
+                                let mut existing = db::get_note(&db, &similar_key)?;
+                                existing.content = format!(
+                                    "{}\n\n{}",
+                                    existing.content.trim_end(),
+                                    new_note.content
+                                );
+                                for tag in new_note.tags {
+                                    if !existing.tags.contains(&tag) {
+                                        existing.tags.push(tag);
+                                    }
+                                }
+                                existing.modified_at = Utc::now();
+                                db::save_note_with_index(&db, &existing, &search_index)?;
+                                colours::success(&format!("Appended to note: '{}'", similar_key));
+                                return Ok(());
+                            }
+                            _ => {
+                                colours::warn("Note creation cancelled.");
+                                return Ok(());
+                            }
+                        }
+                    }
+                }
+
                 // Save the entire Note object
                 db::save_note_with_index(&db, &new_note, &search_index)?;
                 colours::success(&format!("Successfully created note: '{}'", key));
+                // Scripts capture the generated key.
+                if auto {
+                    println!("{}", key);
+                }
             }
         }
         Commands::Edit {
             key,
+            bulk,
+            tag,
+            tag_match,
+            create,
+            template,
             add_tag,
             rm_tag,
+            force,
         } => {
-            let mut existing_note = db::get_note(&db, &key)?;
-            let mut modified = false;
+            // --force lets every save below change a locked note.
+            let _override = force.then(db::override_locks);
+            if bulk {
+                let now = Utc::now();
+                let notes: Vec<Note> = db::get_all_notes(&db)?
+                    .into_iter()
+                    .filter(|note| !note.is_expired(now) && note.has_tags(&tag, tag_match))
+                    .filter(|note| !skip_locked(note, force))
+                    .collect();
+                if notes.is_empty() {
+                    colours::warn("No matching notes found.");
+                    return Ok(());
+                }
+                let total = notes.len();
+                let changed = edit_notes_in_dir(notes)?;
+                db::save_notes_with_index(&db, &changed, &search_index)?;
+                colours::success(&format!("Updated {} of {} note(s).", changed.len(), total));
+                return Ok(());
+            }
+            // clap guarantees a key unless --bulk is set.
+            let key = key.unwrap_or_default();
+            // With --create the argument is the key to create, not a title to look up.
+            let key = if create { key } else { resolve_key(&db, &key)? };
+            let mut existing_note = match db::get_note(&db, &key) {
+                Err(AppError::KeyNotFound(_)) if create || config.edit_creates => {
+                    // The config option asks first; --create is the answer already.
+                    let confirmed = create
+                        || (atty::is(Stream::Stdin)
+                            && interactive::enabled()
+                            && Confirm::new()
+                                .with_prompt(format!("'{}' doesn't exist. Create it?", key))
+                                .default(true)
+                                .interact()?);
+                    if !confirmed {
+                        return Err(AppError::KeyNotFound(key));
+                    }
+                    if let Some(existing) = db::find_key_ignoring_case(&db, &key)? {
+                        return Err(AppError::KeyCaseConflict(key, existing));
+                    }
+                    let initial_content = match &template {
+                        Some(name) => template_content(name)?,
+                        None => String::new(),
+                    };
+                    let content = compose_in_editor(&initial_content)?;
+                    if content.trim().is_empty() {
+                        colours::warn("Note creation cancelled (empty content).");
+                        return Ok(());
+                    }
+                    let new_note = Note {
+                        key: key.clone(),
+                        title: key.clone(),
+                        tags: add_tag,
+                        content,
+                        created_at: Utc::now(),
+                        modified_at: Utc::now(),
+                        ..Default::default()
+                    };
+                    db::save_note_with_index(&db, &new_note, &search_index)?;
+                    colours::success(&format!("Successfully created note: '{}'", key));
+                    return Ok(());
+                }
+                result => result?,
+            };
+            check_unlocked(&existing_note, force)?;
+            let mut modified = false;
 
             // Handle adding tags
             if !add_tag.is_empty() {
@@ -219,40 +1410,27 @@ pub fn run(cli: Cli, config: Config) -> Result<(), AppError> {
             }
 
             // If no tags were modified, proceed to edit the content.
-            let tempfile = TempBuilder::new()
-                .prefix("medi-note-")
-                .suffix(".md")
-                .tempfile()?;
-
-            let temppath = tempfile.path().to_path_buf();
-            fs::write(&temppath, &existing_note.content)?;
-            edit::edit_file(&temppath)?;
-
-            let updated_content = fs::read_to_string(&temppath)?;
-            if updated_content.trim() != existing_note.content.trim() {
-                existing_note.content = updated_content;
-                existing_note.modified_at = Utc::now();
-
-                // This will overwrite the old note.
-                db::save_note_with_index(&db, &existing_note, &search_index)?;
-                colours::success(&format!("Successfully updated note: '{}'", key));
-            } else {
-                colours::info("Note content unchanged.");
-            }
+            edit_note_content(&db, existing_note, &search_index)?;
         }
-        Commands::Get { keys, tag, json } => {
-            let notes_to_show = if !tag.is_empty() {
-                // If tags are provided, retrieve all notes with those tags
-                let all_notes = db::get_all_notes(&db)?;
-                all_notes
-                    .into_iter()
-                    .filter(|note| note.tags.iter().any(|t| tag.contains(t)))
-                    .collect::<Vec<_>>()
+        Commands::Get {
+            keys,
+            filter,
+            json,
+            meta,
+            header,
+            out,
+            render,
+            tasks: with_tasks,
+            output: format,
+        } => {
+            let notes_to_show = if filter.is_set() {
+                // With filters instead of keys, retrieve every note passing them
+                filter.apply(db::get_all_notes(&db)?)
             } else {
                 // If keys are provided, retrieve those specific notes
                 let mut notes = Vec::new();
                 for key in keys {
-                    notes.push(db::get_note(&db, &key)?);
+                    notes.push(db::get_note(&db, &resolve_key(&db, &key)?)?);
                 }
                 notes
             };
@@ -262,115 +1440,293 @@ pub fn run(cli: Cli, config: Config) -> Result<(), AppError> {
                 return Ok(());
             }
 
-            // Print the filtered notes
+            // Colour codes have no place in a file.
+            if out.is_some() {
+                colored::control::set_override(false);
+            }
+
+            let mut all_tasks = if with_tasks {
+                db::get_all_tasks(&db)?
+            } else {
+                Vec::new()
+            };
+            all_tasks.sort_by_key(|task| task.id);
+            let mut output = String::new();
             for (i, note) in notes_to_show.iter().enumerate() {
-                if i > 0 {
-                    println!("---");
-                } // Separator for multiple notes
-                if json {
-                    println!("{}", serde_json::to_string_pretty(note)?);
+                if let NoteOutput::Ndjson = format {
+                    if meta {
+                        output.push_str(&serde_json::to_string(&NoteMeta::from_note(note))?);
+                    } else {
+                        output.push_str(&serde_json::to_string(note)?);
+                    }
+                    output.push('\n');
+                    continue;
+                }
+                if header {
+                    if i > 0 {
+                        output.push('\n');
+                    }
+                    output.push_str(&format!("# {} ({})\n\n", note.key, note.title));
+                } else if i > 0 {
+                    // Separator for multiple notes
+                    output.push_str("---\n");
+                }
+                if meta {
+                    let meta = NoteMeta::from_note(note);
+                    if json {
+                        output.push_str(&serde_json::to_string_pretty(&meta)?);
+                        output.push('\n');
+                    } else {
+                        output.push_str(&format_note_meta(&meta));
+                    }
+                } else if json {
+                    output.push_str(&serde_json::to_string_pretty(note)?);
+                    output.push('\n');
                 } else {
-                    println!("{}", note.content);
+                    let mut content = note.content.clone();
+                    let section = task_section(&all_tasks, &note.key);
+                    if !section.is_empty() {
+                        content = format!("{}\n\n{}", content.trim_end(), section);
+                    }
+                    if render {
+                        output.push_str(&render::render_markdown(&content));
+                    } else {
+                        output.push_str(&content);
+                    }
+                    output.push('\n');
+                }
+            }
+
+            match out {
+                Some(path) => {
+                    fs::write(&path, output)?;
+                    colours::success(&format!(
+                        "Wrote {} note(s) to {}",
+                        notes_to_show.len(),
+                        path.display()
+                    ));
                 }
+                None => print!("{}", output),
             }
         }
-        Commands::List { sort_by } => {
-            let mut notes = db::get_all_notes(&db)?;
+        Commands::List {
+            sort_by,
+            output,
+            prefix,
+            tree,
+            filter,
+        } => {
+            let mut notes = filter.apply(db::get_all_notes(&db)?);
+            if let Some(prefix) = &prefix {
+                notes.retain(|note| note.key.starts_with(prefix.as_str()));
+            }
             if notes.is_empty() {
                 colours::warn("No notes found.");
             }
 
             // Sorting logic
             match sort_by {
-                SortBy::Key => notes.sort_by(|a, b| a.key.cmp(&b.key)),
-                SortBy::Created => notes.sort_by(|a, b| b.created_at.cmp(&a.created_at)), // Newest first
-                SortBy::Modified => notes.sort_by(|a, b| b.modified_at.cmp(&a.modified_at)), // Newest first
+                SortBy::Key => collate::sort_by_key(&mut notes),
+                SortBy::Title => collate::sort_by_title(&mut notes),
+                SortBy::Created => notes.sort_by_key(|n| std::cmp::Reverse(n.created_at)), // Newest first
+                SortBy::Modified => notes.sort_by_key(|n| std::cmp::Reverse(n.modified_at)), // Newest first
+                SortBy::Words => notes.sort_by_key(|n| std::cmp::Reverse(count_words(&n.content))), // Largest first
+                SortBy::Size => notes.sort_by_key(|n| std::cmp::Reverse(n.content.len())), // Largest first
+            }
+
+            if let ListOutput::Ndjson = output {
+                print_ndjson(&notes)?;
+                return Ok(());
             }
 
             // Print rich output
-            println!("{}:", "Notes".bold().underline());
+            eprintln!("{}:", "Notes".bold().underline());
+            if tree {
+                // A tree only makes sense in key order.
+                collate::sort_by_key(&mut notes);
+                print_key_tree(&notes);
+                return Ok(());
+            }
+            if let ListOutput::Table = output {
+                print_note_table(&notes);
+                return Ok(());
+            }
+            let mut open_tasks: HashMap<String, usize> = HashMap::new();
+            for task in db::get_all_tasks(&db)? {
+                if !matches!(task.status, TaskStatus::Done) {
+                    *open_tasks.entry(task.note_key).or_default() += 1;
+                }
+            }
             for note in notes {
                 // Format the tags into a colored string like `[#tag1 #tag2]`
                 let tags_str = format_tags(&note.tags);
+                let tasks_str = match open_tasks.get(&note.key) {
+                    Some(1) => " (1 task)".dimmed(),
+                    Some(count) => format!(" ({} tasks)", count).dimmed(),
+                    None => "".normal(),
+                };
 
                 // Print the formatted line
-                println!("- {}{}", note.key.green().bold(), tags_str);
+                println!("- {}{}{}", colours::key(&note.key), tags_str, tasks_str);
             }
         }
-        Commands::Backlinks { key } => {
-            let all_notes = db::get_all_notes(&db)?;
-
-            // The pattern we're looking for is [[key]]
-            let link_pattern = format!(r"\[\[{}\]\]", regex::escape(&key));
-            let re = Regex::new(&link_pattern)?;
+        Commands::Backlinks {
+            key,
+            outgoing,
+            json,
+        } => {
+            let links = if outgoing {
+                graph::links_in(&db::get_note(&db, &key)?)
+            } else {
+                graph::links_to(&db::get_all_notes(&db)?, &key)
+            };
+            if json {
+                println!("{}", serde_json::to_string_pretty(&links)?);
+                return Ok(());
+            }
 
-            let mut linking_notes = Vec::new();
-            for note in all_notes {
-                // Don't link a note to itself
-                if note.key == key {
-                    continue;
-                }
-                // If the note's content contains a link to our key, add it to the list.
-                if re.is_match(&note.content) {
-                    linking_notes.push(note.key);
+            // One line per linked note, however often it links.
+            let mut linked: Vec<&str> = Vec::new();
+            for link in &links {
+                let other = if outgoing { &link.target } else { &link.source };
+                if !linked.contains(&other.as_str()) {
+                    linked.push(other);
                 }
             }
-
-            if linking_notes.is_empty() {
-                colours::warn(&format!("No backlinks found for '{}'.", key));
+            let kind = if outgoing {
+                "outgoing links"
+            } else {
+                "backlinks"
+            };
+            if linked.is_empty() {
+                colours::warn(&format!("No {} found for '{}'.", kind, key));
             } else {
                 colours::info(&format!(
-                    "Found {} backlinks for '{}':",
-                    linking_notes.len(),
+                    "Found {} {} for '{}':",
+                    linked.len(),
+                    kind,
                     key.bold()
                 ));
-                for linking_key in linking_notes {
-                    println!("- {}", linking_key);
+                for linked_key in linked {
+                    if outgoing && !db::key_exists(&db, linked_key)? {
+                        println!("- {} (missing)", linked_key);
+                    } else {
+                        println!("- {}", linked_key);
+                    }
                 }
             }
         }
-        Commands::Delete { key, force } => {
-            let confirmed = if force {
-                true
+        Commands::Delete {
+            keys,
+            filter,
+            force,
+        } => {
+            let _override = force.then(db::override_locks);
+            let keys = if filter.is_set() {
+                let notes = filter.apply(db::get_all_notes(&db)?);
+                if notes.is_empty() {
+                    colours::warn("No matching notes found.");
+                    return Ok(());
+                }
+                notes.into_iter().map(|note| note.key).collect()
             } else {
-                Confirm::new()
-                    .with_prompt(format!("Are you sure you want to delete '{}'?", key))
-                    .default(false)
-                    .interact()?
+                keys
+            };
+            let mut existing: Vec<String> = Vec::new();
+            let mut first_problem = None;
+            for key in &keys {
+                if existing.contains(key) {
+                    continue;
+                }
+                let (problem, reason) = match db::get_note(&db, key) {
+                    Ok(note) if note.locked && !force => {
+                        (AppError::Locked(key.clone()), "note is locked")
+                    }
+                    Ok(_) => {
+                        existing.push(key.clone());
+                        continue;
+                    }
+                    Err(AppError::KeyNotFound(_)) => {
+                        (AppError::KeyNotFound(key.clone()), "note not found")
+                    }
+                    Err(e) => return Err(e),
+                };
+                if keys.len() > 1 {
+                    colours::warn(&format!("Skipped '{}': {}.", key, reason));
+                }
+                first_problem.get_or_insert(problem);
+            }
+            if existing.is_empty() {
+                return Err(first_problem.unwrap_or(AppError::KeyNotFound(keys[0].clone())));
+            }
+
+            let prompt = match existing.as_slice() {
+                [key] => format!("Are you sure you want to delete '{}'?", key),
+                keys => format!(
+                    "Are you sure you want to delete {} notes ({})?",
+                    keys.len(),
+                    keys.join(", ")
+                ),
             };
+            if !force {
+                interactive::require("ask before deleting")?;
+            }
+            let confirmed = force
+                || Confirm::new()
+                    .with_prompt(prompt)
+                    .default(false)
+                    .interact()?;
 
             if confirmed {
-                // First, delete all associated tasks.
-                let deleted_tasks_count = db::delete_tasks_for_note(&db, &key)?;
+                let (deleted_tasks_count, deleted_reminders_count) =
+                    delete_notes(&db, &existing, &search_index)?;
                 if deleted_tasks_count > 0 {
                     colours::info(&format!(
                         "Deleted {} associated task(s).",
                         deleted_tasks_count
                     ));
                 }
-
-                // Then, delete the note itself.
-                db::delete_note_with_index(&db, &key, &search_index)?;
-                colours::success(&format!("Successfully deleted note: '{}'", key));
+                if deleted_reminders_count > 0 {
+                    colours::info(&format!(
+                        "Deleted {} associated reminder(s).",
+                        deleted_reminders_count
+                    ));
+                }
+                for key in &existing {
+                    colours::success(&format!("Successfully deleted note: '{}'", key));
+                }
             } else {
                 colours::warn("Deletion cancelled.");
             }
         }
-        Commands::Search { query } => {
-            let found_keys = search::search_notes(&search_index, &query)?;
-
-            if found_keys.is_empty() {
-                colours::warn("No matching notes found.");
-                return Ok(());
-            }
+        Commands::Lock { key } => set_locked(&db, &key, true, &search_index)?,
+        Commands::Unlock { key } => set_locked(&db, &key, false, &search_index)?,
+        Commands::Search {
+            query,
+            output,
+            semantic,
+            pinned_first,
+        } => {
+            let found_keys = if semantic {
+                #[cfg(feature = "llm")]
+                {
+                    let llm_config = llm::settings(&config)?;
+                    let embeddings = semantic::refresh(&db, llm_config, &db::get_all_notes(&db)?)?;
+                    semantic::search(llm_config, &query, &embeddings, 10)?
+                }
+                #[cfg(not(feature = "llm"))]
+                return Err(llm_unsupported());
+            } else {
+                search::search_notes(&search_index, &query)?
+            };
 
-            println!("{}:", "Search Results".bold().underline());
+            let now = Utc::now();
+            let mut found_notes = Vec::new();
             for key in found_keys {
                 match db::get_note(&db, &key) {
-                    Ok(note) => {
-                        let tags_str = format_tags(&note.tags);
-                        println!("- {}{}", note.key.green().bold(), tags_str);
-                    }
+                    // Expired notes stay hidden until `medi maintain` purges them.
+                    Ok(note) if note.is_expired(now) => {}
+                    Ok(note) => found_notes.push(note),
                     Err(_) => {
                         colours::error(&format!(
                             "Found key '{}' in index, but failed to retrieve from database.",
@@ -379,6 +1735,11 @@ pub fn run(cli: Cli, config: Config) -> Result<(), AppError> {
                     }
                 }
             }
+
+            if pinned_first {
+                pinned_to_top(&mut found_notes);
+            }
+            print_search_results(&found_notes, output)?;
         }
         Commands::Reindex => {
             colours::info("Starting reindex of all notes...");
@@ -387,124 +1748,395 @@ pub fn run(cli: Cli, config: Config) -> Result<(), AppError> {
             let all_notes = db::get_all_notes(&db)?;
             let note_count = all_notes.len();
 
-            // Get a writer and wipe the old index.
-            let mut index_writer: tantivy::IndexWriter<tantivy::TantivyDocument> =
-                search_index.writer(100_000_000)?; // 100MB heap
-            index_writer.delete_all_documents()?;
-
-            // Add all notes to the index.
-            for note in all_notes {
-                search::add_note_to_index(&note, &mut index_writer)?;
-            }
-
-            index_writer.commit()?;
+            search::rebuild_index(&search_index, &all_notes)?;
 
             colours::success(&format!("Successfully reindexed {} notes.", note_count));
         }
-        #[cfg(unix)]
-        Commands::Find => {
-            let notes = db::get_all_notes(&db)?;
+        Commands::Find { multi } => {
+            let mut notes = db::get_all_notes(&db)?;
             if notes.is_empty() {
                 colours::warn("No notes to find.");
                 return Ok(());
             }
 
-            // Create a crossbeam channel.
-            let (tx, rx) = unbounded();
-
-            // Send each note key through the channel.
-            for note in notes {
-                let item: Arc<dyn SkimItem> = Arc::new(note.key);
-                let _ = tx.send(item);
-            }
-            drop(tx);
-
-            // Configure and run the fuzzy finder.
-            let options = SkimOptionsBuilder::default()
-                .height("30%".to_string())
-                .prompt("Select a note to edit: ".to_string())
-                .reverse(true)
-                .border(Some("─".to_string()))
-                .multi(false)
-                .build()
-                .unwrap();
-
-            // `Skim::run_with` launches the interactive fuzzy finder.
-            // We pass the receiver `rx` which `skim` will use to get the items.
-            let selected_items = Skim::run_with(&options, Some(rx))
-                .map(|out| out.selected_items)
-                .unwrap_or_default();
-
-            // Get the selected key and open it for editing.
-            if let Some(item) = selected_items.first() {
-                let selected_key = item.output().to_string();
-                let mut existing_note = db::get_note(&db, &selected_key)?;
-
-                let tempfile = TempBuilder::new()
-                    .prefix("medi-note-")
-                    .suffix(".md")
-                    .tempfile()?;
-                let temppath = tempfile.path().to_path_buf();
-                fs::write(&temppath, &existing_note.content)?;
-                edit::edit_file(&temppath)?;
-
-                let updated_content = fs::read_to_string(&temppath)?;
-                if updated_content.trim() != existing_note.content.trim() {
-                    existing_note.content = updated_content;
-                    existing_note.modified_at = Utc::now();
-                    db::save_note_with_index(&db, &existing_note, &search_index)?;
-                    colours::success(&format!("Successfully updated note: '{}'", selected_key));
-                } else {
-                    colours::info("Note content unchanged.");
-                }
+            // Before anything is typed, the finder shows pinned notes, then recent ones.
+            notes.sort_by(|a, b| {
+                b.is_pinned()
+                    .cmp(&a.is_pinned())
+                    .then(b.modified_at.cmp(&a.modified_at))
+            });
+            let keys = notes.into_iter().map(|note| note.key).collect();
+            let prompt = if multi {
+                "Select notes (TAB to mark): "
             } else {
+                "Select a note to edit: "
+            };
+            let selected_keys = finder::select_keys(keys, prompt, multi)?;
+
+            if selected_keys.is_empty() {
                 colours::info("No note selected.");
+                return Ok(());
+            }
+
+            if !multi {
+                // Open the single selected note for editing.
+                let existing_note = db::get_note(&db, &selected_keys[0])?;
+                check_unlocked(&existing_note, false)?;
+                edit_note_content(&db, existing_note, &search_index)?;
+                return Ok(());
+            }
+
+            // Ask what to do with all the selected notes.
+            let actions = ["Add tag", "Delete", "Export", "Cancel"];
+            let choice = Select::new()
+                .with_prompt(format!(
+                    "{} notes selected. What do you want to do?",
+                    selected_keys.len()
+                ))
+                .items(actions)
+                .default(0)
+                .interact()?;
+
+            match actions[choice] {
+                "Add tag" => {
+                    let tag: String = Input::new().with_prompt("Tag to add").interact_text()?;
+                    // Only notes that didn't have the tag yet count as tagged.
+                    let mut tagged = 0;
+                    for key in &selected_keys {
+                        let mut note = db::get_note(&db, key)?;
+                        if note.tags.contains(&tag) || skip_locked(&note, false) {
+                            continue;
+                        }
+                        note.tags.push(tag.clone());
+                        note.modified_at = Utc::now();
+                        db::save_note_with_index(&db, &note, &search_index)?;
+                        tagged += 1;
+                    }
+                    colours::success(&format!("Tagged {} notes with #{}", tagged, tag));
+                }
+                "Delete" => {
+                    let mut deletable = Vec::new();
+                    for key in selected_keys {
+                        if !skip_locked(&db::get_note(&db, &key)?, false) {
+                            deletable.push(key);
+                        }
+                    }
+                    if deletable.is_empty() {
+                        return Ok(());
+                    }
+                    let confirmed = Confirm::new()
+                        .with_prompt(format!(
+                            "Are you sure you want to delete {} notes?",
+                            deletable.len()
+                        ))
+                        .default(false)
+                        .interact()?;
+                    if !confirmed {
+                        colours::warn("Deletion cancelled.");
+                        return Ok(());
+                    }
+                    delete_notes(&db, &deletable, &search_index)?;
+                    for key in &deletable {
+                        colours::success(&format!("Successfully deleted note: '{}'", key));
+                    }
+                }
+                "Export" => {
+                    let mut input = Input::<String>::new().with_prompt("Export directory");
+                    if let Some(dir) = &config.default_export_dir {
+                        input = input.default(dir.display().to_string());
+                    }
+                    let path = input.interact_text()?;
+
+                    let notes = selected_keys
+                        .iter()
+                        .map(|key| db::get_note(&db, key))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    export::write_markdown_export(
+                        &notes,
+                        Path::new(&path),
+                        config.export_links,
+                        config
+                            .export_filename
+                            .as_deref()
+                            .unwrap_or(export::DEFAULT_FILENAME),
+                        None,
+                        &HashSet::new(),
+                    )?;
+                    colours::success(&format!(
+                        "Successfully exported {} notes as Markdown to '{}'",
+                        notes.len(),
+                        path
+                    ));
+                }
+                _ => colours::info("No action taken."),
             }
-        }
-        #[cfg(not(unix))]
-        Commands::Find => {
-            return Err(AppError::Unsupported(
-                "The 'find' command is not supported on this operating system.".to_string(),
-            ));
         }
         Commands::Import(args) => {
             // This is a helper closure to handle the logic for a single file.
 
-            let handle_import = |key: &str, content: &str| -> Result<(), AppError> {
+            // `file_path` is where the content came from (none for stdin), and `frontmatter`
+            // its metadata, which wins over the file's.
+            let handle_import = |key: &str,
+                                 content: &str,
+                                 file_path: Option<&Path>,
+                                 frontmatter: Frontmatter|
+             -> Result<(), AppError> {
+                // With --slug, normalise the key but keep the original as the title.
+                let slug = args.slug || config.slugify_keys;
+                let title = key;
+                let key = &if slug {
+                    note::slugify(key)
+                } else {
+                    key.to_string()
+                };
+                // Relative links to other Markdown files become wikilinks, with their
+                // keys derived the same way.
+                let content = &links::from_markdown(content, |path| {
+                    if slug {
+                        note::slugify(path)
+                    } else {
+                        path.to_string()
+                    }
+                });
+
+                // With --preserve-times, take the timestamps from the file instead of now.
+                let (created_at, modified_at) = match file_path {
+                    Some(file_path) if args.preserve_times => file_times(file_path)?,
+                    _ => (Utc::now(), Utc::now()),
+                };
+                let created_at = frontmatter.created_at.unwrap_or(created_at);
+                let modified_at = frontmatter.modified_at.unwrap_or(modified_at);
+
                 if let Ok(existing_note) = db::get_note(&db, key) {
                     if !args.overwrite {
                         colours::warn(&format!("Skipped '{}' (already exists)", key));
                         return Ok(());
                     }
+                    if skip_locked(&existing_note, false) {
+                        return Ok(());
+                    }
                     // Preserve tags and creation date, update content and modified date
                     let mut updated_note = existing_note;
                     updated_note.content = content.to_string();
-                    updated_note.modified_at = Utc::now();
+                    updated_note.modified_at = modified_at;
+                    if let Some(title) = frontmatter.title {
+                        updated_note.title = title;
+                    }
+                    for tag in frontmatter.tags {
+                        if !updated_note.tags.contains(&tag) {
+                            updated_note.tags.push(tag);
+                        }
+                    }
+                    updated_note.metadata.extend(frontmatter.metadata);
 
                     db::save_note_with_index(&db, &updated_note, &search_index)?;
                     colours::success(&format!("Updated '{}'", key));
+                    if args.extract_tasks {
+                        extract_imported_tasks(&db, &[updated_note])?;
+                    }
                 } else {
+                    if let Some(existing) = db::find_key_ignoring_case(&db, key)? {
+                        colours::warn(&format!(
+                            "Skipped '{}' (differs only in case from '{}')",
+                            key, existing
+                        ));
+                        return Ok(());
+                    }
                     // Create a new Note struct from the imported file content.
                     let new_note = Note {
                         key: key.to_string(),
-                        title: key.to_string(), // Default title to the key
-                        tags: vec![],           // Default to no tags
+                        // Default title to the original key
+                        title: frontmatter.title.unwrap_or_else(|| title.to_string()),
+                        tags: frontmatter.tags,
                         content: content.to_string(),
-                        created_at: Utc::now(),
-                        modified_at: Utc::now(),
+                        created_at,
+                        modified_at,
+                        metadata: frontmatter.metadata,
+                        ..Default::default()
                     };
 
                     // Save the complete Note object.
-                    db::save_note(&db, &new_note)?;
+                    db::save_note_with_index(&db, &new_note, &search_index)?;
                     colours::success(&format!("Imported '{}'", key));
+                    if args.extract_tasks {
+                        extract_imported_tasks(&db, &[new_note])?;
+                    }
                 }
                 Ok(())
             };
 
-            if let (Some(file_path), Some(key)) = (args.file, args.key) {
-                // Single file import
-                let content = fs::read_to_string(&file_path)?;
-                handle_import(&key, &content)?;
+            if let Some(path) = &args.ndjson {
+                // Newline-delimited JSON, one complete note object per line.
+                let reader: Box<dyn io::BufRead> = if path.as_os_str() == "-" {
+                    Box::new(io::stdin().lock())
+                } else {
+                    Box::new(io::BufReader::new(fs::File::open(path)?))
+                };
+                let mut imported = Vec::new();
+                for (line_number, line) in io::BufRead::lines(reader).enumerate() {
+                    let line = line?;
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    let mut note: Note = match serde_json::from_str(&line) {
+                        Ok(note) => note,
+                        Err(e) => {
+                            colours::error(&format!(
+                                "Skipped line {}: not a note object ({})",
+                                line_number + 1,
+                                e
+                            ));
+                            continue;
+                        }
+                    };
+                    if args.slug || config.slugify_keys {
+                        note.key = note::slugify(&note.key);
+                    }
+                    if let Ok(existing) = db::get_note(&db, &note.key) {
+                        if !args.overwrite {
+                            colours::warn(&format!("Skipped '{}' (already exists)", note.key));
+                            continue;
+                        }
+                        if skip_locked(&existing, false) {
+                            continue;
+                        }
+                    } else if let Some(existing) = db::find_key_ignoring_case(&db, &note.key)? {
+                        colours::warn(&format!(
+                            "Skipped '{}' (differs only in case from '{}')",
+                            note.key, existing
+                        ));
+                        continue;
+                    }
+                    imported.push(note);
+                }
+                db::save_notes_with_index(&db, &imported, &search_index)?;
+                colours::success(&format!("Imported {} note(s).", imported.len()));
+                if args.extract_tasks {
+                    extract_imported_tasks(&db, &imported)?;
+                }
+            } else if let Some(dir) = &args.raw_dump {
+                let manifest = dump::read_manifest(dir)?;
+                // Every file is read and checked before anything is written.
+                let mut imported = Vec::new();
+                let mut restored = Vec::new();
+                for entry in &manifest.notes {
+                    let contents = dump::read_note(dir, entry)?;
+                    if let Ok(existing) = db::get_note(&db, &entry.key) {
+                        if !args.overwrite {
+                            colours::warn(&format!("Skipped '{}' (already exists)", entry.key));
+                            continue;
+                        }
+                        if skip_locked(&existing, false) {
+                            continue;
+                        }
+                    }
+                    imported.push(contents.note);
+                    restored.push((contents.tasks, contents.reminders));
+                }
+                // The dump's tasks and reminders replace those of overwritten notes.
+                for note in &imported {
+                    db::delete_tasks_for_note(&db, &note.key)?;
+                    db::delete_reminders_for_note(&db, &note.key)?;
+                }
+                db::save_notes_with_index(&db, &imported, &search_index)?;
+                // IDs are handed out anew, as they may be taken in this database.
+                let (mut task_count, mut reminder_count) = (0, 0);
+                for (tasks, reminders) in restored {
+                    for mut task in tasks {
+                        task.id = db::get_next_task_id(&db)?;
+                        db::save_task(&db, &task)?;
+                        task_count += 1;
+                    }
+                    for mut reminder in reminders {
+                        reminder.id = db::get_next_reminder_id(&db)?;
+                        db::save_reminder(&db, &reminder)?;
+                        reminder_count += 1;
+                    }
+                }
+                colours::success(&format!(
+                    "Restored {} of {} note(s), with {} task(s) and {} reminder(s), from a dump made by medi {}.",
+                    imported.len(),
+                    manifest.notes.len(),
+                    task_count,
+                    reminder_count,
+                    manifest.medi_version
+                ));
+            } else if let Some(path) = &args.dayone {
+                let attachments = db::attachments_dir(&config, cli.db.as_deref()).join("dayone");
+                let entries = dayone::read_export(path, &attachments)?;
+                // Several entries on one day become journal/<date>, journal/<date>-2, ...
+                let (imported, skipped) = new_imports(&db, entries, dayone::UUID_FIELD)?;
+                db::save_notes_with_index(&db, &imported, &search_index)?;
+                colours::success(&format!(
+                    "Imported {} Day One entries ({} already imported).",
+                    imported.len(),
+                    skipped
+                ));
+                if args.extract_tasks {
+                    extract_imported_tasks(&db, &imported)?;
+                }
+            } else if let Some(path) = &args.joplin {
+                let attachments = db::attachments_dir(&config, cli.db.as_deref()).join("joplin");
+                let notes = joplin::read_export(path, &attachments)?;
+                let (mut imported, skipped) = new_imports(&db, notes, joplin::ID_FIELD)?;
+
+                // Links may point at notes from this export or from an earlier one.
+                let keys: HashMap<String, String> = db::get_all_notes(&db)?
+                    .iter()
+                    .chain(&imported)
+                    .filter_map(|note| {
+                        let id = note.metadata.get(joplin::ID_FIELD)?;
+                        Some((id.clone(), note.key.clone()))
+                    })
+                    .collect();
+                joplin::link_notes(&mut imported, &keys);
+                db::save_notes_with_index(&db, &imported, &search_index)?;
+                colours::success(&format!(
+                    "Imported {} Joplin notes ({} already imported).",
+                    imported.len(),
+                    skipped
+                ));
+                if args.extract_tasks {
+                    extract_imported_tasks(&db, &imported)?;
+                }
+            } else if let Some(dir) = &args.keep {
+                let attachments = db::attachments_dir(&config, cli.db.as_deref()).join("keep");
+                let notes = keep::read_takeout(dir, &attachments)?;
+                let (imported, skipped) = new_imports(&db, notes, keep::SOURCE_FIELD)?;
+                db::save_notes_with_index(&db, &imported, &search_index)?;
+
+                // Keep checklists become tasks on the imported note.
+                let mut task_count = 0;
+                for note in &imported {
+                    task_count += extract_tasks(&db, note)?;
+                }
+                colours::success(&format!(
+                    "Imported {} Keep notes with {} tasks ({} already imported).",
+                    imported.len(),
+                    task_count,
+                    skipped
+                ));
+            } else if let (Some(file_path), Some(key)) = (&args.file, &args.key) {
+                // Single file import, converting PDFs and other formats to Markdown.
+                let started = Instant::now();
+                let content = convert::to_markdown(Path::new(file_path), &config)?;
+                debug!(path = %file_path, elapsed = ?started.elapsed(), "read file");
+                handle_import(
+                    key,
+                    &content,
+                    Some(Path::new(file_path)),
+                    Frontmatter::default(),
+                )?;
+            } else if let (true, Some(key)) = (args.stdin, &args.key) {
+                // Content piped in, with its frontmatter applied as `new` does.
+                let mut buffer = String::new();
+                io::stdin().read_to_string(&mut buffer)?;
+                let (frontmatter, body) = frontmatter::split(&buffer)?;
+                if body.trim().is_empty() {
+                    colours::warn("Nothing to import (empty input).");
+                    return Ok(());
+                }
+                handle_import(key, body, None, frontmatter.unwrap_or_default())?;
             } else if let Some(dir_path_str) = args.dir {
                 // Directory import
                 let dir_path = Path::new(&dir_path_str);
@@ -515,60 +2147,176 @@ pub fn run(cli: Cli, config: Config) -> Result<(), AppError> {
                     )));
                 }
 
+                let excludes = exclude::Excludes::load(dir_path, &args.exclude)?;
+                let mut excluded = 0;
+
                 // Read the directory contents
                 for entry in fs::read_dir(dir_path)? {
                     let entry = entry?;
                     let file_path = entry.path();
 
                     // Process only if it's a file with a .md extension
-                    if file_path.is_file() && file_path.extension() == Some("md".as_ref()) {
-                        // Use the filename (without extension) as the key
-                        if let Some(key) = file_path.file_stem().and_then(|s| s.to_str()) {
-                            let content = fs::read_to_string(&file_path)?;
-                            if let Err(e) = handle_import(key, &content) {
-                                colours::error(&format!("Failed to import '{}': {}", key, e));
-                            }
-                        }
+                    if !file_path.is_file() || file_path.extension() != Some("md".as_ref()) {
+                        debug!(path = %file_path.display(), "skipping, not a Markdown file");
+                        continue;
                     }
+                    if excludes.matches(file_path.strip_prefix(dir_path).unwrap_or(&file_path)) {
+                        debug!(path = %file_path.display(), "skipping, excluded");
+                        excluded += 1;
+                        continue;
+                    }
+                    // Use the filename (without extension) as the key
+                    let Some(key) = file_path.file_stem().and_then(|s| s.to_str()) else {
+                        debug!(path = %file_path.display(), "skipping, file name isn't UTF-8");
+                        continue;
+                    };
+                    let content = fs::read_to_string(&file_path)?;
+                    if let Err(e) =
+                        handle_import(key, &content, Some(&file_path), Frontmatter::default())
+                    {
+                        colours::error(&format!("Failed to import '{}': {}", key, e));
+                    }
+                }
+                if excluded > 0 {
+                    colours::info(&format!(
+                        "Skipped {} file(s) matching the exclude patterns.",
+                        excluded
+                    ));
                 }
             }
         }
         Commands::Export(args) => {
-            let all_notes = db::get_all_notes(&db)?;
-
-            // Filter notes by tag if the --tag flag was provided
-            let notes_to_export = if !args.tag.is_empty() {
-                all_notes
-                    .into_iter()
-                    .filter(|note| args.tag.iter().all(|t| note.tags.contains(t)))
-                    .collect()
+            // Explicit keys select exactly those notes, otherwise start from all notes.
+            let all_notes = if !args.key.is_empty() {
+                args.key
+                    .iter()
+                    .map(|key| db::get_note(&db, key))
+                    .collect::<Result<Vec<_>, _>>()?
             } else {
-                all_notes // Otherwise, export all notes
+                db::get_all_notes(&db)?
             };
 
+            let mut notes_to_export = args.filter.apply(all_notes);
+
             let note_count = notes_to_export.len();
-            if note_count == 0 {
+            debug!(notes = note_count, format = ?args.format, "exporting");
+            if args.changed && !matches!(args.format, ExportFormat::Markdown) {
+                colours::warn("--changed only applies to Markdown exports; exporting everything.");
+            }
+            // An incremental export with nothing left to export still removes the old files.
+            let incremental = args.changed && matches!(args.format, ExportFormat::Markdown);
+            if note_count == 0 && !incremental {
                 colours::warn("No matching notes to export.");
                 return Ok(());
             }
 
+            // Without --sort-by, explicit keys keep their given order; everything else is by key.
+            match args.sort_by {
+                Some(SortBy::Key) => collate::sort_by_key(&mut notes_to_export),
+                Some(SortBy::Title) => collate::sort_by_title(&mut notes_to_export),
+                Some(SortBy::Created) => notes_to_export.sort_by_key(|n| n.created_at),
+                Some(SortBy::Modified) => notes_to_export.sort_by_key(|n| n.modified_at),
+                Some(SortBy::Words) => notes_to_export.sort_by_key(|n| count_words(&n.content)),
+                Some(SortBy::Size) => notes_to_export.sort_by_key(|n| n.content.len()),
+                None if args.key.is_empty() => collate::sort_by_key(&mut notes_to_export),
+                None => {}
+            }
+            // Backlinks are only added to rendered formats; JSON stays importable as-is.
+            if args.backlinks {
+                if matches!(
+                    args.format,
+                    ExportFormat::Json | ExportFormat::Anki | ExportFormat::RawDump
+                ) {
+                    colours::warn("--backlinks is ignored for JSON, Anki and raw dump exports.");
+                } else {
+                    let anchors = matches!(args.format, ExportFormat::Html);
+                    notes_to_export = export::with_backlinks(notes_to_export, anchors);
+                }
+            }
+            let mut tasks: Vec<Task> = db::get_all_tasks(&db)?
+                .into_iter()
+                .filter(|task| notes_to_export.iter().any(|n| n.key == task.note_key))
+                .collect();
+            tasks.sort_by_key(|task| task.id);
+            // JSON and raw dumps carry the tasks on their own rather than in the notes.
+            if args.tasks && !matches!(args.format, ExportFormat::Json | ExportFormat::RawDump) {
+                if matches!(args.format, ExportFormat::Anki) {
+                    colours::warn("--tasks is ignored for Anki exports.");
+                } else {
+                    for note in &mut notes_to_export {
+                        let section = task_section(&tasks, &note.key);
+                        if !section.is_empty() {
+                            note.content = format!("{}\n\n{}\n", note.content.trim_end(), section);
+                        }
+                    }
+                }
+            }
+            let title = args
+                .title
+                .clone()
+                .unwrap_or_else(|| match args.filter.tag.as_slice() {
+                    [] => "medi notes".to_string(),
+                    tags => tags.join(", "),
+                });
+
             // Use a match statement to handle the different export formats
             match args.format {
                 ExportFormat::Markdown => {
-                    let export_path = Path::new(&args.path);
-                    fs::create_dir_all(export_path)?;
-
-                    // The loop variable is now a `Note` struct
-                    for note in notes_to_export {
-                        // Use the note's key as the filename
-                        let file_path = export_path.join(format!("{}.md", note.key));
-                        // Write the note's .content, not the whole note object
-                        fs::write(file_path, &note.content)?;
+                    let links = args.links.unwrap_or(config.export_links);
+                    if matches!(links, LinkStyle::Markdown) {
+                        let unresolved = export::unresolved_links(&notes_to_export);
+                        if !unresolved.is_empty() {
+                            colours::warn(&format!(
+                                "{} link(s) couldn't be converted and stay as [[key]]:",
+                                unresolved.len()
+                            ));
+                            for (from, target) in &unresolved {
+                                let reason = if db::key_exists(&db, target)? {
+                                    "not exported"
+                                } else {
+                                    "missing"
+                                };
+                                eprintln!("  {} -> [[{}]] ({})", from, target, reason);
+                            }
+                            if args.strict {
+                                return Err(AppError::UnresolvedLinks(unresolved.len()));
+                            }
+                        }
+                    } else if args.strict {
+                        colours::warn("--strict only applies with --links markdown.");
+                    }
+                    let dir = Path::new(&args.path);
+                    let (previous, existing) = if incremental {
+                        let existing = db::get_all_keys(&db)?.into_iter().collect();
+                        (Some(db::get_export_state(&db, dir)?), existing)
+                    } else {
+                        (None, HashSet::new())
+                    };
+                    let written = export::write_markdown_export(
+                        &notes_to_export,
+                        dir,
+                        links,
+                        config
+                            .export_filename
+                            .as_deref()
+                            .unwrap_or(export::DEFAULT_FILENAME),
+                        previous.as_ref(),
+                        &existing,
+                    )?;
+                    // Every Markdown export records its files, so a later --changed export
+                    // can pick up from it.
+                    db::save_export_state(&db, dir, &written.state)?;
+                    if incremental {
+                        colours::success(&format!(
+                            "Exported {} changed of {} notes as Markdown to '{}', removing {} file(s)",
+                            written.written, note_count, args.path, written.removed
+                        ));
+                    } else {
+                        colours::success(&format!(
+                            "Successfully exported {} notes as Markdown to '{}'",
+                            note_count, args.path
+                        ));
                     }
-                    colours::success(&format!(
-                        "Successfully exported {} notes as Markdown to '{}'",
-                        note_count, args.path
-                    ));
                 }
                 ExportFormat::Json => {
                     let mut path = PathBuf::from(&args.path);
@@ -581,6 +2329,7 @@ pub fn run(cli: Cli, config: Config) -> Result<(), AppError> {
                         export_date: Utc::now(),
                         note_count,
                         notes: notes_to_export,
+                        tasks,
                     };
 
                     let json_string = serde_json::to_string_pretty(&export_data)?;
@@ -592,67 +2341,244 @@ pub fn run(cli: Cli, config: Config) -> Result<(), AppError> {
                         path.display()
                     ));
                 }
-            }
-        }
-        Commands::Task { command } => match command {
-            cli::TaskCommands::Add {
-                note_key,
-                description,
-            } => {
-                // First, make sure the note exists.
-                db::get_note(&db, &note_key)?;
+                ExportFormat::Combined | ExportFormat::Html | ExportFormat::Epub => {
+                    let (extension, label) = match args.format {
+                        ExportFormat::Combined => ("md", "a combined Markdown document"),
+                        ExportFormat::Html => ("html", "a combined HTML document"),
+                        _ => ("epub", "an EPUB"),
+                    };
+                    let mut path = PathBuf::from(&args.path);
+                    if path.extension().and_then(|s| s.to_str()) != Some(extension) {
+                        path.set_extension(extension);
+                    }
+                    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                        fs::create_dir_all(parent)?;
+                    }
 
-                let new_task = Task {
-                    id: db::get_next_task_id(&db)?,
-                    note_key,
-                    description,
-                    status: TaskStatus::Open,
-                    created_at: Utc::now(),
-                };
-                db::save_task(&db, &new_task)?;
-                colours::success(&format!("Added new task with ID: {}", new_task.id));
-            }
-            cli::TaskCommands::List => {
+                    match args.format {
+                        ExportFormat::Combined => {
+                            fs::write(&path, export::combined_markdown(&notes_to_export, &title))?
+                        }
+                        ExportFormat::Html => {
+                            fs::write(&path, export::combined_html(&notes_to_export, &title))?
+                        }
+                        _ => export::write_epub(&notes_to_export, &title, &path)?,
+                    }
+
+                    colours::success(&format!(
+                        "Successfully exported {} notes as {} to '{}'",
+                        note_count,
+                        label,
+                        path.display()
+                    ));
+                }
+                ExportFormat::Textbundle => {
+                    let attachments = db::attachments_dir(&config, cli.db.as_deref());
+                    let asset_count = export::write_textbundles(
+                        &notes_to_export,
+                        Path::new(&args.path),
+                        &attachments,
+                    )?;
+                    colours::success(&format!(
+                        "Successfully exported {} notes as TextBundles ({} assets) to '{}'",
+                        note_count, asset_count, args.path
+                    ));
+                }
+                ExportFormat::RawDump => {
+                    let reminders = db::get_all_reminders(&db)?;
+                    let written =
+                        dump::write(notes_to_export, &tasks, &reminders, Path::new(&args.path))?;
+                    colours::success(&format!(
+                        "Successfully exported {} notes as a raw dump to '{}'",
+                        written, args.path
+                    ));
+                }
+                ExportFormat::Anki => {
+                    notes_to_export
+                        .retain(|note| note.tags.iter().any(|tag| tag == flashcard::FLASHCARD_TAG));
+                    let (tsv, card_count) = flashcard::anki_tsv(&notes_to_export);
+                    if card_count == 0 {
+                        colours::warn(&format!(
+                            "No flashcards found. Tag notes '{}' and write cards as \"Q: ...\" / \"A: ...\" lines or {{{{c1::cloze}}}} deletions.",
+                            flashcard::FLASHCARD_TAG
+                        ));
+                        return Ok(());
+                    }
+                    let mut path = PathBuf::from(&args.path);
+                    if path.extension().and_then(|s| s.to_str()) != Some("tsv") {
+                        path.set_extension("tsv");
+                    }
+                    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                        fs::create_dir_all(parent)?;
+                    }
+                    fs::write(&path, tsv)?;
+                    colours::success(&format!(
+                        "Exported {} flashcards from {} notes for Anki to '{}'",
+                        card_count,
+                        notes_to_export.len(),
+                        path.display()
+                    ));
+                }
+            }
+        }
+        Commands::Task { command } => match command {
+            cli::TaskCommands::Add {
+                note_key,
+                description,
+                due,
+            } => {
+                // First, make sure the note exists.
+                db::get_note(&db, &note_key)?;
+
+                let new_task = Task {
+                    id: db::get_next_task_id(&db)?,
+                    note_key,
+                    description,
+                    status: TaskStatus::Open,
+                    created_at: Utc::now(),
+                    due_at: due,
+                    completed_at: None,
+                    resolution: None,
+                    hidden_until: None,
+                };
+                db::save_task(&db, &new_task)?;
+                match new_task.due_at {
+                    Some(due_at) => colours::success(&format!(
+                        "Added new task with ID: {} (due {})",
+                        new_task.id,
+                        timezone::format(due_at, "%Y-%m-%d %H:%M")
+                    )),
+                    None => colours::success(&format!("Added new task with ID: {}", new_task.id)),
+                }
+            }
+            cli::TaskCommands::List {
+                all,
+                group_by,
+                output,
+            } => {
                 let mut tasks = db::get_all_tasks(&db)?;
-                let open_tasks: Vec<_> = tasks.clone().clone().into_iter().collect();
+                let now = Utc::now();
+                if !all {
+                    tasks.retain(|t| !matches!(t.status, TaskStatus::Done) && !t.is_snoozed(now));
+                }
+                // JSON stays parseable when there's nothing to list.
+                if tasks.is_empty() && !matches!(output, TaskOutput::Json) {
+                    colours::info(if all { "No tasks." } else { "No open tasks." });
+                    return Ok(());
+                }
 
-                if open_tasks.is_empty() {
-                    colours::info("No open tasks.");
-                } else {
-                    // Sort tasks by status
-                    tasks.sort_by_key(|t| match t.status {
-                        TaskStatus::Prio => 0,
-                        TaskStatus::Open => 1,
-                        TaskStatus::Done => 2,
-                    });
-                    colours::info("Open tasks:");
-                    for task in open_tasks {
-                        // Format the status with colour
-                        let status_str = match task.status {
-                            TaskStatus::Open => "[Open]".cyan(),
-                            TaskStatus::Prio => "[Prio] ⭐".yellow().bold(),
-                            TaskStatus::Done => "[Done]".green(),
-                        };
-                        println!(
-                            "[{}] {}: {} (for note {})",
-                            task.id,
-                            status_str,
-                            task.description,
-                            task.note_key.cyan().bold()
-                        );
+                // Sort tasks by status
+                tasks.sort_by_key(|t| match t.status {
+                    TaskStatus::Prio => 0,
+                    TaskStatus::Open => 1,
+                    TaskStatus::Done => 2,
+                });
+                let notes: HashMap<String, Note> = match group_by {
+                    Some(_) => db::get_all_notes(&db)?
+                        .into_iter()
+                        .map(|note| (note.key.clone(), note))
+                        .collect(),
+                    None => HashMap::new(),
+                };
+                let groups = group_by.map(|group| group_tasks(&tasks, group, &notes));
+
+                match (output, &groups) {
+                    (TaskOutput::Json, None) => {
+                        println!("{}", serde_json::to_string_pretty(&tasks)?)
+                    }
+                    (TaskOutput::Json, Some(groups)) => {
+                        println!("{}", serde_json::to_string_pretty(groups)?)
+                    }
+                    (TaskOutput::Table, None) => {
+                        let rows: Vec<_> = tasks.iter().map(|task| ("", task)).collect();
+                        print_task_table(&rows, false);
+                    }
+                    (TaskOutput::Table, Some(groups)) => {
+                        let rows: Vec<_> = groups
+                            .iter()
+                            .flat_map(|(name, tasks)| {
+                                tasks.iter().map(|task| (name.as_str(), *task))
+                            })
+                            .collect();
+                        print_task_table(&rows, true);
+                    }
+                    (TaskOutput::Markdown, None) => {
+                        for task in &tasks {
+                            println!("{}", task::checklist_line(task, Some(&task.note_key)));
+                        }
+                    }
+                    (TaskOutput::Markdown, Some(groups)) => {
+                        for (i, (name, group_tasks)) in groups.iter().enumerate() {
+                            if i > 0 {
+                                println!();
+                            }
+                            println!("## {}\n", name);
+                            for task in group_tasks {
+                                let note = matches!(group_by, Some(TaskGroup::Tag))
+                                    .then_some(task.note_key.as_str());
+                                println!("{}", task::checklist_line(task, note));
+                            }
+                        }
+                    }
+                    (TaskOutput::Plain, None) => {
+                        colours::info(if all { "All tasks:" } else { "Open tasks:" });
+                        for task in &tasks {
+                            println!("{}", format_task(task, true, now));
+                        }
+                    }
+                    (TaskOutput::Plain, Some(groups)) => {
+                        for (name, group_tasks) in groups {
+                            let open = group_tasks
+                                .iter()
+                                .filter(|task| !matches!(task.status, TaskStatus::Done))
+                                .count();
+                            let title = match group_by {
+                                Some(TaskGroup::Note) => notes
+                                    .get(name)
+                                    .map(|note| format!(" ({})", note.title))
+                                    .unwrap_or_default(),
+                                _ => String::new(),
+                            };
+                            println!(
+                                "{}{} {}",
+                                colours::key(name),
+                                title.dimmed(),
+                                paint(&format!("- {} open", open), Role::Warn)
+                            );
+                            for task in group_tasks {
+                                let show_note = matches!(group_by, Some(TaskGroup::Tag));
+                                println!("  {}", format_task(task, show_note, now));
+                            }
+                        }
                     }
                 }
             }
-            cli::TaskCommands::Done { task_id } => {
+            cli::TaskCommands::Done { task_id, message } => {
                 let tasks = db::get_all_tasks(&db)?;
                 if let Some(mut task) = tasks.into_iter().find(|t| t.id == task_id) {
                     task.status = TaskStatus::Done;
+                    task.completed_at = Some(Utc::now());
+                    task.resolution = message;
                     db::save_task(&db, &task)?;
                     colours::success(&format!("Completed task: {}", task_id));
                 } else {
                     Err(AppError::TaskNotFound(task_id))?;
                 }
             }
+            cli::TaskCommands::Snooze { task_id, until } => {
+                let tasks = db::get_all_tasks(&db)?;
+                if let Some(mut task) = tasks.into_iter().find(|t| t.id == task_id) {
+                    task.hidden_until = Some(until);
+                    db::save_task(&db, &task)?;
+                    colours::success(&format!(
+                        "Snoozed task {} until {}",
+                        task_id,
+                        timezone::format(until, "%Y-%m-%d %H:%M")
+                    ));
+                } else {
+                    Err(AppError::TaskNotFound(task_id))?;
+                }
+            }
             cli::TaskCommands::Prio { task_id } => {
                 let tasks = db::get_all_tasks(&db)?;
                 if let Some(mut task) = tasks.into_iter().find(|t| t.id == task_id) {
@@ -676,6 +2602,7 @@ pub fn run(cli: Cli, config: Config) -> Result<(), AppError> {
                 let confirmed = if force {
                     true
                 } else {
+                    interactive::require("ask before resetting all tasks")?;
                     Confirm::new()
                         .with_prompt("Are you sure you want to reset all tasks?")
                         .default(false)
@@ -688,12 +2615,946 @@ pub fn run(cli: Cli, config: Config) -> Result<(), AppError> {
                     colours::warn("Task reset cancelled.");
                 }
             }
+            cli::TaskCommands::Export { json } => {
+                let mut tasks = db::get_all_tasks(&db)?;
+                tasks.sort_by_key(|task| task.id);
+                let output = serde_json::to_string_pretty(&tasks)?;
+                if json.as_os_str() == "-" {
+                    println!("{}", output);
+                } else {
+                    fs::write(&json, output + "\n")?;
+                    colours::success(&format!(
+                        "Exported {} task(s) to '{}'",
+                        tasks.len(),
+                        json.display()
+                    ));
+                }
+            }
+            cli::TaskCommands::Import { file } => {
+                let input = if file.as_os_str() == "-" {
+                    io::read_to_string(io::stdin())?
+                } else {
+                    fs::read_to_string(&file)?
+                };
+                let tasks: Vec<Task> = serde_json::from_str(&input)?;
+
+                // Importing the same backup twice shouldn't double the tasks.
+                let existing = db::get_all_tasks(&db)?;
+                let (mut imported, mut skipped, mut orphaned) = (0, 0, BTreeSet::new());
+                for mut task in tasks {
+                    if existing.iter().any(|t| {
+                        t.note_key == task.note_key
+                            && t.description == task.description
+                            && t.created_at == task.created_at
+                    }) {
+                        skipped += 1;
+                        continue;
+                    }
+                    if !db::key_exists(&db, &task.note_key)? {
+                        orphaned.insert(task.note_key.clone());
+                    }
+                    // IDs are per vault, so every imported task gets a fresh one.
+                    task.id = db::get_next_task_id(&db)?;
+                    db::save_task(&db, &task)?;
+                    imported += 1;
+                }
+                colours::success(&format!(
+                    "Imported {} task(s) ({} already present).",
+                    imported, skipped
+                ));
+                if !orphaned.is_empty() {
+                    colours::warn(&format!(
+                        "Some tasks belong to notes that aren't in this vault: {}",
+                        orphaned.into_iter().collect::<Vec<_>>().join(", ")
+                    ));
+                }
+            }
+        },
+        Commands::Remind { command } => match command {
+            cli::RemindCommands::Set { key, when, message } => {
+                // First, make sure the note exists.
+                db::get_note(&db, &key)?;
+
+                let reminder = Reminder {
+                    id: db::get_next_reminder_id(&db)?,
+                    note_key: key,
+                    message,
+                    due_at: when,
+                    created_at: Utc::now(),
+                };
+                db::save_reminder(&db, &reminder)?;
+                colours::success(&format!(
+                    "Added reminder {} for '{}' on {}",
+                    reminder.id,
+                    reminder.note_key,
+                    timezone::format(reminder.due_at, "%Y-%m-%d %H:%M")
+                ));
+            }
+            cli::RemindCommands::Due => {
+                let now = Utc::now();
+                let due: Vec<Reminder> = db::get_all_reminders(&db)?
+                    .into_iter()
+                    .filter(|reminder| reminder.is_due(now))
+                    .collect();
+                if due.is_empty() {
+                    colours::info("No reminders due.");
+                } else {
+                    colours::info("Due reminders:");
+                    for reminder in &due {
+                        print_reminder(reminder);
+                    }
+                }
+            }
+            cli::RemindCommands::List => {
+                let reminders = db::get_all_reminders(&db)?;
+                if reminders.is_empty() {
+                    colours::info("No reminders.");
+                } else {
+                    for reminder in &reminders {
+                        print_reminder(reminder);
+                    }
+                }
+            }
+            cli::RemindCommands::Delete { reminder_id } => {
+                db::delete_reminder(&db, reminder_id)?;
+                colours::success(&format!("Deleted reminder: {}", reminder_id));
+            }
+        },
+        Commands::Replace {
+            pattern,
+            replacement,
+            regex,
+            filter,
+            dry_run,
+            force,
+        } => {
+            let _override = force.then(db::override_locks);
+            // Compile the pattern once; plain text is escaped so both modes share one path.
+            let re = if regex {
+                Regex::new(&pattern)?
+            } else {
+                Regex::new(&regex::escape(&pattern))?
+            };
+
+            let mut changed_notes = Vec::new();
+            for mut note in filter.apply(db::get_all_notes(&db)?) {
+                let new_content = if regex {
+                    re.replace_all(&note.content, replacement.as_str())
+                } else {
+                    // Plain text replacements must not expand `$1`-style references.
+                    re.replace_all(&note.content, regex::NoExpand(&replacement))
+                };
+                if new_content == note.content || skip_locked(&note, force) {
+                    continue;
+                }
+                print!(
+                    "{}",
+                    diff::unified_diff(
+                        &note.content,
+                        &new_content,
+                        &format!("{} (current)", note.key),
+                        &format!("{} (replaced)", note.key)
+                    )
+                );
+                note.content = new_content.into_owned();
+                note.modified_at = Utc::now();
+                changed_notes.push(note);
+            }
+
+            if changed_notes.is_empty() {
+                colours::warn("No notes matched.");
+                return Ok(());
+            }
+            if dry_run {
+                colours::info(&format!(
+                    "Dry run: {} notes would be changed.",
+                    changed_notes.len()
+                ));
+                return Ok(());
+            }
+
+            let confirmed = if force {
+                true
+            } else {
+                interactive::require("ask before applying the changes")?;
+                Confirm::new()
+                    .with_prompt(format!(
+                        "Apply these changes to {} notes?",
+                        changed_notes.len()
+                    ))
+                    .default(false)
+                    .interact()?
+            };
+            if confirmed {
+                db::save_notes_with_index(&db, &changed_notes, &search_index)?;
+                colours::success(&format!("Updated {} notes.", changed_notes.len()));
+            } else {
+                colours::warn("Replace cancelled.");
+            }
+        }
+        Commands::Query { query, output } => {
+            let query = query::Query::parse(&query)?;
+
+            // Resolve the full-text terms against the index once, up front.
+            let mut text_hits = HashMap::new();
+            for term in query.text_terms() {
+                let keys: HashSet<String> = search::search_all_notes(&search_index, term)?
+                    .into_iter()
+                    .collect();
+                text_hits.insert(term.to_string(), keys);
+            }
+
+            let now = Utc::now();
+            let mut notes: Vec<Note> = db::get_all_notes(&db)?
+                .into_iter()
+                .filter(|note| !note.is_expired(now) && query.matches(note, &text_hits))
+                .collect();
+            collate::sort_by_key(&mut notes);
+
+            match output {
+                QueryOutput::Json => println!("{}", serde_json::to_string_pretty(&notes)?),
+                QueryOutput::Keys => {
+                    for note in &notes {
+                        println!("{}", note.key);
+                    }
+                }
+                QueryOutput::Table => {
+                    if notes.is_empty() {
+                        colours::warn("No matching notes found.");
+                        return Ok(());
+                    }
+                    let key_width = notes.iter().map(|n| n.key.len()).max().unwrap_or(0).max(3);
+                    let title_width = notes
+                        .iter()
+                        .map(|n| n.title.len())
+                        .max()
+                        .unwrap_or(0)
+                        .max(5);
+                    println!(
+                        "{}",
+                        format!(
+                            "{:<key_width$}  {:<title_width$}  {:<10}  TAGS",
+                            "KEY", "TITLE", "MODIFIED"
+                        )
+                        .bold()
+                    );
+                    for note in &notes {
+                        println!(
+                            "{}  {:<title_width$}  {}  {}",
+                            colours::key(&format!("{:<key_width$}", note.key)),
+                            note.title,
+                            timezone::format(note.modified_at, "%Y-%m-%d"),
+                            note.tags.join(", ")
+                        );
+                    }
+                }
+            }
+        }
+        Commands::Bookmark { command } => match command {
+            BookmarkCommands::Add {
+                url,
+                key,
+                title,
+                description,
+                tag,
+            } => {
+                let title = match title {
+                    Some(title) => title,
+                    None => bookmark::fetch_title(&url).unwrap_or_else(|| {
+                        colours::warn("Could not fetch a page title; using the URL instead.");
+                        url.clone()
+                    }),
+                };
+                let key = match key {
+                    Some(key) => key,
+                    None => {
+                        let slug = note::slugify(&title);
+                        if slug.is_empty() {
+                            return Err(AppError::Unsupported(format!(
+                                "'{}' does not produce a usable key; pass --key",
+                                title
+                            )));
+                        }
+                        format!("{}{}", bookmark::KEY_PREFIX, slug)
+                    }
+                };
+                db::check_key(&key)?;
+                if db::key_exists(&db, &key)? {
+                    return Err(AppError::KeyExists(key));
+                }
+                if let Some(existing) = db::find_key_ignoring_case(&db, &key)? {
+                    return Err(AppError::KeyCaseConflict(key, existing));
+                }
+
+                let new_note = Note {
+                    key: key.clone(),
+                    content: bookmark::note_content(&title, &url, description.as_deref()),
+                    title,
+                    tags: tag,
+                    created_at: Utc::now(),
+                    modified_at: Utc::now(),
+                    kind: NoteKind::Bookmark,
+                    url: Some(url),
+                    ..Default::default()
+                };
+                db::save_note_with_index(&db, &new_note, &search_index)?;
+                colours::success(&format!("Bookmarked '{}' as '{}'", new_note.title, key));
+            }
+            BookmarkCommands::List => {
+                let mut bookmarks: Vec<Note> = db::get_all_notes(&db)?
+                    .into_iter()
+                    .filter(|note| note.kind == NoteKind::Bookmark)
+                    .collect();
+                if bookmarks.is_empty() {
+                    colours::warn("No bookmarks found.");
+                    return Ok(());
+                }
+                collate::sort_by_key(&mut bookmarks);
+
+                eprintln!("{}:", "Bookmarks".bold().underline());
+                for note in bookmarks {
+                    println!(
+                        "- {}{}\n  {} {}",
+                        colours::key(&note.key),
+                        format_tags(&note.tags),
+                        note.title,
+                        note.url.unwrap_or_default().dimmed()
+                    );
+                }
+            }
+            BookmarkCommands::Open { key } => {
+                let note = db::get_note(&db, &key)?;
+                match (note.kind, note.url) {
+                    (NoteKind::Bookmark, Some(url)) => {
+                        webbrowser::open(&url)?;
+                        colours::info(&format!("Opened {}", url));
+                    }
+                    _ => return Err(AppError::NotABookmark(key)),
+                }
+            }
+        },
+        Commands::Graph { start, interactive } => {
+            let notes = db::get_all_notes(&db)?;
+            let graph = graph::LinkGraph::from_notes(&notes);
+            if !interactive {
+                print!("{}", graph.to_dot());
+                return Ok(());
+            }
+
+            let start = match start {
+                Some(key) => key,
+                None => {
+                    let keys = notes.iter().map(|note| note.key.clone()).collect();
+                    match finder::select_keys(keys, "Start from: ", false)?.pop() {
+                        Some(key) => key,
+                        None => return Ok(()),
+                    }
+                }
+            };
+            if !graph.contains(&start) {
+                return Err(AppError::KeyNotFound(start));
+            }
+
+            let mut history: Vec<String> = Vec::new();
+            let mut current = start;
+            loop {
+                match graph.prompt_step(&current, !history.is_empty())? {
+                    graph::Step::Visit(key) if graph.contains(&key) => {
+                        history.push(std::mem::replace(&mut current, key));
+                    }
+                    graph::Step::Visit(key) => {
+                        colours::warn(&format!("'{}' doesn't exist yet.", key));
+                    }
+                    graph::Step::Edit => {
+                        let note = db::get_note(&db, &current)?;
+                        edit_note_content(&db, note, &search_index)?;
+                    }
+                    graph::Step::Back => {
+                        if let Some(previous) = history.pop() {
+                            current = previous;
+                        }
+                    }
+                    graph::Step::Quit => break,
+                }
+            }
+        }
+        Commands::Serve { addr, .. } => {
+            #[cfg(feature = "grpc")]
+            {
+                colours::info(&format!("Serving gRPC on {} (Ctrl+C to stop)", addr));
+                grpc::serve(db.clone(), search_index.clone(), addr)?;
+            }
+            #[cfg(not(feature = "grpc"))]
+            {
+                let _ = addr;
+                return Err(AppError::Unsupported(
+                    "medi was built without gRPC support; reinstall it with `--features grpc`"
+                        .to_string(),
+                ));
+            }
+        }
+        Commands::Capture { text } => {
+            let text = match text {
+                Some(text) => text,
+                None if !atty::is(Stream::Stdin) => {
+                    let mut buffer = String::new();
+                    io::stdin().read_to_string(&mut buffer)?;
+                    buffer
+                }
+                None => {
+                    return Err(AppError::Unsupported(
+                        "nothing to capture; pass the text or pipe it in".to_string(),
+                    ))
+                }
+            };
+            if text.trim().is_empty() {
+                colours::warn("Nothing captured (empty text).");
+                return Ok(());
+            }
+
+            let key = config
+                .inbox_key
+                .clone()
+                .unwrap_or_else(|| note::DEFAULT_INBOX_KEY.to_string());
+            let mut inbox = match db::get_note(&db, &key) {
+                Ok(inbox) => inbox,
+                Err(AppError::KeyNotFound(_)) => Note {
+                    key: key.clone(),
+                    title: "Inbox".to_string(),
+                    content: "# Inbox\n\n".to_string(),
+                    tags: vec!["inbox".to_string()],
+                    created_at: Utc::now(),
+                    modified_at: Utc::now(),
+                    ..Default::default()
+                },
+                Err(e) => return Err(e),
+            };
+
+            // One list item per capture; continuation lines are indented under it.
+            let stamp = timezone::format(Utc::now(), "%Y-%m-%d %H:%M");
+            let entry = format!("- **{}** {}", stamp, text.trim().replace('\n', "\n  "));
+            inbox.append(&entry);
+            db::save_note_with_index(&db, &inbox, &search_index)?;
+            colours::success(&format!("Captured to '{}'", key));
+        }
+        Commands::Touch { key, message } => {
+            let mut note = db::get_note(&db, &key)?;
+            match message {
+                Some(message) => {
+                    check_unlocked(&note, false)?;
+                    // Same format as `capture` entries.
+                    let stamp = timezone::format(Utc::now(), "%Y-%m-%d %H:%M");
+                    note.append(&format!("- **{}** {}", stamp, message.trim()));
+                }
+                None => note.modified_at = Utc::now(),
+            }
+            db::save_note_with_index(&db, &note, &search_index)?;
+            colours::success(&format!("Touched '{}'", key));
+        }
+        Commands::Review { filter, limit, due } => {
+            let now = Utc::now();
+            let notes = filter.apply(db::get_all_notes(&db)?);
+            let states = db::get_review_states(&db)?;
+
+            // Overdue items first, most overdue first; new items after them.
+            let mut queue: Vec<review::Item> = review::items(&notes)
+                .into_iter()
+                .filter(|item| states.get(&item.id).is_none_or(|s| s.due_at <= now))
+                .collect();
+            queue.sort_by_key(|item| {
+                let due_at = states.get(&item.id).map(|s| s.due_at);
+                (due_at.is_none(), due_at)
+            });
+            queue.truncate(limit as usize);
+            if queue.is_empty() {
+                colours::info("Nothing to review.");
+                return Ok(());
+            }
+
+            if due {
+                colours::info(&format!("{} item(s) due for review:", queue.len()));
+                for item in &queue {
+                    let label = if states.contains_key(&item.id) {
+                        "due"
+                    } else {
+                        "new"
+                    };
+                    println!(
+                        "- {} {} {}",
+                        format!("[{}]", label).dimmed(),
+                        colours::key(&item.note_key),
+                        item.prompt.lines().next().unwrap_or_default()
+                    );
+                }
+                return Ok(());
+            }
+
+            let total = queue.len();
+            let mut reviewed = 0;
+            for (i, item) in queue.iter().enumerate() {
+                let Some(grade) = review::prompt_grade(item, i + 1, total)? else {
+                    break;
+                };
+                let state = review::schedule(states.get(&item.id), item, grade, Utc::now());
+                db::save_review_state(&db, &state)?;
+                println!(
+                    "{}",
+                    format!("Next review in {} day(s).", state.interval_days).dimmed()
+                );
+                reviewed += 1;
+            }
+            colours::success(&format!("Reviewed {} of {} item(s).", reviewed, total));
+        }
+        Commands::Triage => {
+            let inbox_key = config
+                .inbox_key
+                .clone()
+                .unwrap_or_else(|| note::DEFAULT_INBOX_KEY.to_string());
+            let now = Utc::now();
+            let all_notes = db::get_all_notes(&db)?;
+            let queue: Vec<String> = all_notes
+                .iter()
+                .filter(|note| !note.is_expired(now) && triage::needs_triage(note, &inbox_key))
+                .filter(|note| !skip_locked(note, false))
+                .map(|note| note.key.clone())
+                .collect();
+            if queue.is_empty() {
+                colours::info("Nothing to triage.");
+                return Ok(());
+            }
+            let mut all_keys: Vec<String> = all_notes.into_iter().map(|note| note.key).collect();
+
+            let total = queue.len();
+            let mut filed = 0;
+            'notes: for (i, key) in queue.iter().enumerate() {
+                // Retitling keeps the note on screen; every other action moves on.
+                loop {
+                    let mut note = db::get_note(&db, key)?;
+                    let others: Vec<String> =
+                        all_keys.iter().filter(|k| *k != key).cloned().collect();
+                    match triage::prompt_action(&note, i + 1, total, &others)? {
+                        triage::Action::Tag(tags) => {
+                            note.tags.retain(|tag| tag != triage::INBOX_TAG);
+                            for tag in tags {
+                                if !note.tags.contains(&tag) {
+                                    note.tags.push(tag);
+                                }
+                            }
+                            note.modified_at = Utc::now();
+                            db::save_note_with_index(&db, &note, &search_index)?;
+                            colours::success(&format!(
+                                "Tagged '{}': {}",
+                                key,
+                                note.tags.join(", ")
+                            ));
+                            filed += 1;
+                            break;
+                        }
+                        triage::Action::Retitle(title) => {
+                            note.title = title.trim().to_string();
+                            note.modified_at = Utc::now();
+                            db::save_note_with_index(&db, &note, &search_index)?;
+                            colours::success(&format!("Retitled '{}'", key));
+                        }
+                        triage::Action::MergeInto(target_key) => {
+                            let mut target = db::get_note(&db, &target_key)?;
+                            if skip_locked(&target, false) {
+                                continue;
+                            }
+                            target.append(&format!("\n{}", note.content.trim_end()));
+                            for tag in note.tags.iter().filter(|t| *t != triage::INBOX_TAG) {
+                                if !target.tags.contains(tag) {
+                                    target.tags.push(tag.clone());
+                                }
+                            }
+                            db::save_note_with_index(&db, &target, &search_index)?;
+
+                            // Tasks and reminders follow the content to its new home.
+                            for mut task in db::get_all_tasks(&db)? {
+                                if &task.note_key == key {
+                                    task.note_key = target_key.clone();
+                                    db::save_task(&db, &task)?;
+                                }
+                            }
+                            for mut reminder in db::get_all_reminders(&db)? {
+                                if &reminder.note_key == key {
+                                    reminder.note_key = target_key.clone();
+                                    db::save_reminder(&db, &reminder)?;
+                                }
+                            }
+                            db::delete_note_with_index(&db, key, &search_index)?;
+                            all_keys.retain(|k| k != key);
+                            colours::success(&format!("Merged '{}' into '{}'", key, target_key));
+                            filed += 1;
+                            break;
+                        }
+                        triage::Action::Archive => {
+                            note.tags.retain(|tag| tag != triage::INBOX_TAG);
+                            note.tags.push(note::ARCHIVED_TAG.to_string());
+                            db::save_note_with_index(&db, &note, &search_index)?;
+                            colours::success(&format!("Archived '{}'", key));
+                            filed += 1;
+                            break;
+                        }
+                        triage::Action::Delete => {
+                            let confirmed = Confirm::new()
+                                .with_prompt(format!("Are you sure you want to delete '{}'?", key))
+                                .default(false)
+                                .interact()?;
+                            if confirmed {
+                                db::delete_tasks_for_note(&db, key)?;
+                                db::delete_reminders_for_note(&db, key)?;
+                                db::delete_note_with_index(&db, key, &search_index)?;
+                                all_keys.retain(|k| k != key);
+                                colours::success(&format!("Deleted '{}'", key));
+                                filed += 1;
+                                break;
+                            }
+                        }
+                        triage::Action::Skip => break,
+                        triage::Action::Quit => break 'notes,
+                    }
+                }
+            }
+            colours::info(&format!("Filed {} of {} note(s).", filed, total));
+        }
+        Commands::Link { from, to, section } => {
+            let mut note = db::get_note(&db, &from)?;
+            if note::extract_links(&note.content).contains(&to) {
+                colours::warn(&format!("'{}' already links to '{}'.", from, to));
+                return Ok(());
+            }
+            if !db::key_exists(&db, &to)? {
+                colours::warn(&format!("'{}' doesn't exist yet; linking anyway.", to));
+            }
+            note.content = note::insert_link(&note.content, &to, section.as_deref());
+            note.modified_at = Utc::now();
+            db::save_note_with_index(&db, &note, &search_index)?;
+            colours::success(&format!("Linked '{}' to '{}'", from, to));
+        }
+        Commands::SnapshotLinks { key } => {
+            let mut note = db::get_note(&db, &key)?;
+            check_unlocked(&note, false)?;
+            let urls = snapshot::external_urls(&note.content);
+            if urls.is_empty() {
+                colours::warn(&format!("'{}' has no external links.", key));
+                return Ok(());
+            }
+            let dir = db::attachments_dir(&config, cli.db.as_deref())
+                .join("snapshots")
+                .join(&key);
+            let now = Utc::now();
+            let mut results = Vec::new();
+            for url in urls {
+                let outcome = snapshot::snapshot(&url, &dir, now);
+                match &outcome {
+                    snapshot::Outcome::Saved(_) => colours::info(&format!("Saved {}", url)),
+                    snapshot::Outcome::Dead(reason) => {
+                        colours::warn(&format!("Dead link {} ({})", url, reason))
+                    }
+                }
+                results.push((url, outcome));
+            }
+            let dead = results
+                .iter()
+                .filter(|(_, outcome)| matches!(outcome, snapshot::Outcome::Dead(_)))
+                .count();
+            note.content = snapshot::refresh(&note.content, &snapshot::section(&results, now));
+            note.modified_at = now;
+            db::save_note_with_index(&db, &note, &search_index)?;
+            colours::success(&format!(
+                "Snapshotted {} links in '{}' ({} dead).",
+                results.len() - dead,
+                key,
+                dead
+            ));
+        }
+        Commands::Moc { target, key } => {
+            let key = key
+                .unwrap_or_else(|| format!("moc/{}", note::slugify(target.trim_end_matches('/'))));
+            let now = Utc::now();
+            let notes: Vec<Note> = db::get_all_notes(&db)?
+                .into_iter()
+                .filter(|note| note.key != key && !note.is_expired(now))
+                .collect();
+            let selected = moc::select(&notes, &target);
+            if selected.is_empty() {
+                colours::warn(&format!("No notes found for '{}'.", target));
+                return Ok(());
+            }
+            let listing = moc::listing(&selected, &target);
+
+            match db::get_note(&db, &key) {
+                Ok(mut existing) => {
+                    existing.content = moc::refresh(&existing.content, &listing);
+                    existing.modified_at = now;
+                    db::save_note_with_index(&db, &existing, &search_index)?;
+                    colours::success(&format!(
+                        "Refreshed map of content '{}' ({} notes).",
+                        key,
+                        selected.len()
+                    ));
+                }
+                Err(AppError::KeyNotFound(_)) => {
+                    let title = format!("Map of content: {}", target);
+                    let new_note = Note {
+                        key: key.clone(),
+                        content: format!("# {}\n\n{}", title, listing),
+                        title,
+                        tags: vec!["moc".to_string()],
+                        created_at: now,
+                        modified_at: now,
+                        ..Default::default()
+                    };
+                    db::save_note_with_index(&db, &new_note, &search_index)?;
+                    colours::success(&format!(
+                        "Created map of content '{}' ({} notes).",
+                        key,
+                        selected.len()
+                    ));
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Commands::Journal { date, message } => {
+            let date = date.unwrap_or_else(timezone::today);
+            let key = note::journal_key(date);
+            db::check_unlocked(&db, &key)?;
+            // Without a message the entry opens in the editor, so refuse before creating it.
+            if message.is_none() {
+                interactive::require("open the editor")?;
+            }
+            let mut entry = match db::get_note(&db, &key) {
+                Ok(existing) => existing,
+                Err(AppError::KeyNotFound(_)) => {
+                    let title = date.format("%A, %-d %B %Y").to_string();
+                    let new_entry = Note {
+                        key: key.clone(),
+                        content: format!("# {}\n\n", title),
+                        title,
+                        tags: vec!["journal".to_string()],
+                        created_at: Utc::now(),
+                        modified_at: Utc::now(),
+                        ..Default::default()
+                    };
+                    db::save_note_with_index(&db, &new_entry, &search_index)?;
+                    colours::success(&format!("Created journal entry: '{}'", key));
+                    new_entry
+                }
+                Err(e) => return Err(e),
+            };
+            match message {
+                Some(line) => {
+                    entry.append(&line);
+                    db::save_note_with_index(&db, &entry, &search_index)?;
+                    colours::success(&format!("Updated journal entry: '{}'", key));
+                }
+                None => edit_note_content(&db, entry, &search_index)?,
+            }
+        }
+        Commands::Agenda { days } => {
+            let (overdue, agenda) = agenda::build(
+                &db::get_all_notes(&db)?,
+                &db::get_all_reminders(&db)?,
+                &db::get_all_tasks(&db)?,
+                timezone::today(),
+                days,
+            );
+            if overdue.is_empty() && agenda.is_empty() {
+                colours::info(&format!(
+                    "Nothing on the agenda for the next {} day(s).",
+                    days
+                ));
+                return Ok(());
+            }
+            if !overdue.is_empty() {
+                println!("{}", paint("Overdue", Role::Error));
+                for reminder in &overdue {
+                    print_reminder(reminder);
+                }
+                println!();
+            }
+            for day in &agenda {
+                println!("{}", day.date.format("%a %Y-%m-%d").to_string().bold());
+                if let Some(key) = &day.journal {
+                    println!("  {:<8} {}", "journal", colours::key(key));
+                }
+                for reminder in &day.reminders {
+                    println!(
+                        "  {:<8} [{}] {}{}",
+                        timezone::format(reminder.due_at, "%H:%M"),
+                        reminder.id,
+                        colours::key(&reminder.note_key),
+                        reminder
+                            .message
+                            .as_ref()
+                            .map(|message| format!(": {}", message))
+                            .unwrap_or_default()
+                    );
+                }
+                for task in &day.tasks {
+                    println!("  {:<8} [{}] {}", "task", task.id, task.description);
+                }
+                println!();
+            }
+        }
+        Commands::Calendar {
+            month,
+            day,
+            interactive,
+        } => {
+            let first = month.unwrap_or_else(|| timezone::today().with_day(1).unwrap());
+            let notes = db::get_all_notes(&db)?;
+            let days = calendar::notes_by_day(&notes, first);
+
+            if let Some(day) = day {
+                let Some(date) = first.with_day(day) else {
+                    colours::warn(&format!("{} has no day {}.", first.format("%B %Y"), day));
+                    return Ok(());
+                };
+                let Some(day_notes) = days.get(&date) else {
+                    colours::warn(&format!("No notes on {}.", date));
+                    return Ok(());
+                };
+                println!(
+                    "{}:",
+                    date.format("%A %Y-%m-%d").to_string().bold().underline()
+                );
+                for (keys, label) in [
+                    (&day_notes.journal, "journal"),
+                    (&day_notes.created, "created"),
+                    (&day_notes.modified, "modified"),
+                ] {
+                    for key in keys {
+                        println!(
+                            "- {} {}",
+                            colours::key(key),
+                            format!("({})", label).dimmed()
+                        );
+                    }
+                }
+                return Ok(());
+            }
+
+            if interactive {
+                if days.is_empty() {
+                    colours::warn(&format!("No notes in {}.", first.format("%B %Y")));
+                    return Ok(());
+                }
+                interactive::require("ask which day to open")?;
+                let dates: Vec<_> = days.keys().collect();
+                let labels: Vec<String> = days
+                    .iter()
+                    .map(|(date, day)| {
+                        format!("{} ({} notes)", date.format("%a %d"), day.keys().len())
+                    })
+                    .collect();
+                let choice = Select::new()
+                    .with_prompt(format!("Days in {}", first.format("%B %Y")))
+                    .items(&labels)
+                    .default(0)
+                    .interact()?;
+
+                let keys = days[dates[choice]].keys();
+                let choice = Select::new()
+                    .with_prompt("Open note")
+                    .items(&keys)
+                    .default(0)
+                    .interact()?;
+                let note = db::get_note(&db, &keys[choice])?;
+                edit_note_content(&db, note, &search_index)?;
+                return Ok(());
+            }
+
+            print!("{}", calendar::render_month(first, &days));
+            println!(
+                "{}",
+                "* journal entry   + notes created or modified".dimmed()
+            );
+        }
+        Commands::Diff { key, other, file } => {
+            let note = db::get_note(&db, &key)?;
+            let (other_name, other_content) = match (other, file) {
+                (Some(other_key), _) => {
+                    let other_note = db::get_note(&db, &other_key)?;
+                    (other_key, other_note.content)
+                }
+                (None, Some(path)) => (path.display().to_string(), fs::read_to_string(&path)?),
+                (None, None) => unreachable!("clap requires either a second key or --file"),
+            };
+
+            let diff = diff::unified_diff(&note.content, &other_content, &key, &other_name);
+            if diff.is_empty() {
+                colours::info(&format!("'{}' and '{}' are identical.", key, other_name));
+            } else {
+                print!("{}", diff);
+            }
+        }
+        Commands::Tag { command } => match command {
+            cli::TagCommands::Apply {
+                name: tag,
+                matching,
+                keys_from_stdin,
+                filter,
+                remove,
+                force,
+            } => {
+                let _override = force.then(db::override_locks);
+                let keys = if let Some(query) = matching {
+                    search::search_all_notes(&search_index, &query)?
+                } else if keys_from_stdin {
+                    piped_keys.unwrap_or_default()
+                } else {
+                    db::get_all_keys(&db)?
+                };
+
+                let now = Utc::now();
+                let mut changed_notes = Vec::new();
+                for key in keys {
+                    let mut note = match db::get_note(&db, &key) {
+                        Ok(note) => note,
+                        Err(_) => {
+                            colours::warn(&format!("Skipped '{}' (not found)", key));
+                            continue;
+                        }
+                    };
+                    if !filter.matches(&note, now) {
+                        continue;
+                    }
+                    // Only notes the tag change applies to; the rest are left as they are.
+                    if note.tags.contains(&tag) != remove || skip_locked(&note, force) {
+                        continue;
+                    }
+                    if remove {
+                        note.tags.retain(|t| t != &tag);
+                    } else {
+                        note.tags.push(tag.clone());
+                    }
+                    note.modified_at = Utc::now();
+                    changed_notes.push(note);
+                }
+
+                db::save_notes_with_index(&db, &changed_notes, &search_index)?;
+                if remove {
+                    colours::success(&format!(
+                        "Removed #{} from {} notes",
+                        tag,
+                        changed_notes.len()
+                    ));
+                } else {
+                    colours::success(&format!(
+                        "Tagged {} notes with #{}",
+                        changed_notes.len(),
+                        tag
+                    ));
+                }
+            }
         },
-        Commands::Status { key } => {
+        Commands::Status { key, .. } => {
             if let Some(note_key) = key {
                 // --- DETAILED NOTE STATS ---
                 let note = db::get_note(&db, &note_key)?;
-                let word_count = count_words(&note.content).into();
+                let word_count = count_words(&note.content);
                 let reading_time = calculate_reading_time(word_count);
                 let tags_str = if note.tags.is_empty() {
                     "None".to_string()
@@ -702,15 +3563,21 @@ pub fn run(cli: Cli, config: Config) -> Result<(), AppError> {
                 };
 
                 println!("{}", note.title.bold().underline());
-                println!("  Key: {}", note.key.cyan());
-                println!("  Tags: {}", tags_str.cyan());
-                println!("  Words: {}", word_count.to_string().cyan());
+                println!("  Key: {}", colours::key(&note.key));
+                println!("  Tags: {}", colours::tag(&tags_str));
+                println!("  Words: {}", paint(&word_count.to_string(), Role::Info));
                 println!(
                     "  Reading Time: ~{} minute(s)",
-                    reading_time.to_string().cyan()
+                    paint(&reading_time.to_string(), Role::Info)
+                );
+                println!(
+                    "  Created: {}",
+                    timezone::format(note.created_at, "%Y-%m-%d %H:%M %Z")
+                );
+                println!(
+                    "  Modified: {}",
+                    timezone::format(note.modified_at, "%Y-%m-%d %H:%M %Z")
                 );
-                println!("  Created: {}", note.created_at.to_rfc2822());
-                println!("  Modified: {}", note.modified_at.to_rfc2822());
             } else {
                 // --- GLOBAL DATABASE OVERVIEW ---
                 let notes = db::get_all_notes(&db)?;
@@ -725,14 +3592,451 @@ pub fn run(cli: Cli, config: Config) -> Result<(), AppError> {
                     .count();
 
                 println!("{}", "medi status".bold().underline());
-                println!("  Notes: {}", notes.len().to_string().cyan());
+                println!("  Notes: {}", paint(&notes.len().to_string(), Role::Info));
+                println!(
+                    "  Tasks: {} open ({} priority), {} done",
+                    paint(&open_tasks.len().to_string(), Role::Info),
+                    paint(&prio_tasks_count.to_string(), Role::Warn),
+                    paint(&(tasks.len() - open_tasks.len()).to_string(), Role::Success)
+                );
+
+                let mut resolved: Vec<&Task> =
+                    tasks.iter().filter(|t| t.resolution.is_some()).collect();
+                resolved.sort_by_key(|t| std::cmp::Reverse(t.completed_at));
+                if !resolved.is_empty() {
+                    println!("  Recently resolved:");
+                    for task in resolved.iter().take(5) {
+                        println!("    {}", format_task(task, true, Utc::now()));
+                    }
+                }
+            }
+        }
+        Commands::Keywords { key, top, .. } => {
+            let notes = db::get_all_notes(&db)?;
+            let targets: Vec<&Note> = match &key {
+                Some(key) => vec![notes
+                    .iter()
+                    .find(|note| &note.key == key)
+                    .ok_or_else(|| AppError::KeyNotFound(key.clone()))?],
+                None => notes.iter().collect(),
+            };
+
+            let ranked = keywords::rank(&targets, &notes, top);
+            if ranked.is_empty() {
+                colours::warn("No keywords found.");
+                return Ok(());
+            }
+            let heading = match &key {
+                Some(key) => format!("Keywords for '{}'", key),
+                None => "Keywords across all notes".to_string(),
+            };
+            eprintln!("{}:", heading.bold().underline());
+            let width = ranked.iter().map(|(term, _)| term.len()).max().unwrap_or(0);
+            for (i, (term, score)) in ranked.iter().enumerate() {
                 println!(
-                    "  Tasks: {} open ({} priority)",
-                    open_tasks.len().to_string().cyan(),
-                    prio_tasks_count.to_string().yellow()
+                    "{:>3}. {}  {}",
+                    i + 1,
+                    colours::key(&format!("{:<width$}", term)),
+                    format!("{:.3}", score).dimmed()
                 );
             }
         }
+        Commands::Summary {
+            key,
+            sentences,
+            insert,
+            llm,
+        } => {
+            let notes = db::get_all_notes(&db)?;
+            let target = notes
+                .iter()
+                .find(|note| note.key == key)
+                .ok_or_else(|| AppError::KeyNotFound(key.clone()))?;
+
+            let summary = if llm {
+                #[cfg(feature = "llm")]
+                {
+                    llm::summarise(llm::settings(&config)?, target, sentences)?
+                }
+                #[cfg(not(feature = "llm"))]
+                return Err(llm_unsupported());
+            } else {
+                summary::summarise(target, &notes, sentences)
+            };
+            if summary.is_empty() {
+                colours::warn(&format!("'{}' has no sentences to summarise.", key));
+                return Ok(());
+            }
+            for sentence in &summary {
+                println!("- {}", sentence);
+            }
+
+            if insert {
+                check_unlocked(target, false)?;
+                let mut note = db::get_note(&db, &key)?;
+                note.content = summary::with_summary(&note.content, &summary);
+                note.modified_at = Utc::now();
+                db::save_note_with_index(&db, &note, &search_index)?;
+                colours::success(&format!("Added the summary to '{}'", key));
+            }
+        }
+        Commands::Ask { question, notes } => {
+            #[cfg(feature = "llm")]
+            {
+                // The question's terms, without punctuation the query parser would choke on.
+                let query = keywords::terms(&question).join(" ");
+                let keys = if query.is_empty() {
+                    Vec::new()
+                } else {
+                    search::search_notes_with_limit(&search_index, &query, notes)?
+                };
+                let now = Utc::now();
+                let context: Vec<Note> = keys
+                    .iter()
+                    .filter_map(|key| db::get_note(&db, key).ok())
+                    .filter(|note| !note.is_expired(now))
+                    .collect();
+                if context.is_empty() {
+                    colours::warn("No notes match the question.");
+                    return Ok(());
+                }
+
+                let answer = llm::ask(llm::settings(&config)?, &question, &context)?;
+                println!("{}", answer);
+                let sources: Vec<&str> = context.iter().map(|note| note.key.as_str()).collect();
+                colours::info(&format!("Sources: {}", sources.join(", ")));
+            }
+            #[cfg(not(feature = "llm"))]
+            {
+                let _ = (question, notes);
+                return Err(llm_unsupported());
+            }
+        }
+        Commands::SuggestTags { key, top, apply } => {
+            let notes = db::get_all_notes(&db)?;
+            let target = notes
+                .iter()
+                .find(|note| note.key == key)
+                .ok_or_else(|| AppError::KeyNotFound(key.clone()))?;
+
+            let suggestions = keywords::suggest_tags(target, &notes, top);
+            if suggestions.is_empty() {
+                colours::warn(&format!("No tag suggestions for '{}'.", key));
+                return Ok(());
+            }
+            println!(
+                "{}:",
+                format!("Suggested tags for '{}'", key).bold().underline()
+            );
+            let width = suggestions
+                .iter()
+                .map(|(tag, _)| tag.len())
+                .max()
+                .unwrap_or(0);
+            for (tag, score) in &suggestions {
+                println!(
+                    "  {}  {}",
+                    colours::tag(&format!("{:<width$}", tag)),
+                    format!("{:.3}", score).dimmed()
+                );
+            }
+
+            if apply {
+                let mut note = db::get_note(&db, &key)?;
+                note.tags
+                    .extend(suggestions.into_iter().map(|(tag, _)| tag));
+                note.modified_at = Utc::now();
+                db::save_note_with_index(&db, &note, &search_index)?;
+                colours::success(&format!(
+                    "Updated tags for '{}': {}",
+                    key,
+                    note.tags.join(", ")
+                ));
+            }
+        }
+        Commands::Count {
+            filter,
+            group_by,
+            words,
+        } => {
+            let notes = filter.apply(db::get_all_notes(&db)?);
+
+            let counts = stats::count_by(&notes, group_by.as_ref());
+            if group_by.is_none() {
+                let (count, word_count) = counts.get("total").copied().unwrap_or_default();
+                if words {
+                    println!("{} notes, {} words", count, word_count);
+                } else {
+                    println!("{}", count);
+                }
+                return Ok(());
+            }
+
+            let width = counts.keys().map(|group| group.len()).max().unwrap_or(0);
+            for (group, (count, word_count)) in counts {
+                let label = paint(&format!("{:<width$}", group), Role::Info);
+                if words {
+                    println!("{}  {:>6}  {:>8} words", label, count, word_count);
+                } else {
+                    println!("{}  {:>6}", label, count);
+                }
+            }
+        }
+        Commands::Maintain { dry_run, force } => {
+            let _override = force.then(db::override_locks);
+            // Unlike the drain at startup, a failure here is reported as an error.
+            if dry_run {
+                let queued = db::pending_index_count(&db)?;
+                if queued > 0 {
+                    colours::info(&format!("Would re-index {} queued note(s).", queued));
+                }
+            } else {
+                let reindexed = db::replay_pending_index(&db, &search_index)?;
+                if reindexed > 0 {
+                    colours::success(&format!("Re-indexed {} queued note(s).", reindexed));
+                }
+            }
+
+            let now = Utc::now();
+            let (expired, notes): (Vec<Note>, Vec<Note>) = db::get_all_notes(&db)?
+                .into_iter()
+                .partition(|note| note.is_expired(now));
+            let expired: Vec<Note> = expired
+                .into_iter()
+                .filter(|note| !skip_locked(note, force))
+                .collect();
+
+            // Archive old notes according to the configured policies.
+            let mut candidates = maintain::archive_candidates(&notes, &config.archive, now);
+            candidates.retain(|c| {
+                !notes
+                    .iter()
+                    .any(|n| n.key == c.key && skip_locked(n, force))
+            });
+            if candidates.is_empty() {
+                colours::info("No notes to archive.");
+            } else {
+                let verb = if dry_run { "Would archive" } else { "Archived" };
+                colours::info(&format!("{} {} note(s):", verb, candidates.len()));
+                for candidate in &candidates {
+                    let policy = match &candidate.tag {
+                        Some(tag) => format!("#{}", tag),
+                        None => "all notes".to_string(),
+                    };
+                    println!(
+                        "- {} {}",
+                        colours::key(&candidate.key),
+                        format!("({} days old, policy: {})", candidate.age_days, policy).dimmed()
+                    );
+                }
+                if !dry_run {
+                    // Archiving only adds a tag, so it doesn't count as a modification.
+                    let archived: Vec<Note> = notes
+                        .into_iter()
+                        .filter(|note| candidates.iter().any(|c| c.key == note.key))
+                        .map(|mut note| {
+                            note.tags.push(note::ARCHIVED_TAG.to_string());
+                            note
+                        })
+                        .collect();
+                    db::save_notes_with_index(&db, &archived, &search_index)?;
+                }
+            }
+
+            // Purge expired notes, warning first since this can't be undone.
+            if expired.is_empty() {
+                colours::info("No expired notes.");
+                return Ok(());
+            }
+            colours::warn(&format!(
+                "{} expired note(s) will be permanently deleted:",
+                expired.len()
+            ));
+            for note in &expired {
+                let expires_at = note.expires_at.unwrap_or(now);
+                println!(
+                    "- {} {}",
+                    paint(&note.key, Role::Error),
+                    format!(
+                        "(expired {})",
+                        timezone::format(expires_at, "%Y-%m-%d %H:%M")
+                    )
+                    .dimmed()
+                );
+            }
+            if dry_run {
+                return Ok(());
+            }
+
+            if !force {
+                interactive::require("ask before deleting expired notes")?;
+            }
+            let confirmed = force
+                || Confirm::new()
+                    .with_prompt(format!(
+                        "Permanently delete {} expired note(s)?",
+                        expired.len()
+                    ))
+                    .default(false)
+                    .interact()?;
+            if confirmed {
+                let keys: Vec<String> = expired.iter().map(|note| note.key.clone()).collect();
+                delete_notes(&db, &keys, &search_index)?;
+                colours::success(&format!("Deleted {} expired note(s).", expired.len()));
+            } else {
+                colours::warn("Expired notes kept.");
+            }
+        }
+        Commands::Stats { command } => match command {
+            StatsCommands::Tags => {
+                let notes = db::get_all_notes(&db)?;
+                let tasks = db::get_all_tasks(&db)?;
+                let stats = stats::tag_stats(&notes, &tasks);
+                if stats.is_empty() {
+                    colours::warn("No tagged notes found.");
+                    return Ok(());
+                }
+
+                // Newest/oldest are shown as "date (key)".
+                let dated = |(key, date): &(String, DateTime<Utc>)| {
+                    format!("{} ({})", timezone::format(*date, "%Y-%m-%d"), key)
+                };
+                let tag_width = stats
+                    .iter()
+                    .map(|s| s.tag.len() + 1)
+                    .max()
+                    .unwrap_or(0)
+                    .max(3);
+                let newest_width = stats
+                    .iter()
+                    .map(|s| dated(&s.newest).len())
+                    .max()
+                    .unwrap_or(0);
+                println!(
+                    "{}",
+                    format!(
+                        "{:<tag_width$}  {:>5}  {:>7}  {:>10}  {:<newest_width$}  OLDEST",
+                        "TAG", "NOTES", "WORDS", "OPEN TASKS", "NEWEST"
+                    )
+                    .bold()
+                );
+                for tag in stats {
+                    println!(
+                        "{}  {:>5}  {:>7}  {:>10}  {:<newest_width$}  {}",
+                        colours::tag(&format!("{:<tag_width$}", format!("#{}", tag.tag))),
+                        tag.notes,
+                        tag.words,
+                        tag.open_tasks,
+                        dated(&tag.newest),
+                        dated(&tag.oldest)
+                    );
+                }
+            }
+            StatsCommands::Growth { by, sparkline } => {
+                let notes = db::get_all_notes(&db)?;
+                let tasks = db::get_all_tasks(&db)?;
+                let growth = stats::growth(&notes, &tasks, by);
+                let (Some(first), Some(last)) = (growth.first(), growth.last()) else {
+                    colours::warn("No notes or completed tasks yet.");
+                    return Ok(());
+                };
+
+                if sparkline {
+                    eprintln!("{} to {}:", first.period, last.period);
+                    let figures = [
+                        ("notes", growth.iter().map(|g| g.notes).collect::<Vec<_>>()),
+                        ("words", growth.iter().map(|g| g.words).collect()),
+                        ("tasks", growth.iter().map(|g| g.tasks_done).collect()),
+                    ];
+                    for (label, values) in figures {
+                        println!(
+                            "{:<5}  {}  {}",
+                            label,
+                            stats::sparkline(&values),
+                            values.iter().sum::<usize>()
+                        );
+                    }
+                    return Ok(());
+                }
+
+                let period_width = first.period.len().max(6);
+                println!(
+                    "{}",
+                    format!(
+                        "{:<period_width$}  {:>5}  {:>7}  {:>10}  {:>5}",
+                        "PERIOD", "NOTES", "WORDS", "TASKS DONE", "TOTAL"
+                    )
+                    .bold()
+                );
+                let mut total = 0;
+                for period in &growth {
+                    total += period.notes;
+                    println!(
+                        "{:<period_width$}  {:>5}  {:>7}  {:>10}  {:>5}",
+                        period.period, period.notes, period.words, period.tasks_done, total
+                    );
+                }
+            }
+        },
+        Commands::Titles {
+            dupes,
+            rename,
+            force,
+        } => {
+            let _override = force.then(db::override_locks);
+            let mut notes = db::get_all_notes(&db)?;
+            collate::sort_by_key(&mut notes);
+            if !dupes {
+                for note in &notes {
+                    println!("{}\t{}", note.key, note.title);
+                }
+                return Ok(());
+            }
+
+            let groups = note::duplicate_titles(&notes);
+            if groups.is_empty() {
+                colours::success("No two notes share a title.");
+                return Ok(());
+            }
+            for group in &groups {
+                println!("{} ({} notes):", group[0].title.trim(), group.len());
+                for note in group {
+                    println!("  - {}", colours::key(&note.key));
+                }
+            }
+            let shared = groups.iter().map(Vec::len).sum::<usize>();
+            colours::warn(&format!(
+                "{} title(s) are shared by {} notes.",
+                groups.len(),
+                shared
+            ));
+            if !rename {
+                return Ok(());
+            }
+
+            interactive::require("rename the notes")?;
+            let mut renamed = 0;
+            let keys: Vec<String> = groups.iter().flatten().map(|n| n.key.clone()).collect();
+            for key in keys {
+                let mut note = db::get_note(&db, &key)?;
+                if skip_locked(&note, force) {
+                    continue;
+                }
+                let title: String = Input::new()
+                    .with_prompt(format!("Title for '{}'", note.key))
+                    .with_initial_text(&note.title)
+                    .interact_text()?;
+                let title = title.trim();
+                if title.is_empty() || title == note.title {
+                    continue;
+                }
+                note.title = title.to_string();
+                note.modified_at = Utc::now();
+                db::save_note_with_index(&db, &note, &search_index)?;
+                renamed += 1;
+            }
+            colours::success(&format!("Renamed {} note(s).", renamed));
+        }
         Commands::Lint { key } => {
             colours::info("Running linter...");
             let notes_to_lint = if let Some(note_key) = key {
@@ -769,28 +4073,37 @@ pub fn run(cli: Cli, config: Config) -> Result<(), AppError> {
             )
             .map_err(|e| AppError::GuiError(e.to_string()))?;
         }
+        Commands::Init { .. }
+        | Commands::Daemon
+        | Commands::Bench { .. }
+        | Commands::Config { .. }
+        | Commands::MigratePaths { .. } => {
+            unreachable!("handled before the database is opened")
+        }
         Commands::Completion { shell } => {
             let mut cmd = cli::Cli::command();
             let bin_name = cmd.get_name().to_string();
             clap_complete::generate(shell, &mut cmd, bin_name, &mut io::stdout());
         }
-        Commands::Update => {
-            println!("{}", "--- Checking for updates ---".blue());
-            let status = self_update::backends::github::Update::configure()
-                .repo_owner("cladam")
-                .repo_name("medi")
-                .bin_name("medi")
-                .show_download_progress(true)
-                .current_version(self_update::cargo_crate_version!())
-                .build()?
-                .update()?;
-
-            println!("Update status: `{}`!", status.version());
-            if status.updated() {
-                println!("{}", "Successfully updated medi!".green());
-            } else {
-                println!("{}", "medi is already up to date.".green());
+        Commands::Update { channel, check } => {
+            colours::info("--- Checking for updates ---");
+            let current = self_update::cargo_crate_version!();
+            let Some(release) = update::newer_release(&channel)? else {
+                colours::success("medi is already up to date.");
+                return Ok(());
+            };
+
+            if check {
+                println!(
+                    "medi {} is available (you have {}). Run `medi update` to install it.",
+                    paint(&release.version, Role::Success),
+                    current
+                );
+                return Ok(());
             }
+            update::install(&release)?;
+            eprintln!("Update status: `{}`!", release.version);
+            colours::success("Successfully updated medi!");
         }
     }
     Ok(())
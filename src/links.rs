@@ -0,0 +1,128 @@
+use crate::note;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+lazy_static! {
+    static ref WIKILINK: Regex = Regex::new(r"\[\[([^\]\n]+)\]\]").unwrap();
+    /// Markdown links (not images), capturing the text and the target.
+    static ref MARKDOWN_LINK: Regex =
+        Regex::new(r"(^|[^!])\[([^\]\n]*)\]\((<[^>\n]+>|[^)\s]+)\)").unwrap();
+}
+
+/// Rewrites `[[key]]` links as `[title](path.md)` links, for Markdown tools that don't
+/// understand wikilinks. `files` maps the key of each exported note to its title and its
+/// file, relative to the export directory; `from` is the file of the note being written.
+/// Links to notes that aren't exported are left as they are.
+pub fn to_markdown(
+    content: &str,
+    from: &Path,
+    files: &HashMap<String, (String, PathBuf)>,
+) -> String {
+    let from_dir = from.parent().unwrap_or(Path::new(""));
+    WIKILINK
+        .replace_all(content, |caps: &regex::Captures| {
+            match files.get(caps[1].trim()) {
+                Some((title, file)) => {
+                    format!(
+                        "[{}]({})",
+                        title,
+                        note::link_target(&relative_path(from_dir, file))
+                    )
+                }
+                None => caps[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
+/// Turns relative links to `.md` files back into `[[key]]` wikilinks when importing. Link
+/// targets are resolved against the imported file's directory and `key_for` turns the
+/// resulting path (without `.md`) into a key. Links with a URL scheme, an absolute path,
+/// a `#fragment`, or that leave the directory are left as they are.
+pub fn from_markdown(content: &str, key_for: impl Fn(&str) -> String) -> String {
+    MARKDOWN_LINK
+        .replace_all(content, |caps: &regex::Captures| {
+            let target = caps[3].trim_start_matches('<').trim_end_matches('>');
+            let target = target.replace("%20", " ");
+            let key = target
+                .strip_suffix(".md")
+                .filter(|_| !target.contains("://") && !target.contains('#'))
+                .and_then(|path| normalise(Path::new(path)));
+            match key {
+                Some(key) => format!("{}[[{}]]", &caps[1], key_for(&key)),
+                None => caps[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
+/// Resolves `.` and `..` in a relative path, returning `None` for absolute paths or
+/// paths that climb above their starting point.
+fn normalise(path: &Path) -> Option<String> {
+    let mut parts: Vec<String> = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().into_owned()),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                parts.pop()?;
+            }
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+/// The path of `to` relative to the directory `from_dir`, e.g. `../rust.md`.
+fn relative_path(from_dir: &Path, to: &Path) -> PathBuf {
+    let from: Vec<_> = from_dir.components().collect();
+    let to: Vec<_> = to.components().collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let mut path = PathBuf::new();
+    for _ in common..from.len() {
+        path.push("..");
+    }
+    for component in &to[common..] {
+        path.push(component);
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wikilinks_round_trip_through_markdown_links() {
+        let files = HashMap::from([
+            (
+                "rust".to_string(),
+                ("Rust".to_string(), PathBuf::from("rust.md")),
+            ),
+            (
+                "work/plan".to_string(),
+                ("The plan".to_string(), PathBuf::from("work/plan.md")),
+            ),
+        ]);
+        let exported = to_markdown(
+            "See [[rust]], [[work/plan]] and [[elsewhere]].",
+            Path::new("work/notes.md"),
+            &files,
+        );
+        assert_eq!(
+            exported,
+            "See [Rust](../rust.md), [The plan](plan.md) and [[elsewhere]]."
+        );
+
+        let imported = from_markdown(
+            "See [Rust](rust.md), [plan](./work/plan.md), [web](https://x.org/a.md), ![img](pic.md) and [up](../out.md).",
+            str::to_string,
+        );
+        assert_eq!(
+            imported,
+            "See [[rust]], [[work/plan]], [web](https://x.org/a.md), ![img](pic.md) and [up](../out.md)."
+        );
+    }
+}
@@ -0,0 +1,147 @@
+use crate::config::{Config, LlmConfig};
+use crate::error::AppError;
+use crate::note::Note;
+use serde_json::{json, Value};
+use std::env;
+use std::time::Duration;
+use tracing::debug;
+
+/// Ollama's OpenAI-compatible endpoint, used when the config doesn't name one.
+const DEFAULT_ENDPOINT: &str = "http://localhost:11434/v1/chat/completions";
+
+/// Notes are cut to this many characters in a prompt, so a few long ones don't crowd out
+/// the rest of the context.
+const MAX_NOTE_CHARS: usize = 6000;
+
+/// The `[llm]` settings, or an error explaining how to add them.
+pub fn settings(config: &Config) -> Result<&LlmConfig, AppError> {
+    config.llm.as_ref().ok_or_else(|| {
+        AppError::ConfigError(
+            "no language model configured; add an [llm] section with a `model` to config.toml"
+                .to_string(),
+        )
+    })
+}
+
+/// Answers `question` from the given notes, which the model is told to cite by key.
+pub fn ask(llm: &LlmConfig, question: &str, notes: &[Note]) -> Result<String, AppError> {
+    let context: String = notes.iter().map(note_context).collect();
+    complete(
+        llm,
+        "You answer questions using only the user's notes below. Cite the keys of the notes \
+         you used in square brackets. If the notes don't contain the answer, say so.",
+        &format!("{}\nQuestion: {}", context, question),
+    )
+}
+
+/// Summarises `note` in up to `points` bullet points, returned without their markers.
+pub fn summarise(llm: &LlmConfig, note: &Note, points: usize) -> Result<Vec<String>, AppError> {
+    let answer = complete(
+        llm,
+        &format!(
+            "Summarise the note below in at most {} short bullet points, one per line, \
+             each starting with \"- \". Reply with the bullet points only.",
+            points
+        ),
+        &note_context(note),
+    )?;
+    Ok(answer
+        .lines()
+        .map(|line| line.trim().trim_start_matches(['-', '*']).trim())
+        .filter(|line| !line.is_empty())
+        .take(points)
+        .map(str::to_string)
+        .collect())
+}
+
+/// A note as it's shown to the model: its key, title and (possibly shortened) content.
+fn note_context(note: &Note) -> String {
+    let content: String = note.content.chars().take(MAX_NOTE_CHARS).collect();
+    format!(
+        "--- Note [{}]: {}\n{}\n---\n",
+        note.key, note.title, content
+    )
+}
+
+/// Sends a system and a user message to the chat completions endpoint and returns the reply.
+fn complete(llm: &LlmConfig, system: &str, user: &str) -> Result<String, AppError> {
+    let endpoint = llm.endpoint.as_deref().unwrap_or(DEFAULT_ENDPOINT);
+    let body = json!({
+        "model": llm.model,
+        "messages": [
+            { "role": "system", "content": system },
+            { "role": "user", "content": user },
+        ],
+    });
+    let reply = post(llm, endpoint, &body)?;
+    reply["choices"][0]["message"]["content"]
+        .as_str()
+        .map(|content| content.trim().to_string())
+        .ok_or_else(|| AppError::Llm("the reply has no message".to_string()))
+}
+
+/// Embeds each of `inputs` with the configured embedding model, in the same order.
+pub fn embed(llm: &LlmConfig, inputs: &[String]) -> Result<Vec<Vec<f32>>, AppError> {
+    let model = llm.embedding_model.as_deref().ok_or_else(|| {
+        AppError::ConfigError(
+            "no embedding model configured; set `embedding_model` in the [llm] section of config.toml"
+                .to_string(),
+        )
+    })?;
+    // The embeddings endpoint sits next to the chat one, as in OpenAI's and Ollama's APIs.
+    let chat = llm.endpoint.as_deref().unwrap_or(DEFAULT_ENDPOINT);
+    let endpoint = match chat.strip_suffix("/chat/completions") {
+        Some(base) => format!("{}/embeddings", base),
+        None => chat.to_string(),
+    };
+    let reply = post(llm, &endpoint, &json!({ "model": model, "input": inputs }))?;
+    let vectors: Vec<Vec<f32>> = reply["data"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|item| serde_json::from_value(item["embedding"].clone()))
+        .collect::<Result<_, _>>()?;
+    if vectors.len() != inputs.len() {
+        return Err(AppError::Llm(format!(
+            "asked for {} embeddings, got {}",
+            inputs.len(),
+            vectors.len()
+        )));
+    }
+    Ok(vectors)
+}
+
+/// POSTs `body` as JSON to `endpoint`, with the API key if there is one, and returns the
+/// JSON reply.
+fn post(llm: &LlmConfig, endpoint: &str, body: &Value) -> Result<Value, AppError> {
+    let client = reqwest::blocking::Client::builder()
+        .timeout(Duration::from_secs(120))
+        .user_agent(concat!("medi/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(|e| AppError::Llm(e.to_string()))?;
+    let mut request = client
+        .post(endpoint)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(body.to_string());
+    if let Some(var) = &llm.api_key_env {
+        let key = env::var(var).map_err(|_| {
+            AppError::ConfigError(format!("llm.api_key_env names '{}', which isn't set", var))
+        })?;
+        request = request.bearer_auth(key);
+    }
+
+    debug!(endpoint, "calling the language model API");
+    let response = request.send().map_err(|e| {
+        AppError::Llm(if e.is_connect() {
+            format!("couldn't connect to '{}'", endpoint)
+        } else {
+            e.to_string()
+        })
+    })?;
+    let status = response.status();
+    let text = response.text().map_err(|e| AppError::Llm(e.to_string()))?;
+    if !status.is_success() {
+        return Err(AppError::Llm(format!("{}: {}", status, text.trim())));
+    }
+    Ok(serde_json::from_str(&text)?)
+}
@@ -0,0 +1,43 @@
+use crate::error::AppError;
+use std::fs::OpenOptions;
+use std::io::IsTerminal;
+use std::path::Path;
+use std::sync::Mutex;
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::fmt::time::Uptime;
+use tracing_subscriber::prelude::*;
+
+/// Turns on diagnostics for `-v` (operations and their timings) or `-vv` (every step),
+/// written to stderr or appended to `log_file`. Without either, nothing is logged.
+pub fn init(verbose: u8, log_file: Option<&Path>) -> Result<(), AppError> {
+    let level = match verbose {
+        0 if log_file.is_none() => return Ok(()),
+        0 | 1 => LevelFilter::DEBUG,
+        _ => LevelFilter::TRACE,
+    };
+    // Only medi's own events; dependencies are too noisy to be useful here.
+    let filter = Targets::new().with_target("medi", level);
+    let layer = tracing_subscriber::fmt::layer()
+        .with_timer(Uptime::default())
+        .with_target(false);
+    // Only fails if a subscriber is already set, e.g. when `run` is called twice.
+    let _ = match log_file {
+        Some(path) => {
+            let file = OpenOptions::new().create(true).append(true).open(path)?;
+            tracing_subscriber::registry()
+                .with(layer.with_ansi(false).with_writer(Mutex::new(file)))
+                .with(filter)
+                .try_init()
+        }
+        None => tracing_subscriber::registry()
+            .with(
+                layer
+                    .with_ansi(std::io::stderr().is_terminal())
+                    .with_writer(std::io::stderr),
+            )
+            .with(filter)
+            .try_init(),
+    };
+    Ok(())
+}
@@ -1,21 +1,67 @@
 use clap::Parser;
-use medi::{colours, config, run, Cli};
+use medi::{
+    colours, config, offer_setup, parse_with_defaults, run, set_timezone, validate_config, Cli,
+    Commands, ConfigCommands,
+};
 
 /// Main entry point for medi
 /// The application logic is contained in lib.rs, and this file is a thin wrapper responsible
 /// only for parsing arguments and handling top-level errors.
 fn main() {
+    let cli = Cli::parse();
+
+    // Validating the config must work even when the config can't be loaded.
+    if let Commands::Config {
+        command: ConfigCommands::Validate,
+    } = cli.command
+    {
+        if let Err(e) = validate_config(cli) {
+            colours::error(&format!("Error: {}", e));
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // Without a config file this is the first run, and medi offers to set itself up.
+    let first_run = config::config_path().is_ok_and(|path| !path.exists());
     let config = match config::load() {
         Ok(cfg) => cfg,
         Err(e) => {
-            colours::error(&format!("Failed to load configuration: {}", e));
+            colours::error(&format!("Error: {}", e));
             std::process::exit(1);
         }
     };
-    let cli = Cli::parse();
+
+    // Dates on the command line are read in the configured time zone, so the arguments are
+    // parsed again once it's set. Options from the config's [defaults] fill in what the
+    // command line leaves out.
+    set_timezone(&cli, &config);
+    let cli = if config.defaults.is_empty() {
+        Cli::parse()
+    } else {
+        match parse_with_defaults(std::env::args_os().collect(), &config.defaults) {
+            Ok(cli) => cli,
+            Err(e) => {
+                colours::error(&format!("Error: {}", e));
+                std::process::exit(1);
+            }
+        }
+    };
+
+    let config = if first_run {
+        match offer_setup(&cli, config) {
+            Ok(config) => config,
+            Err(e) => {
+                colours::error(&format!("Error: {}", e));
+                std::process::exit(e.exit_code());
+            }
+        }
+    } else {
+        config
+    };
 
     if let Err(e) = run(cli, config) {
         colours::error(&format!("Error: {}", e));
-        std::process::exit(1);
+        std::process::exit(e.exit_code());
     }
 }
@@ -0,0 +1,42 @@
+use crate::config::ArchivePolicy;
+use crate::note::{Note, ARCHIVED_TAG};
+use chrono::{DateTime, Utc};
+
+/// A note that an archive policy says should be archived.
+pub struct ArchiveCandidate {
+    pub key: String,
+    /// The policy's tag, or `None` for a vault-wide policy.
+    pub tag: Option<String>,
+    pub age_days: i64,
+}
+
+/// Finds the notes that an archive policy applies to and that aren't archived yet.
+/// Age is measured from the last modification.
+pub fn archive_candidates(
+    notes: &[Note],
+    policies: &[ArchivePolicy],
+    now: DateTime<Utc>,
+) -> Vec<ArchiveCandidate> {
+    let mut candidates = Vec::new();
+    for note in notes {
+        if note.tags.iter().any(|tag| tag == ARCHIVED_TAG) {
+            continue;
+        }
+        let age_days = (now - note.modified_at).num_days();
+        let matching = policies.iter().find(|policy| {
+            let tag_matches = policy
+                .tag
+                .as_ref()
+                .is_none_or(|tag| note.tags.contains(tag));
+            tag_matches && age_days >= policy.archive_after_days
+        });
+        if let Some(policy) = matching {
+            candidates.push(ArchiveCandidate {
+                key: note.key.clone(),
+                tag: policy.tag.clone(),
+                age_days,
+            });
+        }
+    }
+    candidates
+}
@@ -0,0 +1,113 @@
+use crate::config::{self, Config};
+use crate::error::AppError;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Moving the database from `~/.medi/medi_db`, where medi used to keep it when the config
+/// had no `db_path`, to the data directory `Config::default` uses.
+pub struct Migration {
+    pub from: PathBuf,
+    pub to: PathBuf,
+    /// A search index kept outside the database directory, in `<data dir>/medi/search_index`,
+    /// which moves into the database directory with it.
+    pub stray_index: Option<PathBuf>,
+}
+
+/// Works out what `migrate-paths` has to move. Returns `None` when there is no legacy
+/// database, and an error when moving it would mix it up with other data.
+pub fn plan(config: &Config) -> Result<Option<Migration>, AppError> {
+    let Some(from) = dirs::home_dir().map(|home| home.join(".medi").join("medi_db")) else {
+        return Ok(None);
+    };
+    if !from.is_dir() {
+        return Ok(None);
+    }
+    let to = Config::default()
+        .db_path
+        .ok_or_else(|| AppError::ConfigError("No data directory to migrate to".into()))?;
+    if let Some(configured) = &config.db_path {
+        if *configured != from && *configured != to {
+            return Err(AppError::ConfigError(format!(
+                "config.toml points db_path at '{}', so '{}' isn't in use. Export its notes with \
+                 `medi --db {} export` and import them instead.",
+                configured.display(),
+                from.display(),
+                from.display()
+            )));
+        }
+    }
+    if is_non_empty_dir(&to) {
+        return Err(AppError::ConfigError(format!(
+            "'{}' already holds a database; merge the two with `medi --db {} export` and \
+             `medi import` instead.",
+            to.display(),
+            from.display()
+        )));
+    }
+    let stray_index = dirs::data_dir()
+        .map(|dir| dir.join("medi").join("search_index"))
+        .filter(|index| index.is_dir() && !from.join("search_index").exists());
+    Ok(Some(Migration {
+        from,
+        to,
+        stray_index,
+    }))
+}
+
+/// Moves the database, and the stray search index if there is one, into place.
+pub fn apply(migration: &Migration) -> Result<(), AppError> {
+    if let Some(parent) = migration.to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // An empty directory left by an earlier run is in the way of a rename.
+    if migration.to.is_dir() {
+        fs::remove_dir(&migration.to)?;
+    }
+    move_dir(&migration.from, &migration.to)?;
+    if let Some(index) = &migration.stray_index {
+        move_dir(index, &migration.to.join("search_index"))?;
+    }
+    // Remove ~/.medi too if nothing else is left in it.
+    if let Some(legacy_dir) = migration.from.parent() {
+        let _ = fs::remove_dir(legacy_dir);
+    }
+    Ok(())
+}
+
+/// Sets `db_path` in the config file, keeping the rest of it (and its comments) as it is.
+pub fn update_config(config_path: &Path, db_path: &Path) -> Result<(), AppError> {
+    config::set_value(
+        config_path,
+        "db_path",
+        toml::Value::String(db_path.to_string_lossy().into_owned()),
+    )
+}
+
+fn is_non_empty_dir(path: &Path) -> bool {
+    fs::read_dir(path).is_ok_and(|mut entries| entries.next().is_some())
+}
+
+/// Renames `from` to `to`, copying and deleting instead when they're on different file
+/// systems.
+fn move_dir(from: &Path, to: &Path) -> Result<(), AppError> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    copy_dir(from, to)?;
+    fs::remove_dir_all(from)?;
+    Ok(())
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<(), AppError> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
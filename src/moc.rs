@@ -0,0 +1,73 @@
+use crate::note::Note;
+use std::collections::BTreeMap;
+
+/// Markers around the generated listing, so a refresh keeps anything written around it.
+const START_MARKER: &str = "<!-- medi moc: start -->";
+const END_MARKER: &str = "<!-- medi moc: end -->";
+
+/// Group for notes that have no tags besides the one the map is built for.
+const OTHER_GROUP: &str = "Other";
+
+/// Selects the notes a map of content covers: notes under a key prefix when `target`
+/// ends with `/`, otherwise notes tagged `target`.
+pub fn select<'a>(notes: &'a [Note], target: &str) -> Vec<&'a Note> {
+    notes
+        .iter()
+        .filter(|note| {
+            if target.ends_with('/') {
+                note.key.starts_with(target)
+            } else {
+                note.tags.iter().any(|tag| tag == target)
+            }
+        })
+        .collect()
+}
+
+/// Builds the linked listing, grouped by the notes' other tags. A note with several
+/// tags is listed under each of them.
+pub fn listing(notes: &[&Note], target: &str) -> String {
+    let mut groups: BTreeMap<&str, Vec<&Note>> = BTreeMap::new();
+    for note in notes {
+        let sub_tags: Vec<&str> = note
+            .tags
+            .iter()
+            .map(String::as_str)
+            .filter(|tag| *tag != target)
+            .collect();
+        if sub_tags.is_empty() {
+            groups.entry(OTHER_GROUP).or_default().push(note);
+        } else {
+            for tag in sub_tags {
+                groups.entry(tag).or_default().push(note);
+            }
+        }
+    }
+
+    let mut out = format!("{}\n", START_MARKER);
+    // Tagged groups first, the catch-all last.
+    let other = groups.remove(OTHER_GROUP);
+    for (group, mut group_notes) in groups.into_iter().chain(other.map(|n| (OTHER_GROUP, n))) {
+        group_notes.sort_by(|a, b| a.key.cmp(&b.key));
+        out.push_str(&format!("\n## {}\n\n", group));
+        for note in group_notes {
+            if note.title == note.key {
+                out.push_str(&format!("- [[{}]]\n", note.key));
+            } else {
+                out.push_str(&format!("- [[{}]] {}\n", note.key, note.title));
+            }
+        }
+    }
+    out.push_str(&format!("\n{}\n", END_MARKER));
+    out
+}
+
+/// Replaces the generated listing in `content`, or appends it if there is none yet.
+pub fn refresh(content: &str, listing: &str) -> String {
+    if let (Some(start), Some(end)) = (content.find(START_MARKER), content.find(END_MARKER)) {
+        if start < end {
+            let after = &content[end + END_MARKER.len()..];
+            return format!("{}{}{}", &content[..start], listing.trim_end(), after);
+        }
+    }
+    format!("{}\n\n{}", content.trim_end(), listing)
+}
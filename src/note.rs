@@ -1,9 +1,14 @@
-use chrono::{DateTime, Utc};
+use crate::cli::TagMatch;
+use crate::task::Task;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
 
 /// This module defines the structure of a Note in the medi application.
 /// A Note consists of a key, title, tags, content, and timestamps for creation and modification.
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub struct Note {
     pub key: String,
     pub title: String,
@@ -12,13 +17,294 @@ pub struct Note {
     pub content: String,
     pub created_at: DateTime<Utc>,
     pub modified_at: DateTime<Utc>,
+    #[serde(default)]
+    pub kind: NoteKind,
+    /// The bookmarked URL, for notes of kind `bookmark`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// When the note expires; expired notes are hidden and purged by `medi maintain`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Free-form fields carried over from imports, e.g. the location of a journal entry.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
+    /// Locked notes can't be edited or deleted without `--force`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub locked: bool,
+}
+
+impl Note {
+    /// Whether the note passes a `--tag` filter. An empty filter matches every note.
+    pub fn has_tags(&self, tags: &[String], mode: TagMatch) -> bool {
+        match mode {
+            TagMatch::All => tags.iter().all(|tag| self.tags.contains(tag)),
+            TagMatch::Any => tags.is_empty() || tags.iter().any(|tag| self.tags.contains(tag)),
+        }
+    }
+
+    /// Whether the note is tagged `pinned`.
+    pub fn is_pinned(&self) -> bool {
+        self.tags.iter().any(|tag| tag == PINNED_TAG)
+    }
+
+    /// Whether the note has an expiry time that has passed.
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+
+    /// Appends `text` as new line(s) at the end of the content and bumps the modified time.
+    pub fn append(&mut self, text: &str) {
+        if !self.content.is_empty() && !self.content.ends_with('\n') {
+            self.content.push('\n');
+        }
+        self.content.push_str(text);
+        self.content.push('\n');
+        self.modified_at = Utc::now();
+    }
+}
+
+/// What a note represents. Plain notes are the default; other kinds carry extra metadata.
+#[derive(Serialize, Deserialize, ValueEnum, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum NoteKind {
+    #[default]
+    Note,
+    Bookmark,
 }
 
 /// Represents the JSON structure for exporting notes.
-/// This structure includes the export date, the count of notes, and a vector of Note objects
+/// This structure includes the export date, the count of notes, a vector of Note objects
+/// and the tasks of those notes.
 #[derive(Serialize)]
 pub struct JsonExport {
     pub export_date: DateTime<Utc>,
     pub note_count: usize,
     pub notes: Vec<Note>,
+    pub tasks: Vec<Task>,
+}
+
+/// A hex SHA-256 digest of `text`, to tell when something derived from a note changed.
+pub fn digest(text: &str) -> String {
+    ring::digest::digest(&ring::digest::SHA256, text.as_bytes())
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Normalises a key into a lowercase-dash slug, e.g. "Meeting Notes!" -> "meeting-notes".
+/// Slashes are kept so namespaced keys like `work/Project X` become `work/project-x`.
+pub fn slugify(key: &str) -> String {
+    key.split('/')
+        .map(|segment| {
+            segment
+                .to_lowercase()
+                .split(|c: char| !c.is_alphanumeric())
+                .filter(|part| !part.is_empty())
+                .collect::<Vec<_>>()
+                .join("-")
+        })
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// The text of the first Markdown heading in the content, e.g. `Weekly review` for
+/// `## Weekly review`.
+pub fn first_heading(content: &str) -> Option<String> {
+    content.lines().find_map(|line| {
+        let text = line.trim_start().strip_prefix('#')?.trim_start_matches('#');
+        // `#tag` is not a heading; a heading needs a space after the hashes.
+        if !text.starts_with(char::is_whitespace) {
+            return None;
+        }
+        let text = text.trim().trim_end_matches('#').trim();
+        (!text.is_empty()).then(|| text.to_string())
+    })
+}
+
+/// Builds a Zettelkasten key from a timestamp and a title, e.g. `202405181530-title-slug`.
+pub fn zk_key(timestamp: NaiveDateTime, title: &str) -> String {
+    let stamp = timestamp.format("%Y%m%d%H%M").to_string();
+    let slug = slugify(&title.replace('/', " "));
+    if slug.is_empty() {
+        stamp
+    } else {
+        format!("{}-{}", stamp, slug)
+    }
+}
+
+/// Metadata about a note without its content, as printed by `medi get --meta`.
+#[derive(Serialize)]
+pub struct NoteMeta {
+    pub key: String,
+    pub title: String,
+    pub tags: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub modified_at: DateTime<Utc>,
+    pub word_count: usize,
+    pub links: Vec<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub locked: bool,
+}
+
+impl NoteMeta {
+    pub fn from_note(note: &Note) -> Self {
+        NoteMeta {
+            key: note.key.clone(),
+            title: note.title.clone(),
+            tags: note.tags.clone(),
+            created_at: note.created_at,
+            modified_at: note.modified_at,
+            word_count: note.content.split_whitespace().count(),
+            links: extract_links(&note.content),
+            metadata: note.metadata.clone(),
+            locked: note.locked,
+        }
+    }
+}
+
+/// The notes whose title is `query`, ignoring case, or if there are none, the notes whose
+/// title contains every word of it.
+pub fn match_title<'a>(notes: &'a [Note], query: &str) -> Vec<&'a Note> {
+    let query = query.to_lowercase();
+    let exact: Vec<&Note> = notes
+        .iter()
+        .filter(|note| note.title.to_lowercase() == query)
+        .collect();
+    if !exact.is_empty() {
+        return exact;
+    }
+    let words: Vec<&str> = query.split_whitespace().collect();
+    if words.is_empty() {
+        return Vec::new();
+    }
+    notes
+        .iter()
+        .filter(|note| {
+            let title = note.title.to_lowercase();
+            words.iter().all(|word| title.contains(word))
+        })
+        .collect()
+}
+
+/// Groups the notes that share a title, ignoring case and surrounding whitespace. Groups
+/// come in order of title and hold their notes in order of key.
+pub fn duplicate_titles(notes: &[Note]) -> Vec<Vec<&Note>> {
+    let mut by_title: BTreeMap<String, Vec<&Note>> = BTreeMap::new();
+    for note in notes {
+        by_title
+            .entry(note.title.trim().to_lowercase())
+            .or_default()
+            .push(note);
+    }
+    by_title
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .map(|mut group| {
+            group.sort_by(|a, b| a.key.cmp(&b.key));
+            group
+        })
+        .collect()
+}
+
+/// Returns the keys of all `[[key]]` links in the content, in order of first appearance.
+pub fn extract_links(content: &str) -> Vec<String> {
+    let mut links: Vec<String> = Vec::new();
+    let mut rest = content;
+    while let Some(start) = rest.find("[[") {
+        rest = &rest[start + 2..];
+        let Some(end) = rest.find("]]") else { break };
+        let link = rest[..end].trim();
+        if !link.is_empty() && !link.contains('\n') && !links.iter().any(|l| l == link) {
+            links.push(link.to_string());
+        }
+        rest = &rest[end + 2..];
+    }
+    links
+}
+
+/// Adds a `[[to]]` link to the content. With a `section`, the link is added as a list item
+/// at the end of that heading's section, creating the heading at the end if it's missing;
+/// otherwise it goes on a new line at the end.
+pub fn insert_link(content: &str, to: &str, section: Option<&str>) -> String {
+    let link = format!("[[{}]]", to);
+    let Some(section) = section else {
+        return format!("{}\n\n{}\n", content.trim_end(), link);
+    };
+
+    let heading_level = |line: &str| {
+        let hashes = line.chars().take_while(|c| *c == '#').count();
+        (hashes > 0 && line[hashes..].starts_with(' ')).then_some(hashes)
+    };
+    let lines: Vec<&str> = content.lines().collect();
+    let heading = lines
+        .iter()
+        .position(|line| heading_level(line).is_some_and(|level| line[level..].trim() == section));
+    let Some(heading) = heading else {
+        return format!("{}\n\n## {}\n\n- {}\n", content.trim_end(), section, link);
+    };
+
+    // The section runs until the next heading of the same or a higher level.
+    let level = heading_level(lines[heading]).unwrap_or(2);
+    let end = lines[heading + 1..]
+        .iter()
+        .position(|line| heading_level(line).is_some_and(|l| l <= level))
+        .map_or(lines.len(), |offset| heading + 1 + offset);
+    // Insert after the section's last non-blank line.
+    let insert_at = (heading + 1..end)
+        .rev()
+        .find(|&i| !lines[i].trim().is_empty())
+        .map_or(heading + 1, |i| i + 1);
+
+    let item = format!("- {}", link);
+    let mut out: Vec<&str> = lines[..insert_at].to_vec();
+    if insert_at == heading + 1 {
+        out.push("");
+    }
+    out.push(&item);
+    if insert_at < lines.len() {
+        if !lines[insert_at].trim().is_empty() {
+            out.push("");
+        }
+        out.extend(&lines[insert_at..]);
+    }
+    format!("{}\n", out.join("\n"))
+}
+
+/// Formats a file path as a Markdown link target. Targets with spaces, common in macOS
+/// data directories, are wrapped in angle brackets.
+pub fn link_target(path: &Path) -> String {
+    let target = path.display().to_string();
+    if target.contains(char::is_whitespace) {
+        format!("<{}>", target)
+    } else {
+        target
+    }
+}
+
+/// Tag added to notes archived by `medi maintain`.
+pub const ARCHIVED_TAG: &str = "archived";
+
+/// Tag marking pinned notes, which `medi find` and `search --pinned-first` list first.
+/// Notes pinned in Google Keep get it on import.
+pub const PINNED_TAG: &str = "pinned";
+
+/// The note `medi capture` appends to unless `inbox_key` is configured.
+pub const DEFAULT_INBOX_KEY: &str = "inbox";
+
+/// Namespace for daily journal notes, keyed as `journal/YYYY-MM-DD`.
+pub const JOURNAL_PREFIX: &str = "journal/";
+
+/// Returns the key of the journal note for `date`.
+pub fn journal_key(date: NaiveDate) -> String {
+    format!("{}{}", JOURNAL_PREFIX, date.format("%Y-%m-%d"))
+}
+
+/// Returns the date of a journal note from its key, or `None` for other notes.
+pub fn journal_date(key: &str) -> Option<NaiveDate> {
+    let date = key.strip_prefix(JOURNAL_PREFIX)?;
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
 }
@@ -0,0 +1,134 @@
+use crate::error::AppError;
+use crate::interactive;
+use crate::note::Note;
+use chrono::Utc;
+use dialoguer::{Confirm, Input};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The key of the note the setup creates.
+pub const WELCOME_KEY: &str = "welcome";
+
+/// Templates the setup offers, next to the `meeting` one every install gets.
+const SAMPLE_TEMPLATES: &[(&str, &str)] = &[
+    (
+        "daily",
+        "# {{ YYYY-MM-DD }}\n\n## Focus\n\n- [ ] \n\n## Notes\n\n-\n\n## Done today\n\n-\n",
+    ),
+    (
+        "project",
+        "# Project: {{ NAME }}\n\n**Status:** planning\n\n## Goal\n\n\n## Tasks\n\n- [ ] \n\n## Related\n\n- [[ ]]\n",
+    ),
+];
+
+const WELCOME: &str = "# Welcome to medi
+
+Every note has a key, like `welcome` for this one. See it again with `medi get welcome`,
+or change it with `medi edit welcome`.
+
+## Links
+
+Put a key in double brackets to link to that note, like this: [[ideas]]. The note doesn't
+have to exist yet. `medi backlinks ideas` lists the notes linking to it, and `medi graph`
+shows how your notes connect.
+
+## Tags
+
+This note is tagged `medi` and `getting-started`. Add tags when creating a note with
+`medi new my-note --tag work`, and find them again with `medi query tag:work`.
+
+## Tasks
+
+Checklist items like these are tasks too. See them with `medi task list` and tick one off
+with `medi task done <id>`.
+
+- [ ] Create your first note with `medi new my-first-note`
+- [ ] Jot down a quick thought with `medi capture \"...\"`
+- [ ] Find it again with `medi search thought`
+";
+
+/// What medi prints at the end of the setup.
+pub const QUICKSTART: &str = "Quickstart:
+  medi new my-note          create a note in your editor
+  medi capture \"an idea\"    append a line to your inbox note
+  medi list                 list your notes
+  medi search <words>       search titles, tags and content
+  medi task list            see your open tasks
+  medi --help               everything else";
+
+/// The answers to the setup questions.
+pub struct Setup {
+    pub db_path: PathBuf,
+    pub editor: Option<String>,
+    pub templates: bool,
+}
+
+/// Asks where notes are stored, which editor to use and whether to add the sample
+/// templates, offering the current settings as defaults. Without prompts
+/// (`--non-interactive`) the defaults are taken as they are.
+pub fn prompt(db_path: &Path, editor: Option<&str>) -> Result<Setup, AppError> {
+    if !interactive::enabled() {
+        return Ok(Setup {
+            db_path: db_path.to_path_buf(),
+            editor: editor.map(str::to_string),
+            templates: true,
+        });
+    }
+    let db_path: String = Input::new()
+        .with_prompt("Where should your notes be stored?")
+        .default(db_path.display().to_string())
+        .interact_text()?;
+    let editor: String = Input::new()
+        .with_prompt("Editor command (leave empty to use $VISUAL or $EDITOR)")
+        .with_initial_text(editor.unwrap_or_default())
+        .allow_empty(true)
+        .interact_text()?;
+    let templates = Confirm::new()
+        .with_prompt("Add sample templates (daily, project)?")
+        .default(true)
+        .interact()?;
+    Ok(Setup {
+        db_path: expand_home(db_path.trim()),
+        editor: Some(editor.trim())
+            .filter(|editor| !editor.is_empty())
+            .map(str::to_string),
+        templates,
+    })
+}
+
+/// Resolves a leading `~/`, which the shell would have expanded on the command line.
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Writes the sample templates into `dir`, leaving alone any that already exist. Returns
+/// the names of those written.
+pub fn write_templates(dir: &Path) -> Result<Vec<&'static str>, AppError> {
+    fs::create_dir_all(dir)?;
+    let mut written = Vec::new();
+    for (name, content) in SAMPLE_TEMPLATES {
+        let path = dir.join(format!("{}.md", name));
+        if !path.exists() {
+            fs::write(path, content)?;
+            written.push(*name);
+        }
+    }
+    Ok(written)
+}
+
+/// A note showing off links, tags and tasks, for new vaults.
+pub fn welcome_note() -> Note {
+    let now = Utc::now();
+    Note {
+        key: WELCOME_KEY.to_string(),
+        title: "Welcome to medi".to_string(),
+        tags: vec!["medi".to_string(), "getting-started".to_string()],
+        content: WELCOME.to_string(),
+        created_at: now,
+        modified_at: now,
+        ..Default::default()
+    }
+}
@@ -0,0 +1,292 @@
+use crate::error::AppError;
+use crate::note::Note;
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use std::collections::{HashMap, HashSet};
+
+/// A parsed `medi query` expression.
+///
+/// Terms are `field:value` filters on note metadata (`tag`, `key`, `title`, `content`,
+/// `created`, `modified`) or bare words, which go to the full-text index. Terms can be
+/// combined with `AND`, `OR`, `NOT` and parentheses; adjacent terms are ANDed.
+#[derive(Debug)]
+pub enum Query {
+    And(Box<Query>, Box<Query>),
+    Or(Box<Query>, Box<Query>),
+    Not(Box<Query>),
+    Term(Term),
+}
+
+#[derive(Debug)]
+pub enum Term {
+    /// The note has exactly this tag.
+    Tag(String),
+    /// The key equals the value, or starts with it if the value ends in `*`.
+    Key(String),
+    /// Case-insensitive substring match on the title.
+    Title(String),
+    /// Case-insensitive substring match on the content.
+    Content(String),
+    /// The timestamp falls inside `[start, end)`.
+    Date {
+        field: DateField,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
+    },
+    /// A full-text search against the index.
+    Text(String),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum DateField {
+    Created,
+    Modified,
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Word(String),
+}
+
+impl Query {
+    /// Parses a query string such as `tag:rust AND (title:async OR tokio) modified:>2024-06`.
+    pub fn parse(input: &str) -> Result<Query, AppError> {
+        let tokens = tokenize(input)?;
+        let mut pos = 0;
+        let query = parse_or(&tokens, &mut pos)?;
+        if pos < tokens.len() {
+            return Err(AppError::Query(format!(
+                "unexpected {:?} at position {}",
+                tokens[pos],
+                pos + 1
+            )));
+        }
+        Ok(query)
+    }
+
+    /// All bare full-text terms, so they can be looked up in the index up front.
+    pub fn text_terms(&self) -> Vec<&str> {
+        match self {
+            Query::And(a, b) | Query::Or(a, b) => {
+                let mut terms = a.text_terms();
+                terms.extend(b.text_terms());
+                terms
+            }
+            Query::Not(inner) => inner.text_terms(),
+            Query::Term(Term::Text(text)) => vec![text.as_str()],
+            Query::Term(_) => Vec::new(),
+        }
+    }
+
+    /// Evaluates the query against a note. `text_hits` maps each full-text term to the
+    /// keys the search index returned for it.
+    pub fn matches(&self, note: &Note, text_hits: &HashMap<String, HashSet<String>>) -> bool {
+        match self {
+            Query::And(a, b) => a.matches(note, text_hits) && b.matches(note, text_hits),
+            Query::Or(a, b) => a.matches(note, text_hits) || b.matches(note, text_hits),
+            Query::Not(inner) => !inner.matches(note, text_hits),
+            Query::Term(term) => match term {
+                Term::Tag(tag) => note.tags.iter().any(|t| t == tag),
+                Term::Key(key) => match key.strip_suffix('*') {
+                    Some(prefix) => note.key.starts_with(prefix),
+                    None => &note.key == key,
+                },
+                Term::Title(text) => note.title.to_lowercase().contains(&text.to_lowercase()),
+                Term::Content(text) => note.content.to_lowercase().contains(&text.to_lowercase()),
+                Term::Date { field, start, end } => {
+                    let value = match field {
+                        DateField::Created => note.created_at,
+                        DateField::Modified => note.modified_at,
+                    };
+                    start.is_none_or(|start| value >= start) && end.is_none_or(|end| value < end)
+                }
+                Term::Text(text) => text_hits
+                    .get(text)
+                    .is_some_and(|keys| keys.contains(&note.key)),
+            },
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, AppError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            _ => {
+                // A word runs until whitespace or a parenthesis; quotes may appear
+                // anywhere in it (e.g. `title:"async rust"`) and keep spaces together.
+                let mut word = String::new();
+                let mut quoted = false;
+                while let Some(&c) = chars.peek() {
+                    if c == '"' {
+                        chars.next();
+                        quoted = true;
+                        let mut closed = false;
+                        for c in chars.by_ref() {
+                            if c == '"' {
+                                closed = true;
+                                break;
+                            }
+                            word.push(c);
+                        }
+                        if !closed {
+                            return Err(AppError::Query("unterminated quote".to_string()));
+                        }
+                    } else if c.is_whitespace() || c == '(' || c == ')' {
+                        break;
+                    } else {
+                        word.push(c);
+                        chars.next();
+                    }
+                }
+                tokens.push(match word.as_str() {
+                    "AND" if !quoted => Token::And,
+                    "OR" if !quoted => Token::Or,
+                    "NOT" if !quoted => Token::Not,
+                    _ => Token::Word(word),
+                });
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn parse_or(tokens: &[Token], pos: &mut usize) -> Result<Query, AppError> {
+    let mut left = parse_and(tokens, pos)?;
+    while tokens.get(*pos) == Some(&Token::Or) {
+        *pos += 1;
+        let right = parse_and(tokens, pos)?;
+        left = Query::Or(Box::new(left), Box::new(right));
+    }
+    Ok(left)
+}
+
+fn parse_and(tokens: &[Token], pos: &mut usize) -> Result<Query, AppError> {
+    let mut left = parse_not(tokens, pos)?;
+    loop {
+        match tokens.get(*pos) {
+            Some(Token::And) => *pos += 1,
+            // Adjacent terms are implicitly ANDed.
+            Some(Token::Word(_)) | Some(Token::Not) | Some(Token::Open) => {}
+            _ => break,
+        }
+        let right = parse_not(tokens, pos)?;
+        left = Query::And(Box::new(left), Box::new(right));
+    }
+    Ok(left)
+}
+
+fn parse_not(tokens: &[Token], pos: &mut usize) -> Result<Query, AppError> {
+    if tokens.get(*pos) == Some(&Token::Not) {
+        *pos += 1;
+        return Ok(Query::Not(Box::new(parse_not(tokens, pos)?)));
+    }
+    parse_primary(tokens, pos)
+}
+
+fn parse_primary(tokens: &[Token], pos: &mut usize) -> Result<Query, AppError> {
+    match tokens.get(*pos) {
+        Some(Token::Open) => {
+            *pos += 1;
+            let inner = parse_or(tokens, pos)?;
+            if tokens.get(*pos) != Some(&Token::Close) {
+                return Err(AppError::Query("missing closing parenthesis".to_string()));
+            }
+            *pos += 1;
+            Ok(inner)
+        }
+        Some(Token::Word(word)) => {
+            *pos += 1;
+            Ok(Query::Term(parse_term(word)?))
+        }
+        Some(token) => Err(AppError::Query(format!("unexpected {:?}", token))),
+        None => Err(AppError::Query("unexpected end of query".to_string())),
+    }
+}
+
+fn parse_term(word: &str) -> Result<Term, AppError> {
+    let Some((field, value)) = word.split_once(':') else {
+        return Ok(Term::Text(word.to_string()));
+    };
+    let value = value.to_string();
+    match field {
+        "tag" => Ok(Term::Tag(value)),
+        "key" => Ok(Term::Key(value)),
+        "title" => Ok(Term::Title(value)),
+        "content" => Ok(Term::Content(value)),
+        "created" => parse_date_term(DateField::Created, &value),
+        "modified" => parse_date_term(DateField::Modified, &value),
+        // Anything else (e.g. a URL) is treated as plain text.
+        _ => Ok(Term::Text(word.to_string())),
+    }
+}
+
+/// Parses a date comparison such as `>2024-06`, `<=2024` or `2024-06-15`.
+/// Partial dates cover the whole year or month, so `>2024-06` means "after June 2024".
+fn parse_date_term(field: DateField, value: &str) -> Result<Term, AppError> {
+    let (op, date) = ["<=", ">=", "<", ">", "="]
+        .iter()
+        .find_map(|op| value.strip_prefix(op).map(|rest| (*op, rest)))
+        .unwrap_or(("=", value));
+    let (period_start, period_end) = parse_period(date).ok_or_else(|| {
+        AppError::Query(format!(
+            "invalid date '{}' (use YYYY, YYYY-MM or YYYY-MM-DD)",
+            date
+        ))
+    })?;
+
+    let (start, end) = match op {
+        ">" => (Some(period_end), None),
+        ">=" => (Some(period_start), None),
+        "<" => (None, Some(period_start)),
+        "<=" => (None, Some(period_end)),
+        _ => (Some(period_start), Some(period_end)),
+    };
+    Ok(Term::Date { field, start, end })
+}
+
+/// Returns the start and (exclusive) end of the year, month or day named by `date`.
+fn parse_period(date: &str) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let parts: Vec<&str> = date.split('-').collect();
+    let year: i32 = parts.first()?.parse().ok()?;
+    let (start, end) = match parts.len() {
+        1 => (
+            NaiveDate::from_ymd_opt(year, 1, 1)?,
+            NaiveDate::from_ymd_opt(year + 1, 1, 1)?,
+        ),
+        2 => {
+            let start = NaiveDate::from_ymd_opt(year, parts[1].parse().ok()?, 1)?;
+            let end = match start.month() {
+                12 => NaiveDate::from_ymd_opt(year + 1, 1, 1)?,
+                month => NaiveDate::from_ymd_opt(year, month + 1, 1)?,
+            };
+            (start, end)
+        }
+        3 => {
+            let start = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+            (start, start.succ_opt()?)
+        }
+        _ => return None,
+    };
+    Some((
+        start.and_hms_opt(0, 0, 0)?.and_utc(),
+        end.and_hms_opt(0, 0, 0)?.and_utc(),
+    ))
+}
@@ -0,0 +1,19 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A point in time at which a note should be resurfaced.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Reminder {
+    pub id: u64,
+    pub note_key: String,
+    pub message: Option<String>,
+    pub due_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Reminder {
+    /// Whether the reminder has come due at `now`.
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.due_at <= now
+    }
+}
@@ -0,0 +1,73 @@
+use colored::Colorize;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::parsing::SyntaxSet;
+use syntect::util::{as_24_bit_terminal_escaped, LinesWithEndings};
+
+/// Renders a note's Markdown for the terminal: headings are emphasised and fenced
+/// code blocks are syntax highlighted using the language hint after the fence.
+pub fn render_markdown(markdown: &str) -> String {
+    let syntaxes = SyntaxSet::load_defaults_newlines();
+    let themes = ThemeSet::load_defaults();
+    let theme = &themes.themes["base16-ocean.dark"];
+
+    let mut out = String::new();
+    // The opening fence and language hint of the code block we're inside, if any.
+    let mut fence: Option<(String, String)> = None;
+    let mut code = String::new();
+
+    for line in LinesWithEndings::from(markdown) {
+        let trimmed = line.trim_start();
+        match &fence {
+            Some((marker, lang)) => {
+                if trimmed.trim_end() == marker {
+                    out.push_str(&highlight_code(&code, lang, &syntaxes, theme));
+                    out.push_str(&format!("{}\n", marker.dimmed()));
+                    code.clear();
+                    fence = None;
+                } else {
+                    code.push_str(line);
+                }
+            }
+            None if trimmed.starts_with("```") || trimmed.starts_with("~~~") => {
+                let marker = trimmed[..3].to_string();
+                let lang = trimmed[3..].trim().to_string();
+                out.push_str(&format!("{}\n", trimmed.trim_end().dimmed()));
+                fence = Some((marker, lang));
+            }
+            None if trimmed.starts_with('#') => {
+                out.push_str(&format!("{}\n", line.trim_end().bold().underline()));
+            }
+            None => out.push_str(line),
+        }
+    }
+
+    // An unterminated block still gets highlighted rather than dropped.
+    if let Some((_, lang)) = fence {
+        out.push_str(&highlight_code(&code, &lang, &syntaxes, theme));
+    }
+    out
+}
+
+/// Highlights a code block, falling back to plain text for unknown languages.
+fn highlight_code(code: &str, lang: &str, syntaxes: &SyntaxSet, theme: &Theme) -> String {
+    // Respect NO_COLOR and non-terminal output, like the rest of medi's colouring.
+    if !colored::control::SHOULD_COLORIZE.should_colorize() {
+        return code.to_string();
+    }
+    let syntax = syntaxes
+        .find_syntax_by_token(lang)
+        .unwrap_or_else(|| syntaxes.find_syntax_plain_text());
+    let mut highlighter = HighlightLines::new(syntax, theme);
+
+    let mut out = String::new();
+    for line in LinesWithEndings::from(code) {
+        match highlighter.highlight_line(line, syntaxes) {
+            Ok(ranges) => out.push_str(&as_24_bit_terminal_escaped(&ranges, false)),
+            Err(_) => out.push_str(line),
+        }
+    }
+    // Reset the colours so they don't bleed into the following text.
+    out.push_str("\x1b[0m");
+    out
+}
@@ -0,0 +1,208 @@
+use crate::colours::{paint, Role};
+use crate::error::AppError;
+use crate::flashcard::{self, FLASHCARD_TAG};
+use crate::interactive;
+use crate::note::Note;
+use chrono::{DateTime, Duration, Utc};
+use colored::Colorize;
+use dialoguer::{Input, Select};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Tag marking notes that are reviewed as a whole: the title is the prompt, the content
+/// the answer.
+pub const REVIEW_TAG: &str = "review";
+
+/// The ease every item starts with, as in SM-2.
+const INITIAL_EASE: f64 = 2.5;
+/// SM-2 never lets the ease drop below this.
+const MINIMUM_EASE: f64 = 1.3;
+
+lazy_static! {
+    static ref CLOZE: Regex = Regex::new(r"\{\{c\d+::(.*?)(?:::(.*?))?\}\}").unwrap();
+}
+
+/// Something to review: a flashcard, or a whole note tagged `review`.
+pub struct Item {
+    /// Identifies the item across edits to the rest of the note.
+    pub id: String,
+    pub note_key: String,
+    pub prompt: String,
+    pub answer: String,
+}
+
+/// The spaced-repetition schedule of one item, stored in the database.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReviewState {
+    pub item_id: String,
+    pub note_key: String,
+    pub ease: f64,
+    pub interval_days: u32,
+    /// Successful reviews in a row.
+    pub repetitions: u32,
+    pub due_at: DateTime<Utc>,
+    pub last_grade: u8,
+    pub reviewed_at: DateTime<Utc>,
+}
+
+/// How well an item was remembered, mapped onto SM-2's 0-5 quality scale.
+#[derive(Clone, Copy)]
+pub enum Grade {
+    Again = 1,
+    Hard = 3,
+    Good = 4,
+    Easy = 5,
+}
+
+/// Collects the reviewable items in `notes`: the cards of notes tagged `flashcard` and
+/// notes tagged `review`.
+pub fn items(notes: &[Note]) -> Vec<Item> {
+    let mut items = Vec::new();
+    for note in notes {
+        if note.tags.iter().any(|tag| tag == FLASHCARD_TAG) {
+            for card in flashcard::extract(note) {
+                let (prompt, answer) = if card.cloze {
+                    (
+                        CLOZE
+                            .replace_all(&card.front, |caps: &regex::Captures| {
+                                format!("[{}]", caps.get(2).map_or("...", |hint| hint.as_str()))
+                            })
+                            .into_owned(),
+                        CLOZE.replace_all(&card.front, "$1").into_owned(),
+                    )
+                } else {
+                    (card.front.clone(), card.back)
+                };
+                items.push(Item {
+                    id: format!("{}#{}", note.key, card.front),
+                    note_key: note.key.clone(),
+                    prompt,
+                    answer,
+                });
+            }
+        }
+        if note.tags.iter().any(|tag| tag == REVIEW_TAG) {
+            items.push(Item {
+                id: note.key.clone(),
+                note_key: note.key.clone(),
+                prompt: note.title.clone(),
+                answer: note.content.trim().to_string(),
+            });
+        }
+    }
+    items
+}
+
+/// Applies SM-2 to a review graded `grade` at `now`. Items without a schedule start fresh.
+pub fn schedule(
+    previous: Option<&ReviewState>,
+    item: &Item,
+    grade: Grade,
+    now: DateTime<Utc>,
+) -> ReviewState {
+    let quality = grade as u8;
+    let (ease, interval, repetitions) = previous
+        .map(|state| (state.ease, state.interval_days, state.repetitions))
+        .unwrap_or((INITIAL_EASE, 0, 0));
+
+    let (interval_days, repetitions) = if quality < 3 {
+        (1, 0)
+    } else {
+        let interval_days = match repetitions {
+            0 => 1,
+            1 => 6,
+            _ => (interval as f64 * ease).round() as u32,
+        };
+        (interval_days, repetitions + 1)
+    };
+    let lapse = (5 - quality) as f64;
+    let ease = (ease + 0.1 - lapse * (0.08 + lapse * 0.02)).max(MINIMUM_EASE);
+
+    ReviewState {
+        item_id: item.id.clone(),
+        note_key: item.note_key.clone(),
+        ease,
+        interval_days,
+        repetitions,
+        due_at: now + Duration::days(interval_days as i64),
+        last_grade: quality,
+        reviewed_at: now,
+    }
+}
+
+/// Shows an item's prompt, then its answer, and asks how well it was remembered.
+/// Returns `None` when the user quits.
+pub fn prompt_grade(item: &Item, position: usize, total: usize) -> Result<Option<Grade>, AppError> {
+    interactive::require("ask for review grades")?;
+    println!(
+        "\n{} {}",
+        format!("[{}/{}]", position, total).dimmed(),
+        item.note_key.dimmed()
+    );
+    println!("{}", item.prompt.bold());
+    Input::<String>::new()
+        .with_prompt("Press Enter to show the answer")
+        .allow_empty(true)
+        .interact_text()?;
+    println!("{}", paint(&item.answer, Role::Success));
+
+    let grades = [
+        ("Again", Grade::Again),
+        ("Hard", Grade::Hard),
+        ("Good", Grade::Good),
+        ("Easy", Grade::Easy),
+    ];
+    let labels: Vec<&str> = grades.iter().map(|(label, _)| *label).collect();
+    let choice = Select::new()
+        .with_prompt("How well did you remember it?")
+        .items(&labels)
+        .default(2)
+        .interact_opt()?;
+    Ok(choice.map(|index| grades[index].1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item() -> Item {
+        Item {
+            id: "capitals#Capital of France?".to_string(),
+            note_key: "capitals".to_string(),
+            prompt: "Capital of France?".to_string(),
+            answer: "Paris".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_schedule_follows_sm2_intervals() {
+        let now = Utc::now();
+        let first = schedule(None, &item(), Grade::Good, now);
+        assert_eq!((first.interval_days, first.repetitions), (1, 1));
+        let second = schedule(Some(&first), &item(), Grade::Good, now);
+        assert_eq!(second.interval_days, 6);
+        let third = schedule(Some(&second), &item(), Grade::Easy, now);
+        assert_eq!(third.interval_days, 15);
+        assert!(third.ease > second.ease);
+
+        // Forgetting starts the item over and makes it harder.
+        let lapsed = schedule(Some(&third), &item(), Grade::Again, now);
+        assert_eq!((lapsed.interval_days, lapsed.repetitions), (1, 0));
+        assert!(lapsed.ease < third.ease);
+        assert_eq!(lapsed.due_at, now + Duration::days(1));
+    }
+
+    #[test]
+    fn test_cloze_cards_hide_the_deletion() {
+        let note = Note {
+            key: "capitals".to_string(),
+            content: "The capital of Italy is {{c1::Rome::city}}.\n".to_string(),
+            tags: vec![FLASHCARD_TAG.to_string()],
+            ..Default::default()
+        };
+        let items = items(&[note]);
+        assert_eq!(items[0].prompt, "The capital of Italy is [city].");
+        assert_eq!(items[0].answer, "The capital of Italy is Rome.");
+    }
+}
@@ -1,11 +1,15 @@
+use crate::error::AppError;
 use crate::note::Note;
 use lazy_static::lazy_static;
 use std::path::Path;
+use std::sync::OnceLock;
+use std::time::Instant;
 use tantivy::collector::TopDocs;
 use tantivy::directory::MmapDirectory;
 use tantivy::query::QueryParser;
 use tantivy::schema::*;
-use tantivy::{doc, Index, IndexWriter, ReloadPolicy, TantivyDocument};
+use tantivy::{doc, Index, IndexReader, IndexWriter, ReloadPolicy, TantivyDocument};
+use tracing::{debug, trace};
 
 // Define the schema for your search index.
 // `lazy_static` ensures this is initialised only once.
@@ -24,14 +28,85 @@ lazy_static! {
     };
 }
 
+/// Writer heaps, in MB, unless `index_heap_mb` says otherwise: rebuilding the whole index
+/// gets more room than updating a few notes.
+pub const REBUILD_HEAP_MB: u64 = 100;
+pub const UPDATE_HEAP_MB: u64 = 50;
+/// tantivy refuses writer heaps smaller than this.
+pub const MIN_HEAP_MB: u64 = 15;
+
+/// The writer heap set with `index_heap_mb` in the config.
+static HEAP_MB: OnceLock<u64> = OnceLock::new();
+
+/// Sets the heap, in MB, used by every index writer for the rest of the run.
+pub fn set_heap_mb(mb: u64) {
+    let _ = HEAP_MB.set(mb);
+}
+
+/// Opens a writer with the configured heap, or `default_mb`. The heap is scaled down to
+/// half of the memory that's available, so small machines don't run out of it mid-write,
+/// and it's an error when not even tantivy's minimum fits.
+pub fn writer(index: &Index, default_mb: u64) -> Result<IndexWriter<TantivyDocument>, AppError> {
+    let mut heap_mb = HEAP_MB
+        .get()
+        .copied()
+        .unwrap_or(default_mb)
+        .max(MIN_HEAP_MB);
+    if let Some(available_mb) = available_memory_mb() {
+        if available_mb / 2 < MIN_HEAP_MB {
+            return Err(AppError::Search(format!(
+                "only {} MB of memory is available, but updating the search index needs at least {} MB",
+                available_mb,
+                MIN_HEAP_MB * 2
+            )));
+        }
+        if heap_mb > available_mb / 2 {
+            debug!(heap_mb, available_mb, "scaling down the index writer heap");
+            heap_mb = available_mb / 2;
+        }
+    }
+    trace!(heap_mb, "opening index writer");
+    Ok(index.writer((heap_mb * 1_000_000) as usize)?)
+}
+
+/// The memory the system can hand out without swapping, from `MemAvailable` in
+/// `/proc/meminfo`. `None` where that isn't known.
+fn available_memory_mb() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let line = meminfo
+        .lines()
+        .find_map(|line| line.strip_prefix("MemAvailable:"))?;
+    let kb: u64 = line.trim().trim_end_matches("kB").trim().parse().ok()?;
+    Some(kb / 1024)
+}
+
 /// Opens an existing index or creates a new one.
 pub fn open_index(path: &Path) -> Result<Index, tantivy::error::TantivyError> {
+    let started = Instant::now();
     std::fs::create_dir_all(path)?;
     let directory = MmapDirectory::open(path)?;
     let index = Index::open_or_create(directory, SCHEMA.clone())?;
+    debug!(path = %path.display(), elapsed = ?started.elapsed(), "opened search index");
     Ok(index)
 }
 
+/// Wipes the index and rebuilds it from `notes`, with a single commit.
+pub fn rebuild_index(index: &Index, notes: &[Note]) -> Result<(), AppError> {
+    let started = Instant::now();
+    let mut index_writer = writer(index, REBUILD_HEAP_MB)?;
+    index_writer.delete_all_documents()?;
+    for note in notes {
+        trace!(key = %note.key, bytes = note.content.len(), "indexing");
+        add_note_to_index(note, &mut index_writer)?;
+    }
+    debug!(elapsed = ?started.elapsed(), "added notes to the index");
+
+    let started = Instant::now();
+    index_writer.commit()?;
+    debug!(elapsed = ?started.elapsed(), "committed index");
+    Ok(())
+}
+
 /// Adds a single note to the search index.
 /// This function is designed to be called within a re-indexing loop.
 pub fn add_note_to_index(
@@ -74,12 +149,41 @@ pub fn delete_note_from_index(
 pub fn search_notes(
     index: &Index,
     query_str: &str,
+) -> Result<Vec<String>, tantivy::error::TantivyError> {
+    search_notes_with_limit(index, query_str, 10)
+}
+
+/// Searches the index and returns every matching note key, not just the top hits.
+pub fn search_all_notes(
+    index: &Index,
+    query_str: &str,
+) -> Result<Vec<String>, tantivy::error::TantivyError> {
+    let num_docs = index.reader()?.searcher().num_docs() as usize;
+    search_notes_with_limit(index, query_str, num_docs.max(1))
+}
+
+/// Searches the index and returns up to `limit` matching note keys, best match first.
+pub fn search_notes_with_limit(
+    index: &Index,
+    query_str: &str,
+    limit: usize,
 ) -> Result<Vec<String>, tantivy::error::TantivyError> {
     let reader = index
         .reader_builder()
         .reload_policy(ReloadPolicy::OnCommitWithDelay)
         .try_into()?;
+    search_with_reader(&reader, index, query_str, limit)
+}
 
+/// Like [`search_notes_with_limit`], but with a reader that is already open, as the
+/// daemon keeps one between searches.
+pub fn search_with_reader(
+    reader: &IndexReader,
+    index: &Index,
+    query_str: &str,
+    limit: usize,
+) -> Result<Vec<String>, tantivy::error::TantivyError> {
+    let started = Instant::now();
     let searcher = reader.searcher();
     let key_field = SCHEMA.get_field("key")?;
     let title_field = SCHEMA.get_field("title")?;
@@ -89,7 +193,7 @@ pub fn search_notes(
     let query_parser = QueryParser::for_index(index, vec![title_field, content_field, tags_field]);
     let query = query_parser.parse_query(query_str)?;
 
-    let top_docs = searcher.search(&query, &TopDocs::with_limit(10))?;
+    let top_docs = searcher.search(&query, &TopDocs::with_limit(limit))?;
 
     let mut results = Vec::new();
     for (_score, doc_address) in top_docs {
@@ -101,5 +205,11 @@ pub fn search_notes(
         }
     }
 
+    debug!(
+        query = query_str,
+        hits = results.len(),
+        elapsed = ?started.elapsed(),
+        "searched index"
+    );
     Ok(results)
 }
@@ -0,0 +1,109 @@
+use crate::config::LlmConfig;
+use crate::db;
+use crate::error::AppError;
+use crate::llm;
+use crate::note::{self, Note};
+use serde::{Deserialize, Serialize};
+use sled::Db;
+use std::collections::HashMap;
+use tracing::debug;
+
+/// How many notes are sent to the embeddings endpoint per request.
+const BATCH_SIZE: usize = 32;
+
+/// A note's embedding, with a digest of the text it was made from so it's only redone
+/// when the note changes.
+#[derive(Serialize, Deserialize)]
+pub struct Embedding {
+    pub digest: String,
+    pub vector: Vec<f32>,
+}
+
+/// Brings the stored embeddings in line with `notes`: embeds new and changed notes and
+/// drops those of deleted ones. Returns the embeddings by note key.
+pub fn refresh(
+    db: &Db,
+    llm_config: &LlmConfig,
+    notes: &[Note],
+) -> Result<HashMap<String, Embedding>, AppError> {
+    let mut embeddings = db::get_embeddings(db)?;
+    let gone: Vec<String> = embeddings
+        .keys()
+        .filter(|key| !notes.iter().any(|note| &note.key == *key))
+        .cloned()
+        .collect();
+    db::delete_embeddings(db, &gone)?;
+    for key in &gone {
+        embeddings.remove(key);
+    }
+
+    let stale: Vec<(&Note, String)> = notes
+        .iter()
+        .map(|note| (note, note::digest(&note_text(note))))
+        .filter(|(note, digest)| {
+            embeddings
+                .get(&note.key)
+                .is_none_or(|embedding| &embedding.digest != digest)
+        })
+        .collect();
+    debug!(
+        stale = stale.len(),
+        removed = gone.len(),
+        "refreshing embeddings"
+    );
+    for batch in stale.chunks(BATCH_SIZE) {
+        let texts: Vec<String> = batch.iter().map(|(note, _)| note_text(note)).collect();
+        let vectors = llm::embed(llm_config, &texts)?;
+        let fresh: Vec<(String, Embedding)> = batch
+            .iter()
+            .zip(vectors)
+            .map(|((note, digest), vector)| {
+                let embedding = Embedding {
+                    digest: digest.clone(),
+                    vector,
+                };
+                (note.key.clone(), embedding)
+            })
+            .collect();
+        db::save_embeddings(db, &fresh)?;
+        embeddings.extend(fresh);
+    }
+    Ok(embeddings)
+}
+
+/// Ranks the embedded notes by cosine similarity to `query` and returns the `limit` best
+/// matching keys.
+pub fn search(
+    llm_config: &LlmConfig,
+    query: &str,
+    embeddings: &HashMap<String, Embedding>,
+    limit: usize,
+) -> Result<Vec<String>, AppError> {
+    let query_vector = llm::embed(llm_config, &[query.to_string()])?
+        .pop()
+        .unwrap_or_default();
+    let mut ranked: Vec<(&String, f32)> = embeddings
+        .iter()
+        .map(|(key, embedding)| (key, cosine(&query_vector, &embedding.vector)))
+        .filter(|(_, score)| *score > 0.0)
+        .collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    ranked.truncate(limit);
+    Ok(ranked.into_iter().map(|(key, _)| key.clone()).collect())
+}
+
+/// The text a note is embedded from: its title, tags and content.
+fn note_text(note: &Note) -> String {
+    format!("{}\n{}\n{}", note.title, note.tags.join(" "), note.content)
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norms = norm(a) * norm(b);
+    if norms == 0.0 {
+        0.0
+    } else {
+        dot / norms
+    }
+}
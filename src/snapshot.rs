@@ -0,0 +1,190 @@
+use crate::bookmark::decode_entities;
+use crate::note;
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::fs;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "web")]
+use std::time::Duration;
+
+/// Markers around the generated section, so a refresh keeps anything written around it.
+const START_MARKER: &str = "<!-- medi snapshots: start -->";
+const END_MARKER: &str = "<!-- medi snapshots: end -->";
+
+lazy_static! {
+    static ref URL: Regex = Regex::new(r#"https?://[^\s<>()\[\]"']+"#).unwrap();
+    static ref HIDDEN: Regex =
+        Regex::new(r"(?is)<(script|style|noscript|head|nav|footer|svg)\b.*?</(script|style|noscript|head|nav|footer|svg)>")
+            .unwrap();
+    static ref TITLE: Regex = Regex::new(r"(?is)<title[^>]*>(.*?)</title>").unwrap();
+    static ref BLOCK: Regex =
+        Regex::new(r"(?i)</?(p|div|section|article|h[1-6]|li|ul|ol|tr|blockquote|pre)\b[^>]*>|<br\s*/?>").unwrap();
+    static ref TAG: Regex = Regex::new(r"(?s)<[^>]+>").unwrap();
+}
+
+/// What happened to a link when taking its snapshot.
+pub enum Outcome {
+    /// The readable text was saved to this file.
+    Saved(PathBuf),
+    /// The link couldn't be fetched, for this reason.
+    Dead(String),
+}
+
+/// Finds the distinct external URLs in `content`, leaving out the snapshot section.
+pub fn external_urls(content: &str) -> Vec<String> {
+    let content = match (content.find(START_MARKER), content.find(END_MARKER)) {
+        (Some(start), Some(end)) if start < end => {
+            format!(
+                "{}{}",
+                &content[..start],
+                &content[end + END_MARKER.len()..]
+            )
+        }
+        _ => content.to_string(),
+    };
+    let mut urls: Vec<String> = Vec::new();
+    for url in URL.find_iter(&content) {
+        let url = url
+            .as_str()
+            .trim_end_matches(['.', ',', ';', ':', '!', '?']);
+        if !urls.iter().any(|u| u == url) {
+            urls.push(url.to_string());
+        }
+    }
+    urls
+}
+
+/// Fetches `url` and saves its readable text as a Markdown file in `dir`.
+pub fn snapshot(url: &str, dir: &Path, now: DateTime<Utc>) -> Outcome {
+    let text = match fetch(url) {
+        Ok(text) => text,
+        Err(reason) => return Outcome::Dead(reason),
+    };
+    let name: String = note::slugify(url.split_once("://").map_or(url, |(_, rest)| rest))
+        .replace('/', "-")
+        .chars()
+        .take(80)
+        .collect();
+    let path = dir.join(format!("{}.md", name));
+    let saved = fs::create_dir_all(dir).and_then(|_| {
+        fs::write(
+            &path,
+            format!(
+                "Source: <{}>\nCaptured: {}\n\n{}\n",
+                url,
+                now.format("%Y-%m-%d %H:%M UTC"),
+                text
+            ),
+        )
+    });
+    match saved {
+        Ok(()) => Outcome::Saved(path),
+        Err(e) => Outcome::Dead(format!("couldn't save snapshot: {}", e)),
+    }
+}
+
+/// Downloads a page and returns its readable text. HTML is reduced to its text; plain
+/// text is kept as it is.
+#[cfg(feature = "web")]
+pub fn fetch(url: &str) -> Result<String, String> {
+    let client = reqwest::blocking::Client::builder()
+        .timeout(Duration::from_secs(15))
+        .user_agent(concat!("medi/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let response = client.get(url).send().map_err(|e| {
+        if e.is_timeout() {
+            "timed out".to_string()
+        } else if e.is_connect() {
+            "couldn't connect".to_string()
+        } else {
+            e.to_string()
+        }
+    })?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(status.to_string());
+    }
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("text/html")
+        .to_string();
+    let body = response.text().map_err(|e| e.to_string())?;
+    if content_type.starts_with("text/html") || content_type.contains("xhtml") {
+        Ok(readable_text(&body))
+    } else if content_type.starts_with("text/") {
+        Ok(body.trim().to_string())
+    } else {
+        Err(format!("not a web page ({})", content_type))
+    }
+}
+
+/// Without the `web` feature there's nothing to download the page with.
+#[cfg(not(feature = "web"))]
+pub fn fetch(_url: &str) -> Result<String, String> {
+    Err("medi was built without the `web` feature".to_string())
+}
+
+/// Reduces an HTML page to its title as a heading and the text of its body, one block
+/// per paragraph.
+#[cfg_attr(not(feature = "web"), allow(dead_code))]
+fn readable_text(html: &str) -> String {
+    let title = TITLE.captures(html).map(|caps| {
+        decode_entities(
+            caps[1]
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+                .as_str(),
+        )
+    });
+    let body = HIDDEN.replace_all(html, "");
+    let body = BLOCK.replace_all(&body, "\n\n");
+    let body = decode_entities(&TAG.replace_all(&body, ""));
+
+    let paragraphs: Vec<String> = body
+        .split("\n\n")
+        .map(|block| block.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|block| !block.is_empty())
+        .collect();
+    match title.filter(|title| !title.is_empty()) {
+        Some(title) => format!("# {}\n\n{}", title, paragraphs.join("\n\n")),
+        None => paragraphs.join("\n\n"),
+    }
+}
+
+/// Builds the "Link snapshots" section listing each link's snapshot, or why it's dead.
+pub fn section(results: &[(String, Outcome)], now: DateTime<Utc>) -> String {
+    let date = now.format("%Y-%m-%d");
+    let mut out = format!("{}\n## Link snapshots\n\n", START_MARKER);
+    for (url, outcome) in results {
+        match outcome {
+            Outcome::Saved(path) => out.push_str(&format!(
+                "- <{}> — [snapshot {}]({})\n",
+                url,
+                date,
+                note::link_target(path)
+            )),
+            Outcome::Dead(reason) => out.push_str(&format!(
+                "- <{}> — **dead link** ({}), checked {}\n",
+                url, reason, date
+            )),
+        }
+    }
+    out.push_str(&format!("{}\n", END_MARKER));
+    out
+}
+
+/// Replaces the snapshot section in `content`, or appends it if there is none yet.
+pub fn refresh(content: &str, section: &str) -> String {
+    if let (Some(start), Some(end)) = (content.find(START_MARKER), content.find(END_MARKER)) {
+        if start < end {
+            let after = &content[end + END_MARKER.len()..];
+            return format!("{}{}{}", &content[..start], section.trim_end(), after);
+        }
+    }
+    format!("{}\n\n{}", content.trim_end(), section)
+}
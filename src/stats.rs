@@ -0,0 +1,156 @@
+use crate::cli::{CountGroup, GrowthPeriod};
+use crate::note::Note;
+use crate::task::{Task, TaskStatus};
+use crate::timezone;
+use chrono::{DateTime, Datelike, Months, NaiveDate, Utc};
+use std::collections::BTreeMap;
+
+/// The bars of a sparkline, lowest first.
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Aggregated figures for every note carrying a tag.
+pub struct TagStats {
+    pub tag: String,
+    pub notes: usize,
+    pub words: usize,
+    pub open_tasks: usize,
+    /// Key and creation time of the most recently created note.
+    pub newest: (String, DateTime<Utc>),
+    /// Key and creation time of the earliest created note.
+    pub oldest: (String, DateTime<Utc>),
+}
+
+/// Computes per-tag statistics, busiest tags first.
+pub fn tag_stats(notes: &[Note], tasks: &[Task]) -> Vec<TagStats> {
+    // Open (or prioritised) tasks per note key.
+    let mut open_by_note: BTreeMap<&str, usize> = BTreeMap::new();
+    for task in tasks {
+        if !matches!(task.status, TaskStatus::Done) {
+            *open_by_note.entry(task.note_key.as_str()).or_default() += 1;
+        }
+    }
+
+    let mut by_tag: BTreeMap<&str, TagStats> = BTreeMap::new();
+    for note in notes {
+        let words = note.content.split_whitespace().count();
+        let open_tasks = open_by_note.get(note.key.as_str()).copied().unwrap_or(0);
+        for tag in &note.tags {
+            let stats = by_tag.entry(tag).or_insert_with(|| TagStats {
+                tag: tag.clone(),
+                notes: 0,
+                words: 0,
+                open_tasks: 0,
+                newest: (note.key.clone(), note.created_at),
+                oldest: (note.key.clone(), note.created_at),
+            });
+            stats.notes += 1;
+            stats.words += words;
+            stats.open_tasks += open_tasks;
+            if note.created_at > stats.newest.1 {
+                stats.newest = (note.key.clone(), note.created_at);
+            }
+            if note.created_at < stats.oldest.1 {
+                stats.oldest = (note.key.clone(), note.created_at);
+            }
+        }
+    }
+
+    let mut stats: Vec<TagStats> = by_tag.into_values().collect();
+    stats.sort_by(|a, b| b.notes.cmp(&a.notes).then_with(|| a.tag.cmp(&b.tag)));
+    stats
+}
+
+/// Counts notes (and their words) per group, keyed by the group's label.
+/// Notes without tags are counted under `(untagged)` when grouping by tag.
+pub fn count_by(notes: &[Note], group_by: Option<&CountGroup>) -> BTreeMap<String, (usize, usize)> {
+    let mut counts: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+    for note in notes {
+        let words = note.content.split_whitespace().count();
+        let groups = match group_by {
+            None => vec!["total".to_string()],
+            Some(CountGroup::Tag) if note.tags.is_empty() => vec!["(untagged)".to_string()],
+            Some(CountGroup::Tag) => note.tags.clone(),
+            Some(CountGroup::Month) => vec![timezone::format(note.created_at, "%Y-%m")],
+            Some(CountGroup::Type) => vec![format!("{:?}", note.kind).to_lowercase()],
+        };
+        for group in groups {
+            let entry = counts.entry(group).or_default();
+            entry.0 += 1;
+            entry.1 += words;
+        }
+    }
+    counts
+}
+
+/// What was added to the vault in one period.
+#[derive(Default)]
+pub struct Growth {
+    /// The period's label, e.g. `2025-07`, `2025-W07` or `2025`.
+    pub period: String,
+    pub notes: usize,
+    /// Words in the notes created in the period, as they read now.
+    pub words: usize,
+    pub tasks_done: usize,
+}
+
+/// Counts the notes created, their words and the tasks completed per period, from the
+/// first period with any activity to the last. Quiet periods in between are included, so
+/// the trajectory isn't distorted.
+pub fn growth(notes: &[Note], tasks: &[Task], by: GrowthPeriod) -> Vec<Growth> {
+    let mut periods: BTreeMap<NaiveDate, Growth> = BTreeMap::new();
+    for note in notes {
+        let entry = periods
+            .entry(period_start(timezone::date(note.created_at), by))
+            .or_default();
+        entry.notes += 1;
+        entry.words += note.content.split_whitespace().count();
+    }
+    for completed in tasks.iter().filter_map(|task| task.completed_at) {
+        periods
+            .entry(period_start(timezone::date(completed), by))
+            .or_default()
+            .tasks_done += 1;
+    }
+
+    let (Some(&first), Some(&last)) = (periods.keys().next(), periods.keys().next_back()) else {
+        return Vec::new();
+    };
+    let mut growth = Vec::new();
+    let mut start = first;
+    while start <= last {
+        let mut period = periods.remove(&start).unwrap_or_default();
+        period.period = match by {
+            GrowthPeriod::Week => start.format("%G-W%V").to_string(),
+            GrowthPeriod::Month => start.format("%Y-%m").to_string(),
+            GrowthPeriod::Year => start.format("%Y").to_string(),
+        };
+        growth.push(period);
+        start = match by {
+            GrowthPeriod::Week => start + chrono::Duration::weeks(1),
+            GrowthPeriod::Month => start + Months::new(1),
+            GrowthPeriod::Year => start + Months::new(12),
+        };
+    }
+    growth
+}
+
+/// The first day of the period `date` falls in; weeks start on Monday.
+fn period_start(date: NaiveDate, by: GrowthPeriod) -> NaiveDate {
+    match by {
+        GrowthPeriod::Week => {
+            date - chrono::Duration::days(date.weekday().num_days_from_monday().into())
+        }
+        GrowthPeriod::Month => date.with_day(1).unwrap_or(date),
+        GrowthPeriod::Year => date.with_ordinal(1).unwrap_or(date),
+    }
+}
+
+/// Draws `values` as a row of bars scaled to the largest value. Zero is always the
+/// lowest bar.
+pub fn sparkline(values: &[usize]) -> String {
+    let max = values.iter().copied().max().unwrap_or(0).max(1);
+    values
+        .iter()
+        .map(|&value| BARS[value * (BARS.len() - 1) / max])
+        .collect()
+}
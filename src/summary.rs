@@ -0,0 +1,128 @@
+use crate::keywords;
+use crate::note::Note;
+
+/// The heading of the summary section `medi summary --insert` writes.
+pub const SUMMARY_HEADING: &str = "## Summary";
+
+/// Sentences with fewer words than this are fragments, not worth a place in a summary.
+const MIN_SENTENCE_WORDS: usize = 4;
+
+/// Picks the `count` sentences of `target` whose terms matter most, by TF-IDF against
+/// `corpus`, and returns them in the order they appear in the note.
+pub fn summarise(target: &Note, corpus: &[Note], count: usize) -> Vec<String> {
+    let body = Note {
+        key: target.key.clone(),
+        title: target.title.clone(),
+        content: without_summary(&target.content),
+        ..Default::default()
+    };
+    let weights = keywords::weights(&body, corpus);
+
+    let mut scored: Vec<(usize, f64, String)> = sentences(&body.content)
+        .into_iter()
+        .enumerate()
+        .filter_map(|(position, sentence)| {
+            let terms = keywords::terms(&sentence);
+            if terms.is_empty() {
+                return None;
+            }
+            let total: f64 = terms.iter().filter_map(|term| weights.get(term)).sum();
+            // Dampen the length, so long sentences don't win just by having more terms.
+            Some((position, total / (terms.len() as f64).sqrt(), sentence))
+        })
+        .collect();
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    scored.truncate(count);
+    scored.sort_by_key(|(position, _, _)| *position);
+    scored
+        .into_iter()
+        .map(|(_, _, sentence)| sentence)
+        .collect()
+}
+
+/// Splits Markdown into sentences, skipping headings and code blocks and stripping list
+/// and checkbox markers.
+fn sentences(content: &str) -> Vec<String> {
+    let mut in_code = false;
+    let mut sentences = Vec::new();
+    for line in content.lines() {
+        let line = line.trim();
+        if line.starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code || line.starts_with('#') || line.is_empty() {
+            continue;
+        }
+        let text = strip_list_marker(line);
+        let mut start = 0;
+        for (i, c) in text.char_indices() {
+            let end = i + c.len_utf8();
+            let at_break = text[end..].starts_with(' ') || end == text.len();
+            if matches!(c, '.' | '!' | '?') && at_break {
+                sentences.push(text[start..end].trim().to_string());
+                start = end;
+            }
+        }
+        sentences.push(text[start..].trim().to_string());
+    }
+    sentences.retain(|sentence| sentence.split_whitespace().count() >= MIN_SENTENCE_WORDS);
+    sentences
+}
+
+fn strip_list_marker(line: &str) -> &str {
+    let line = line
+        .strip_prefix("- ")
+        .or_else(|| line.strip_prefix("* "))
+        .or_else(|| line.strip_prefix("> "))
+        .or_else(|| {
+            // Numbered items, e.g. `12. `.
+            let digits = line.find(|c: char| !c.is_ascii_digit())?;
+            line[digits..].strip_prefix(". ").filter(|_| digits > 0)
+        })
+        .unwrap_or(line);
+    ["[ ] ", "[x] ", "[X] "]
+        .iter()
+        .find_map(|checkbox| line.strip_prefix(checkbox))
+        .unwrap_or(line)
+}
+
+/// Puts `sentences` at the top of `content` under [`SUMMARY_HEADING`], after a leading
+/// `# Title` line if there is one, replacing a summary inserted earlier.
+pub fn with_summary(content: &str, sentences: &[String]) -> String {
+    let content = without_summary(content);
+    let (title, body) = match content.split_once('\n') {
+        Some((first, rest)) if first.starts_with("# ") => {
+            (format!("{}\n\n", first), rest.trim_start_matches('\n'))
+        }
+        _ => (String::new(), content.as_str()),
+    };
+    let bullets: String = sentences.iter().map(|s| format!("- {}\n", s)).collect();
+    format!("{}{}\n\n{}\n{}", title, SUMMARY_HEADING, bullets, body)
+}
+
+/// Removes a summary section written by [`with_summary`]: the heading, its bullets and
+/// the blank lines around them.
+fn without_summary(content: &str) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let Some(start) = lines.iter().position(|line| line.trim() == SUMMARY_HEADING) else {
+        return content.to_string();
+    };
+    let mut end = start + 1;
+    while end < lines.len() && lines[end].trim().is_empty() {
+        end += 1;
+    }
+    while end < lines.len() && lines[end].starts_with("- ") {
+        end += 1;
+    }
+    while end < lines.len() && lines[end].trim().is_empty() {
+        end += 1;
+    }
+    let mut kept: Vec<&str> = lines[..start].to_vec();
+    kept.extend(&lines[end..]);
+    let mut result = kept.join("\n");
+    if content.ends_with('\n') {
+        result.push('\n');
+    }
+    result
+}
@@ -1,3 +1,4 @@
+use crate::timezone;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
@@ -15,4 +16,59 @@ pub struct Task {
     pub description: String,
     pub status: TaskStatus,
     pub created_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<DateTime<Utc>>,
+    /// How the task was closed, given with `task done -m`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolution: Option<String>,
+    /// The task is left out of `task list` until this time, set with `task snooze`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hidden_until: Option<DateTime<Utc>>,
+}
+
+/// Finds the Markdown checklist items (`- [ ] ...` and `- [x] ...`) in `content`,
+/// returning each item's text and whether it is checked.
+pub fn checklist_items(content: &str) -> Vec<(String, bool)> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let item = line
+                .trim_start()
+                .strip_prefix("- [")
+                .or_else(|| line.trim_start().strip_prefix("* ["))?;
+            let (mark, text) = item.split_once("] ")?;
+            let checked = match mark {
+                " " => false,
+                "x" | "X" => true,
+                _ => return None,
+            };
+            Some((text.trim().to_string(), checked)).filter(|(text, _)| !text.is_empty())
+        })
+        .collect()
+}
+
+/// Formats a task as a Markdown checklist item, e.g. `- [ ] Ship it (project-x, due 2025-07-01)`,
+/// naming `note` if given.
+pub fn checklist_line(task: &Task, note: Option<&str>) -> String {
+    let done = matches!(task.status, TaskStatus::Done);
+    let mark = if done { "x" } else { " " };
+    let mut details: Vec<String> = note.map(str::to_string).into_iter().collect();
+    // A due date no longer matters once the task is done.
+    if let Some(due_at) = task.due_at.filter(|_| !done) {
+        details.push(format!("due {}", timezone::format(due_at, "%Y-%m-%d")));
+    }
+    if details.is_empty() {
+        format!("- [{}] {}", mark, task.description)
+    } else {
+        format!("- [{}] {} ({})", mark, task.description, details.join(", "))
+    }
+}
+
+impl Task {
+    /// Whether the task has been snoozed past `now`.
+    pub fn is_snoozed(&self, now: DateTime<Utc>) -> bool {
+        self.hidden_until.is_some_and(|until| until > now)
+    }
 }
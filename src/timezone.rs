@@ -0,0 +1,69 @@
+use crate::dates;
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use chrono_tz::Tz;
+use std::sync::OnceLock;
+
+/// The time zone timestamps are shown in and dates are read in; they're always stored in UTC.
+#[derive(Clone, Copy, Debug)]
+pub enum Zone {
+    /// The system's time zone.
+    Local,
+    /// UTC, chosen with `--utc`.
+    Utc,
+    /// A named zone from `timezone` in the config, e.g. `Europe/Stockholm`.
+    Named(Tz),
+}
+
+static ZONE: OnceLock<Zone> = OnceLock::new();
+
+/// Parses an IANA time zone name, e.g. `America/New_York`.
+pub fn parse(name: &str) -> Result<Zone, String> {
+    name.parse::<Tz>().map(Zone::Named).map_err(|_| {
+        format!(
+            "unknown time zone '{}', expected a name like 'Europe/Stockholm'",
+            name
+        )
+    })
+}
+
+/// Sets the zone used for the rest of the run. Defaults to the system's.
+pub fn set(zone: Zone) {
+    let _ = ZONE.set(zone);
+}
+
+fn zone() -> Zone {
+    ZONE.get().copied().unwrap_or(Zone::Local)
+}
+
+/// Formats `timestamp` in the chosen time zone.
+pub fn format(timestamp: DateTime<Utc>, fmt: &str) -> String {
+    match zone() {
+        Zone::Local => timestamp.with_timezone(&Local).format(fmt).to_string(),
+        Zone::Utc => timestamp.format(fmt).to_string(),
+        Zone::Named(tz) => timestamp.with_timezone(&tz).format(fmt).to_string(),
+    }
+}
+
+/// The calendar day `timestamp` falls on in the chosen time zone.
+pub fn date(timestamp: DateTime<Utc>) -> NaiveDate {
+    match zone() {
+        Zone::Local => timestamp.with_timezone(&Local).date_naive(),
+        Zone::Utc => timestamp.date_naive(),
+        Zone::Named(tz) => timestamp.with_timezone(&tz).date_naive(),
+    }
+}
+
+/// Today's date in the chosen time zone.
+pub fn today() -> NaiveDate {
+    date(Utc::now())
+}
+
+/// Parses a point in time with [`dates::parse_when`], reading days and times of day in the
+/// chosen time zone.
+pub fn parse_when(input: &str) -> Result<DateTime<Utc>, String> {
+    match zone() {
+        Zone::Local => dates::parse_when(input, Local::now()),
+        Zone::Utc => dates::parse_when(input, Utc::now()),
+        Zone::Named(tz) => dates::parse_when(input, Utc::now().with_timezone(&tz)),
+    }
+}
@@ -0,0 +1,116 @@
+use crate::error::AppError;
+use crate::note::{Note, ARCHIVED_TAG};
+use crate::{colours, interactive};
+use colored::Colorize;
+use dialoguer::{FuzzySelect, Input, Select};
+
+/// Tag marking notes that still need filing.
+pub const INBOX_TAG: &str = "inbox";
+
+/// How many lines of a note are shown while triaging it.
+const PREVIEW_LINES: usize = 6;
+
+/// What to do with the note being triaged.
+pub enum Action {
+    /// Replace the `inbox` tag with these tags.
+    Tag(Vec<String>),
+    Retitle(String),
+    /// Append the note to another note and delete it.
+    MergeInto(String),
+    Archive,
+    Delete,
+    Skip,
+    Quit,
+}
+
+/// Whether a note is waiting to be filed: untagged, or tagged `inbox`. The capture inbox
+/// itself and archived notes are left alone.
+pub fn needs_triage(note: &Note, inbox_key: &str) -> bool {
+    note.key != inbox_key
+        && !note.tags.iter().any(|tag| tag == ARCHIVED_TAG)
+        && (note.tags.is_empty() || note.tags.iter().any(|tag| tag == INBOX_TAG))
+}
+
+/// Shows a note and asks what to do with it.
+pub fn prompt_action(
+    note: &Note,
+    position: usize,
+    total: usize,
+    other_keys: &[String],
+) -> Result<Action, AppError> {
+    interactive::require("ask what to do with each note")?;
+    println!(
+        "\n{} {} {}",
+        format!("[{}/{}]", position, total).dimmed(),
+        colours::key(&note.key),
+        format!("({})", note.title).dimmed()
+    );
+    if !note.tags.is_empty() {
+        println!("  tags: {}", note.tags.join(", "));
+    }
+    for line in note.content.lines().take(PREVIEW_LINES) {
+        println!("  {}", line.dimmed());
+    }
+    if note.content.lines().count() > PREVIEW_LINES {
+        println!("  {}", "…".dimmed());
+    }
+
+    let items = [
+        "Tag",
+        "Retitle",
+        "Merge into…",
+        "Archive",
+        "Delete",
+        "Skip",
+        "Quit",
+    ];
+    let Some(choice) = Select::new()
+        .with_prompt("Action")
+        .items(items)
+        .default(0)
+        .interact_opt()?
+    else {
+        return Ok(Action::Quit);
+    };
+
+    Ok(match items[choice] {
+        "Tag" => {
+            let input: String = Input::new()
+                .with_prompt("Tags (comma separated)")
+                .interact_text()?;
+            let tags: Vec<String> = input
+                .split(',')
+                .map(|tag| tag.trim().to_string())
+                .filter(|tag| !tag.is_empty())
+                .collect();
+            if tags.is_empty() {
+                Action::Skip
+            } else {
+                Action::Tag(tags)
+            }
+        }
+        "Retitle" => Action::Retitle(
+            Input::new()
+                .with_prompt("Title")
+                .with_initial_text(&note.title)
+                .interact_text()?,
+        ),
+        "Merge into…" => {
+            if other_keys.is_empty() {
+                return Ok(Action::Skip);
+            }
+            match FuzzySelect::new()
+                .with_prompt("Merge into")
+                .items(other_keys)
+                .interact_opt()?
+            {
+                Some(index) => Action::MergeInto(other_keys[index].clone()),
+                None => Action::Skip,
+            }
+        }
+        "Archive" => Action::Archive,
+        "Delete" => Action::Delete,
+        "Skip" => Action::Skip,
+        _ => Action::Quit,
+    })
+}
@@ -0,0 +1,125 @@
+use crate::cli::UpdateChannel;
+use crate::error::AppError;
+use ring::digest::{digest, SHA256};
+use self_update::backends::github::ReleaseList;
+use self_update::update::{Release, ReleaseAsset};
+use std::io::Write;
+
+/// Extension of the checksum file published next to each release binary, holding the
+/// output of `sha256sum` for it.
+const CHECKSUM_EXTENSION: &str = ".sha256";
+
+/// Finds the newest release on `channel` that is newer than the running binary.
+pub fn newer_release(channel: &UpdateChannel) -> Result<Option<Release>, AppError> {
+    let releases = ReleaseList::configure()
+        .repo_owner("cladam")
+        .repo_name("medi")
+        .with_target(self_update::get_target())
+        .build()?
+        .fetch()?;
+
+    // GitHub lists releases newest first.
+    let current = self_update::cargo_crate_version!();
+    for release in releases {
+        let on_channel = match channel {
+            UpdateChannel::Stable => !is_prerelease(&release.version),
+            UpdateChannel::Prerelease => true,
+        };
+        if on_channel && self_update::version::bump_is_greater(current, &release.version)? {
+            return Ok(Some(release));
+        }
+    }
+    Ok(None)
+}
+
+/// Pre-release versions carry a suffix, e.g. `0.14.0-rc.1`.
+fn is_prerelease(version: &str) -> bool {
+    version.contains('-')
+}
+
+/// Downloads the release binary for this platform, checks it against the release's
+/// published SHA-256 checksum, and only then replaces the running binary with it.
+pub fn install(release: &Release) -> Result<(), AppError> {
+    let target = self_update::get_target();
+    let binary = release
+        .assets
+        .iter()
+        .find(|asset| asset.name.contains(target) && !asset.name.ends_with(CHECKSUM_EXTENSION))
+        .ok_or_else(|| {
+            AppError::UpdateVerification(format!(
+                "release {} has no binary for {}",
+                release.version, target
+            ))
+        })?;
+    let checksum_name = format!("{}{}", binary.name, CHECKSUM_EXTENSION);
+    let checksum = release
+        .assets
+        .iter()
+        .find(|asset| asset.name == checksum_name)
+        .ok_or_else(|| {
+            AppError::UpdateVerification(format!(
+                "release {} publishes no checksum for {}, refusing to install it",
+                release.version, binary.name
+            ))
+        })?;
+
+    let expected = parse_checksum(&String::from_utf8(download(checksum, false)?)?)
+        .ok_or_else(|| AppError::UpdateVerification(format!("{} is malformed", checksum.name)))?;
+    let bytes = download(binary, true)?;
+    let actual = sha256_hex(&bytes);
+    if actual != expected {
+        return Err(AppError::UpdateVerification(format!(
+            "checksum mismatch for {}: expected {}, got {}",
+            binary.name, expected, actual
+        )));
+    }
+
+    let mut new_binary = tempfile::NamedTempFile::new()?;
+    new_binary.write_all(&bytes)?;
+    new_binary.flush()?;
+    self_update::self_replace::self_replace(new_binary.path())?;
+    Ok(())
+}
+
+fn download(asset: &ReleaseAsset, show_progress: bool) -> Result<Vec<u8>, AppError> {
+    let mut bytes = Vec::new();
+    self_update::Download::from_url(&asset.download_url)
+        .set_header(
+            "accept".parse().expect("a valid header name"),
+            "application/octet-stream"
+                .parse()
+                .expect("a valid header value"),
+        )
+        .show_progress(show_progress)
+        .download_to(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Reads the hex digest from `sha256sum` output (`<digest>  <file name>`).
+fn parse_checksum(text: &str) -> Option<String> {
+    let digest = text.split_whitespace().next()?.to_lowercase();
+    (digest.len() == 64 && digest.chars().all(|c| c.is_ascii_hexdigit())).then_some(digest)
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    digest(&SHA256, bytes)
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksum_matches_sha256sum_output() {
+        let published =
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824  medi-x86_64-unknown-linux-gnu\n";
+        assert_eq!(parse_checksum(published), Some(sha256_hex(b"hello")));
+        assert_eq!(parse_checksum("not-a-digest  medi"), None);
+        assert!(is_prerelease("0.14.0-rc.1"));
+        assert!(!is_prerelease("0.14.0"));
+    }
+}
@@ -0,0 +1,112 @@
+use crate::error::AppError;
+use crate::interactive;
+use dialoguer::{Completion, Input, Select};
+use sled::Db;
+use std::fs;
+use std::path::Path;
+
+/// The metadata collected by the interactive `new` wizard.
+pub struct NewNoteInput {
+    pub key: String,
+    pub title: String,
+    pub tags: Vec<String>,
+    pub template: Option<String>,
+}
+
+/// Completes the last tag being typed against the tags already used in the vault.
+struct TagCompletion {
+    known_tags: Vec<String>,
+}
+
+impl Completion for TagCompletion {
+    fn get(&self, input: &str) -> Option<String> {
+        let (done, partial) = match input.rfind([',', ' ']) {
+            Some(pos) => input.split_at(pos + 1),
+            None => ("", input),
+        };
+        if partial.is_empty() {
+            return None;
+        }
+        self.known_tags
+            .iter()
+            .find(|tag| tag.starts_with(partial) && tag.as_str() != partial)
+            .map(|tag| format!("{}{}", done, tag))
+    }
+}
+
+/// Walks the user through choosing a key, title, tags and template for a new note.
+/// Values already given on the command line are offered as defaults.
+pub fn new_note(
+    db: &Db,
+    key: Option<String>,
+    title: Option<String>,
+    tags: Vec<String>,
+    known_tags: Vec<String>,
+    templates_dir: Option<&Path>,
+) -> Result<NewNoteInput, AppError> {
+    interactive::require("prompt for the new note's details")?;
+    let mut key_input = Input::<String>::new().with_prompt("Key").validate_with(
+        |input: &String| -> Result<(), String> {
+            match db.contains_key(input.trim()) {
+                Ok(false) => Ok(()),
+                Ok(true) => Err(format!("A note with key '{}' already exists", input)),
+                Err(e) => Err(e.to_string()),
+            }
+        },
+    );
+    if let Some(key) = key {
+        key_input = key_input.default(key);
+    }
+    let key = key_input.interact_text()?.trim().to_string();
+
+    let title: String = Input::new()
+        .with_prompt("Title")
+        .default(title.unwrap_or_else(|| key.clone()))
+        .interact_text()?;
+
+    let completion = TagCompletion { known_tags };
+    let tags_line: String = Input::new()
+        .with_prompt("Tags (comma separated, TAB to complete)")
+        .with_initial_text(tags.join(", "))
+        .allow_empty(true)
+        .completion_with(&completion)
+        .interact_text()?;
+    let tags = tags_line
+        .split([',', ' '])
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+        .map(str::to_string)
+        .collect();
+
+    // Offer every template in the templates directory, plus a blank note.
+    let mut templates: Vec<String> = templates_dir
+        .and_then(|dir| fs::read_dir(dir).ok())
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension() == Some("md".as_ref()))
+        .filter_map(|path| path.file_stem().and_then(|s| s.to_str()).map(String::from))
+        .collect();
+    templates.sort();
+
+    let template = if templates.is_empty() {
+        None
+    } else {
+        let mut choices = vec!["(blank note)".to_string()];
+        choices.extend(templates.iter().cloned());
+        let choice = Select::new()
+            .with_prompt("Template")
+            .items(&choices)
+            .default(0)
+            .interact()?;
+        (choice > 0).then(|| templates[choice - 1].clone())
+    };
+
+    Ok(NewNoteInput {
+        key,
+        title,
+        tags,
+        template,
+    })
+}
@@ -1,3 +1,6 @@
+// assert_cmd 2.1 deprecated `Command::cargo_bin`; these tests still use it.
+#![allow(deprecated)]
+
 use assert_cmd::Command;
 use predicates::prelude::*;
 use rand::distr::{Alphanumeric, SampleString};
@@ -90,6 +93,179 @@ fn test_new_with_piped_input() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[test]
+fn test_new_from_file() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    let source = harness._temp_dir.path().join("draft.md");
+    fs::write(&source, "# Draft\n\nSeeded from a file.\n")?;
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["new", "draft", "--from", &source.to_string_lossy()])
+        .args(["--tag", "inbox"])
+        .assert()
+        .success();
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "draft", "--json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Seeded from a file."))
+        .stdout(predicate::str::contains("\"inbox\""));
+    Ok(())
+}
+
+#[test]
+fn test_non_interactive_refuses_prompts() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["new", "keep-me", "-m", "still here"])
+        .assert()
+        .success();
+
+    // Prompts and the editor fail with exit code 3 instead of waiting for input.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["--non-interactive", "delete", "keep-me"])
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("--non-interactive"));
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["edit", "keep-me", "--non-interactive"])
+        .assert()
+        .code(3);
+
+    // Refusing the editor leaves no empty journal entry behind.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["--non-interactive", "journal", "--date", "2024-06-14"])
+        .assert()
+        .code(3);
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("journal/2024-06-14").not());
+
+    // Flags that answer the prompt up front still work.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["--non-interactive", "delete", "keep-me", "--force"])
+        .assert()
+        .success();
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_edit_bulk_saves_changed_files() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    for (key, tag) in [
+        ("plan", "project-x"),
+        ("notes", "project-x"),
+        ("other", "misc"),
+        ("../escaped", "misc"),
+    ] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["new", key, "-m", "original", "--tag", tag])
+            .assert()
+            .success();
+    }
+    // An "editor" that is given the directory and changes one of the files in it.
+    let editor = harness._temp_dir.path().join("dir_editor.sh");
+    fs::write(
+        &editor,
+        "#!/usr/bin/env sh\necho \"revised\" > \"$1/plan.md\"\n",
+    )?;
+    fs::set_permissions(&editor, fs::Permissions::from_mode(0o755))?;
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .env("EDITOR", &editor)
+        .args(["edit", "--bulk", "--tag", "project-x"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Updated 1 of 2 note(s)."));
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "plan"])
+        .assert()
+        .stdout(predicate::str::contains("revised"));
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["search", "revised"])
+        .assert()
+        .stdout(predicate::str::contains("plan"));
+
+    // A key with `..` gets a file inside the directory rather than next to it.
+    fs::write(
+        &editor,
+        "#!/usr/bin/env sh
+test ! -e \"$1/../escaped.md\" || exit 1
+\
+         for f in \"$1\"/__note-*.md; do echo \"inside\" > \"$f\"; done\n",
+    )?;
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .env("EDITOR", &editor)
+        .args(["edit", "--bulk", "--tag", "misc"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Updated 1 of 2 note(s)."));
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "../escaped"])
+        .assert()
+        .stdout(predicate::str::contains("inside"));
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_large_notes_round_trip_and_limit() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    let config_home = harness.db_path.with_file_name("config");
+    fs::create_dir_all(config_home.join("medi"))?;
+    let medi = || {
+        let mut cmd = Command::cargo_bin("medi").unwrap();
+        cmd.env("MEDI_DB_PATH", &harness.db_path)
+            .env("XDG_CONFIG_HOME", &config_home);
+        cmd
+    };
+    // Big enough to be stored in chunks, with multi-byte characters across chunk edges.
+    let content = format!("needle\n{}", "lorem ipsum é dolor\n".repeat(20_000));
+
+    medi()
+        .args(["new", "big-log"])
+        .write_stdin(content.clone())
+        .assert()
+        .success();
+    medi()
+        .args(["get", "big-log"])
+        .assert()
+        .success()
+        .stdout(format!("{}\n", content));
+    medi()
+        .args(["search", "needle"])
+        .assert()
+        .stdout(predicate::str::contains("big-log"));
+
+    fs::write(config_home.join("medi/config.toml"), "max_note_kb = 100\n")?;
+    medi()
+        .args(["new", "too-big"])
+        .write_stdin(content)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("over the 100 KB limit"));
+    Ok(())
+}
+
 /// The only test that uses the mock editor. This is known to be
 /// flaky in some test runners due to I/O capture conflicts.
 #[test]
@@ -102,7 +278,7 @@ fn test_new_with_editor() -> Result<(), Box<dyn std::error::Error>> {
         .args(["new", "editor-note"])
         .assert()
         .success()
-        .stdout(predicate::str::contains("Successfully created note"));
+        .stderr(predicate::str::contains("Successfully created note"));
     Ok(())
 }
 
@@ -270,7 +446,7 @@ fn test_edit_command() -> Result<(), Box<dyn std::error::Error>> {
         .args(["edit", "edit-me"])
         .assert()
         .success()
-        .stdout(predicate::str::contains("Successfully updated note"));
+        .stderr(predicate::str::contains("Successfully updated note"));
 
     Command::cargo_bin("medi")?
         .env("MEDI_DB_PATH", &harness.db_path)
@@ -352,14 +528,14 @@ fn test_import_single_file() -> Result<(), Box<dyn std::error::Error>> {
         .args([
             "import",
             "--file",
-            &import_file_path.to_string_lossy().as_ref(),
+            import_file_path.to_string_lossy().as_ref(),
             "--key",
             "imported-note",
         ])
         .assert()
         .success()
         // The assertion is now simpler and matches your actual output
-        .stdout(predicate::str::contains("Imported 'imported-note'"));
+        .stderr(predicate::str::contains("Imported 'imported-note'"));
 
     Command::cargo_bin("medi")?
         .env("MEDI_DB_PATH", &harness.db_path)
@@ -371,6 +547,56 @@ fn test_import_single_file() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[test]
+fn test_import_from_stdin_with_frontmatter() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    let piped = "---\ntitle: Piped plan\ntags: [work]\n---\nShip the importer.\n";
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["import", "--stdin", "--key", "plan"])
+        .write_stdin(piped)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Imported 'plan'"));
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["query", "tag:work"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Piped plan"));
+
+    // An existing key is skipped unless --overwrite is given, as for files.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["import", "--stdin", "--key", "plan"])
+        .write_stdin("Replaced.")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Skipped 'plan' (already exists)"));
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["import", "--stdin", "--key", "plan", "--overwrite"])
+        .write_stdin("Replaced.")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Updated 'plan'"));
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "plan"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Replaced."))
+        .stdout(predicate::str::contains("Ship the importer.").not());
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["import", "--stdin"])
+        .write_stdin("No key.")
+        .assert()
+        .failure();
+    Ok(())
+}
+
 #[test]
 fn test_import_directory() -> Result<(), Box<dyn std::error::Error>> {
     let harness = TestHarness::new();
@@ -385,9 +611,13 @@ fn test_import_directory() -> Result<(), Box<dyn std::error::Error>> {
         .args(["import", "--dir", &import_dir.to_string_lossy()])
         .assert()
         .success()
-        .stdout(
+        .stderr(
             predicate::str::contains("Imported 'import-one'")
-                .and(predicate::str::contains("Imported 'import-two'")),
+                .and(predicate::str::contains("Imported 'import-two'"))
+                // Both notes go into the search index in one commit at the end.
+                .and(predicate::str::contains(
+                    "Updated the search index for 2 note(s).",
+                )),
         );
 
     Command::cargo_bin("medi")?
@@ -397,6 +627,72 @@ fn test_import_directory() -> Result<(), Box<dyn std::error::Error>> {
         .success()
         .stdout(predicate::str::contains("content for import one"));
 
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["search", "two"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("import-two"));
+
+    Ok(())
+}
+
+#[test]
+fn test_import_directory_skips_excluded_files() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    let import_dir = harness._temp_dir.path().join("docs");
+    fs::create_dir_all(&import_dir)?;
+    for name in ["guide.md", "README.md", "draft-idea.md"] {
+        fs::write(import_dir.join(name), name)?;
+    }
+    fs::write(import_dir.join(".mediignore"), "# Repo files\nREADME.md\n")?;
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["import", "--dir", &import_dir.to_string_lossy()])
+        .args(["--exclude", "draft-*"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Imported 'guide'"))
+        .stderr(predicate::str::contains(
+            "Skipped 2 file(s) matching the exclude patterns.",
+        ));
+    for key in ["README", "draft-idea"] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["get", key])
+            .assert()
+            .failure();
+    }
+    Ok(())
+}
+
+#[test]
+fn test_import_extracts_checklist_tasks() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    let import_dir = harness._temp_dir.path().join("import_test");
+    fs::create_dir_all(&import_dir)?;
+    fs::write(
+        import_dir.join("plan.md"),
+        "# Plan\n\n- [ ] Book venue\n- [x] Pick a date\n- not a task\n",
+    )?;
+
+    for _ in 0..2 {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["import", "--dir", &import_dir.to_string_lossy()])
+            .args(["--extract-tasks", "--overwrite"])
+            .assert()
+            .success();
+    }
+
+    // Importing twice doesn't duplicate the tasks.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["task", "list", "--all", "--output", "markdown"])
+        .assert()
+        .success()
+        .stdout("- [ ] Book venue (plan)\n- [x] Pick a date (plan)\n");
     Ok(())
 }
 
@@ -425,7 +721,7 @@ fn test_export_command() -> Result<(), Box<dyn std::error::Error>> {
         .arg(&export_dir)
         .assert()
         .success()
-        .stdout(predicate::str::contains("Successfully exported 2 notes"));
+        .stderr(predicate::str::contains("Successfully exported 2 notes"));
 
     // VERIFY: Check that the files were created with the correct content.
     let note_one_path = export_dir.join("note-one.md");
@@ -526,14 +822,14 @@ fn test_task_workflow() -> Result<(), Box<dyn std::error::Error>> {
         .args(["task", "add", "task-note", "My first task"])
         .assert()
         .success()
-        .stdout(predicate::str::contains("Added new task with ID: 1"));
+        .stderr(predicate::str::contains("Added new task with ID: 1"));
 
     Command::cargo_bin("medi")?
         .env("MEDI_DB_PATH", &harness.db_path)
         .args(["task", "add", "task-note", "My second task"])
         .assert()
         .success()
-        .stdout(predicate::str::contains("Added new task with ID: 2"));
+        .stderr(predicate::str::contains("Added new task with ID: 2"));
 
     // TEST 2: List the tasks to verify they were added.
     Command::cargo_bin("medi")?
@@ -554,17 +850,24 @@ fn test_task_workflow() -> Result<(), Box<dyn std::error::Error>> {
         .args(["task", "done", "1"])
         .assert()
         .success()
-        .stdout(predicate::str::contains("Completed task: 1"));
+        .stderr(predicate::str::contains("Completed task: 1"));
 
-    // TEST 4: List tasks again to verify the first one is gone.
+    // TEST 4: List tasks again to verify the first one is gone, unless asked for.
     Command::cargo_bin("medi")?
         .env("MEDI_DB_PATH", &harness.db_path)
         .args(["task", "list"])
         .assert()
         .success()
+        .stdout(predicate::str::contains("[1]").not());
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["task", "list", "--all"])
+        .assert()
+        .success()
         .stdout(predicate::str::contains(
             "[1] [Done]: My first task (for note task-note)",
-        ));
+        ))
+        .stderr(predicate::str::contains("All tasks:"));
 
     // TEST 5: Prioritise the second task.
     Command::cargo_bin("medi")?
@@ -601,7 +904,7 @@ fn test_performance() -> Result<(), Box<dyn std::error::Error>> {
 
         // Inject the search term into one note
         if i == note_count / 2 {
-            content.push_str(" ");
+            content.push(' ');
             content.push_str(search_term);
         }
 
@@ -643,3 +946,4310 @@ fn test_performance() -> Result<(), Box<dyn std::error::Error>> {
     // Cleanup is handled automatically when `harness` goes out of scope and `TempDir` is dropped.
     Ok(())
 }
+
+#[test]
+fn test_global_db_flag_overrides_env() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    let other_db = harness._temp_dir.path().join("other_db");
+    let other_db_str = other_db.to_string_lossy().to_string();
+
+    // Create a note in the alternate vault via --db.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args([
+            "--db",
+            &other_db_str,
+            "new",
+            "vault-note",
+            "-m",
+            "in other vault",
+        ])
+        .assert()
+        .success();
+
+    // It is visible through --db ...
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "vault-note", "--db", &other_db_str])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("in other vault"));
+
+    // ... but not in the vault pointed to by MEDI_DB_PATH.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "vault-note"])
+        .assert()
+        .failure();
+
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_init_global_sets_up_templates_and_welcome_note() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    let config_home = harness.db_path.with_file_name("config");
+
+    for _ in 0..2 {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .env("XDG_CONFIG_HOME", &config_home)
+            .args(["--non-interactive", "init", "--global"])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("Quickstart:"));
+    }
+    let templates = config_home.join("medi/templates");
+    assert!(templates.join("daily.md").is_file() && templates.join("project.md").is_file());
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .env("XDG_CONFIG_HOME", &config_home)
+        .args(["backlinks", "ideas"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("welcome"));
+    // Running it again keeps the welcome note and doesn't add its tasks twice.
+    let tasks = Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .env("XDG_CONFIG_HOME", &config_home)
+        .args(["task", "list"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    assert_eq!(
+        String::from_utf8(tasks)?
+            .matches("Create your first note")
+            .count(),
+        1
+    );
+
+    // The configured editor is the one notes open in.
+    fs::write(
+        config_home.join("medi/config.toml"),
+        "editor = \"sed -i s/first/second/\"\n",
+    )?;
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .env("XDG_CONFIG_HOME", &config_home)
+        .env("VISUAL", "false")
+        .args(["edit", "welcome"])
+        .assert()
+        .success();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .env("XDG_CONFIG_HOME", &config_home)
+        .args(["get", "welcome"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Create your second note"));
+    Ok(())
+}
+
+#[test]
+fn test_init_creates_project_local_vault() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    let project_dir = harness._temp_dir.path().join("project");
+    let sub_dir = project_dir.join("src");
+    fs::create_dir_all(&sub_dir)?;
+
+    Command::cargo_bin("medi")?
+        .env_remove("MEDI_DB_PATH")
+        .current_dir(&project_dir)
+        .arg("init")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Initialised a project-local vault",
+        ));
+    assert!(project_dir.join(".medi").is_dir());
+
+    // A note created from a subdirectory lands in the project vault.
+    Command::cargo_bin("medi")?
+        .env_remove("MEDI_DB_PATH")
+        .current_dir(&sub_dir)
+        .args(["new", "local-note", "-m", "project scoped"])
+        .assert()
+        .success();
+
+    Command::cargo_bin("medi")?
+        .env_remove("MEDI_DB_PATH")
+        .current_dir(&project_dir)
+        .args(["get", "local-note"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("project scoped"));
+
+    // MEDI_DB_PATH still takes precedence over the local vault.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .current_dir(&project_dir)
+        .args(["get", "local-note"])
+        .assert()
+        .failure();
+
+    Ok(())
+}
+
+#[test]
+fn test_export_selected_keys_and_since() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    for key in ["keep-me", "also-me", "skip-me"] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["new", key, "-m", "content"])
+            .assert()
+            .success();
+    }
+
+    // Only the requested keys are exported.
+    let export_dir = harness._temp_dir.path().join("export_keys");
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .arg("export")
+        .arg(&export_dir)
+        .args(["--key", "keep-me", "--key", "also-me"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Successfully exported 2 notes"));
+    assert!(export_dir.join("keep-me.md").exists());
+    assert!(!export_dir.join("skip-me.md").exists());
+
+    // Nothing has been modified in the far future.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .arg("export")
+        .arg(harness._temp_dir.path().join("export_since"))
+        .args(["--since", "2999-01-01"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("No matching notes to export."));
+
+    Ok(())
+}
+
+#[test]
+fn test_export_combined_and_epub() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    for (key, content) in [("b-chapter", "second body"), ("a-chapter", "first body")] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["new", key, "-m", content, "--tag", "book"])
+            .assert()
+            .success();
+    }
+
+    // Combined Markdown: one file, ordered by key, a heading per note.
+    let combined_path = harness._temp_dir.path().join("book.md");
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .arg("export")
+        .arg(&combined_path)
+        .args(["--format", "combined", "--tag", "book"])
+        .assert()
+        .success();
+    let combined = fs::read_to_string(&combined_path)?;
+    assert!(combined.starts_with("# book"));
+    let first = combined.find("## a-chapter").unwrap();
+    let second = combined.find("## b-chapter").unwrap();
+    assert!(first < second);
+    assert!(combined.contains("first body"));
+
+    // EPUB: a zip whose first entry is the mimetype.
+    let epub_path = harness._temp_dir.path().join("book");
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .arg("export")
+        .arg(&epub_path)
+        .args(["--format", "epub", "--title", "My Book"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("as an EPUB"));
+    let bytes = fs::read(epub_path.with_extension("epub"))?;
+    assert_eq!(&bytes[0..2], b"PK");
+    assert_eq!(&bytes[30..38], b"mimetype");
+
+    Ok(())
+}
+
+#[test]
+fn test_import_preserve_times() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    let import_file_path = harness._temp_dir.path().join("old-note.md");
+    fs::write(&import_file_path, "written long ago")?;
+
+    // Backdate the file to 2020-01-01T00:00:00Z.
+    let old_time = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_577_836_800);
+    fs::File::options()
+        .write(true)
+        .open(&import_file_path)?
+        .set_modified(old_time)?;
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["import", "--file"])
+        .arg(&import_file_path)
+        .args(["--key", "old-note", "--preserve-times"])
+        .assert()
+        .success();
+
+    let output = Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "old-note", "--json"])
+        .output()?;
+    let note: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert!(note["modified_at"]
+        .as_str()
+        .unwrap()
+        .starts_with("2020-01-01"));
+
+    Ok(())
+}
+
+#[test]
+fn test_tag_apply_bulk() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    for (key, content) in [
+        ("tokio-intro", "Getting started with tokio"),
+        ("tokio-tips", "More tokio tricks"),
+        ("unrelated", "Nothing to see here"),
+    ] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["new", key, "-m", content])
+            .assert()
+            .success();
+    }
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["tag", "apply", "async", "--matching", "tokio"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Tagged 2 notes with #async"));
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "--tag", "async"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Getting started with tokio"))
+        .stdout(predicate::str::contains("Nothing to see here").not());
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["tag", "apply", "async", "--remove", "--keys-from-stdin"])
+        .write_stdin("tokio-intro\ntokio-tips\n")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Removed #async from 2 notes"));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn test_tag_apply_reads_keys_piped_from_list() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    for (key, tag) in [
+        ("draft-1", "draft"),
+        ("draft-2", "draft"),
+        ("done", "final"),
+    ] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["new", key, "-m", "text", "--tag", tag])
+            .assert()
+            .success();
+    }
+
+    // `medi list` still holds the database while the keys are piped on.
+    let output = std::process::Command::new("sh")
+        .arg("-c")
+        .arg("\"$MEDI\" list | grep draft | \"$MEDI\" tag apply draft --remove --keys-from-stdin")
+        .env("MEDI", assert_cmd::cargo::cargo_bin!("medi"))
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .output()?;
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(output.status.success(), "{}", stderr);
+    assert!(stderr.contains("Removed #draft from 2 notes"), "{}", stderr);
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["list", "--tag", "draft"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("draft-1").not());
+    Ok(())
+}
+
+#[test]
+fn test_replace_dry_run_and_apply() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["new", "proj", "-m", "Working on Project X today"])
+        .assert()
+        .success();
+
+    // A dry run shows the diff but writes nothing.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["replace", "Project X", "Project $1", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("+Working on Project $1 today"));
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "proj"])
+        .assert()
+        .stdout(predicate::str::contains("Project X"));
+
+    // A regex replacement with --force is applied without prompting.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["replace", r"Project (\w)", "Team $1", "--regex", "--force"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Updated 1 notes."));
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "proj"])
+        .assert()
+        .stdout(predicate::str::contains("Working on Team X today"));
+
+    Ok(())
+}
+
+#[test]
+fn test_new_slug_and_case_conflict() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+
+    // --slug normalises the key and keeps the original as the title.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["new", "Meeting Notes!", "-m", "agenda", "--slug"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Using key 'meeting-notes'"));
+
+    let output = Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "meeting-notes", "--json"])
+        .output()?;
+    let note: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(note["title"], "Meeting Notes!");
+
+    // A key differing only in case is rejected.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["new", "Meeting-Notes", "-m", "duplicate"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("differs only in case"));
+
+    Ok(())
+}
+
+#[test]
+fn test_list_prefix_and_tree() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    for key in ["work/projects/alpha", "work/todo", "home/garden"] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["new", key, "-m", "content"])
+            .assert()
+            .success();
+    }
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["list", "--prefix", "work/"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("work/todo"))
+        .stdout(predicate::str::contains("home/garden").not());
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["list", "--tree"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "work/\n  projects/\n    - alpha\n  - todo",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn test_get_meta() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args([
+            "new",
+            "medi",
+            "-m",
+            "A CLI tool built in [[rust]] with [[sled]].",
+            "--tag",
+            "cli",
+        ])
+        .assert()
+        .success();
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "medi", "--meta"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("words:"))
+        .stdout(predicate::str::contains("rust, sled"))
+        .stdout(predicate::str::contains("A CLI tool").not());
+
+    let output = Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "medi", "--meta", "--json"])
+        .output()?;
+    let meta: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(meta["word_count"], 8);
+    assert_eq!(meta["tags"], serde_json::json!(["cli"]));
+    assert!(meta.get("content").is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_get_header_and_out() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    for (key, content) in [("alpha", "First note"), ("beta", "Second note")] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["new", key, "-m", content])
+            .assert()
+            .success();
+    }
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "alpha", "beta", "--header"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "# alpha (alpha)\n\nFirst note\n\n# beta (beta)\n\nSecond note",
+        ))
+        .stdout(predicate::str::contains("---").not());
+
+    let out_path = harness.db_path.join("out.md");
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "alpha", "beta", "--out"])
+        .arg(&out_path)
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(&out_path)?,
+        "First note\n---\nSecond note\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_get_render_highlights_code() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args([
+            "new",
+            "snippet",
+            "-m",
+            "# Example\n\n```rust\nfn main() {}\n```\n",
+        ])
+        .assert()
+        .success();
+
+    // Without a terminal the code is left as plain text.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "snippet", "--render"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("fn main() {}"));
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .env("CLICOLOR_FORCE", "1")
+        .args(["get", "snippet", "--render"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b[38;2;"));
+
+    Ok(())
+}
+
+#[test]
+fn test_diff_notes_and_file() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    for (key, content) in [
+        ("draft", "line one\nline two\n"),
+        ("final", "line one\nline 2\n"),
+    ] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["new", key, "-m", content])
+            .assert()
+            .success();
+    }
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["diff", "draft", "final"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--- draft"))
+        .stdout(predicate::str::contains("+++ final"))
+        .stdout(predicate::str::contains("-line two"))
+        .stdout(predicate::str::contains("+line 2"));
+
+    let file_path = harness.db_path.join("draft.md");
+    fs::write(&file_path, "line one\nline two\n")?;
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["diff", "draft", "--file"])
+        .arg(&file_path)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("identical"));
+
+    Ok(())
+}
+
+#[test]
+fn test_query_combines_filters() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    for (key, title, content, tag) in [
+        ("async-rust", "Async Rust", "Futures and executors", "rust"),
+        (
+            "tokio-notes",
+            "Runtime notes",
+            "Using tokio in practice",
+            "rust",
+        ),
+        ("garden", "Garden", "Planting tokio tomatoes", "home"),
+    ] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["new", key, "--title", title, "-m", content, "--tag", tag])
+            .assert()
+            .success();
+    }
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args([
+            "query",
+            "tag:rust AND (title:async OR content:tokio) AND modified:>2020-06",
+            "--output",
+            "keys",
+        ])
+        .assert()
+        .success()
+        .stdout("async-rust\ntokio-notes\n");
+
+    // Bare words use the full-text index; NOT excludes.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["query", "tokio NOT tag:rust", "-o", "keys"])
+        .assert()
+        .success()
+        .stdout("garden\n");
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["query", "modified:<2020", "-o", "keys"])
+        .assert()
+        .success()
+        .stdout("");
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["query", "(tag:rust"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid query"));
+
+    Ok(())
+}
+
+#[test]
+fn test_bookmark_add_and_list() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args([
+            "bookmark",
+            "add",
+            "https://doc.rust-lang.org/book/",
+            "--title",
+            "The Rust Book",
+            "-d",
+            "The official guide",
+            "--tag",
+            "rust",
+        ])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("bookmarks/the-rust-book"));
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["new", "plain", "-m", "Not a link"])
+        .assert()
+        .success();
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["bookmark", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("https://doc.rust-lang.org/book/"))
+        .stdout(predicate::str::contains("plain").not());
+
+    let output = Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "bookmarks/the-rust-book", "--json"])
+        .output()?;
+    let note: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(note["kind"], "bookmark");
+    assert_eq!(note["url"], "https://doc.rust-lang.org/book/");
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["bookmark", "open", "plain"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("not a bookmark"));
+
+    // Titles without letters or digits need an explicit key, and keys can't differ from
+    // existing ones only in case.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["bookmark", "add", "https://example.com", "--title", "!!!"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("does not produce a usable key"));
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["bookmark", "add", "https://example.com", "--title", "x"])
+        .args(["--key", "bookmarks/The-Rust-Book"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("differs only in case"));
+
+    Ok(())
+}
+
+#[test]
+fn test_calendar_marks_journal_days() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["new", "journal/2024-06-14", "-m", "A good day"])
+        .assert()
+        .success();
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["calendar", "2024-06"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("June 2024"))
+        // June 2024 starts on a Saturday, so the 14th is the Friday of the third row.
+        .stdout(predicate::str::contains(" 10  11  12  13  14* 15  16 "));
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["calendar", "2024-06", "--day", "14"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("journal/2024-06-14 (journal)"));
+
+    Ok(())
+}
+
+#[test]
+fn test_graph_dot_output() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    for (key, content) in [
+        ("rust", "A systems language."),
+        ("medi", "Built in [[rust]], inspired by [[zettelkasten]]."),
+    ] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["new", key, "-m", content])
+            .assert()
+            .success();
+    }
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .arg("graph")
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("digraph medi {"))
+        .stdout(predicate::str::contains("\"medi\" -> \"rust\";"))
+        .stdout(predicate::str::contains(
+            "\"medi\" -> \"zettelkasten\" [style=dashed];",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn test_stats_tags() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    for (key, content, tag) in [
+        ("rust-a", "one two three", "rust"),
+        ("rust-b", "four five", "rust"),
+        ("garden", "tomatoes", "home"),
+    ] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["new", key, "-m", content, "--tag", tag])
+            .assert()
+            .success();
+    }
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["task", "add", "rust-b", "Write more"])
+        .assert()
+        .success();
+
+    let output = Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["stats", "tags"])
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    let rows: Vec<Vec<&str>> = stdout
+        .lines()
+        .skip(1)
+        .map(|line| line.split_whitespace().collect())
+        .collect();
+
+    // Busiest tag first: tag, notes, words, open tasks, newest date, newest key.
+    assert_eq!(rows[0][..4], ["#rust", "2", "5", "1"]);
+    assert_eq!(rows[0][5], "(rust-b)");
+    assert_eq!(rows[1][..4], ["#home", "1", "1", "0"]);
+
+    Ok(())
+}
+
+#[test]
+fn test_stats_growth_by_month() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    for (key, created, content) in [
+        ("jan", "2024-01-05 10:00", "one two three"),
+        ("mar", "2024-03-20 10:00", "four five"),
+    ] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["new", key])
+            .write_stdin(format!("---\ncreated: {}\n---\n{}\n", created, content))
+            .assert()
+            .success();
+    }
+
+    // February had no activity but still gets a row.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["stats", "growth", "--by", "month"])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(
+            r"2024-01 +1 +3 +0 +1\n2024-02 +0 +0 +0 +1\n2024-03 +1 +2 +0 +2\n",
+        )?);
+    Ok(())
+}
+
+#[test]
+fn test_keywords_ranks_distinctive_terms() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    for (key, content) in [
+        (
+            "async",
+            "Tokio runtime notes. Tokio tasks and tokio channels in the runtime.",
+        ),
+        ("ownership", "Borrowing and lifetimes in the runtime."),
+    ] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["new", key, "-m", content])
+            .assert()
+            .success();
+    }
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["keywords", "async", "--top", "2"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("  1. tokio"))
+        .stdout(predicate::str::contains("the").not());
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["keywords", "--all"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Keywords across all notes"))
+        .stdout(predicate::str::contains("lifetimes"));
+
+    Ok(())
+}
+
+#[test]
+fn test_list_table_sorted_by_words() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    for (key, content) in [("short", "just three words"), ("long", "a b c d e f g h")] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["new", key, "-m", content])
+            .assert()
+            .success();
+    }
+
+    let output = Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["list", "--output", "table", "--sort-by", "words"])
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    let rows: Vec<Vec<&str>> = stdout
+        .lines()
+        .skip(1)
+        .map(|line| line.split_whitespace().collect())
+        .collect();
+
+    // Largest first: key, words, reading time, age.
+    assert_eq!(rows[0][..3], ["long", "8", "1m"]);
+    assert_eq!(rows[1][..3], ["short", "3", "1m"]);
+    assert_eq!(rows[0][3], "0m");
+
+    Ok(())
+}
+
+#[test]
+fn test_count_grouped() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    for (key, content, tag) in [
+        ("a", "one two", "rust"),
+        ("b", "three", "rust"),
+        ("c", "four five six", "home"),
+    ] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["new", key, "-m", content, "--tag", tag])
+            .assert()
+            .success();
+    }
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .arg("count")
+        .assert()
+        .success()
+        .stdout("3\n");
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["count", "--group-by", "tag", "--words"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("home       1         3 words"))
+        .stdout(predicate::str::contains("rust       2         3 words"));
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["count", "--tag", "rust", "--since", "2100-01-01"])
+        .assert()
+        .success()
+        .stdout("0\n");
+
+    Ok(())
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_maintain_archives_old_notes() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    let config_home = harness._temp_dir.path().join("config");
+    fs::create_dir_all(config_home.join("medi"))?;
+    fs::write(
+        config_home.join("medi/config.toml"),
+        "[[archive]]\narchive_after_days = 365\n",
+    )?;
+
+    let import_file_path = harness._temp_dir.path().join("old-note.md");
+    fs::write(&import_file_path, "written long ago")?;
+    let old_time = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_577_836_800);
+    fs::File::options()
+        .write(true)
+        .open(&import_file_path)?
+        .set_modified(old_time)?;
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["import", "--file"])
+        .arg(&import_file_path)
+        .args(["--key", "old-note", "--preserve-times"])
+        .assert()
+        .success();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["new", "fresh-note", "-m", "written today"])
+        .assert()
+        .success();
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .env("XDG_CONFIG_HOME", &config_home)
+        .arg("maintain")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("old-note"))
+        .stdout(predicate::str::contains("fresh-note").not());
+
+    for (key, archived) in [("old-note", true), ("fresh-note", false)] {
+        let output = Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["get", key, "--json"])
+            .output()?;
+        let note: NoteTags = serde_json::from_slice(&output.stdout)?;
+        assert_eq!(note.tags.contains(&"archived".to_string()), archived);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_expired_notes_hidden_and_purged() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["new", "tmp-token", "-m", "secret agenda", "--expires", "0m"])
+        .assert()
+        .success();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args([
+            "new",
+            "keeper",
+            "-m",
+            "long lived agenda",
+            "--expires",
+            "7d",
+        ])
+        .assert()
+        .success();
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("keeper"))
+        .stdout(predicate::str::contains("tmp-token").not());
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["search", "agenda"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("tmp-token").not());
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["maintain", "--force"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("permanently deleted"))
+        .stderr(predicate::str::contains("Deleted 1 expired note(s)."));
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "tmp-token"])
+        .assert()
+        .failure();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "keeper"])
+        .assert()
+        .success();
+
+    Ok(())
+}
+
+#[test]
+fn test_new_rejects_invalid_expiry() {
+    let harness = TestHarness::new();
+    Command::cargo_bin("medi")
+        .unwrap()
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["new", "tmp", "-m", "x", "--expires", "soon"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid duration"));
+}
+
+#[test]
+fn test_remind_set_and_due() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["new", "my-note", "-m", "Something to revisit"])
+        .assert()
+        .success();
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args([
+            "remind",
+            "set",
+            "my-note",
+            "2000-01-01 09:00",
+            "-m",
+            "follow up",
+        ])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Added reminder 1 for 'my-note'"));
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["remind", "set", "my-note", "next tuesday 09:00"])
+        .assert()
+        .success();
+
+    // Only the reminder in the past is due; the list shows both.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["remind", "due"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "[1] 2000-01-01 09:00 my-note: follow up",
+        ))
+        .stdout(predicate::str::contains("[2]").not());
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["remind", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[2]"));
+
+    // Reminders are not mistaken for notes.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .arg("list")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Skipping").not());
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["remind", "set", "missing-note", "tomorrow"])
+        .assert()
+        .failure();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["remind", "set", "my-note", "someday"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("couldn't understand 'someday'"));
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["remind", "delete", "1"])
+        .assert()
+        .success();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["remind", "due"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("No reminders due."));
+    Ok(())
+}
+
+#[test]
+fn test_reserved_key_prefixes_are_refused() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["new", "reminders/x", "-m", "not a reminder"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Key 'reminders/x' is reserved"));
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["import", "--stdin", "--key", "tasks/x"])
+        .write_stdin("not a task")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Key 'tasks/x' is reserved"));
+
+    // Nothing was written that would break listing notes or reading reminders.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["new", "my-note", "-m", "Something to revisit"])
+        .assert()
+        .success();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["remind", "set", "my-note", "2000-01-01 09:00"])
+        .assert()
+        .success();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("my-note"))
+        .stdout(predicate::str::contains("reminders/x").not());
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["remind", "due"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("my-note"));
+    Ok(())
+}
+
+#[test]
+fn test_journal_and_agenda() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    let tomorrow = (chrono::Local::now() + chrono::Duration::days(1))
+        .format("%Y-%m-%d")
+        .to_string();
+    let key = format!("journal/{}", tomorrow);
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["journal", "--date", "tomorrow", "-m", "Quarterly planning"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(format!(
+            "Created journal entry: '{}'",
+            key
+        )));
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["journal", "--date", &tomorrow, "-m", "Book the room"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Updated journal entry"));
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", &key])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Quarterly planning\nBook the room\n",
+        ));
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .arg("agenda")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Nothing on the agenda").not());
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["task", "add", &key, "Send the agenda"])
+        .assert()
+        .success();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["remind", "set", &key, "tomorrow 10:30", "-m", "prepare"])
+        .assert()
+        .success();
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["agenda", "--days", "3"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(&tomorrow))
+        .stdout(predicate::str::contains(format!("journal  {}", key)))
+        .stdout(predicate::str::contains("10:30    [1]"))
+        .stdout(predicate::str::contains("task     [1] Send the agenda"));
+    Ok(())
+}
+
+#[test]
+fn test_task_add_with_natural_language_due_date() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    let in_three_days = (chrono::Local::now() + chrono::Duration::days(3))
+        .format("%Y-%m-%d")
+        .to_string();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["new", "my-note", "-m", "Some content"])
+        .assert()
+        .success();
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["task", "add", "my-note", "Ship it", "--due", "in 3 days"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(format!(
+            "Added new task with ID: 1 (due {}",
+            in_three_days
+        )));
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["task", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!("due {}", in_three_days)));
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["agenda", "--days", "7"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("task     [1] Ship it"));
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["task", "add", "my-note", "Later", "--due", "friday 25:00"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid time of day '25:00'"));
+    Ok(())
+}
+
+#[test]
+fn test_task_list_group_by() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    for (key, tag) in [("alpha", "work"), ("beta", "home")] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args([
+                "new",
+                key,
+                "--title",
+                key,
+                "-m",
+                "Some content",
+                "--tag",
+                tag,
+            ])
+            .assert()
+            .success();
+    }
+    for (key, description) in [("alpha", "First"), ("beta", "Second"), ("alpha", "Third")] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["task", "add", key, description])
+            .assert()
+            .success();
+    }
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["task", "done", "3"])
+        .assert()
+        .success();
+
+    let output = Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["task", "list", "--all", "--group-by", "note"])
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    let alpha = stdout.find("alpha (alpha) - 1 open").expect("alpha group");
+    let beta = stdout.find("beta (beta) - 1 open").expect("beta group");
+    assert!(alpha < stdout.find("[1] [Open]: First").unwrap());
+    assert!(stdout.find("[3] [Done]: Third").unwrap() < beta);
+    assert!(beta < stdout.find("[2] [Open]: Second").unwrap());
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["task", "list", "--group-by", "tag"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("#home - 1 open"))
+        .stdout(predicate::str::contains("#work - 1 open"))
+        .stdout(predicate::str::contains(
+            "[2] [Open]: Second (for note beta)",
+        ));
+    Ok(())
+}
+
+#[test]
+fn test_list_and_get_show_tasks() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    for key in ["busy", "idle"] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["new", key, "-m", "Some content"])
+            .assert()
+            .success();
+    }
+    for description in ["First", "Second", "Third"] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["task", "add", "busy", description])
+            .assert()
+            .success();
+    }
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["task", "done", "3"])
+        .assert()
+        .success();
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("- busy (2 tasks)\n- idle\n"));
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "busy", "--tasks"])
+        .assert()
+        .success()
+        .stdout("Some content\n\n## Tasks\n\n- [ ] First\n- [ ] Second\n- [x] Third\n");
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "idle", "--tasks"])
+        .assert()
+        .success()
+        .stdout("Some content\n");
+    Ok(())
+}
+
+#[test]
+fn test_task_list_output_formats() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["new", "project", "-m", "content"])
+        .assert()
+        .success();
+    for description in ["Write docs", "Ship release"] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["task", "add", "project", description])
+            .assert()
+            .success();
+    }
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["task", "done", "1"])
+        .assert()
+        .success();
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["task", "list", "--all", "--output", "markdown"])
+        .assert()
+        .success()
+        .stdout("- [ ] Ship release (project)\n- [x] Write docs (project)\n");
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["task", "list", "--output", "markdown", "--group-by", "note"])
+        .assert()
+        .success()
+        .stdout("## project\n\n- [ ] Ship release\n");
+
+    let output = Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["task", "list", "--output", "json"])
+        .output()?;
+    let tasks: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(tasks.as_array().map(Vec::len), Some(1));
+    assert_eq!(tasks[0]["description"], "Ship release");
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["task", "list", "--output", "table"])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(r"ID\s+STATUS\s+DUE\s+NOTE\s+DESCRIPTION").unwrap())
+        .stdout(predicate::str::is_match(r"2\s+open\s+project\s+Ship release").unwrap());
+    Ok(())
+}
+
+#[test]
+fn test_task_done_with_resolution() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["new", "release", "-m", "Release checklist"])
+        .assert()
+        .success();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["task", "add", "release", "Tag the release"])
+        .assert()
+        .success();
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["task", "done", "1", "-m", "shipped in v1.2"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Completed task: 1"));
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["task", "list", "--all"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "[1] [Done]: Tag the release (for note release) — shipped in v1.2",
+        ));
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .arg("status")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("0 open (0 priority), 1 done"))
+        .stdout(predicate::str::contains("Recently resolved:"))
+        .stdout(predicate::str::contains("— shipped in v1.2"));
+    Ok(())
+}
+
+#[test]
+fn test_task_export_and_import_between_vaults() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    let other_db = harness._temp_dir.path().join("other_db");
+    let backup = harness._temp_dir.path().join("tasks.json");
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["new", "release", "-m", "Release checklist"])
+        .assert()
+        .success();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["task", "add", "release", "Tag the release"])
+        .assert()
+        .success();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["task", "export", "--json", &backup.to_string_lossy()])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Exported 1 task(s)"));
+
+    // The other vault has no `release` note, and a second import adds nothing.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &other_db)
+        .args(["task", "import", &backup.to_string_lossy()])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Imported 1 task(s) (0 already present).",
+        ))
+        .stderr(predicate::str::contains(
+            "Some tasks belong to notes that aren't in this vault: release",
+        ));
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &other_db)
+        .args(["task", "import", &backup.to_string_lossy()])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Imported 0 task(s) (1 already present).",
+        ));
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &other_db)
+        .args(["task", "export", "--json", "-"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "\"description\": \"Tag the release\"",
+        ));
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_dates_shown_in_configured_time_zone() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    let config_home = harness.db_path.with_file_name("config");
+    fs::create_dir_all(config_home.join("medi"))?;
+    fs::write(
+        config_home.join("medi/config.toml"),
+        "timezone = \"Asia/Tokyo\"\n",
+    )?;
+    let medi = || {
+        let mut cmd = Command::cargo_bin("medi").unwrap();
+        cmd.env("MEDI_DB_PATH", &harness.db_path)
+            .env("XDG_CONFIG_HOME", &config_home);
+        cmd
+    };
+    medi()
+        .args(["new", "release", "-m", "Release checklist"])
+        .assert()
+        .success();
+    medi()
+        .args([
+            "task",
+            "add",
+            "release",
+            "Tag the release",
+            "--due",
+            "2030-01-01T09:00:00Z",
+        ])
+        .assert()
+        .success();
+
+    medi()
+        .args(["task", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("due 2030-01-01 18:00"));
+    // Dates typed on the command line are read in the configured zone too.
+    medi()
+        .args([
+            "task",
+            "add",
+            "release",
+            "Publish the notes",
+            "--due",
+            "2030-02-01 09:00",
+        ])
+        .assert()
+        .success();
+    medi()
+        .args(["task", "list", "--utc"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("due 2030-01-01 09:00"))
+        .stdout(predicate::str::contains("due 2030-02-01 00:00"));
+    medi()
+        .args(["status", "release"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(" JST"));
+
+    // 20:00 UTC is already the next day in Tokyo.
+    medi()
+        .args(["new", "late"])
+        .write_stdin("---\ncreated: 2030-01-01T20:00:00Z\ntags: [night]\n---\nWritten late.")
+        .assert()
+        .success();
+    medi()
+        .args(["stats", "tags"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("2030-01-02 (late)"));
+    medi()
+        .args(["stats", "tags", "--utc"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("2030-01-01 (late)"));
+    Ok(())
+}
+
+#[test]
+fn test_task_snooze_hides_until_expiry() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["new", "garden", "-m", "Garden plans"])
+        .assert()
+        .success();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["task", "add", "garden", "Plant tomatoes"])
+        .assert()
+        .success();
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["task", "snooze", "1", "3d"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Snoozed task 1 until"));
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["task", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Plant tomatoes").not());
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["task", "list", "--all"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Plant tomatoes (for note garden) snoozed until",
+        ));
+
+    // Snoozing until now wakes the task up again.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["task", "snooze", "1", "now"])
+        .assert()
+        .success();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["task", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[1] [Open]: Plant tomatoes"));
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["task", "snooze", "7", "3d"])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+#[test]
+fn test_status_prompt_is_one_plain_line() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    for key in ["one", "two"] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["new", key, "-m", "Some content"])
+            .assert()
+            .success();
+    }
+    for description in ["First", "Second", "Third"] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["task", "add", "one", description])
+            .assert()
+            .success();
+    }
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["task", "done", "1"])
+        .assert()
+        .success();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["task", "prio", "2"])
+        .assert()
+        .success();
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .env("CLICOLOR_FORCE", "1")
+        .args(["status", "--prompt"])
+        .assert()
+        .success()
+        .stdout("📝 2 ✅ 1/3 ⭐1\n");
+
+    // The counts are cached, and writes that change them are seen on the next prompt.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["delete", "two", "--force"])
+        .assert()
+        .success();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["task", "done", "2"])
+        .assert()
+        .success();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["status", "--prompt"])
+        .assert()
+        .success()
+        .stdout("📝 1 ✅ 2/3 ⭐0\n");
+    Ok(())
+}
+
+#[test]
+fn test_export_anki_flashcards() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    let content = "Q: Capital of France?\nA: Paris\n\nThe capital of Italy is {{c1::Rome}}.\n";
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args([
+            "new",
+            "capitals",
+            "-m",
+            content,
+            "--tag",
+            "flashcard",
+            "--tag",
+            "geo",
+        ])
+        .assert()
+        .success();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["new", "untagged", "-m", "Q: Ignored?\nA: Yes"])
+        .assert()
+        .success();
+
+    let path = harness._temp_dir.path().join("cards");
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["export", path.to_str().unwrap(), "--format", "anki"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Exported 2 flashcards from 1 notes",
+        ));
+
+    let tsv = fs::read_to_string(path.with_extension("tsv"))?;
+    assert!(tsv.starts_with("#separator:tab\n"));
+    assert!(tsv.contains("Basic\tCapital of France?\tParis\tgeo\n"));
+    assert!(tsv.contains("Cloze\tThe capital of Italy is {{c1::Rome}}.\t\tgeo\n"));
+    assert!(!tsv.contains("Ignored"));
+    Ok(())
+}
+
+#[test]
+fn test_review_due_lists_new_items() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args([
+            "new",
+            "capitals",
+            "-m",
+            "Q: Capital of France?\nA: Paris\n\nThe capital of Italy is {{c1::Rome}}.",
+            "--tag",
+            "flashcard",
+        ])
+        .assert()
+        .success();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args([
+            "new",
+            "Ownership rules",
+            "-m",
+            "Each value has one owner.",
+            "--tag",
+            "review",
+        ])
+        .assert()
+        .success();
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["review", "--due"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("3 item(s) due for review:"))
+        .stdout(predicate::str::contains(
+            "[new] capitals Capital of France?",
+        ))
+        .stdout(predicate::str::contains("The capital of Italy is [...]."))
+        .stdout(predicate::str::contains("Ownership rules"));
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["review", "--due", "--tag", "flashcard", "--limit", "1"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("1 item(s) due for review:"));
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["review", "--due", "--tag", "missing"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Nothing to review."));
+    Ok(())
+}
+
+#[test]
+fn test_export_textbundle_copies_assets() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    let attachments = harness.db_path.join("attachments");
+    fs::create_dir_all(&attachments)?;
+    let image = attachments.join("diagram.png");
+    fs::write(&image, b"png bytes")?;
+    let secret = harness._temp_dir.path().join("secret.txt");
+    fs::write(&secret, b"private")?;
+    let content = format!(
+        "![Diagram]({})\n\nSee [the docs](https://example.com) and [missing](nowhere.png).\n\n\
+         [Again](diagram.png) [key]({}) [up](../../secret.txt)",
+        image.display(),
+        secret.display()
+    );
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["new", "design", "-m", &content])
+        .assert()
+        .success();
+
+    let export_dir = harness._temp_dir.path().join("bundles");
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .arg("export")
+        .arg(&export_dir)
+        .args(["--format", "textbundle"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("(1 assets)"));
+
+    let bundle = export_dir.join("design.textbundle");
+    let text = fs::read_to_string(bundle.join("text.markdown"))?;
+    assert!(text.starts_with("![Diagram](assets/diagram.png)"));
+    assert!(text.contains("(https://example.com)"));
+    assert!(text.contains("(nowhere.png)"));
+    assert!(text.contains("[Again](assets/diagram.png)"));
+    assert!(text.contains(&format!("[key]({})", secret.display())));
+    assert!(text.contains("[up](../../secret.txt)"));
+    assert_eq!(fs::read(bundle.join("assets/diagram.png"))?, b"png bytes");
+    assert_eq!(fs::read_dir(bundle.join("assets"))?.count(), 1);
+    let info: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(bundle.join("info.json"))?)?;
+    assert_eq!(info["version"], 2);
+
+    Ok(())
+}
+
+#[test]
+fn test_import_dayone_export() -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::Write;
+
+    let harness = TestHarness::new();
+    let export_path = harness._temp_dir.path().join("dayone.zip");
+    let journal = r##"{"metadata": {"version": "1.0"}, "entries": [
+        {"uuid": "A1", "creationDate": "2021-06-15T12:00:00Z", "text": "# Hiking\n\nGreat day\\.\n\n![](dayone-moment://PHOTO1)",
+         "tags": ["outdoors"], "location": {"placeName": "Kebnekaise", "country": "Sweden", "latitude": 67.9, "longitude": 18.5},
+         "weather": {"conditionsDescription": "Sunny", "temperatureCelsius": 14.2},
+         "photos": [{"identifier": "PHOTO1", "md5": "abc123", "type": "jpeg"}]},
+        {"uuid": "A2", "creationDate": "2021-06-15T13:00:00Z", "text": "Second entry"}
+    ]}"##;
+    let mut zip = zip::ZipWriter::new(fs::File::create(&export_path)?);
+    let options = zip::write::SimpleFileOptions::default();
+    zip.start_file("Journal.json", options)?;
+    zip.write_all(journal.as_bytes())?;
+    zip.start_file("photos/abc123.jpeg", options)?;
+    zip.write_all(b"jpeg bytes")?;
+    zip.finish()?;
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .arg("import")
+        .arg("--dayone")
+        .arg(&export_path)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Imported 2 Day One entries"));
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "journal/2021-06-15", "--meta"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Hiking"))
+        .stdout(predicate::str::contains("journal, outdoors"))
+        .stdout(predicate::str::contains("Kebnekaise, Sweden"))
+        .stdout(predicate::str::contains("Sunny, 14°C"));
+
+    let photo = harness.db_path.join("attachments/dayone/abc123.jpeg");
+    assert_eq!(fs::read(&photo)?, b"jpeg bytes");
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "journal/2021-06-15"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Great day."))
+        .stdout(predicate::str::contains(photo.display().to_string()));
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "journal/2021-06-15-2"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Second entry"));
+
+    // Importing the same export again skips the entries.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .arg("import")
+        .arg("--dayone")
+        .arg(&export_path)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Imported 0 Day One entries (2 already imported)",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn test_import_google_keep_takeout() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    let keep_dir = harness._temp_dir.path().join("Keep");
+    fs::create_dir_all(&keep_dir)?;
+    fs::write(
+        keep_dir.join("Groceries.json"),
+        r#"{"title": "Groceries", "isPinned": true, "isArchived": false, "isTrashed": false,
+            "userEditedTimestampUsec": 1600000000000000, "labels": [{"name": "home"}],
+            "listContent": [{"text": "Milk", "isChecked": false}, {"text": "Bread", "isChecked": true}]}"#,
+    )?;
+    fs::write(
+        keep_dir.join("Old idea.json"),
+        r#"{"title": "", "textContent": "Old idea\nworth keeping", "isArchived": true,
+            "userEditedTimestampUsec": 1500000000000000}"#,
+    )?;
+    fs::write(
+        keep_dir.join("Deleted.json"),
+        r#"{"title": "Deleted", "textContent": "gone", "isTrashed": true}"#,
+    )?;
+    fs::write(
+        keep_dir.join("Legacy.html"),
+        r#"<html><head><title>Legacy note</title></head><body><div class="note">
+            <div class="content">First line<br>second line</div>
+            <div class="labels"><span class="label-name">old</span></div></div></body></html>"#,
+    )?;
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .arg("import")
+        .arg("--keep")
+        .arg(&keep_dir)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Imported 3 Keep notes with 2 tasks (0 already imported)",
+        ));
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "groceries", "--meta"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("home, pinned"));
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "groceries"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("- [ ] Milk\n- [x] Bread"));
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["task", "list", "--all"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Milk"))
+        .stdout(predicate::str::contains("Bread"));
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "old-idea", "--meta"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("archived"));
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "legacy-note"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("First line\nsecond line"));
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "deleted"])
+        .assert()
+        .failure();
+
+    Ok(())
+}
+
+#[test]
+fn test_import_joplin_jex() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    let id = |c: char| c.to_string().repeat(32);
+    let items = [
+        format!("Work\n\nid: {}\nparent_id: \ntype_: 2", id('a')),
+        format!("Meetings\n\nid: {}\nparent_id: {}\ntype_: 2", id('b'), id('a')),
+        format!(
+            "Standup\n\nSee [ideas](:/{}) and ![chart](:/{}).\n\nid: {}\nparent_id: {}\nupdated_time: 2022-03-01T09:30:00.000Z\ntype_: 1",
+            id('d'),
+            id('e'),
+            id('c'),
+            id('b')
+        ),
+        format!("Ideas\n\nSome ideas.\n\nid: {}\nparent_id: {}\ntype_: 1", id('d'), id('a')),
+        format!("chart.png\n\nid: {}\nfile_extension: png\ntype_: 4", id('e')),
+        format!("team\n\nid: {}\ntype_: 5", id('f')),
+        format!("id: 11111111111111111111111111111111\nnote_id: {}\ntag_id: {}\ntype_: 6", id('c'), id('f')),
+    ];
+    let jex_path = harness._temp_dir.path().join("notes.jex");
+    let mut builder = tar::Builder::new(fs::File::create(&jex_path)?);
+    let mut append = |name: String, bytes: &[u8]| -> std::io::Result<()> {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(bytes.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, name, bytes)
+    };
+    for (i, item) in items.iter().enumerate() {
+        append(format!("{:032}.md", i), item.as_bytes())?;
+    }
+    append(format!("resources/{}.png", id('e')), b"png bytes")?;
+    builder.finish()?;
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .arg("import")
+        .arg("--joplin")
+        .arg(&jex_path)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Imported 2 Joplin notes"));
+
+    let attachment = harness
+        .db_path
+        .join("attachments/joplin")
+        .join(format!("{}.png", id('e')));
+    assert_eq!(fs::read(&attachment)?, b"png bytes");
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "work/meetings/standup"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("See [[work/ideas]] and ![chart]("))
+        .stdout(predicate::str::contains(attachment.display().to_string()));
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "work/meetings/standup", "--meta"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("team"))
+        .stdout(predicate::str::contains("2022-03-01 09:30"));
+
+    Ok(())
+}
+
+#[test]
+fn test_export_markdown_links_and_import_them_back() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    for (key, content) in [("rust", "A language."), ("work/plan", "Learn [[rust]].")] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["new", key, "-m", content])
+            .assert()
+            .success();
+    }
+
+    let export_dir = harness._temp_dir.path().join("site");
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .arg("export")
+        .arg(&export_dir)
+        .args(["--links", "markdown"])
+        .assert()
+        .success();
+    let plan = fs::read_to_string(export_dir.join("work/plan.md"))?;
+    assert!(plan.contains("Learn [rust](../rust.md)."));
+
+    // Relative .md links become wikilinks again on import.
+    let import_path = harness._temp_dir.path().join("todo.md");
+    fs::write(
+        &import_path,
+        "Read [the plan](work/plan.md) and [docs](https://x.org/a.md).",
+    )?;
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .arg("import")
+        .arg("--file")
+        .arg(&import_path)
+        .args(["--key", "todo"])
+        .assert()
+        .success();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "todo"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Read [[work/plan]] and [docs](https://x.org/a.md).",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn test_export_changed_writes_only_changes() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    for key in ["alpha", "beta", "gamma"] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["new", key, "-m", key])
+            .assert()
+            .success();
+    }
+    let export_dir = harness._temp_dir.path().join("site");
+    let export = |expected: &str| {
+        Command::cargo_bin("medi")
+            .unwrap()
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .arg("export")
+            .arg(&export_dir)
+            .arg("--changed")
+            .assert()
+            .success()
+            .stderr(predicate::str::contains(expected.to_string()));
+    };
+    export("Exported 3 changed of 3 notes");
+    export("Exported 0 changed of 3 notes");
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["import", "--stdin", "--key", "alpha", "--overwrite"])
+        .write_stdin("alpha, revised")
+        .assert()
+        .success();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["delete", "beta", "--force"])
+        .assert()
+        .success();
+    export("Exported 1 changed of 2 notes as Markdown to");
+    assert_eq!(
+        fs::read_to_string(export_dir.join("alpha.md"))?,
+        "alpha, revised"
+    );
+    assert!(!export_dir.join("beta.md").exists());
+    assert!(export_dir.join("gamma.md").exists());
+
+    // A file deleted by hand is written again.
+    fs::remove_file(export_dir.join("gamma.md"))?;
+    export("Exported 1 changed of 2 notes");
+    assert!(export_dir.join("gamma.md").exists());
+
+    // A filtered export leaves the files of the notes it skips alone.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .arg("export")
+        .arg(&export_dir)
+        .args(["--changed", "--key", "alpha"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("removing 0 file(s)"));
+    assert!(export_dir.join("gamma.md").exists());
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["delete", "gamma", "--force"])
+        .assert()
+        .success();
+    export("Exported 0 changed of 1 notes as Markdown to");
+    assert!(!export_dir.join("gamma.md").exists());
+    Ok(())
+}
+
+#[test]
+fn test_export_reports_unresolved_links() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    for (key, tags, content) in [
+        ("rust", "lang", "A language."),
+        (
+            "plan",
+            "work",
+            "Learn [[rust]] and [[ghost]], with [[todo]].",
+        ),
+        ("todo", "work", "Nothing yet."),
+    ] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["new", key, "--tag", tags, "-m", content])
+            .assert()
+            .success();
+    }
+
+    let export_dir = harness._temp_dir.path().join("site");
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .arg("export")
+        .arg(&export_dir)
+        .args(["--tag", "work", "--links", "markdown"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("2 link(s) couldn't be converted"))
+        .stderr(predicate::str::contains("plan -> [[rust]] (not exported)"))
+        .stderr(predicate::str::contains("plan -> [[ghost]] (missing)"));
+    let plan = fs::read_to_string(export_dir.join("plan.md"))?;
+    assert!(plan.contains("[[rust]]") && plan.contains("[todo](todo.md)"));
+
+    // --strict fails before writing anything.
+    let strict_dir = harness._temp_dir.path().join("strict");
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .arg("export")
+        .arg(&strict_dir)
+        .args(["--tag", "work", "--links", "markdown", "--strict"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "2 link(s) in the export couldn't be resolved",
+        ));
+    assert!(!strict_dir.exists());
+
+    Ok(())
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_export_filename_template() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    let config_home = harness._temp_dir.path().join("config");
+    fs::create_dir_all(config_home.join("medi"))?;
+    fs::write(
+        config_home.join("medi/config.toml"),
+        "export_filename = \"posts/{date}-{slug}.md\"\n",
+    )?;
+    for (key, title) in [("a", "Hello World"), ("b", "Hello World")] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["new", key, "--title", title, "-m", key])
+            .assert()
+            .success();
+    }
+
+    let export_dir = harness._temp_dir.path().join("site");
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .env("XDG_CONFIG_HOME", &config_home)
+        .arg("export")
+        .arg(&export_dir)
+        .assert()
+        .success();
+    let today = chrono::Utc::now().format("%Y-%m-%d");
+    let first = export_dir.join(format!("posts/{}-hello-world.md", today));
+    let second = export_dir.join(format!("posts/{}-hello-world-2.md", today));
+    assert_eq!(fs::read_to_string(first)?, "a");
+    assert_eq!(fs::read_to_string(second)?, "b");
+
+    fs::write(
+        config_home.join("medi/config.toml"),
+        "export_filename = \"{author}.md\"\n",
+    )?;
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .env("XDG_CONFIG_HOME", &config_home)
+        .arg("export")
+        .arg(&export_dir)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unknown field '{author}'"));
+
+    Ok(())
+}
+
+#[test]
+fn test_snapshot_links_saves_text_and_marks_dead_links() -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::{Read, Write};
+
+    // A tiny web server with one page; everything else is a 404.
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { continue };
+            let mut request = [0u8; 1024];
+            let read = stream.read(&mut request).unwrap_or(0);
+            let request = String::from_utf8_lossy(&request[..read]);
+            let response = if request.starts_with("GET /article ") {
+                let body = "<html><head><title>An Article</title><script>var x;</script></head>\
+                    <body><nav>Menu</nav><p>First &amp; foremost.</p><p>Second paragraph.</p></body></html>";
+                format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                )
+            } else {
+                "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                    .to_string()
+            };
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+
+    let harness = TestHarness::new();
+    let content = format!(
+        "Read http://{addr}/article and <http://{addr}/gone>, then http://{addr}/article again."
+    );
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["new", "reading", "-m", &content])
+        .assert()
+        .success();
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["snapshot-links", "reading"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Snapshotted 1 links in 'reading' (1 dead)",
+        ));
+
+    let snapshot = fs::read_dir(harness.db_path.join("attachments/snapshots/reading"))?
+        .next()
+        .unwrap()?
+        .path();
+    let text = fs::read_to_string(&snapshot)?;
+    assert!(text.contains("# An Article\n\nFirst & foremost.\n\nSecond paragraph."));
+    assert!(!text.contains("Menu") && !text.contains("var x"));
+
+    let output = Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "reading"])
+        .output()?;
+    let note = String::from_utf8(output.stdout)?;
+    assert!(note.contains(&snapshot.display().to_string()));
+    assert!(note.contains("**dead link** (404 Not Found)"));
+
+    // Running it again replaces the section instead of adding another.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["snapshot-links", "reading"])
+        .assert()
+        .success();
+    let output = Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "reading"])
+        .output()?;
+    assert_eq!(
+        String::from_utf8(output.stdout)?
+            .matches("## Link snapshots")
+            .count(),
+        1
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_status_goes_to_stderr_and_color_flag() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["new", "alpha", "-m", "First", "--tag", "rust"])
+        .assert()
+        .success()
+        .stdout("")
+        .stderr(predicate::str::contains("Successfully created note"));
+
+    // Only the data reaches stdout, so it can be piped.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["list", "--color", "never"])
+        .assert()
+        .success()
+        .stdout("- alpha [#rust]\n")
+        .stderr(predicate::str::contains("Notes:"));
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["list", "--color", "always"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b[").and(predicate::str::contains("alpha")));
+    Ok(())
+}
+
+#[test]
+fn test_verbose_logs_diagnostics() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["new", "alpha", "-m", "First"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("opened database").not());
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["-v", "search", "first"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("alpha"))
+        .stderr(predicate::str::contains("opened database"))
+        .stderr(predicate::str::contains("searched index"));
+
+    let log = harness.db_path.with_file_name("medi.log");
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["reindex", "-vv", "--log-file"])
+        .arg(&log)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("indexing").not());
+    let logged = fs::read_to_string(&log)?;
+    assert!(logged.contains("committed index"));
+    assert!(logged.contains("indexing"));
+    Ok(())
+}
+
+#[test]
+fn test_bench_reports_each_operation() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["bench", "--notes", "20"])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(
+            r"(?s)create .*ms.*list .*search .*-\n.*reindex .*export .*µs\n$",
+        )?);
+    // The benchmark never touches the real vault.
+    assert!(!harness.db_path.exists());
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_config_errors_and_validate() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    let config_home = harness.db_path.with_file_name("config");
+    fs::create_dir_all(config_home.join("medi"))?;
+    let config_file = config_home.join("medi").join("config.toml");
+
+    // A bad value is an error naming the line, not a panic.
+    fs::write(&config_file, "zk_ids = false\nslugify_keys = \"yes\"\n")?;
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .env("XDG_CONFIG_HOME", &config_home)
+        .arg("list")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("line 2: `slugify_keys = \"yes\"`"))
+        .stderr(predicate::str::contains("expected a boolean"))
+        .stderr(predicate::str::contains("panicked").not());
+    Command::cargo_bin("medi")?
+        .env("XDG_CONFIG_HOME", &config_home)
+        .args(["config", "validate"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("expected a boolean"));
+
+    fs::write(
+        &config_file,
+        "slugify_key = true\n[theme]\nkey = \"chartreuse\"\n",
+    )?;
+    Command::cargo_bin("medi")?
+        .env("XDG_CONFIG_HOME", &config_home)
+        .args(["config", "validate"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "unknown setting 'slugify_key' (line 1)",
+        ))
+        .stderr(predicate::str::contains(
+            "theme.key = 'chartreuse' (line 3)",
+        ))
+        .stderr(predicate::str::contains("2 problem(s)"));
+
+    fs::write(&config_file, "slugify_keys = true\n")?;
+    Command::cargo_bin("medi")?
+        .env("XDG_CONFIG_HOME", &config_home)
+        .args(["config", "validate"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("is valid"));
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_index_heap_is_configurable() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    let config_home = harness.db_path.with_file_name("config");
+    let config_file = config_home.join("medi/config.toml");
+    fs::create_dir_all(config_file.parent().unwrap())?;
+
+    fs::write(&config_file, "index_heap_mb = 5\n")?;
+    Command::cargo_bin("medi")?
+        .env("XDG_CONFIG_HOME", &config_home)
+        .args(["config", "validate"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "index_heap_mb = 5 (line 1): the search index needs at least 15 MB",
+        ));
+
+    fs::write(&config_file, "index_heap_mb = 20\n")?;
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .env("XDG_CONFIG_HOME", &config_home)
+        .args(["new", "heap", "-m", "A small wombat."])
+        .assert()
+        .success();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .env("XDG_CONFIG_HOME", &config_home)
+        .arg("reindex")
+        .assert()
+        .success();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .env("XDG_CONFIG_HOME", &config_home)
+        .args(["search", "wombat"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("heap"));
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_config_defaults_fill_in_unset_options() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    let config_home = harness.db_path.with_file_name("config");
+    fs::create_dir_all(config_home.join("medi"))?;
+    fs::write(
+        config_home.join("medi").join("config.toml"),
+        "[defaults]\nnew.tags = [\"inbox\"]\n",
+    )?;
+    let medi = || {
+        let mut cmd = Command::cargo_bin("medi").unwrap();
+        cmd.env("MEDI_DB_PATH", &harness.db_path)
+            .env("XDG_CONFIG_HOME", &config_home);
+        cmd
+    };
+
+    medi().args(["new", "plain", "-m", "x"]).assert().success();
+    medi()
+        .args(["new", "tagged", "-m", "y", "--tag", "work"])
+        .assert()
+        .success();
+
+    let tags = |key: &str| -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let output = medi().args(["get", key, "--json"]).output()?;
+        Ok(serde_json::from_slice::<NoteTags>(&output.stdout)?.tags)
+    };
+    assert_eq!(tags("plain")?, vec!["inbox"]);
+    // An explicit --tag replaces the default.
+    assert_eq!(tags("tagged")?, vec!["work"]);
+
+    fs::write(
+        config_home.join("medi").join("config.toml"),
+        "[defaults]\nlist.sortby = \"modified\"\n",
+    )?;
+    medi()
+        .args(["config", "validate"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "defaults.list.sortby is not an option of 'medi list'",
+        ));
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_migrate_paths_moves_legacy_database() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    let home = harness.db_path.with_file_name("home");
+    let legacy = home.join(".medi").join("medi_db");
+    let config_dir = home.join("config").join("medi");
+    fs::create_dir_all(&config_dir)?;
+    // An old config without db_path, so medi falls back to ~/.medi/medi_db.
+    fs::write(
+        config_dir.join("config.toml"),
+        "# my settings\nzk_ids = false\n",
+    )?;
+    let medi = || {
+        let mut cmd = Command::cargo_bin("medi").unwrap();
+        cmd.env_remove("MEDI_DB_PATH")
+            .env("HOME", &home)
+            .env("XDG_CONFIG_HOME", home.join("config"))
+            .env("XDG_DATA_HOME", home.join("data"))
+            .current_dir(&home);
+        cmd
+    };
+    fs::create_dir_all(&home)?;
+    medi()
+        .args(["new", "old-note", "-m", "Written before the move"])
+        .assert()
+        .success();
+    assert!(legacy.is_dir());
+
+    medi()
+        .args(["migrate-paths", "--dry-run"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Would move the database"));
+    assert!(legacy.is_dir());
+
+    medi().arg("migrate-paths").assert().success();
+    let moved = home.join("data").join("medi_db");
+    assert!(!legacy.exists());
+    assert!(moved.join("search_index").is_dir());
+    let config = fs::read_to_string(config_dir.join("config.toml"))?;
+    assert!(config.starts_with(&format!("db_path = \"{}\"", moved.display())));
+    assert!(config.contains("# my settings"));
+
+    medi()
+        .args(["search", "move"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("old-note"));
+    medi()
+        .arg("migrate-paths")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("nothing to migrate"));
+    Ok(())
+}
+
+#[test]
+fn test_tag_filters_match_all_or_any() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    for (key, tags) in [
+        ("rusty", vec!["rust"]),
+        ("gopher", vec!["go"]),
+        ("polyglot", vec!["rust", "go"]),
+    ] {
+        let mut args = vec!["new", key, "-m", key];
+        for tag in tags {
+            args.extend(["--tag", tag]);
+        }
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(&args)
+            .assert()
+            .success();
+    }
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "--tag", "rust", "--tag", "go"])
+        .assert()
+        .success()
+        .stdout("polyglot\n");
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["count", "--tag", "rust", "--tag", "go", "--match", "any"])
+        .assert()
+        .success()
+        .stdout("3\n");
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["count", "--tag", "rust", "--tag", "go"])
+        .assert()
+        .success()
+        .stdout("1\n");
+    Ok(())
+}
+
+#[test]
+fn test_delete_several_notes() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    for key in ["one", "two", "three"] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["new", key, "-m", "content"])
+            .assert()
+            .success();
+    }
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["task", "add", "one", "a task"])
+        .assert()
+        .success();
+
+    // A missing key is reported and the rest are still deleted.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["delete", "one", "missing", "two", "--force"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Skipped 'missing': note not found.",
+        ))
+        .stderr(predicate::str::contains("Deleted 1 associated task(s)."))
+        .stderr(predicate::str::contains("Successfully deleted note: 'one'"))
+        .stderr(predicate::str::contains("Successfully deleted note: 'two'"));
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("three"))
+        .stdout(predicate::str::contains("one").not())
+        .stdout(predicate::str::contains("two").not());
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["search", "content"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("three"))
+        .stdout(predicate::str::contains("one").not());
+
+    // Nothing to delete at all is still an error.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["delete", "missing", "--force"])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+#[test]
+fn test_edit_create_missing_note() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+
+    Command::cargo_bin("medi")?
+        .env("EDITOR", &harness.editor_script_path)
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["edit", "fresh-note"])
+        .assert()
+        .failure();
+
+    Command::cargo_bin("medi")?
+        .env("EDITOR", &harness.editor_script_path)
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["edit", "fresh-note", "--create", "--add-tag", "draft"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Successfully created note: 'fresh-note'",
+        ));
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "fresh-note", "--json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("integration test content"))
+        .stdout(predicate::str::contains("draft"));
+    Ok(())
+}
+
+#[test]
+fn test_locked_notes_refuse_changes() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    for key in ["reference", "scratch"] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["new", key, "-m", "shared words"])
+            .assert()
+            .success();
+    }
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["lock", "reference"])
+        .assert()
+        .success();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "reference", "--meta"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("locked:"));
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["edit", "reference", "--add-tag", "rust"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Note 'reference' is locked"));
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["delete", "reference"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Note 'reference' is locked"));
+
+    // Bulk operations skip it and carry on with the rest.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["tag", "apply", "rust", "--matching", "shared"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Skipped 'reference': note is locked.",
+        ))
+        .stderr(predicate::str::contains("Tagged 1 notes with #rust"));
+
+    // --force overrides the lock.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["edit", "reference", "--add-tag", "rust", "--force"])
+        .assert()
+        .success();
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["unlock", "reference"])
+        .assert()
+        .success();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["edit", "reference", "--rm-tag", "rust"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Successfully updated tags"));
+    Ok(())
+}
+
+#[test]
+fn test_locked_notes_refuse_every_write_path() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    let medi = || {
+        let mut cmd = Command::cargo_bin("medi").unwrap();
+        cmd.env("MEDI_DB_PATH", &harness.db_path);
+        cmd
+    };
+    let original = "The borrow checker tracks references. https://example.invalid/page";
+    medi()
+        .args(["new", "reference", "-m", original])
+        .assert()
+        .success();
+    for (key, content) in [
+        (
+            "borrowing",
+            "The borrow checker enforces ownership of references.",
+        ),
+        (
+            "lifetimes",
+            "Lifetimes tell the borrow checker how long references live.",
+        ),
+    ] {
+        medi()
+            .args(["new", key, "-m", content, "--tag", "rust"])
+            .assert()
+            .success();
+    }
+    medi().args(["moc", "rust"]).assert().success();
+    medi().args(["capture", "A scrap"]).assert().success();
+    medi()
+        .args(["journal", "--date", "2024-06-14", "-m", "First entry"])
+        .assert()
+        .success();
+    let dump_dir = harness._temp_dir.path().join("dump");
+    medi()
+        .arg("export")
+        .arg(&dump_dir)
+        .args(["--format", "raw-dump", "--key", "reference"])
+        .assert()
+        .success();
+    let output = medi().args(["get", "reference", "--json"]).output()?;
+    let mut record: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    record["content"] = "Replaced by the import.".into();
+
+    for key in ["reference", "moc/rust", "inbox", "journal/2024-06-14"] {
+        medi().args(["lock", key]).assert().success();
+    }
+
+    // Commands that change one note fail, whether it's edited in place or in the editor.
+    let refused: [&[&str]; 7] = [
+        &["link", "reference", "borrowing"],
+        &["snapshot-links", "reference"],
+        &["suggest-tags", "reference", "--apply"],
+        &["moc", "rust"],
+        &["capture", "Another scrap"],
+        &["journal", "--date", "2024-06-14", "-m", "More"],
+        &["--non-interactive", "journal", "--date", "2024-06-14"],
+    ];
+    for args in refused {
+        medi()
+            .args(args)
+            .assert()
+            .code(1)
+            .stderr(predicate::str::contains("is locked"));
+    }
+
+    // Imports skip it and carry on.
+    medi()
+        .args(["import", "--stdin", "--key", "reference", "--overwrite"])
+        .write_stdin("Replaced by the import.")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Skipped 'reference': note is locked.",
+        ));
+    medi()
+        .args(["import", "--ndjson", "-", "--overwrite"])
+        .write_stdin(format!("{}\n", record))
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Skipped 'reference': note is locked.",
+        ));
+    medi()
+        .args(["import", "--overwrite", "--raw-dump"])
+        .arg(&dump_dir)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Skipped 'reference': note is locked.",
+        ));
+
+    medi()
+        .args(["get", "reference"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(original))
+        .stdout(predicate::str::contains("Replaced").not());
+    medi()
+        .args(["get", "journal/2024-06-14"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("More").not());
+    medi()
+        .args(["get", "inbox"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Another scrap").not());
+    Ok(())
+}
+
+#[test]
+fn test_get_and_edit_resolve_titles() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    for (key, title) in [
+        ("rust-1", "Rust ownership rules"),
+        ("rust-2", "Rust async patterns"),
+    ] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args([
+                "new",
+                key,
+                "--title",
+                title,
+                "-m",
+                &format!("about {}", key),
+            ])
+            .assert()
+            .success();
+    }
+
+    // An exact title, ignoring case.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "rust ownership rules"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("about rust-1"));
+
+    // Words of a title.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["edit", "async rust", "--add-tag", "tokio"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Using 'rust-2'"))
+        .stderr(predicate::str::contains(
+            "Successfully updated tags for 'rust-2'",
+        ));
+
+    // Several matches are listed instead of guessing.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "rust"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("rust-1 (Rust ownership rules)"))
+        .stderr(predicate::str::contains("rust-2 (Rust async patterns)"));
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "python"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("not found"));
+    Ok(())
+}
+
+#[test]
+fn test_new_reads_frontmatter_from_stdin() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["new", "weekly-sync", "--tag", "team"])
+        .write_stdin(
+            "---\ntitle: Weekly sync\ntags: [work, meetings]\n\
+             created: 2024-05-18T09:30:00Z\nsource: calendar\n---\n\nAgenda items\n",
+        )
+        .assert()
+        .success();
+
+    let output = Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "weekly-sync", "--json"])
+        .output()?;
+    let note: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(note["title"], "Weekly sync");
+    assert_eq!(
+        note["tags"],
+        serde_json::json!(["team", "work", "meetings"])
+    );
+    assert_eq!(note["created_at"], "2024-05-18T09:30:00Z");
+    assert_eq!(note["metadata"]["source"], "calendar");
+    assert_eq!(note["content"], "Agenda items\n");
+
+    // A bad timestamp is reported rather than ignored.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["new", "bad-date"])
+        .write_stdin("---\ncreated: someday\n---\ntext\n")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid frontmatter: created"));
+    Ok(())
+}
+
+#[test]
+fn test_touch_bumps_modified_time() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    for key in ["older", "newer"] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["new", key, "-m", "content"])
+            .assert()
+            .success();
+    }
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["touch", "older", "-m", "Revisited"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Touched 'older'"));
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["list", "--sort-by", "modified"])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match("(?s)older.*newer").unwrap());
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "older"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::is_match(r"content\n- \*\*\d{4}-\d{2}-\d{2} \d{2}:\d{2}\*\* Revisited")
+                .unwrap(),
+        );
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["touch", "missing"])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+#[test]
+fn test_new_zk_generates_timestamped_keys() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    let key_pattern = predicate::str::is_match(r"Using key '\d{12}-atomic-notes'")?;
+    for _ in 0..2 {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["new", "Atomic Notes", "--zk", "-m", "One idea per note."])
+            .assert()
+            .success()
+            .stderr(key_pattern.clone());
+    }
+
+    // The second note gets its own key rather than clashing with the first.
+    let output = Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .arg("list")
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    assert_eq!(stdout.matches("-atomic-notes").count(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_export_with_backlinks() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    for (key, content) in [
+        ("hub", "The centre."),
+        ("spoke-a", "Part of [[hub]]."),
+        ("spoke-b", "Also see [[hub]] and [[spoke-a]]."),
+    ] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["new", key, "-m", content])
+            .assert()
+            .success();
+    }
+
+    let export_dir = harness._temp_dir.path().join("garden");
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .arg("export")
+        .arg(&export_dir)
+        .arg("--backlinks")
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(export_dir.join("hub.md"))?,
+        "The centre.\n\n## Linked from\n\n- [[spoke-a]]\n- [[spoke-b]]\n"
+    );
+    assert!(!fs::read_to_string(export_dir.join("spoke-b.md"))?.contains("Linked from"));
+
+    let html_path = harness._temp_dir.path().join("garden.html");
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .arg("export")
+        .arg(&html_path)
+        .args(["--format", "html", "--backlinks"])
+        .assert()
+        .success();
+    assert!(fs::read_to_string(&html_path)?.contains("<a href=\"#spoke-b\">spoke-b</a>"));
+    Ok(())
+}
+
+#[test]
+fn test_export_with_tasks() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    for key in ["project", "other"] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["new", key, "-m", "Plans."])
+            .assert()
+            .success();
+    }
+    for (key, description) in [
+        ("project", "Draft"),
+        ("project", "Publish"),
+        ("other", "Elsewhere"),
+    ] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["task", "add", key, description])
+            .assert()
+            .success();
+    }
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["task", "done", "1"])
+        .assert()
+        .success();
+
+    let export_dir = harness._temp_dir.path().join("out");
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .arg("export")
+        .arg(&export_dir)
+        .args(["--key", "project", "--tasks"])
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(export_dir.join("project.md"))?,
+        "Plans.\n\n## Tasks\n\n- [x] Draft\n- [ ] Publish\n"
+    );
+
+    let json_path = harness._temp_dir.path().join("out.json");
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .arg("export")
+        .arg(&json_path)
+        .args(["--format", "json", "--key", "project"])
+        .assert()
+        .success();
+    let export: serde_json::Value = serde_json::from_str(&fs::read_to_string(&json_path)?)?;
+    assert_eq!(export["notes"][0]["content"], "Plans.");
+    let tasks = export["tasks"].as_array().expect("tasks array");
+    assert_eq!(tasks.len(), 2);
+    assert_eq!(tasks[1]["description"], "Publish");
+    Ok(())
+}
+
+#[test]
+fn test_moc_generates_and_refreshes() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    for (key, tags) in [
+        ("tokio", vec!["rust", "async"]),
+        ("serde", vec!["rust"]),
+        ("python-tips", vec!["python"]),
+    ] {
+        let mut args = vec!["new", key, "-m", "content"];
+        for tag in &tags {
+            args.extend(["--tag", tag]);
+        }
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(args)
+            .assert()
+            .success();
+    }
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["moc", "rust"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Created map of content 'moc/rust' (2 notes).",
+        ));
+    let output = Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "moc/rust"])
+        .output()?;
+    let content = String::from_utf8(output.stdout)?;
+    assert!(content.contains("## async\n\n- [[tokio]]\n\n## Other\n\n- [[serde]]\n"));
+    assert!(!content.contains("python-tips"));
+
+    // A new note shows up on refresh.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["new", "clap", "-m", "content", "--tag", "rust"])
+        .assert()
+        .success();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["moc", "rust"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Refreshed map of content"));
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "moc/rust"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("- [[clap]]\n- [[serde]]\n"))
+        .stdout(predicate::str::contains("# Map of content: rust").count(1));
+    Ok(())
+}
+
+#[test]
+fn test_summary_picks_key_sentences_and_inserts_them() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    let content = "# Q3 planning\n\nWe met to plan the third quarter roadmap.\n\
+        - The database migration to Postgres must finish before September.\n\
+        - Alice will own the Postgres migration and report weekly.\n\
+        - Lunch was pizza.\n";
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["new", "q3", "-m", content])
+        .assert()
+        .success();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args([
+            "new",
+            "lunch",
+            "-m",
+            "Lunch was pizza again, the weather was nice.",
+        ])
+        .assert()
+        .success();
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["summary", "q3", "-n", "1"])
+        .assert()
+        .success()
+        .stdout("- The database migration to Postgres must finish before September.\n");
+
+    // Inserting twice replaces the first summary instead of stacking them.
+    for _ in 0..2 {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["summary", "q3", "-n", "2", "--insert"])
+            .assert()
+            .success();
+    }
+    let output = Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "q3"])
+        .output()?;
+    let note = String::from_utf8(output.stdout)?;
+    assert!(note.starts_with(
+        "# Q3 planning\n\n## Summary\n\n\
+        - The database migration to Postgres must finish before September.\n\
+        - Alice will own the Postgres migration and report weekly.\n\n\
+        We met to plan"
+    ));
+    assert_eq!(note.matches("## Summary").count(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_suggest_tags_from_similar_notes() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    for (key, content, tag) in [
+        (
+            "borrowing",
+            "The borrow checker enforces ownership and lifetimes.",
+            "rust",
+        ),
+        (
+            "lifetimes",
+            "Lifetimes tell the borrow checker how long references live.",
+            "rust",
+        ),
+        (
+            "sourdough",
+            "Feed the starter flour and water daily.",
+            "baking",
+        ),
+    ] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["new", key, "-m", content, "--tag", tag])
+            .assert()
+            .success();
+    }
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args([
+            "new",
+            "ownership",
+            "-m",
+            "Ownership moves values; the borrow checker tracks references.",
+        ])
+        .assert()
+        .success();
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["suggest-tags", "ownership", "--top", "1", "--apply"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("rust"))
+        .stdout(predicate::str::contains("baking").not())
+        .stderr(predicate::str::contains(
+            "Updated tags for 'ownership': rust",
+        ));
+
+    // Once applied, the tag is no longer suggested.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["suggest-tags", "ownership"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("No tag suggestions"));
+    Ok(())
+}
+
+#[test]
+fn test_new_warns_about_near_duplicates() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    let content = "Deploy checklist: run migrations, restart workers, verify dashboards.";
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["new", "deploy", "-m", content])
+        .assert()
+        .success();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args([
+            "new",
+            "groceries",
+            "-m",
+            "Buy apples, oats and coffee beans.",
+        ])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("similar").not());
+
+    // Without a terminal the note is still saved after the warning.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["new", "deploy-steps", "-m", content])
+        .assert()
+        .success()
+        .stderr(predicate::str::is_match(
+            r"This looks \d+% similar to 'deploy'\.",
+        )?)
+        .stderr(predicate::str::contains(
+            "Successfully created note: 'deploy-steps'",
+        ));
+    Ok(())
+}
+
+#[test]
+fn test_ndjson_output_and_import() -> Result<(), Box<dyn std::error::Error>> {
+    let source = TestHarness::new();
+    for (key, content) in [("alpha", "First note"), ("beta", "Second note")] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &source.db_path)
+            .args(["new", key, "-m", content, "--tag", "stream"])
+            .assert()
+            .success();
+    }
+
+    let output = Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &source.db_path)
+        .args(["list", "--output", "ndjson"])
+        .output()?;
+    let stream = String::from_utf8(output.stdout)?;
+    assert_eq!(stream.lines().count(), 2);
+    let first: serde_json::Value = serde_json::from_str(stream.lines().next().unwrap())?;
+    assert_eq!(first["key"], "alpha");
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &source.db_path)
+        .args(["get", "alpha", "beta", "--meta", "--output", "ndjson"])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(
+            r#"^\{"key":"alpha".*\}\n\{"key":"beta".*\}\n$"#,
+        )?);
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &source.db_path)
+        .args(["search", "second", "--output", "ndjson"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""key":"beta""#))
+        .stdout(predicate::str::contains("alpha").not());
+
+    // Stream into a second vault; a bad line is reported and skipped.
+    let target = TestHarness::new();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &target.db_path)
+        .args(["import", "--ndjson", "-"])
+        .write_stdin(format!("{}not json\n", stream))
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Imported 2 note(s)."))
+        .stderr(predicate::str::contains("Skipped line 3"));
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &target.db_path)
+        .args(["get", "--tag", "stream"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("First note"))
+        .stdout(predicate::str::contains("Second note"));
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &target.db_path)
+        .args(["search", "second"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("beta"));
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "llm"))]
+fn test_llm_commands_need_the_llm_feature() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["ask", "what did we decide?"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--features llm"));
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["search", "decisions", "--semantic"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--features llm"));
+    Ok(())
+}
+
+/// A stand-in for a language model API: answers one request per reply, in order, and
+/// hands back the request bodies. Writes a config pointing `[llm]` at it.
+#[cfg(all(feature = "llm", target_os = "linux"))]
+fn mock_llm(
+    config_home: &Path,
+    replies: Vec<&'static str>,
+) -> Result<std::thread::JoinHandle<Vec<String>>, Box<dyn std::error::Error>> {
+    use std::io::{BufRead, BufReader, Read, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    fs::create_dir_all(config_home.join("medi"))?;
+    fs::write(
+        config_home.join("medi/config.toml"),
+        format!(
+            "[llm]\nendpoint = \"http://{}/v1/chat/completions\"\nmodel = \"test\"\n\
+             embedding_model = \"test-embed\"\n",
+            listener.local_addr()?
+        ),
+    )?;
+    Ok(std::thread::spawn(move || {
+        let mut requests = Vec::new();
+        for reply in replies {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+                    length = value.trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            write!(
+                reader.get_mut(),
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                reply.len(),
+                reply
+            )
+            .unwrap();
+            requests.push(String::from_utf8(body).unwrap());
+        }
+        requests
+    }))
+}
+
+#[test]
+#[cfg(all(feature = "llm", target_os = "linux"))]
+fn test_ask_sends_matching_notes_to_the_model() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    let config_home = harness.db_path.with_file_name("config");
+    let server = mock_llm(
+        &config_home,
+        vec![r#"{"choices":[{"message":{"content":"Alice owns it [q3]."}}]}"#],
+    )?;
+    let medi = || {
+        let mut cmd = Command::cargo_bin("medi").unwrap();
+        cmd.env("MEDI_DB_PATH", &harness.db_path)
+            .env("XDG_CONFIG_HOME", &config_home);
+        cmd
+    };
+    medi()
+        .args(["new", "q3", "-m", "Alice will own the Postgres migration."])
+        .assert()
+        .success();
+    medi()
+        .args(["ask", "Who owns the Postgres migration?"])
+        .assert()
+        .success()
+        .stdout("Alice owns it [q3].\n")
+        .stderr(predicate::str::contains("Sources: q3"));
+
+    let requests = server.join().unwrap();
+    assert!(requests[0].contains("Alice will own the Postgres migration."));
+    assert!(requests[0].contains("Question: Who owns the Postgres migration?"));
+    Ok(())
+}
+
+#[test]
+#[cfg(all(feature = "llm", target_os = "linux"))]
+fn test_semantic_search_ranks_by_embedding() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    let config_home = harness.db_path.with_file_name("config");
+    let server = mock_llm(
+        &config_home,
+        vec![
+            // The two notes (in key order), then the query, which is closest to `food`.
+            r#"{"data":[{"embedding":[1.0,0.0]},{"embedding":[0.0,1.0]}]}"#,
+            r#"{"data":[{"embedding":[0.1,0.9]}]}"#,
+            // The second search only embeds the query.
+            r#"{"data":[{"embedding":[0.9,0.1]}]}"#,
+        ],
+    )?;
+    let medi = || {
+        let mut cmd = Command::cargo_bin("medi").unwrap();
+        cmd.env("MEDI_DB_PATH", &harness.db_path)
+            .env("XDG_CONFIG_HOME", &config_home);
+        cmd
+    };
+    medi()
+        .args(["new", "db", "-m", "Postgres migration"])
+        .assert()
+        .success();
+    medi()
+        .args(["new", "food", "-m", "Pizza for lunch"])
+        .assert()
+        .success();
+
+    medi()
+        .args([
+            "search",
+            "something to eat",
+            "--semantic",
+            "--output",
+            "ndjson",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("{\"key\":\"food\""));
+    medi()
+        .args(["search", "sql", "--semantic", "--output", "ndjson"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("{\"key\":\"db\""));
+
+    let requests = server.join().unwrap();
+    assert!(requests[0].contains("Pizza for lunch"));
+    assert!(requests[2].contains("\"input\":[\"sql\"]"));
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "grpc")]
+fn test_grpc_notes_and_tasks() -> Result<(), Box<dyn std::error::Error>> {
+    use medi::grpc::proto;
+    use proto::note_service_client::NoteServiceClient;
+    use proto::task_service_client::TaskServiceClient;
+
+    let harness = TestHarness::new();
+    let addr = "127.0.0.1:50987";
+    let mut server = std::process::Command::new(assert_cmd::cargo::cargo_bin!("medi"))
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["serve", "--grpc", "--addr", addr])
+        .spawn()?;
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    let result: Result<(), Box<dyn std::error::Error>> = runtime.block_on(async {
+        // Wait for the server to come up.
+        let mut notes = loop {
+            match NoteServiceClient::connect(format!("http://{}", addr)).await {
+                Ok(client) => break client,
+                Err(_) => std::thread::sleep(std::time::Duration::from_millis(100)),
+            }
+        };
+        let saved = notes
+            .save_note(proto::SaveNoteRequest {
+                note: Some(proto::Note {
+                    key: "grpc-note".to_string(),
+                    content: "Hello from gRPC".to_string(),
+                    tags: vec!["api".to_string()],
+                    ..Default::default()
+                }),
+            })
+            .await?
+            .into_inner();
+        assert_eq!(saved.title, "grpc-note");
+
+        let mut stream = notes
+            .list_notes(proto::ListNotesRequest {
+                tags: vec!["api".to_string()],
+                prefix: String::new(),
+            })
+            .await?
+            .into_inner();
+        let listed = stream.message().await?.expect("one note");
+        assert_eq!(listed.content, "Hello from gRPC");
+        assert!(stream.message().await?.is_none());
+
+        notes
+            .save_note(proto::SaveNoteRequest {
+                note: Some(proto::Note {
+                    key: "linker".to_string(),
+                    content: "See [[grpc-note]].".to_string(),
+                    ..Default::default()
+                }),
+            })
+            .await?;
+        let mut links = notes
+            .list_links(proto::ListLinksRequest {
+                key: "grpc-note".to_string(),
+                outgoing: false,
+            })
+            .await?
+            .into_inner();
+        let link = links.message().await?.expect("one backlink");
+        assert_eq!(
+            (link.source.as_str(), link.start, link.end),
+            ("linker", 4, 17)
+        );
+
+        let missing = notes
+            .get_note(proto::GetNoteRequest {
+                key: "nope".to_string(),
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(missing.code(), tonic::Code::NotFound);
+
+        let mut tasks = TaskServiceClient::connect(format!("http://{}", addr)).await?;
+        let task = tasks
+            .add_task(proto::AddTaskRequest {
+                note_key: "grpc-note".to_string(),
+                description: "Wire it up".to_string(),
+            })
+            .await?
+            .into_inner();
+        let done = tasks
+            .complete_task(proto::CompleteTaskRequest { id: task.id })
+            .await?
+            .into_inner();
+        assert_eq!(done.status(), proto::TaskStatus::Done);
+        Ok(())
+    });
+    server.kill()?;
+    result?;
+
+    // The CLI sees what was written over gRPC.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "grpc-note"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Hello from gRPC"));
+    Ok(())
+}
+
+#[test]
+fn test_capture_appends_to_inbox() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["capture", "First thought"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Captured to 'inbox'"));
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .arg("capture")
+        .write_stdin("Piped thought\nwith a second line\n")
+        .assert()
+        .success();
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "inbox"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("# Inbox\n\n- **"))
+        .stdout(predicate::str::is_match(
+            r"\*\* First thought\n- \*\*\d{4}-\d{2}-\d{2} \d{2}:\d{2}\*\* Piped thought\n  with a second line\n",
+        )?);
+    Ok(())
+}
+
+#[test]
+fn test_triage_skips_filed_notes() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    // The capture inbox itself and tagged notes don't need triage.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["capture", "A scrap"])
+        .assert()
+        .success();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["new", "filed", "-m", "content", "--tag", "rust"])
+        .assert()
+        .success();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .arg("triage")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Nothing to triage."));
+    Ok(())
+}
+
+#[test]
+fn test_link_inserts_wikilinks() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    for key in ["rust", "sled", "tantivy"] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["new", key, "-m", "content"])
+            .assert()
+            .success();
+    }
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args([
+            "new",
+            "medi",
+            "-m",
+            "# medi\n\n## Related\n\n- [[rust]]\n\n## Notes\n\nSome notes.",
+        ])
+        .assert()
+        .success();
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["link", "medi", "sled", "--section", "Related"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Linked 'medi' to 'sled'"));
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["link", "medi", "tantivy"])
+        .assert()
+        .success();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["link", "medi", "rust", "--section", "Related"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("already links to 'rust'"));
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "medi"])
+        .assert()
+        .success()
+        .stdout(
+            "# medi\n\n## Related\n\n- [[rust]]\n- [[sled]]\n\n## Notes\n\nSome notes.\n\n[[tantivy]]\n\n",
+        );
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["backlinks", "sled"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("- medi"));
+    Ok(())
+}
+
+#[test]
+fn test_backlinks_outgoing_and_json() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    for (key, content) in [("a", "Intro\nSee [[b]] and [[ghost]].\n[[b]]"), ("b", "B.")] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["new", key, "-m", content])
+            .assert()
+            .success();
+    }
+
+    let output = Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["backlinks", "b", "--json"])
+        .output()?;
+    assert!(output.status.success());
+    let links: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(
+        links,
+        serde_json::json!([
+            { "source": "a", "target": "b", "start": 10, "end": 15, "line": 2,
+              "context": "See [[b]] and [[ghost]]." },
+            { "source": "a", "target": "b", "start": 31, "end": 36, "line": 3,
+              "context": "[[b]]" },
+        ])
+    );
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["backlinks", "a", "--outgoing"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Found 2 outgoing links for"))
+        .stdout("- b\n- ghost (missing)\n");
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_daemon_serves_search_and_pauses_for_writes() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["new", "alpha", "-m", "hello world"])
+        .assert()
+        .success();
+
+    let mut daemon = std::process::Command::new(assert_cmd::cargo::cargo_bin!("medi"))
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .arg("daemon")
+        .stderr(std::process::Stdio::null())
+        .spawn()?;
+    let socket = harness.db_path.join("daemon.sock");
+    let started = Instant::now();
+    while !socket.exists() && started.elapsed().as_secs() < 10 {
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+
+    // A client that connects and never sends anything doesn't hold up the others.
+    let _idle = std::os::unix::net::UnixStream::connect(&socket)?;
+
+    // Writing works while the daemon runs, and its searches see the new note.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["new", "beta", "-m", "hello again"])
+        .assert()
+        .success();
+    let search = Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["search", "hello"])
+        .assert();
+    daemon.kill()?;
+    daemon.wait()?;
+
+    search
+        .success()
+        .stdout(predicate::str::contains("alpha"))
+        .stdout(predicate::str::contains("beta"));
+    Ok(())
+}
+
+#[test]
+fn test_search_pinned_first() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    let config_home = harness.db_path.with_file_name("config");
+    for (key, tag, content) in [
+        (
+            "often",
+            "misc",
+            "Wombat, wombat, wombat: all about wombats.",
+        ),
+        (
+            "pinned-once",
+            "pinned",
+            "A long list of animals, among them a wombat, a quokka and an echidna.",
+        ),
+    ] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .env("XDG_CONFIG_HOME", &config_home)
+            .args(["new", key, "--tag", tag, "-m", content])
+            .assert()
+            .success();
+    }
+
+    let search = |args: &[&str]| -> Result<String, Box<dyn std::error::Error>> {
+        let output = Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .env("XDG_CONFIG_HOME", &config_home)
+            .args(args)
+            .output()?;
+        assert!(output.status.success());
+        Ok(String::from_utf8(output.stdout)?)
+    };
+    let pinned_before =
+        |stdout: &str| stdout.find("pinned-once").unwrap() < stdout.find("often").unwrap();
+
+    // Without the flag the more relevant note comes first.
+    assert!(!pinned_before(&search(&["search", "wombat"])?));
+    assert!(pinned_before(&search(&[
+        "search",
+        "wombat",
+        "--pinned-first"
+    ])?));
+
+    // The flag can be made the default for every search.
+    let config_file = config_home.join("medi/config.toml");
+    fs::create_dir_all(config_file.parent().unwrap())?;
+    fs::write(&config_file, "[defaults]\nsearch.pinned_first = true\n")?;
+    assert!(pinned_before(&search(&["search", "wombat"])?));
+    Ok(())
+}
+
+#[test]
+fn test_titles_dupes() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    for (key, title) in [
+        ("standup", "Meeting"),
+        ("work/meeting", "meeting "),
+        ("ideas", "Ideas"),
+    ] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["new", key, "--title", title, "-m", "Some text."])
+            .assert()
+            .success();
+    }
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .arg("titles")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("ideas\tIdeas"));
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["titles", "--dupes"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Meeting (2 notes):\n  - standup\n  - work/meeting\n",
+        ))
+        .stdout(predicate::str::contains("ideas").not())
+        .stderr(predicate::str::contains(
+            "1 title(s) are shared by 2 notes.",
+        ));
+
+    // Renaming asks for each title, so it needs a terminal.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["--non-interactive", "titles", "--dupes", "--rename"])
+        .assert()
+        .code(3);
+    Ok(())
+}
+
+#[test]
+fn test_note_filters_are_shared() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    for (key, tags) in [
+        ("current", vec!["work"]),
+        ("shelved", vec!["work", "archived"]),
+        ("home", vec!["home"]),
+    ] {
+        let mut cmd = Command::cargo_bin("medi")?;
+        cmd.env("MEDI_DB_PATH", &harness.db_path)
+            .args(["new", key, "-m", "Some text."]);
+        for tag in tags {
+            cmd.args(["--tag", tag]);
+        }
+        cmd.assert().success();
+    }
+
+    // The same filters select notes in every command.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["list", "--tag", "work", "--archived", "exclude"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("current"))
+        .stdout(predicate::str::contains("shelved").not())
+        .stdout(predicate::str::contains("home").not());
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["count", "--archived", "only"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("1"));
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["count", "--kind", "bookmark"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("0"));
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["tag", "apply", "old", "--before", "2000-01-01"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Tagged 0 notes"));
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args([
+            "tag",
+            "apply",
+            "stale",
+            "--tag",
+            "work",
+            "--since",
+            "2000-01-01",
+        ])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Tagged 2 notes with #stale"));
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["delete", "--tag", "stale", "--archived", "only", "--force"])
+        .assert()
+        .success();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "--tag", "stale"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Some text."));
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "shelved"])
+        .assert()
+        .failure();
+
+    // Keys and filters don't mix.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["delete", "home", "--tag", "work"])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+#[test]
+fn test_raw_dump_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    let dump_dir = harness.db_path.with_file_name("dump");
+    let restored_db = harness.db_path.with_file_name("restored_db");
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["new", "work/plan", "--tag", "work", "-m", "The plan."])
+        .assert()
+        .success();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["lock", "work/plan"])
+        .assert()
+        .success();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["task", "add", "work/plan", "Write it down"])
+        .assert()
+        .success();
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args([
+            "remind",
+            "set",
+            "work/plan",
+            "2000-01-01 09:00",
+            "-m",
+            "check",
+        ])
+        .assert()
+        .success();
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["export", dump_dir.to_str().unwrap(), "--format", "raw-dump"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("exported 1 notes as a raw dump"));
+    let manifest = fs::read_to_string(dump_dir.join("manifest.json"))?;
+    assert!(manifest.contains("\"format\": \"medi-raw-dump\""));
+
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &restored_db)
+        .args(["import", "--raw-dump", dump_dir.to_str().unwrap()])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Restored 1 of 1 note(s), with 1 task(s) and 1 reminder(s)",
+        ));
+    let get_json = |db: &PathBuf| {
+        Command::cargo_bin("medi")
+            .unwrap()
+            .env("MEDI_DB_PATH", db)
+            .args(["get", "work/plan", "--json"])
+            .output()
+            .map(|output| output.stdout)
+    };
+    assert_eq!(get_json(&harness.db_path)?, get_json(&restored_db)?);
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &restored_db)
+        .args(["task", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Write it down"));
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &restored_db)
+        .args(["remind", "due"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("work/plan: check"));
+
+    // A file changed after the export is refused.
+    let note_file = fs::read_dir(dump_dir.join("notes"))?
+        .next()
+        .unwrap()?
+        .path();
+    let tampered = fs::read_to_string(&note_file)?.replace("The plan.", "Another plan.");
+    fs::write(&note_file, tampered)?;
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &restored_db)
+        .args([
+            "import",
+            "--raw-dump",
+            dump_dir.to_str().unwrap(),
+            "--overwrite",
+        ])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("doesn't match its digest"));
+    Ok(())
+}
+
+#[test]
+fn test_list_sorts_for_the_configured_locale() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+    let config_home = harness.db_path.with_file_name("config");
+    let config_file = config_home.join("medi/config.toml");
+    fs::create_dir_all(config_file.parent().unwrap())?;
+    for key in ["öl", "Zon", "äpple", "apple"] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .env("XDG_CONFIG_HOME", &config_home)
+            .args(["new", key, "-m", "Some text."])
+            .assert()
+            .success();
+    }
+
+    for (locale, expected) in [
+        ("sv", "- apple\n- Zon\n- äpple\n- öl\n"),
+        ("de", "- apple\n- äpple\n- öl\n- Zon\n"),
+    ] {
+        fs::write(&config_file, format!("sort_locale = \"{}\"\n", locale))?;
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .env("XDG_CONFIG_HOME", &config_home)
+            .arg("list")
+            .assert()
+            .success()
+            .stdout(expected);
+    }
+
+    // Bookmarks are listed in the same order, here the German one.
+    for name in ["öl", "Zon", "äpple", "apple"] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .env("XDG_CONFIG_HOME", &config_home)
+            .args(["bookmark", "add", "https://example.com"])
+            .args(["--key", &format!("bookmarks/{}", name), "--title", name])
+            .assert()
+            .success();
+    }
+    let output = Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .env("XDG_CONFIG_HOME", &config_home)
+        .args(["bookmark", "list"])
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    let keys: Vec<&str> = stdout
+        .lines()
+        .filter_map(|line| line.strip_prefix("- bookmarks/"))
+        .collect();
+    assert_eq!(keys, ["apple", "äpple", "öl", "Zon"]);
+    Ok(())
+}
+
+#[test]
+fn test_new_auto_derives_and_prints_the_key() -> Result<(), Box<dyn std::error::Error>> {
+    let harness = TestHarness::new();
+
+    // The first heading of piped content names the note; a taken key gets a suffix.
+    for expected in ["weekly-review\n", "weekly-review-2\n"] {
+        Command::cargo_bin("medi")?
+            .env("MEDI_DB_PATH", &harness.db_path)
+            .args(["new", "--auto"])
+            .write_stdin("Notes from Friday.\n\n## Weekly Review\n\n- shipped it\n")
+            .assert()
+            .success()
+            .stdout(expected);
+    }
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["get", "weekly-review", "--meta"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Weekly Review"));
+
+    // --title wins over the heading.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args([
+            "new",
+            "--auto",
+            "--title",
+            "Plans for Q3",
+            "-m",
+            "# Ignored",
+        ])
+        .assert()
+        .success()
+        .stdout("plans-for-q3\n");
+
+    // Without a title or heading there is nothing to derive the key from.
+    Command::cargo_bin("medi")?
+        .env("MEDI_DB_PATH", &harness.db_path)
+        .args(["new", "--auto", "-m", "just #words"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--auto needs a --title"));
+    Ok(())
+}